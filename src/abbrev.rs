@@ -0,0 +1,52 @@
+//! Abbreviations, which expand into longer text as they're typed.
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// Whether `c` can be part of a word. Anything else typed after a
+/// word is a boundary, and triggers expansion.
+pub fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// If the text up to byte `end` finishes with a word that's an
+/// abbreviation, returns where that word is and what it expands to.
+pub fn find_expansion<'a>(
+    text: &str,
+    end: usize,
+    abbreviations: &'a HashMap<String, String>,
+) -> Option<(Range<usize>, &'a str)> {
+    let before = &text[..end];
+    let start = before
+        .char_indices()
+        .rev()
+        .take_while(|&(_, c)| is_word_char(c))
+        .last()
+        .map(|(i, _)| i)?;
+
+    abbreviations
+        .get(&before[start..])
+        .map(|expansion| (start..end, expansion.as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_a_whole_word_before_the_cursor_expands() {
+        let abbreviations = HashMap::from([(String::from("teh"), String::from("the"))]);
+        assert_eq!(
+            find_expansion("a teh", 5, &abbreviations),
+            Some((2..5, "the"))
+        );
+        assert_eq!(
+            find_expansion("(teh", 4, &abbreviations),
+            Some((1..4, "the"))
+        );
+        // Part of a longer word isn't one, and nor is anything past a boundary.
+        assert_eq!(find_expansion("xteh", 4, &abbreviations), None);
+        assert_eq!(find_expansion("teh ", 4, &abbreviations), None);
+        assert_eq!(find_expansion("", 0, &abbreviations), None);
+    }
+}
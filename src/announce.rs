@@ -0,0 +1,219 @@
+//! Screen-reader mode, which says what's happening in the editor as
+//! lines of plain text, so BuffeRS can be used without seeing the grid.
+//!
+//! The editor has the terminal to itself, so what's said goes somewhere
+//! else: to standard error, when that's been pointed at another
+//! terminal (like `ws03 2>/dev/pts/3`), or to a file or FIFO which a
+//! speech program or braille display reads from. It's turned on with
+//! `announce <path>` or `announce stderr`, or `announce` in the config.
+//!
+//! What's said:
+//! - the line the cursor is on, whenever it moves to another line,
+//! - the character the cursor moves onto, when it moves along a line,
+//! - the character deleted, by Backspace,
+//! - messages and prompts, when they appear.
+//!
+//! The word count and typing speed aren't said, since they change with
+//! every key.
+
+use termgame::Game;
+
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::i18n::tr;
+use crate::BufferEditor;
+
+/// Where announcements are written.
+pub struct Announcer {
+    out: Box<dyn Write>,
+    target: String,
+    /// What was last said, so only what changes is said again.
+    last: Option<View>,
+}
+
+/// Everything that can be announced, as it is at one moment.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct View {
+    pub buffer: String,
+    /// The (0-indexed) line the cursor is on, and its text.
+    pub line: usize,
+    pub text: String,
+    /// The column the cursor is at, in chars.
+    pub col: usize,
+    pub message: Option<String>,
+    pub prompt: Option<String>,
+}
+
+impl Announcer {
+    /// Announces to `target`, which is either `stderr` or a file, which
+    /// is appended to.
+    pub fn open(target: &Path) -> io::Result<Announcer> {
+        let out: Box<dyn Write> = match target.to_str() {
+            Some("stderr") => Box::new(io::stderr()),
+            _ => Box::new(OpenOptions::new().create(true).append(true).open(target)?),
+        };
+        Ok(Announcer {
+            out,
+            target: target.display().to_string(),
+            last: None,
+        })
+    }
+
+    /// Where announcements go, as it was given.
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    /// Forgets what was last said, so the next view is said in full.
+    pub fn reset(&mut self) {
+        self.last = None;
+    }
+
+    /// Says whatever's different about `view` from the last one.
+    pub fn update(&mut self, view: View) -> io::Result<()> {
+        let lines = changes(self.last.as_ref(), &view);
+        self.last = Some(view);
+        for line in &lines {
+            writeln!(self.out, "{}", line)?;
+        }
+        match lines.is_empty() {
+            true => Ok(()),
+            false => self.out.flush(),
+        }
+    }
+}
+
+/// What to say, going from `old` to `new`.
+fn changes(old: Option<&View>, new: &View) -> Vec<String> {
+    let mut said = Vec::new();
+    let line = || {
+        let text = match new.text.is_empty() {
+            true => tr("announce.blank", &[]),
+            false => new.text.clone(),
+        };
+        tr(
+            "announce.line",
+            &[("line", &(new.line + 1)), ("text", &text)],
+        )
+    };
+    match old {
+        Some(old) if old.buffer == new.buffer => {
+            if old.line != new.line {
+                said.push(line());
+            } else if old.text == new.text && old.col != new.col {
+                said.push(char_name(new.text.chars().nth(new.col)));
+            } else if new.col + 1 == old.col
+                && new.text.chars().count() + 1 == old.text.chars().count()
+            {
+                let deleted = old.text.chars().nth(new.col);
+                said.push(tr("announce.deleted", &[("char", &char_name(deleted))]));
+            }
+        }
+        _ => {
+            said.push(tr("announce.buffer", &[("name", &new.buffer)]));
+            said.push(line());
+        }
+    }
+    let old_message = old.and_then(|old| old.message.as_ref());
+    if let Some(message) = new.message.as_ref().filter(|&m| Some(m) != old_message) {
+        said.push(message.clone());
+    }
+    let old_prompt = old.and_then(|old| old.prompt.as_ref());
+    if let Some(prompt) = new.prompt.as_ref().filter(|&p| Some(p) != old_prompt) {
+        said.push(prompt.clone());
+    }
+    said
+}
+
+/// How to say a character, so that ones which can't be heard can be.
+fn char_name(c: Option<char>) -> String {
+    match c {
+        None => tr("announce.end-of-line", &[]),
+        Some(' ') => tr("announce.space", &[]),
+        Some('\t') => tr("announce.tab", &[]),
+        Some(c) => c.to_string(),
+    }
+}
+
+impl BufferEditor {
+    /// Says what's changed since the last announcement, if screen-reader
+    /// mode is on.
+    pub fn announce(&mut self, game: &Game) {
+        let Some(announcer) = &mut self.session.announcer else {
+            return;
+        };
+        let position = self.buffer.position_of(self.buffer.cursor());
+        let view = View {
+            buffer: self.name.clone(),
+            line: position.line,
+            text: String::from(self.buffer.line(position.line).unwrap_or("")),
+            col: position.col,
+            message: game
+                .get_message()
+                .as_ref()
+                .map(|message| message.text.clone()),
+            prompt: self.prompt.as_ref().map(|(prompt, _)| prompt.display()),
+        };
+        if let Err(err) = announcer.update(view) {
+            tracing::warn!(target = announcer.target(), error = %err, "stopped announcing");
+            self.session.announcer = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use termgame::KeyCode;
+
+    use crate::harness::Harness;
+
+    fn view(line: usize, text: &str, col: usize) -> View {
+        View {
+            buffer: String::from("notes"),
+            line,
+            text: String::from(text),
+            col,
+            ..View::default()
+        }
+    }
+
+    #[test]
+    fn moving_says_where_the_cursor_went() {
+        let start = view(0, "a b", 0);
+        assert_eq!(changes(None, &start), ["Editing notes", "Line 1: a b"]);
+        assert_eq!(changes(Some(&start), &view(0, "a b", 1)), ["space"]);
+        assert_eq!(changes(Some(&start), &view(0, "a b", 3)), ["end of line"]);
+        assert_eq!(changes(Some(&start), &view(1, "", 0)), ["Line 2: blank"]);
+        assert!(changes(Some(&start), &start).is_empty());
+    }
+
+    #[test]
+    fn backspace_says_what_it_deleted() {
+        let typed = view(0, "ab", 2);
+        assert_eq!(changes(Some(&typed), &view(0, "a", 1)), ["Deleted b"]);
+        // Typing isn't read back.
+        assert!(changes(Some(&view(0, "a", 1)), &typed).is_empty());
+
+        let mut message = typed.clone();
+        message.message = Some(String::from("Saved"));
+        assert_eq!(changes(Some(&typed), &message), ["Saved"]);
+        assert!(changes(Some(&message), &message).is_empty());
+    }
+
+    #[test]
+    fn the_editor_is_announced_to_a_file() {
+        let path = std::env::temp_dir().join(format!("buffers-announce-{}", std::process::id()));
+        let mut harness = Harness::new("one\ntwo");
+        harness.editor.session.announcer = Some(Announcer::open(&path).unwrap());
+        harness.press(KeyCode::Right).type_text("\n");
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "Editing test\nLine 1: one\nLine 2: ne\n"
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+}
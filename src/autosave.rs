@@ -0,0 +1,206 @@
+//! Saving on a timer. With `autosave_secs` set in the config, a buffer
+//! with unsaved changes is saved once it's been that long since it was
+//! last autosaved, or since it was first changed. A buffer without a
+//! file is written to `~/.local/share/buffers/recovery/` instead, under
+//! its name, where it's kept until it's saved properly, or the buffer's
+//! closed without it. At the `>>` prompt, where there's no timer, a
+//! buffer's autosaved once the command which changed it is done.
+//!
+//! The status bar says how long ago the last autosave was.
+
+use termgame::{Game, Message};
+
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::config::data_dir;
+use crate::i18n::tr;
+use crate::save;
+use crate::transform;
+use crate::{App, BufferEditor};
+
+/// When a buffer was last autosaved, and what it had in it then.
+#[derive(Debug, Default)]
+pub struct Autosave {
+    /// When it was last autosaved, or first checked if it hasn't been.
+    last: Option<Duration>,
+    /// The buffer's revision when it was last autosaved.
+    revision: Option<usize>,
+    /// When it was last autosaved without anything going wrong.
+    saved: Option<Duration>,
+}
+
+impl Autosave {
+    /// Whether there's been a change since the last autosave, and it's
+    /// been at least `every` since.
+    fn is_due(&mut self, revision: usize, now: Duration, every: Duration) -> bool {
+        let last = *self.last.get_or_insert(now);
+        self.revision != Some(revision) && now.saturating_sub(last) >= every
+    }
+
+    /// How long ago the last autosave was, if there's been one.
+    pub fn ago(&self, now: Duration) -> Option<Duration> {
+        self.saved.map(|saved| now.saturating_sub(saved))
+    }
+}
+
+/// Where a buffer without a file is autosaved, by its name.
+pub fn recovery_path(name: &str) -> Option<PathBuf> {
+    // Encoded, so a buffer named after a path is still one file.
+    let name = transform::url_encode(name).ok()?;
+    Some(data_dir()?.join("recovery").join(name))
+}
+
+/// Says how long `ago` was, to the minute after the first one.
+pub fn describe_ago(ago: Duration) -> String {
+    match ago.as_secs() {
+        secs @ 0..=59 => format!("{}s", secs),
+        secs => format!("{}m", secs / 60),
+    }
+}
+
+impl BufferEditor {
+    /// Autosaves the buffer, if it has changes and it's time to.
+    pub fn autosave_tick(&mut self, game: &mut Game) {
+        let every = Duration::from_secs(self.config.autosave_secs);
+        if every.is_zero() || self.read_only || self.follow || !self.buffer.is_dirty() {
+            return;
+        }
+        let now = self.session.clock.now();
+        if !self.autosave.is_due(self.buffer.revision(), now, every) {
+            return;
+        }
+        if let Err(err) = self.autosave_at(now) {
+            let message = tr("editor.autosave-failed", &[("error", &err)]);
+            game.set_message(Some(Message::new(message)));
+        }
+    }
+
+    /// Autosaves the buffer if it's changed since it last was, without
+    /// waiting for the interval: at the `>>` prompt nothing ticks while
+    /// it waits for a line, so a command's changes are saved once it's
+    /// done.
+    pub fn autosave_changes(&mut self, now: Duration) -> Result<(), Box<dyn Error>> {
+        if self.config.autosave_secs == 0
+            || self.read_only
+            || self.follow
+            || !self.buffer.is_dirty()
+            || self.autosave.revision == Some(self.buffer.revision())
+        {
+            return Ok(());
+        }
+        self.autosave_at(now)
+    }
+
+    fn autosave_at(&mut self, now: Duration) -> Result<(), Box<dyn Error>> {
+        self.autosave.last = Some(now);
+        self.autosave.revision = Some(self.buffer.revision());
+        let saved = self.autosave();
+        match &saved {
+            Ok(()) => self.autosave.saved = Some(now),
+            Err(err) => tracing::warn!(buffer = %self.name, error = %err, "couldn't autosave"),
+        }
+        saved
+    }
+
+    fn autosave(&mut self) -> Result<(), Box<dyn Error>> {
+        if let Some(file) = self.buffer.file() {
+            let file = PathBuf::from(file);
+            return self.save_to(&file, false, false);
+        }
+        let path = recovery_path(&self.name).ok_or("there's nowhere to keep recovery files")?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        save::write_file(&path, self.buffer.text().as_bytes(), None)?;
+        Ok(())
+    }
+
+    /// Deletes the buffer's recovery file, once it's been saved to a
+    /// file of its own.
+    pub fn remove_recovery(&self) {
+        if let Some(path) = recovery_path(&self.name).filter(|path| Path::exists(path)) {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// Autosaves every buffer a command at the `>>` prompt has changed,
+/// saying which couldn't be.
+pub fn autosave_changes(app: &mut App) {
+    let now = app.session.clock.now();
+    for editor in app.editors.values_mut() {
+        if let Err(err) = editor.autosave_changes(now) {
+            let name = &editor.name;
+            println!(
+                "{}",
+                tr("repl.autosave-failed", &[("name", name), ("error", &err)])
+            );
+        }
+    }
+}
+
+/// Moves the recovery file kept for the buffer called `name`, if there
+/// is one, to where it's kept for `new_name`.
+pub fn rename_recovery(name: &str, new_name: &str) -> io::Result<()> {
+    let (Some(from), Some(to)) = (recovery_path(name), recovery_path(new_name)) else {
+        return Ok(());
+    };
+    match from.exists() {
+        true => fs::rename(from, to),
+        false => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::rc::Rc;
+
+    use crate::config::Config;
+    use crate::harness::{self, Harness, TempFile};
+
+    #[test]
+    fn changes_are_saved_once_the_interval_is_up() {
+        let config = Config {
+            autosave_secs: 1,
+            ..Config::default()
+        };
+        let mut harness = Harness::with_file_and_config("auto", "one", config);
+        let path = harness.path().to_path_buf();
+        harness.type_text("x").tick(20);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "one");
+
+        // A tick is 25ms, so a second is 40 of them.
+        harness.tick(21);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "xone");
+        assert!(!harness.editor.buffer.is_dirty());
+        assert!(harness.editor.status_text().ends_with("Autosaved 0s ago"));
+        harness.tick(80);
+        assert!(harness.editor.status_text().ends_with("Autosaved 2s ago"));
+    }
+
+    #[test]
+    fn changes_made_at_the_prompt_are_saved_once_the_command_is_done() {
+        let file = TempFile::new("auto", "one");
+        let path = file.path();
+        let mut app = harness::app();
+        app.config = Rc::new(Config {
+            autosave_secs: 60,
+            ..Config::default()
+        });
+        let editor = file.editor("auto", Rc::clone(&app.config));
+        app.editors.insert(String::from("auto"), editor);
+        autosave_changes(&mut app);
+        assert_eq!(fs::read_to_string(path).unwrap(), "one");
+
+        app.editors.get_mut("auto").unwrap().buffer.insert("x");
+        autosave_changes(&mut app);
+        assert_eq!(fs::read_to_string(path).unwrap(), "xone");
+        assert!(!app.editors["auto"].buffer.is_dirty());
+    }
+}
@@ -0,0 +1,100 @@
+//! Copies of files as they were before BuffeRS overwrote them, kept in
+//! `~/.local/share/buffers/backups/`.
+//!
+//! Each backup is named after the file's full path and when it was
+//! taken, like `%2Fhome%2Fme%2Fnotes.txt~20260101T120000.000Z`, and only
+//! the newest few of each file are kept.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::config::data_dir;
+use crate::dates::DateTime;
+use crate::transform;
+
+/// Where backups go.
+fn backup_dir() -> Option<PathBuf> {
+    Some(data_dir()?.join("backups"))
+}
+
+/// Copies `path`, as it is now, into the backup directory, then deletes
+/// all but the newest `keep` backups of it. A file that doesn't exist
+/// yet has nothing to back up.
+pub fn back_up(path: &Path, keep: usize) -> io::Result<()> {
+    if !path.exists() {
+        return Ok(());
+    }
+    let dir = backup_dir().ok_or_else(|| io::Error::other("no home directory"))?;
+    back_up_into(path, &dir, keep, SystemTime::now())
+}
+
+/// Copies `path` into the backup directory `dir`, as taken at `when`,
+/// and deletes all but the newest `keep` backups of it there.
+fn back_up_into(path: &Path, dir: &Path, keep: usize, when: SystemTime) -> io::Result<()> {
+    let full_path = fs::canonicalize(path)?;
+    let prefix = format!(
+        "{}~",
+        transform::url_encode(&full_path.to_string_lossy()).map_err(io::Error::other)?
+    );
+
+    fs::create_dir_all(dir)?;
+    fs::copy(path, dir.join(format!("{}{}", prefix, timestamp(when))))?;
+
+    let mut backups: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|backup| {
+            backup
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with(&prefix))
+        })
+        .collect();
+    // The timestamps sort the same way as the times they stand for.
+    backups.sort();
+    let excess = backups.len().saturating_sub(keep);
+    for old in &backups[..excess] {
+        fs::remove_file(old)?;
+    }
+    Ok(())
+}
+
+/// Writes `time` like `20260101T120000.000Z`, in UTC.
+fn timestamp(time: SystemTime) -> String {
+    let time = DateTime::utc(time);
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}.{:03}Z",
+        time.year, time.month, time.day, time.hour, time.minute, time.second, time.millis
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use crate::harness::TempFile;
+
+    #[test]
+    fn only_the_newest_backups_are_kept() {
+        let dir = std::env::temp_dir().join(format!("buffers-backups-{}", std::process::id()));
+        let file = TempFile::new("backed-up", "");
+        let other = TempFile::new("left-alone", "other");
+        back_up_into(other.path(), &dir, 1, UNIX_EPOCH).unwrap();
+        for n in 0..4 {
+            fs::write(file.path(), format!("version {}", n)).unwrap();
+            let when = UNIX_EPOCH + Duration::from_secs(1_700_000_000 + n);
+            back_up_into(file.path(), &dir, 2, when).unwrap();
+        }
+
+        let mut kept: Vec<String> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| fs::read_to_string(entry.unwrap().path()).unwrap())
+            .collect();
+        kept.sort();
+        fs::remove_dir_all(&dir).unwrap();
+        // Another file's backups don't count towards this one's.
+        assert_eq!(kept, ["other", "version 2", "version 3"]);
+    }
+}
@@ -0,0 +1,105 @@
+//! Matching brackets. When the cursor's on one of `( ) [ ] { }`, the
+//! bracket it pairs with is highlighted, and Ctrl+] jumps to it. One
+//! with nothing to pair with is drawn in the theme's warning colour.
+
+use crate::BufferEditor;
+
+const PAIRS: [(char, char); 3] = [('(', ')'), ('[', ']'), ('{', '}')];
+
+/// What the bracket at some offset pairs with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Match {
+    /// The offset of the bracket it pairs with.
+    Found(usize),
+    Unmatched,
+}
+
+/// What the bracket at `offset` in `text` pairs with, skipping over any
+/// pairs of the same kind in between, or `None` if there isn't a bracket
+/// there.
+pub fn matching(text: &str, offset: usize) -> Option<Match> {
+    let c = text.get(offset..)?.chars().next()?;
+    let (open, close) = *PAIRS
+        .iter()
+        .find(|&&(open, close)| c == open || c == close)?;
+    let mut depth = 0usize;
+    let step = |(i, other): (usize, char)| {
+        if other == c {
+            depth += 1;
+        } else if other == open || other == close {
+            depth -= 1;
+        }
+        (depth == 0).then_some(i)
+    };
+    let found = match c == open {
+        true => text[offset..]
+            .char_indices()
+            .map(|(i, other)| (offset + i, other))
+            .find_map(step),
+        false => text[..offset + c.len_utf8()]
+            .char_indices()
+            .rev()
+            .find_map(step),
+    };
+    Some(found.map_or(Match::Unmatched, Match::Found))
+}
+
+impl BufferEditor {
+    /// What the bracket under the cursor pairs with, if it's on one.
+    pub fn bracket_match(&self) -> Option<Match> {
+        // This is asked every time the buffer's drawn, so the whole text
+        // is only put together when there's a bracket to pair.
+        let c = self.buffer.char_after_cursor()?;
+        if !PAIRS.iter().any(|&(open, close)| c == open || c == close) {
+            return None;
+        }
+        matching(self.buffer.text(), self.buffer.cursor())
+    }
+
+    /// Moves the cursor to the bracket the one under it pairs with.
+    pub fn jump_to_match(&mut self) {
+        if let Some(Match::Found(offset)) = self.bracket_match() {
+            self.buffer.set_cursor(offset);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use termgame::KeyCode;
+
+    use crate::harness::{ctrl, Harness};
+
+    #[test]
+    fn brackets_pair_with_the_one_at_the_same_depth() {
+        let text = "f(a, (b)) [}";
+        assert_eq!(matching(text, 1), Some(Match::Found(8)));
+        assert_eq!(matching(text, 8), Some(Match::Found(1)));
+        assert_eq!(matching(text, 5), Some(Match::Found(7)));
+        assert_eq!(matching(text, 10), Some(Match::Unmatched));
+        assert_eq!(matching(text, 11), Some(Match::Unmatched));
+        assert_eq!(matching(text, 0), None);
+        assert_eq!(matching(text, text.len()), None);
+    }
+
+    #[test]
+    fn ctrl_bracket_jumps_to_the_match_and_its_highlighted() {
+        let mut harness = Harness::new("if (x) {\n  y\n}");
+        let theme = harness.editor.session.theme;
+        for _ in 0..7 {
+            harness.press(KeyCode::Right);
+        }
+        assert_eq!(harness.paint_at(0, 2), Some(theme.bracket));
+        harness.run([ctrl(']')]);
+        assert_eq!(harness.editor.buffer.cursor_position().line, 2);
+        assert_eq!(harness.paint_at(7, 0), Some(theme.bracket));
+        harness.press(KeyCode::Left).press(KeyCode::Left);
+        assert_eq!(harness.paint_at(7, 0), None);
+
+        let mut harness = Harness::new("x)");
+        harness.press(KeyCode::Right);
+        assert_eq!(harness.paint_at(1, 0), Some(theme.unmatched));
+    }
+}
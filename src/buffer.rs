@@ -0,0 +1,1086 @@
+use termgame::{Game, StyledCharacter};
+
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::Path;
+
+use crate::encoding::{self, Encoding};
+use crate::eol::LineEnding;
+use crate::history::{Edit, History};
+use crate::rope::Rope;
+use crate::theme::Paint;
+use crate::wordcount::WordCount;
+
+/// A place in the text, as a (0-indexed) line and a column counted in
+/// chars from the start of that line.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Position {
+    pub line: usize,
+    pub col: usize,
+}
+
+/// How much memory a buffer uses, in bytes, and on what.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Memory {
+    pub text: usize,
+    /// Where the text's chunks and lines start.
+    pub index: usize,
+    /// The text as it was last saved, kept for merging.
+    pub saved_text: usize,
+    /// The undo history, which is `states` states.
+    pub history: usize,
+    pub states: usize,
+}
+
+impl Memory {
+    pub fn total(&self) -> usize {
+        self.text + self.index + self.saved_text + self.history
+    }
+}
+
+/// This is a single "buffer".
+///
+/// The text is kept in a [`Rope`] of chunks of whole lines, so typing
+/// only moves the bytes of the chunk it's in, and lines are found without
+/// reading the text. Anything which needs it all as one `&str` can still
+/// have it from [`Buffer::text`]. The buffer also keeps track of which
+/// lines have changed since they were last drawn.
+pub struct Buffer {
+    text: Rope,
+    /// The lines changed since [`Buffer::take_changed_lines`] last said,
+    /// running to `usize::MAX` when every line after the first moved.
+    changed_lines: Option<Range<usize>>,
+    file: Option<String>,
+    history: History,
+    /// Goes up by one every time the text changes.
+    revision: usize,
+    word_count: WordCount,
+    /// The byte offset that typing inserts at.
+    cursor: usize,
+    /// The cell across Up and Down keep to, through lines too short to
+    /// reach it. It's forgotten as soon as the cursor moves any other way.
+    goal_col: Option<usize>,
+    /// How many cells apart tab stops are.
+    tab_width: usize,
+    /// Where the selection started, when there is one. It runs from
+    /// here to the cursor, and goes as soon as the text is edited.
+    anchor: Option<usize>,
+    /// The revision that matches what's in the file.
+    saved_revision: usize,
+    /// The text as it was when last loaded or saved, which is what both
+    /// sides of a conflict started from.
+    saved_text: String,
+    /// What the file is written in, which the text is read from and
+    /// written back to.
+    encoding: Encoding,
+    /// What the file ends its lines with. The text itself always uses
+    /// `\n`, and they're swapped back when it's written.
+    eol: LineEnding,
+    /// Whether the file ended its lines more than one way when it was
+    /// loaded, so saving it will make them all `eol`.
+    mixed_eol: bool,
+}
+
+impl Buffer {
+    /// This creates a new Buffer, to use it you should run:
+    /// ```rust
+    /// Buffer::new()
+    /// ```
+    pub fn new(file: Option<String>) -> Buffer {
+        Buffer {
+            text: Rope::new(String::new()),
+            changed_lines: Some(0..usize::MAX),
+            file,
+            history: History::new(),
+            revision: 0,
+            word_count: WordCount::default(),
+            cursor: 0,
+            goal_col: None,
+            tab_width: 4,
+            anchor: None,
+            saved_revision: 0,
+            saved_text: String::new(),
+            encoding: Encoding::Utf8,
+            eol: LineEnding::Lf,
+            mixed_eol: false,
+        }
+    }
+
+    /// Creates a Buffer which starts out holding `text`.
+    pub fn from_text(file: Option<String>, text: String) -> Buffer {
+        Buffer {
+            word_count: WordCount::of(&text),
+            saved_text: text.clone(),
+            text: Rope::new(text),
+            ..Buffer::new(file)
+        }
+    }
+
+    /// Creates a Buffer holding the contents of the file at `path`, in
+    /// whichever encoding and line endings it looks to have.
+    pub fn from_file(path: &str) -> io::Result<Buffer> {
+        let bytes = fs::read(path)?;
+        let encoding = Encoding::detect(&bytes);
+        let text = encoding
+            .decode(&bytes)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        let eol = LineEnding::detect(&text);
+        Ok(Buffer {
+            encoding,
+            eol,
+            mixed_eol: LineEnding::is_mixed(&text),
+            ..Buffer::from_text(Some(String::from(path)), eol.strip(text))
+        })
+    }
+
+    /// Reads the buffer's file as it is now, in the buffer's encoding and
+    /// with its line endings made `\n`, like the text.
+    pub fn read_file(&self) -> Option<io::Result<String>> {
+        self.read_file_in(self.encoding)
+    }
+
+    /// Reads the buffer's file like [`Buffer::read_file`], but as though
+    /// it were in `encoding`.
+    pub fn read_file_in(&self, encoding: Encoding) -> Option<io::Result<String>> {
+        let text = encoding::read(self.file()?, encoding);
+        Some(text.map(|text| self.eol.strip(text)))
+    }
+
+    /// The full text of the buffer.
+    pub fn text(&self) -> &str {
+        self.text.text()
+    }
+
+    /// The bytes of the text in `range`, without putting the whole text
+    /// together like [`Buffer::text`] has to after an edit.
+    pub fn slice(&self, range: Range<usize>) -> String {
+        self.text.slice(range)
+    }
+
+    /// Draws the bytes in `part` of (0-indexed) line `index`, from the
+    /// cell at `(left, row)` across, with the cell under the cursor in
+    /// `cursor` and each of the rest in what `paint` gives for its byte
+    /// offset into the line. Returns how many cells across it reaches, or
+    /// 0 if there's no such line.
+    pub fn draw_part(
+        &self,
+        game: &mut Game,
+        index: usize,
+        part: Range<usize>,
+        (left, row): (usize, i32),
+        cursor: Paint,
+        paint: impl Fn(usize) -> Option<Paint>,
+    ) -> usize {
+        let (Some(start), Some(text)) = (self.text.line_start(index), self.text.line(index)) else {
+            return 0;
+        };
+        let line = text.strip_suffix('\n').unwrap_or(text);
+        let part = part.start.min(line.len())..part.end.min(line.len());
+        let mut width = left;
+        for (i, c) in line[part.clone()].char_indices() {
+            let i = part.start + i;
+            // A tab is drawn as spaces up to the next tab stop, with the
+            // cursor on the first of them.
+            let (c, cells) = match c {
+                '\t' => (' ', cell_width(c, width - left, self.tab_width)),
+                c => (c, 1),
+            };
+            for cell in 0..cells {
+                let styled = match (cell == 0 && start + i == self.cursor, paint(i)) {
+                    (true, _) => StyledCharacter::new(c).style(cursor.style()),
+                    (false, Some(paint)) => StyledCharacter::new(c).style(paint.style()),
+                    (false, None) => StyledCharacter::new(c),
+                };
+                game.set_screen_char(width as i32, row, Some(styled));
+                width += 1;
+            }
+        }
+        // At the end of the line there's nothing to show, so the cursor
+        // is drawn as a space.
+        if part.end == line.len() && self.cursor == start + line.len() {
+            let styled = StyledCharacter::new(' ').style(cursor.style());
+            game.set_screen_char(width as i32, row, Some(styled));
+            width += 1;
+        }
+        width
+    }
+
+    /// How many lines there are to draw: [`Buffer::len_lines`], and the
+    /// empty line after a newline at the very end, where the cursor can go.
+    pub fn drawn_lines(&self) -> usize {
+        self.text.lines()
+    }
+
+    /// The lines which have changed since this was last called.
+    pub fn take_changed_lines(&mut self) -> Option<Range<usize>> {
+        self.changed_lines.take()
+    }
+
+    /// The lines [`Buffer::take_changed_lines`] would say have changed,
+    /// without forgetting them.
+    pub fn changed_lines(&self) -> Option<Range<usize>> {
+        self.changed_lines.clone()
+    }
+
+    /// Where the cursor is, as a byte offset into the text.
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Moves the cursor to `offset`, or as close as it can get without
+    /// landing in the middle of a character or past the end.
+    pub fn set_cursor(&mut self, offset: usize) {
+        let mut offset = offset.min(self.text.len());
+        while !self.text.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        self.cursor = offset;
+        self.goal_col = None;
+        self.history.seal();
+    }
+
+    /// The line and column the cursor is at.
+    pub fn cursor_position(&self) -> Position {
+        self.position_of(self.cursor)
+    }
+
+    pub fn set_tab_width(&mut self, tab_width: usize) {
+        self.tab_width = tab_width.max(1);
+    }
+
+    /// How many cells across its line `position` is drawn, counting
+    /// each tab as however many it takes to reach the next tab stop.
+    pub fn display_col(&self, position: Position) -> usize {
+        let line = self.line(position.line).unwrap_or("");
+        line.chars()
+            .take(position.col)
+            .fold(0, |cells, c| cells + cell_width(c, cells, self.tab_width))
+    }
+
+    /// The column of the char drawn over the cell `cells` across `line`,
+    /// or the end of the line if it's not that long.
+    fn col_at_cells(&self, line: &str, cells: usize) -> usize {
+        let mut across = 0;
+        for (col, c) in line.chars().enumerate() {
+            across += cell_width(c, across, self.tab_width);
+            if across > cells {
+                return col;
+            }
+        }
+        line.chars().count()
+    }
+
+    /// The (0-indexed) line the cursor is on.
+    pub fn cursor_line(&self) -> usize {
+        self.cursor_position().line
+    }
+
+    /// The character just after the cursor.
+    pub fn char_after_cursor(&self) -> Option<char> {
+        self.text.char_after(self.cursor)
+    }
+
+    /// The character just before the cursor.
+    pub fn char_before_cursor(&self) -> Option<char> {
+        self.text.char_before(self.cursor)
+    }
+
+    /// Moves the cursor back a character.
+    pub fn move_left(&mut self) {
+        if let Some(c) = self.char_before_cursor() {
+            self.cursor -= c.len_utf8();
+        }
+        self.goal_col = None;
+        self.history.seal();
+    }
+
+    /// Moves the cursor forward a character.
+    pub fn move_right(&mut self) {
+        if let Some(c) = self.char_after_cursor() {
+            self.cursor += c.len_utf8();
+        }
+        self.goal_col = None;
+        self.history.seal();
+    }
+
+    /// Moves the cursor up a line, staying in the same column if the
+    /// line above is long enough, or else going to its end.
+    pub fn move_up(&mut self) {
+        if let Some(line) = self.cursor_line().checked_sub(1) {
+            self.move_to_line_keeping_column(line);
+        }
+    }
+
+    /// Moves the cursor down a line, like [`Buffer::move_up`].
+    pub fn move_down(&mut self) {
+        self.move_to_line_keeping_column(self.cursor_line() + 1);
+    }
+
+    /// Moves the cursor `lines` lines down, or up if it's negative, as
+    /// far as the buffer goes, keeping to a column like [`Buffer::move_up`].
+    pub fn move_lines(&mut self, lines: isize) {
+        let last = self.len_lines().saturating_sub(1);
+        let line = self.cursor_line().saturating_add_signed(lines).min(last);
+        self.move_to_line_keeping_column(line);
+    }
+
+    /// Moves the cursor to the first char on its line which isn't a space
+    /// or a tab, or if it's already there, to the very start of the line.
+    pub fn move_home(&mut self) {
+        let line = self.cursor_line();
+        let start = self.line_span(line..line).start;
+        let text = self.line(line).unwrap_or_default();
+        let indent = start + text.len() - text.trim_start_matches([' ', '\t']).len();
+        self.cursor = match self.cursor == indent {
+            true => start,
+            false => indent,
+        };
+        self.goal_col = None;
+        self.history.seal();
+    }
+
+    /// Moves the cursor to the end of its line.
+    pub fn move_end(&mut self) {
+        let line = self.cursor_line();
+        let start = self.line_span(line..line).start;
+        self.cursor = start + self.line(line).map_or(0, str::len);
+        self.goal_col = None;
+        self.history.seal();
+    }
+
+    /// Moves the cursor to `line`, if there is one, in the goal column.
+    fn move_to_line_keeping_column(&mut self, line: usize) {
+        let cells = self
+            .goal_col
+            .unwrap_or_else(|| self.display_col(self.cursor_position()));
+        let col = self.col_at_cells(self.line(line).unwrap_or(""), cells);
+        if let Some(offset) = self.offset_of(Position { line, col }) {
+            self.cursor = offset;
+        }
+        self.goal_col = Some(cells);
+        self.history.seal();
+    }
+
+    /// Starts selecting from the cursor, unless something's already
+    /// being selected.
+    pub fn start_selection(&mut self) {
+        self.anchor.get_or_insert(self.cursor);
+    }
+
+    pub fn clear_selection(&mut self) {
+        self.anchor = None;
+    }
+
+    /// The bytes between where the selection started and the cursor, if
+    /// there are any.
+    pub fn selection(&self) -> Option<Range<usize>> {
+        let anchor = self.anchor?;
+        let range = anchor.min(self.cursor)..anchor.max(self.cursor);
+        Some(range).filter(|range| !range.is_empty())
+    }
+
+    /// Deletes the selection, as a single undo step, and returns what it
+    /// held.
+    pub fn delete_selection(&mut self) -> Option<String> {
+        let range = self.selection()?;
+        let removed = self.text.slice(range.clone());
+        self.replace_range(range, "");
+        Some(removed)
+    }
+
+    /// The (0-indexed) lines the selection is on, or the cursor's line if
+    /// nothing's selected. A selection ending at the very start of a line
+    /// doesn't take that line in.
+    pub fn selected_lines(&self) -> Range<usize> {
+        let Some(range) = self.selection() else {
+            let line = self.cursor_line();
+            return line..line + 1;
+        };
+        let first = self.position_of(range.start).line;
+        let end = self.position_of(range.end);
+        match end.col == 0 && end.line > first {
+            true => first..end.line,
+            false => first..end.line + 1,
+        }
+    }
+
+    /// Swaps the selected lines with the line above them, or below, as a
+    /// single undo step, keeping the same text selected. Returns whether
+    /// there was a line there to swap with.
+    pub fn move_selected_lines(&mut self, up: bool) -> bool {
+        let lines = self.selected_lines();
+        let (span, neighbour) = match up {
+            true if lines.start > 0 => (lines.start - 1..lines.end, lines.start - 1),
+            false if lines.end < self.len_lines() => (lines.start..lines.end + 1, lines.end),
+            _ => return false,
+        };
+        let neighbour_text = String::from(self.line(neighbour).unwrap_or_default());
+        let mut moved: Vec<&str> = lines.clone().filter_map(|line| self.line(line)).collect();
+        match up {
+            true => moved.push(&neighbour_text),
+            false => moved.insert(0, &neighbour_text),
+        }
+        let range = self.line_span(span);
+        let mut text = moved.join("\n");
+        if self.text.char_before(range.end) == Some('\n') {
+            text.push('\n');
+        }
+        let shift = |offset: usize| match up {
+            true => offset - neighbour_text.len() - 1,
+            false => offset + neighbour_text.len() + 1,
+        };
+        let (cursor, anchor) = (shift(self.cursor), self.anchor.map(shift));
+        self.replace_range(range, &text);
+        self.cursor = cursor;
+        self.anchor = anchor;
+        true
+    }
+
+    /// Copies the selected lines to just below them, as a single undo
+    /// step, and moves the cursor and selection onto the copy.
+    pub fn duplicate_selected_lines(&mut self) {
+        let range = self.line_span(self.selected_lines());
+        let mut copy = self.text.slice(range.clone());
+        // The last line has no newline to come after.
+        if !copy.ends_with('\n') {
+            copy.insert(0, '\n');
+        }
+        let shift = |offset: usize| offset + copy.len();
+        let (cursor, anchor) = (shift(self.cursor), self.anchor.map(shift));
+        self.replace_range(range.end..range.end, &copy);
+        self.cursor = cursor;
+        self.anchor = anchor;
+    }
+
+    /// Joins (0-indexed) line `line` and the one after it, with a single
+    /// space between them in place of any spaces either side of the
+    /// newline, as a single undo step. The cursor goes where they meet.
+    /// Returns whether there was a line after to join.
+    pub fn join_line(&mut self, line: usize) -> bool {
+        if line + 1 >= self.len_lines() {
+            return false;
+        }
+        let (first, second) = (
+            self.line(line).unwrap_or_default(),
+            self.line(line + 1).unwrap_or_default(),
+        );
+        let start = self.line_span(line..line).start + first.trim_end().len();
+        let next = self.line_span(line + 1..line + 1).start;
+        let end = next + second.len() - second.trim_start().len();
+        let space = match first.trim().is_empty() || second.trim().is_empty() {
+            true => "",
+            false => " ",
+        };
+        self.replace_range(start..end, space);
+        self.cursor = start;
+        true
+    }
+
+    /// Removes the char after the cursor.
+    pub fn delete_forward(&mut self) -> Option<char> {
+        let c = self.char_after_cursor()?;
+        self.replace_range(self.cursor..self.cursor + c.len_utf8(), "");
+        Some(c)
+    }
+
+    /// Inserts text at the cursor, leaving the cursor after it.
+    pub fn insert(&mut self, text: &str) {
+        self.replace_range(self.cursor..self.cursor, text);
+    }
+
+    /// Adds a char at the cursor. Typing a word this way is undone all
+    /// at once.
+    pub fn insert_char(&mut self, c: char) {
+        let edit = Edit {
+            offset: self.cursor,
+            removed: String::new(),
+            inserted: String::from(c),
+        };
+        self.apply(&edit);
+        self.history.record_typing(edit);
+    }
+
+    /// Removes the char before the cursor. Deleting back through a word
+    /// this way is undone all at once.
+    pub fn delete_back(&mut self) -> Option<char> {
+        let c = self.char_before_cursor()?;
+        let start = self.cursor - c.len_utf8();
+        let edit = Edit {
+            offset: start,
+            removed: self.text.slice(start..self.cursor),
+            inserted: String::new(),
+        };
+        self.apply(&edit);
+        self.history.record_typing(edit);
+        Some(c)
+    }
+
+    /// Pastes `block` as a rectangle: each of its lines is inserted at the
+    /// cursor's column, on consecutive lines starting at the cursor's line.
+    /// Lines that are too short are padded with spaces, and lines are
+    /// added to the end of the buffer if it runs out.
+    pub fn insert_block(&mut self, block: &str) {
+        let Position { line, col } = self.position_of(self.cursor);
+        let rows: Vec<&str> = block.lines().collect();
+        if rows.is_empty() {
+            return;
+        }
+        let span = self.line_span(line..line + rows.len());
+        let region = self.text.slice(span.clone());
+        let existing: Vec<&str> = region.lines().collect();
+
+        let mut pasted = Vec::new();
+        for (i, row) in rows.iter().enumerate() {
+            let existing = existing.get(i).copied().unwrap_or("");
+            let split = existing
+                .char_indices()
+                .nth(col)
+                .map_or(existing.len(), |(i, _)| i);
+            let padding = match row.is_empty() {
+                true => 0,
+                false => col.saturating_sub(existing.chars().count()),
+            };
+            pasted.push(format!(
+                "{}{}{}{}",
+                &existing[..split],
+                " ".repeat(padding),
+                row,
+                &existing[split..]
+            ));
+        }
+        let mut pasted = pasted.join("\n");
+        if region.ends_with('\n') {
+            pasted.push('\n');
+        }
+
+        self.replace_range(span.clone(), &pasted);
+        // Leave the cursor at the top-left corner of what was pasted.
+        let first_row = pasted.lines().next().unwrap_or("");
+        let corner = first_row
+            .char_indices()
+            .nth(col)
+            .map_or(first_row.len(), |(i, _)| i);
+        self.cursor = span.start + corner;
+    }
+
+    /// The line and column of the byte at `offset`.
+    pub fn position_of(&self, offset: usize) -> Position {
+        let line = self.text.line_of(offset);
+        let start = self.text.line_start(line).unwrap_or_default();
+        let text = self.text.line(line).unwrap_or_default();
+        Position {
+            line,
+            col: text[..offset - start].chars().count(),
+        }
+    }
+
+    /// The byte offset of `position`, or `None` if there's no such line,
+    /// or the line isn't that long. The column just past the end of a
+    /// line, where its newline is, counts.
+    pub fn offset_of(&self, position: Position) -> Option<usize> {
+        if position.line >= self.text.lines() {
+            return None;
+        }
+        let start = self.line_span(position.line..position.line).start;
+        let line = self.line(position.line).unwrap_or("");
+        match line.char_indices().nth(position.col) {
+            Some((i, _)) => Some(start + i),
+            None if position.col == line.chars().count() => Some(start + line.len()),
+            None => None,
+        }
+    }
+
+    /// Replaces the bytes in `range` with `text`, as a single undo step.
+    pub fn replace_range(&mut self, range: Range<usize>, text: &str) {
+        let edit = Edit {
+            offset: range.start,
+            removed: self.text.slice(range),
+            inserted: String::from(text),
+        };
+        self.apply(&edit);
+        self.history.record(edit);
+    }
+
+    /// Applies an edit to the text, keeping everything that describes
+    /// the text up to date. All changes to `text` go through here.
+    fn apply(&mut self, edit: &Edit) {
+        let end = edit.offset + edit.removed.len();
+        let (before, after) = (
+            self.text.char_before(edit.offset),
+            self.text.char_after(end),
+        );
+        self.word_count.update(before, after, edit);
+        let first = self.text.line_of(edit.offset);
+        let changed = match edit.removed.contains('\n') || edit.inserted.contains('\n') {
+            true => first..usize::MAX,
+            false => first..first + 1,
+        };
+        self.changed_lines = Some(match self.changed_lines.take() {
+            Some(before) => before.start.min(changed.start)..before.end.max(changed.end),
+            None => changed,
+        });
+
+        self.text.replace(edit.offset..end, &edit.inserted);
+        self.cursor = edit.shift(self.cursor);
+        self.goal_col = None;
+        self.anchor = None;
+        self.revision += 1;
+    }
+
+    /// Replaces all of the text, as a single undo step. Only the part
+    /// that actually changed is recorded.
+    pub fn set_text(&mut self, text: &str) {
+        let old = self.text.text();
+        let prefix = common_prefix(old, text);
+        let suffix = common_suffix(&old[prefix..], &text[prefix..]);
+        if prefix == old.len() && prefix == text.len() {
+            return;
+        }
+        self.replace_range(
+            prefix..old.len() - suffix,
+            &text[prefix..text.len() - suffix],
+        );
+    }
+
+    /// The byte range covering the (0-indexed) lines in `lines`,
+    /// including the newline at the end of each one.
+    pub fn line_span(&self, lines: Range<usize>) -> Range<usize> {
+        let line_start = |line: usize| self.text.line_start(line).unwrap_or(self.text.len());
+        line_start(lines.start)..line_start(lines.end)
+    }
+
+    /// How many chars the buffer has.
+    pub fn len_chars(&self) -> usize {
+        self.word_count.chars
+    }
+
+    /// How many lines the buffer has. A newline at the very end finishes
+    /// the last line, rather than starting another.
+    pub fn len_lines(&self) -> usize {
+        match self.text.char_before(self.text.len()) {
+            None | Some('\n') => self.text.lines() - 1,
+            Some(_) => self.text.lines(),
+        }
+    }
+
+    /// The text of the (0-indexed) line `index`, without its newline
+    /// (which may be `\r\n`).
+    pub fn line(&self, index: usize) -> Option<&str> {
+        if index >= self.len_lines() {
+            return None;
+        }
+        let line = self.text.line(index)?;
+        Some(match line.strip_suffix('\n') {
+            Some(line) => line.strip_suffix('\r').unwrap_or(line),
+            None => line,
+        })
+    }
+
+    /// Every line of the text, without their newlines.
+    pub fn lines(&self) -> impl Iterator<Item = &str> {
+        (0..self.len_lines()).filter_map(|line| self.line(line))
+    }
+
+    /// The char at `position`, or `None` if it's past the end of its line.
+    pub fn char_at(&self, position: Position) -> Option<char> {
+        self.line(position.line)?.chars().nth(position.col)
+    }
+
+    /// Undoes the most recent change. Returns `false` if there was nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        match self.history.undo() {
+            Some(edits) => {
+                edits.iter().for_each(|edit| self.apply(edit));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Redoes the most recently undone change. Returns `false` if there
+    /// was nothing to redo.
+    pub fn redo(&mut self) -> bool {
+        match self.history.redo() {
+            Some(edits) => {
+                edits.iter().for_each(|edit| self.apply(edit));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns the buffer to any state it has been in, by the number
+    /// [`Buffer::undo_tree`] gives it. Returns `false` if there's no
+    /// such state.
+    pub fn undo_to(&mut self, state: usize) -> bool {
+        match self.history.go_to(state) {
+            Some(edits) => {
+                edits.iter().for_each(|edit| self.apply(edit));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Sets how big the undo history can get before its oldest states
+    /// are forgotten, whatever the edits come from.
+    pub fn set_undo_limit(&mut self, limit: usize) {
+        self.history.set_limit(limit);
+    }
+
+    /// Forgets the oldest undo states until the history takes up no more
+    /// than `limit` bytes. Returns how many states were forgotten.
+    pub fn trim_history(&mut self, limit: usize) -> usize {
+        self.history.trim(limit)
+    }
+
+    /// Roughly how much memory the buffer takes up.
+    pub fn memory(&self) -> Memory {
+        let (text, index) = self.text.memory();
+        Memory {
+            text,
+            index,
+            saved_text: self.saved_text.capacity(),
+            history: self.history.bytes(),
+            states: self.history.states(),
+        }
+    }
+
+    /// Every state the buffer has been in, numbered and drawn as a tree.
+    pub fn undo_tree(&self) -> String {
+        self.history.tree()
+    }
+
+    /// How many words and characters are in the buffer.
+    pub fn word_count(&self) -> WordCount {
+        self.word_count
+    }
+
+    /// Whether this buffer holds prose (rather than, say, code), going by
+    /// its file's extension. Buffers without a file are assumed to be prose.
+    pub fn is_prose(&self) -> bool {
+        const PROSE_EXTENSIONS: &[&str] = &["txt", "md", "markdown", "rst", "org", "tex"];
+        match &self.file {
+            Some(_) => self
+                .extension()
+                .is_some_and(|extension| PROSE_EXTENSIONS.contains(&extension)),
+            None => true,
+        }
+    }
+
+    /// The extension of the buffer's file, which is how its filetype is decided.
+    pub fn extension(&self) -> Option<&str> {
+        Path::new(self.file.as_ref()?)
+            .extension()
+            .and_then(|extension| extension.to_str())
+    }
+
+    /// The file this buffer is backed by, if any.
+    pub fn file(&self) -> Option<&str> {
+        self.file.as_deref()
+    }
+
+    /// Changes which file backs this buffer.
+    pub fn set_file(&mut self, file: String) {
+        self.file = Some(file);
+    }
+
+    /// Whether there are edits which haven't made it to the file.
+    pub fn is_dirty(&self) -> bool {
+        self.revision != self.saved_revision
+    }
+
+    /// Notes that the text now matches what's in the file.
+    pub fn mark_saved(&mut self) {
+        self.saved_revision = self.revision;
+        self.saved_text = String::from(self.text.text());
+    }
+
+    /// The text as it was when it was last loaded or saved.
+    pub fn saved_text(&self) -> &str {
+        &self.saved_text
+    }
+
+    /// Notes that the file now holds `text`, without the buffer being
+    /// saved, like after merging in changes made to the file.
+    pub fn set_saved_text(&mut self, text: String) {
+        self.saved_text = text;
+    }
+
+    /// The encoding the file is read from and written back in.
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    pub fn set_encoding(&mut self, encoding: Encoding) {
+        self.encoding = encoding;
+    }
+
+    /// What the file's lines end with.
+    pub fn eol(&self) -> LineEnding {
+        self.eol
+    }
+
+    /// Whether the file's lines ended more than one way when it was
+    /// loaded.
+    pub fn mixed_eol(&self) -> bool {
+        self.mixed_eol
+    }
+
+    /// Changes what the file's lines end with. That changes what's
+    /// written, so the buffer has unsaved changes until it's saved.
+    pub fn set_eol(&mut self, eol: LineEnding) {
+        if eol != self.eol {
+            self.eol = eol;
+            self.revision += 1;
+        }
+    }
+
+    /// The text, as it's written to the file.
+    pub fn encoded(&self) -> Result<Vec<u8>, String> {
+        self.encoding.encode(&self.eol.restore(self.text.text()))
+    }
+
+    /// A number which changes whenever the text, or how it's written, does, so callers can
+    /// cheaply tell whether anything was edited.
+    pub fn revision(&self) -> usize {
+        self.revision
+    }
+}
+
+/// How many cells `c` takes up when it's drawn `cells` across: a tab
+/// reaches the next tab stop, and anything else takes one.
+pub fn cell_width(c: char, cells: usize, tab_width: usize) -> usize {
+    match c {
+        '\t' => tab_width - cells % tab_width,
+        _ => 1,
+    }
+}
+
+/// The length, in bytes, of the longest common prefix of `a` and `b`
+/// that ends on a char boundary.
+fn common_prefix(a: &str, b: &str) -> usize {
+    a.char_indices()
+        .zip(b.chars())
+        .find(|((_, x), y)| x != y)
+        .map(|((i, _), _)| i)
+        .unwrap_or_else(|| a.len().min(b.len()))
+}
+
+/// The length, in bytes, of the longest common suffix of `a` and `b`
+/// that starts on a char boundary.
+fn common_suffix(a: &str, b: &str) -> usize {
+    a.chars()
+        .rev()
+        .zip(b.chars().rev())
+        .take_while(|(x, y)| x == y)
+        .map(|(x, _)| x.len_utf8())
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buffer(text: &str) -> Buffer {
+        Buffer::from_text(None, String::from(text))
+    }
+
+    #[test]
+    fn lines_and_chars_are_counted_without_the_last_newline() {
+        let buffer = buffer("héllo\nwörld\n");
+        assert_eq!(buffer.len_chars(), 12);
+        assert_eq!(buffer.len_lines(), 2);
+        assert_eq!(buffer.line(1), Some("wörld"));
+        assert_eq!(buffer.line(2), None);
+        assert_eq!(buffer.lines().collect::<Vec<_>>(), ["héllo", "wörld"]);
+    }
+
+    #[test]
+    fn chars_are_found_by_line_and_column() {
+        let buffer = buffer("日本\nab");
+        assert_eq!(buffer.char_at(Position { line: 0, col: 1 }), Some('本'));
+        assert_eq!(buffer.char_at(Position { line: 1, col: 0 }), Some('a'));
+        assert_eq!(buffer.char_at(Position { line: 1, col: 2 }), None);
+    }
+
+    #[test]
+    fn trimming_history_keeps_the_latest_states() {
+        let mut buffer = buffer("");
+        for c in "abcdef".chars() {
+            buffer.insert(&String::from(c));
+        }
+        let limit = buffer.memory().history / 2;
+        assert!(buffer.trim_history(limit) > 0);
+        assert!(buffer.memory().history <= limit);
+        assert_eq!(buffer.text(), "abcdef");
+
+        // What's left can still be undone, but not past where it starts.
+        while buffer.undo() {}
+        assert!(!buffer.text().is_empty());
+        while buffer.redo() {}
+        assert_eq!(buffer.text(), "abcdef");
+    }
+
+    #[test]
+    fn states_keep_their_numbers_as_history_over_its_limit_is_forgotten() {
+        let mut buffer = buffer("");
+        buffer.insert("a");
+        let limit = buffer.memory().history * 3;
+        buffer.set_undo_limit(limit);
+        for c in "bcdefghij".chars() {
+            buffer.insert(&String::from(c));
+            assert!(buffer.memory().history <= limit);
+        }
+        let tree = buffer.undo_tree();
+        assert!(!tree.contains(" 0: original"));
+        assert!(tree.ends_with("* 10: inserted \"j\"\n"));
+
+        assert!(buffer.undo_to(8));
+        assert_eq!(buffer.text(), "abcdefgh");
+        assert!(!buffer.undo_to(1));
+        assert!(buffer.undo_to(10));
+        assert_eq!(buffer.text(), "abcdefghij");
+    }
+
+    #[test]
+    fn typing_is_undone_a_word_at_a_time() {
+        let mut buffer = buffer("");
+        for c in "hello world".chars() {
+            buffer.insert_char(c);
+        }
+        assert!(buffer.undo());
+        assert_eq!(buffer.text(), "hello ");
+        assert!(buffer.undo());
+        assert_eq!(buffer.text(), "");
+
+        // Moving the cursor starts a new step, even mid-word.
+        for c in "abc".chars() {
+            buffer.insert_char(c);
+        }
+        buffer.move_left();
+        buffer.move_right();
+        buffer.insert_char('d');
+        assert!(buffer.undo());
+        assert_eq!(buffer.text(), "abc");
+        assert!(buffer.undo());
+        assert_eq!(buffer.text(), "");
+        assert!(buffer.redo());
+        assert_eq!(buffer.text(), "abc");
+
+        buffer.set_cursor(3);
+        buffer.delete_back();
+        buffer.delete_back();
+        assert!(buffer.undo());
+        assert_eq!(buffer.text(), "abc");
+    }
+
+    #[test]
+    fn up_and_down_keep_to_a_column() {
+        let mut buffer = buffer("long line\nab\nanother line");
+        buffer.set_cursor(6);
+        buffer.move_down();
+        assert_eq!(buffer.cursor_position(), Position { line: 1, col: 2 });
+        buffer.move_down();
+        assert_eq!(buffer.cursor_position(), Position { line: 2, col: 6 });
+        buffer.move_down();
+        assert_eq!(buffer.cursor_position(), Position { line: 2, col: 6 });
+
+        // Typing is at the cursor, and sets a new column to keep to.
+        buffer.move_up();
+        buffer.insert_char('c');
+        assert_eq!(buffer.text(), "long line\nabc\nanother line");
+        buffer.move_up();
+        assert_eq!(buffer.cursor_position(), Position { line: 0, col: 3 });
+    }
+
+    #[test]
+    fn home_goes_to_the_indent_and_then_the_start() {
+        let mut buffer = buffer("    let x;\nnext");
+        buffer.set_cursor(7);
+        buffer.move_home();
+        assert_eq!(buffer.cursor(), 4);
+        buffer.move_home();
+        assert_eq!(buffer.cursor(), 0);
+        buffer.move_home();
+        assert_eq!(buffer.cursor(), 4);
+        buffer.move_end();
+        assert_eq!(buffer.cursor(), 10);
+
+        buffer.move_lines(5);
+        assert_eq!(buffer.cursor_position(), Position { line: 1, col: 4 });
+        buffer.move_lines(-5);
+        assert_eq!(buffer.cursor_position(), Position { line: 0, col: 10 });
+    }
+
+    #[test]
+    fn lines_move_and_duplicate_with_the_selection() {
+        let mut buffer = buffer("one\ntwo\nthree");
+        buffer.set_cursor(5);
+        assert!(buffer.move_selected_lines(true));
+        assert_eq!(buffer.text(), "two\none\nthree");
+        assert_eq!(buffer.cursor(), 1);
+        assert!(!buffer.move_selected_lines(true));
+
+        // Down past the last line, which has no newline.
+        buffer.start_selection();
+        buffer.move_down();
+        assert_eq!(buffer.selected_lines(), 0..2);
+        assert!(buffer.move_selected_lines(false));
+        assert_eq!(buffer.text(), "three\ntwo\none");
+        assert_eq!(buffer.selection(), Some(7..11));
+        assert!(!buffer.move_selected_lines(false));
+
+        buffer.clear_selection();
+        buffer.duplicate_selected_lines();
+        assert_eq!(buffer.text(), "three\ntwo\none\none");
+        assert_eq!(buffer.cursor_position(), Position { line: 3, col: 1 });
+        buffer.undo();
+        assert_eq!(buffer.text(), "three\ntwo\none");
+    }
+
+    #[test]
+    fn joined_lines_meet_at_a_single_space() {
+        let mut buffer = buffer("one  \n   two\n\nthree");
+        assert!(buffer.join_line(0));
+        assert_eq!(buffer.text(), "one two\n\nthree");
+        assert_eq!(buffer.cursor(), 3);
+        // There's no space to put before an empty line.
+        assert!(buffer.join_line(0));
+        assert_eq!(buffer.text(), "one two\nthree");
+        assert!(!buffer.join_line(1));
+    }
+
+    #[test]
+    fn tabs_reach_the_next_tab_stop() {
+        let mut buffer = buffer("\tab\n12345\na\tb");
+        assert_eq!(buffer.display_col(Position { line: 0, col: 1 }), 4);
+        assert_eq!(buffer.display_col(Position { line: 2, col: 2 }), 4);
+
+        // Up and Down keep to where the cursor's drawn, not how many
+        // chars along it is.
+        buffer.set_cursor(buffer.offset_of(Position { line: 1, col: 5 }).unwrap());
+        buffer.move_up();
+        assert_eq!(buffer.cursor_position(), Position { line: 0, col: 2 });
+        buffer.move_down();
+        buffer.move_down();
+        assert_eq!(buffer.cursor_position(), Position { line: 2, col: 3 });
+        // Partway across a tab is on the tab.
+        buffer.set_cursor(buffer.offset_of(Position { line: 1, col: 2 }).unwrap());
+        buffer.move_up();
+        assert_eq!(buffer.cursor_position(), Position { line: 0, col: 0 });
+    }
+
+    #[test]
+    fn positions_and_offsets_convert_both_ways() {
+        let buffer = buffer("日本\nab\n");
+        for offset in [0, 3, 6, 7, 8, 9] {
+            let position = buffer.position_of(offset);
+            assert_eq!(buffer.offset_of(position), Some(offset), "{:?}", position);
+        }
+        assert_eq!(buffer.position_of(7), Position { line: 1, col: 0 });
+        assert_eq!(buffer.offset_of(Position { line: 1, col: 3 }), None);
+        assert_eq!(buffer.offset_of(Position { line: 3, col: 0 }), None);
+    }
+}
@@ -0,0 +1,202 @@
+//! Changing the case of the selection, or the word under the cursor if
+//! nothing's selected, from inside the editor or with `case` at the `>>`
+//! prompt.
+
+use std::ops::Range;
+
+use crate::cursors::word_at;
+use crate::BufferEditor;
+
+/// A way of changing the case of some text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Case {
+    Upper,
+    Lower,
+    /// A capital at the start of each word, and the rest lower case.
+    Title,
+    /// Upper case letters to lower case, and lower to upper.
+    Toggle,
+    /// `camelCase` to `snake_case`.
+    Snake,
+    /// `snake_case` to `camelCase`.
+    Camel,
+}
+
+/// Every case, as `case` calls them.
+pub const CASES: &[(Case, &str)] = &[
+    (Case::Upper, "upper"),
+    (Case::Lower, "lower"),
+    (Case::Title, "title"),
+    (Case::Toggle, "toggle"),
+    (Case::Snake, "snake"),
+    (Case::Camel, "camel"),
+];
+
+impl Case {
+    pub fn name(self) -> &'static str {
+        CASES
+            .iter()
+            .find(|(case, _)| *case == self)
+            .map_or("upper", |(_, name)| name)
+    }
+
+    pub fn by_name(name: &str) -> Option<Case> {
+        CASES
+            .iter()
+            .find(|(_, other)| *other == name)
+            .map(|(case, _)| *case)
+    }
+
+    /// `text` in this case.
+    pub fn apply(self, text: &str) -> String {
+        match self {
+            Case::Upper => text.to_uppercase(),
+            Case::Lower => text.to_lowercase(),
+            Case::Title => title_case(text),
+            Case::Toggle => text
+                .chars()
+                .flat_map(|c| match c.is_uppercase() {
+                    true => c.to_lowercase().collect::<Vec<_>>(),
+                    false => c.to_uppercase().collect(),
+                })
+                .collect(),
+            Case::Snake => snake_case(text),
+            Case::Camel => camel_case(text),
+        }
+    }
+}
+
+fn title_case(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut in_word = false;
+    for c in text.chars() {
+        match in_word {
+            true => result.extend(c.to_lowercase()),
+            false => result.extend(c.to_uppercase()),
+        }
+        in_word = c.is_alphanumeric() || c == '\'';
+    }
+    result
+}
+
+/// Puts an underscore wherever a capital starts a new word, and makes
+/// it lower case, so `parseHTTPRequest` is `parse_http_request`.
+fn snake_case(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut result = String::with_capacity(text.len() + 4);
+    for (i, &c) in chars.iter().enumerate() {
+        let before = i.checked_sub(1).map(|i| chars[i]);
+        let after = chars.get(i + 1);
+        let starts_word = c.is_uppercase()
+            && before.is_some_and(|before| {
+                before.is_lowercase()
+                    || before.is_ascii_digit()
+                    || (before.is_uppercase() && after.is_some_and(|after| after.is_lowercase()))
+            });
+        if starts_word {
+            result.push('_');
+        }
+        result.extend(c.to_lowercase());
+    }
+    result
+}
+
+/// Takes out the underscores between words, and starts each word after
+/// the first with a capital, so `parse_http_request` is
+/// `parseHttpRequest`. Underscores at the start are left where they are.
+fn camel_case(text: &str) -> String {
+    let body = text.trim_start_matches('_');
+    let mut result = String::from(&text[..text.len() - body.len()]);
+    for (i, word) in body.split('_').filter(|word| !word.is_empty()).enumerate() {
+        let mut chars = word.chars();
+        match (i, chars.next()) {
+            (0, _) => result.push_str(word),
+            (_, Some(first)) => {
+                result.extend(first.to_uppercase());
+                result.push_str(chars.as_str());
+            }
+            (_, None) => {}
+        }
+    }
+    result
+}
+
+impl BufferEditor {
+    /// What changing case works on: the selection, or else the word under
+    /// the cursor.
+    fn case_range(&self) -> Option<Range<usize>> {
+        self.buffer
+            .selection()
+            .or_else(|| word_at(self.buffer.text(), self.buffer.cursor()))
+    }
+
+    /// Changes the case of the selection, keeping it selected, or else of
+    /// the word under the cursor. Returns whether there was anything to
+    /// change.
+    pub fn change_case(&mut self, case: Case) -> bool {
+        let Some(range) = self.case_range() else {
+            return false;
+        };
+        let selected = self.buffer.selection().is_some();
+        let cursor = self.buffer.cursor();
+        let text = case.apply(&self.buffer.text()[range.clone()]);
+        if text != self.buffer.text()[range.clone()] {
+            self.buffer.replace_range(range.clone(), &text);
+        }
+        let end = range.start + text.len();
+        match selected {
+            true => {
+                self.buffer.set_cursor(range.start);
+                self.buffer.start_selection();
+                self.buffer.set_cursor(end);
+            }
+            false => self.buffer.set_cursor(cursor.min(end)),
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use termgame::KeyCode;
+
+    use crate::harness::{alt, shift_key, Harness};
+
+    #[test]
+    fn text_changes_case() {
+        assert_eq!(Case::Title.apply("the CAT's hat"), "The Cat's Hat");
+        assert_eq!(Case::Toggle.apply("Hello"), "hELLO");
+        assert_eq!(
+            Case::Snake.apply("parseHTTPRequest2Go"),
+            "parse_http_request2_go"
+        );
+        assert_eq!(
+            Case::Camel.apply("_parse_http_request"),
+            "_parseHttpRequest"
+        );
+        assert_eq!(Case::Snake.apply("already_snake"), "already_snake");
+    }
+
+    #[test]
+    fn alt_keys_change_the_case_of_the_selection_or_word() {
+        let mut harness = Harness::new("make_it_camel and shout");
+        harness.press(KeyCode::Right).run([alt('m')]);
+        assert_eq!(harness.text(), "makeItCamel and shout");
+        assert_eq!(harness.editor.buffer.cursor(), 1);
+
+        for _ in 0..3 {
+            harness.press(KeyCode::Right);
+        }
+        harness.run([alt('s')]);
+        assert_eq!(harness.text(), "make_it_camel and shout");
+
+        harness.editor.buffer.set_cursor(18);
+        harness
+            .run([shift_key(KeyCode::Right), shift_key(KeyCode::Right)])
+            .run([alt('u')]);
+        assert_eq!(harness.text(), "make_it_camel and SHout");
+        assert_eq!(harness.editor.buffer.selection(), Some(18..20));
+    }
+}
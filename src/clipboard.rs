@@ -0,0 +1,254 @@
+//! BuffeRS's own clipboard, shared between every buffer. It remembers
+//! what was copied before, so older clips can be found with `clips` and
+//! pasted again.
+//!
+//! What's copied and cut in the editor goes on the system clipboard as
+//! well, and anything copied in another program since is what Ctrl+V
+//! pastes.
+
+use std::ops::Range;
+
+/// How many clips are remembered, not counting pinned ones, which are
+/// kept however old they get.
+pub const HISTORY: usize = 50;
+
+/// Something which has been copied, ready to be pasted.
+#[derive(Debug, Clone)]
+pub struct Clip {
+    pub text: String,
+    /// Whether this was copied from a block (rectangular) selection,
+    /// in which case it's pasted as a rectangle too.
+    pub block: bool,
+    /// Whether the clip is kept even once it's old.
+    pub pinned: bool,
+}
+
+impl Clip {
+    pub fn new(text: String) -> Clip {
+        Clip {
+            text,
+            block: false,
+            pinned: false,
+        }
+    }
+
+    /// Copies the rectangle of `text` covering the (0-indexed) lines in
+    /// `lines` and the char columns in `columns`. Lines which are too
+    /// short to reach the rectangle contribute what they can.
+    pub fn block(text: &str, lines: Range<usize>, columns: Range<usize>) -> Clip {
+        let rows: Vec<String> = text
+            .lines()
+            .skip(lines.start)
+            .take(lines.len())
+            .map(|line| {
+                line.chars()
+                    .skip(columns.start)
+                    .take(columns.len())
+                    .collect()
+            })
+            .collect();
+        Clip {
+            text: rows.join("\n"),
+            block: true,
+            pinned: false,
+        }
+    }
+
+    /// The clip's first line, cut down to `width` chars, with how many
+    /// more lines there are, for listing the clip in one line.
+    pub fn preview(&self, width: usize) -> String {
+        let mut lines = self.text.lines();
+        let first = lines.next().unwrap_or("");
+        let mut preview: String = first.chars().take(width).collect();
+        if first.chars().count() > width {
+            preview.push_str("...");
+        }
+        match lines.count() {
+            0 => preview,
+            more => format!("{} (+{} lines)", preview, more),
+        }
+    }
+}
+
+/// Everything that's been copied, newest first. The newest is what
+/// Ctrl+V pastes.
+#[derive(Debug, Clone, Default)]
+pub struct Clipboard {
+    clips: Vec<Clip>,
+}
+
+impl Clipboard {
+    /// Adds `clip` as the newest. If the same text was copied before, it
+    /// moves up rather than being in the history twice.
+    pub fn copy(&mut self, mut clip: Clip) {
+        if let Some(i) = self
+            .clips
+            .iter()
+            .position(|old| old.text == clip.text && old.block == clip.block)
+        {
+            clip.pinned |= self.clips.remove(i).pinned;
+        }
+        self.clips.insert(0, clip);
+
+        let mut unpinned = 0;
+        self.clips.retain(|clip| {
+            unpinned += usize::from(!clip.pinned);
+            clip.pinned || unpinned <= HISTORY
+        });
+    }
+
+    /// What Ctrl+V pastes.
+    pub fn current(&self) -> Option<&Clip> {
+        self.clips.first()
+    }
+
+    pub fn clips(&self) -> &[Clip] {
+        &self.clips
+    }
+
+    /// Makes the clip at `index` the newest, so it's what's pasted.
+    pub fn select(&mut self, index: usize) -> Option<&Clip> {
+        if index >= self.clips.len() {
+            return None;
+        }
+        let clip = self.clips.remove(index);
+        self.clips.insert(0, clip);
+        self.clips.first()
+    }
+
+    /// Pins or unpins the clip at `index`. Returns `None` if there's no
+    /// such clip.
+    pub fn set_pinned(&mut self, index: usize, pinned: bool) -> Option<()> {
+        self.clips.get_mut(index)?.pinned = pinned;
+        Some(())
+    }
+
+    /// Forgets every clip which isn't pinned.
+    pub fn clear(&mut self) {
+        self.clips.retain(|clip| clip.pinned);
+    }
+}
+
+/// The operating system's clipboard, opened the first time it's needed.
+/// If it can't be, like when there's no display, BuffeRS gets by with its
+/// own clipboard alone.
+#[derive(Default)]
+pub struct SystemClipboard {
+    clipboard: Option<arboard::Clipboard>,
+    unavailable: bool,
+    /// What was last put on the clipboard from here, or found on it, so
+    /// text which has only been left there isn't taken for something new.
+    seen: Option<String>,
+    /// Stands in for the system's clipboard, in tests.
+    #[cfg(any(test, feature = "test-harness"))]
+    memory: Option<String>,
+}
+
+impl SystemClipboard {
+    /// A clipboard which is never opened, so tests don't touch the real
+    /// one.
+    #[cfg(any(test, feature = "test-harness"))]
+    pub fn unavailable() -> SystemClipboard {
+        SystemClipboard {
+            unavailable: true,
+            ..SystemClipboard::default()
+        }
+    }
+
+    /// A clipboard kept in memory, for tests of what other programs
+    /// copy.
+    #[cfg(test)]
+    pub fn in_memory(text: &str) -> SystemClipboard {
+        SystemClipboard {
+            memory: Some(String::from(text)),
+            ..SystemClipboard::unavailable()
+        }
+    }
+
+    fn open(&mut self) -> Option<&mut arboard::Clipboard> {
+        if self.clipboard.is_none() && !self.unavailable {
+            match arboard::Clipboard::new() {
+                Ok(clipboard) => self.clipboard = Some(clipboard),
+                Err(err) => {
+                    tracing::warn!(error = %err, "couldn't open the system clipboard");
+                    self.unavailable = true;
+                }
+            }
+        }
+        self.clipboard.as_mut()
+    }
+
+    /// Puts `text` on the system clipboard, if there is one.
+    pub fn set(&mut self, text: &str) {
+        self.seen = Some(String::from(text));
+        #[cfg(any(test, feature = "test-harness"))]
+        if let Some(memory) = &mut self.memory {
+            *memory = String::from(text);
+        }
+        if let Some(clipboard) = self.open() {
+            if let Err(err) = clipboard.set_text(text) {
+                tracing::warn!(error = %err, "couldn't copy to the system clipboard");
+            }
+        }
+    }
+
+    /// The text on the system clipboard, if there's any.
+    fn text(&mut self) -> Option<String> {
+        #[cfg(any(test, feature = "test-harness"))]
+        if let Some(memory) = &self.memory {
+            return Some(memory.clone());
+        }
+        self.open()?.get_text().ok()
+    }
+
+    /// The text on the system clipboard, if it's changed since it was
+    /// last set or looked at, like when another program has copied
+    /// something.
+    pub fn changed(&mut self) -> Option<String> {
+        let text = self.text()?;
+        if self.seen.as_ref() == Some(&text) {
+            return None;
+        }
+        self.seen = Some(text.clone());
+        Some(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(clipboard: &Clipboard) -> Vec<&str> {
+        clipboard
+            .clips()
+            .iter()
+            .map(|clip| clip.text.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn copying_again_moves_a_clip_up() {
+        let mut clipboard = Clipboard::default();
+        for text in ["a", "b", "a"] {
+            clipboard.copy(Clip::new(String::from(text)));
+        }
+        assert_eq!(texts(&clipboard), ["a", "b"]);
+        clipboard.select(1);
+        assert_eq!(clipboard.current().unwrap().text, "b");
+    }
+
+    #[test]
+    fn pinned_clips_outlive_the_history() {
+        let mut clipboard = Clipboard::default();
+        clipboard.copy(Clip::new(String::from("keep")));
+        clipboard.set_pinned(0, true);
+        for i in 0..HISTORY + 5 {
+            clipboard.copy(Clip::new(i.to_string()));
+        }
+        assert_eq!(clipboard.clips().len(), HISTORY + 1);
+        assert_eq!(clipboard.clips().last().unwrap().text, "keep");
+
+        clipboard.clear();
+        assert_eq!(texts(&clipboard), ["keep"]);
+    }
+}
@@ -0,0 +1,86 @@
+//! The time, as far as tick-driven things like snapshots, file watching
+//! and typing stats are concerned.
+//!
+//! Normally that's just the time on the wall, but it can be switched to
+//! a fixed clock, which only moves on by the same amount each tick. Then
+//! a scripted session does exactly the same thing however fast or slow
+//! it's run, which is what recording a demo (or testing) needs.
+
+use std::time::{Duration, Instant};
+
+/// How often the editor ticks, unless `tick_ms` in the config says
+/// otherwise.
+pub const TICK: Duration = Duration::from_millis(25);
+
+/// Where the time comes from. Times are measured from when the clock
+/// was started.
+#[derive(Debug, Clone)]
+pub enum Clock {
+    /// Real time, `offset` on from `started`.
+    Wall { started: Instant, offset: Duration },
+    /// Time which stands still, except for going on by `tick` each tick.
+    Fixed { now: Duration, tick: Duration },
+}
+
+impl Default for Clock {
+    fn default() -> Clock {
+        Clock::wall(Duration::ZERO)
+    }
+}
+
+impl Clock {
+    /// A wall clock, which starts out reading `now`.
+    pub fn wall(now: Duration) -> Clock {
+        Clock::Wall {
+            started: Instant::now(),
+            offset: now,
+        }
+    }
+
+    /// A fixed clock, which starts out reading `now` and goes on by
+    /// `tick` each tick.
+    pub fn fixed(now: Duration, tick: Duration) -> Clock {
+        Clock::Fixed { now, tick }
+    }
+
+    /// The time now.
+    pub fn now(&self) -> Duration {
+        match self {
+            Clock::Wall { started, offset } => *offset + started.elapsed(),
+            Clock::Fixed { now, .. } => *now,
+        }
+    }
+
+    /// Called each tick. A wall clock doesn't need telling.
+    pub fn tick(&mut self) {
+        if let Clock::Fixed { now, tick } = self {
+            *now += *tick;
+        }
+    }
+
+    /// What kind of clock this is, as `clock` shows it.
+    pub fn describe(&self) -> String {
+        match self {
+            Clock::Wall { .. } => String::from("The clock follows the time on the wall"),
+            Clock::Fixed { tick, .. } => format!(
+                "The clock is fixed, going on {}ms each tick",
+                tick.as_millis()
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_fixed_clock_only_moves_on_ticks() {
+        let mut clock = Clock::fixed(Duration::from_secs(1), TICK);
+        assert_eq!(clock.now(), Duration::from_secs(1));
+        for _ in 0..4 {
+            clock.tick();
+        }
+        assert_eq!(clock.now(), Duration::from_millis(1100));
+    }
+}
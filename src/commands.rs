@@ -0,0 +1,3576 @@
+//! Everything that can be typed at the `>>` prompt.
+//!
+//! Commands live in a [`Registry`], so adding one is just a matter of
+//! calling [`Registry::register`] rather than growing a `match`.
+
+use regex::RegexBuilder;
+use termgame::{run_game, GameEvent, GameSettings, KeyCode, SimpleEvent, ViewportLocation};
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::io::{self, ErrorKind, Write};
+use std::mem;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use crate::announce::Announcer;
+use crate::autosave;
+use crate::buffer::Buffer;
+use crate::case::{Case, CASES};
+use crate::clipboard::{Clip, Clipboard};
+use crate::clock::Clock;
+use crate::config;
+use crate::diff;
+use crate::dired::Directory;
+use crate::encoding::{Encoding, ENCODINGS};
+use crate::eol::LineEnding;
+use crate::goto;
+use crate::help::Help;
+use crate::i18n::{self, tr};
+use crate::keymap::Keymap;
+use crate::lines::{self, DedupMode, SortOptions};
+use crate::lock::{Lock, LockError};
+use crate::logging;
+use crate::metrics::Metrics;
+use crate::paths;
+use crate::pipe;
+use crate::play::Playback;
+use crate::project;
+use crate::recent;
+use crate::record::{self, Recording};
+use crate::registers::Registers;
+use crate::replace::{self, ReplaceOptions};
+use crate::search;
+use crate::sessions::{self, SavedBuffer, SavedSession};
+use crate::snapshot;
+use crate::split::Split;
+use crate::stats::TypingStats;
+use crate::stress;
+use crate::swap::{self, Swap};
+use crate::tabs::Tabs;
+use crate::theme::{Theme, THEMES};
+use crate::transform::{self, Transform};
+use crate::trash;
+use crate::units::size;
+use crate::vim::EditingMode;
+use crate::watch::FileWatch;
+use crate::{App, BufferEditor};
+
+/// What every command is given: the state of the whole program, and
+/// whatever was typed after the command's name.
+pub type CommandFn = dyn Fn(&mut App, &str) -> Result<(), Box<dyn Error>>;
+
+/// A single command that can be run from the REPL.
+pub struct Command {
+    run: Box<CommandFn>,
+    /// What `help` says about it.
+    help: &'static Help,
+}
+
+/// All the commands the REPL knows about, by name.
+pub struct Registry {
+    commands: HashMap<String, Command>,
+}
+
+impl Registry {
+    /// Creates a registry with no commands in it.
+    pub fn new() -> Registry {
+        Registry {
+            commands: HashMap::new(),
+        }
+    }
+
+    /// Creates a registry containing every command that ships with BuffeRS.
+    pub fn with_builtins() -> Registry {
+        let mut registry = Registry::new();
+        registry.register_strings(STRINGS);
+        registry.register(&HELP, show_help);
+        registry.register(&SOURCE, source_script);
+        registry.register(&OPEN, open_editor);
+        registry.register(&SESSION, manage_sessions);
+        registry.register(&SEARCH, search_buffers);
+        registry.register(&NOHL, clear_highlights);
+        registry.register(&DEDUP, |app, args| dedup_lines(app, args, &DEDUP));
+        registry.register(&UNIQ, |app, args| dedup_lines(app, args, &UNIQ));
+        registry.register(&SORT, sort_lines);
+        registry.register(&REVERSE, reverse_lines);
+        registry.register(&CASE, change_case);
+        registry.register(&FILTER, filter_lines);
+        registry.register(&PIPE, pipe_buffer);
+        registry.register(&INSERT, insert_output);
+        registry.register(&BLAME, show_blame);
+        registry.register(&JOIN, join_lines);
+        registry.register(&REFLOW, reflow_paragraph);
+        registry.register(&JUMP, jump_to_line);
+        registry.register(&GOTO, goto_line);
+        registry.register(&STATS, show_stats);
+        registry.register(&CLOCK, set_clock);
+        registry.register(&METRICS, show_metrics);
+        registry.register(&LOG, show_log);
+        registry.register(&DASHBOARD, open_dashboard);
+        registry.register(&TUTOR, open_tutor);
+        registry.register(&RECORD, record_session);
+        registry.register(&ANNOUNCE, set_announce);
+        registry.register(&KEYS, show_keys);
+        registry.register(&THEME, set_theme);
+        registry.register(&SET, set_option);
+        registry.register(&ALIAS, set_alias);
+        registry.register(&UNALIAS, remove_alias);
+        registry.register(&WRAP, set_wrap);
+        registry.register(&PLAY, play_recording);
+        registry.register(&COPY_BLOCK, copy_block);
+        registry.register(&CLIPS, show_clips);
+        registry.register(&REGISTERS, show_registers);
+        registry.register(&PASTE, paste_register);
+        registry.register(&WRITE, write_buffer);
+        registry.register(&DELETEFILE, delete_file);
+        registry.register(&MOVE, move_file);
+        registry.register(&LS, list_buffers);
+        registry.register(&CLOSE, close_buffer);
+        registry.register(&QUIT, quit);
+        registry.register(&RENAME, rename_buffer);
+        registry.register(&MERGE, merge_file);
+        registry.register(&RECOVER, recover_buffer);
+        registry.register(&TIMELINE, show_timeline);
+        registry.register(&AUTOREVERT, set_auto_revert);
+        registry.register(&ENCODING, set_encoding);
+        registry.register(&REPLACE, replace_text);
+        registry.register(&REPLACE_ALL, replace_in_project);
+        registry.register(&APPLY_REPLACE, apply_replacements);
+        registry.register(&UNDO, |app, args| {
+            match editable_editor(&mut app.editors, first_word(args))?
+                .buffer
+                .undo()
+            {
+                true => println!("{}", tr("undo.done", &[])),
+                false => println!("{}", tr("editor.nothing-to-undo", &[])),
+            }
+            Ok(())
+        });
+        registry.register(&REDO, |app, args| {
+            match editable_editor(&mut app.editors, first_word(args))?
+                .buffer
+                .redo()
+            {
+                true => println!("{}", tr("redo.done", &[])),
+                false => println!("{}", tr("editor.nothing-to-redo", &[])),
+            }
+            Ok(())
+        });
+        registry.register(&UNDO_TREE, show_undo_tree);
+        registry.register(&MEMORY, show_memory);
+        registry.register(&STRESS, run_stress);
+        registry.register(&UNDO_TO, |app, args| {
+            let (name, state) = match args.split_whitespace().collect::<Vec<_>>()[..] {
+                [name, state] => (name, state.parse::<usize>()?),
+                _ => return Err(usage("undo-to <buffer> <state>")),
+            };
+            match editable_editor(&mut app.editors, name)?
+                .buffer
+                .undo_to(state)
+            {
+                true => println!("{}", tr("undo-to.moved", &[("state", &state)])),
+                false => println!(
+                    "{}",
+                    tr("undo-to.no-state", &[("name", &name), ("state", &state)])
+                ),
+            }
+            Ok(())
+        });
+        for (help, transform) in transform::BUILTIN {
+            registry.register_transform(help, *transform);
+        }
+        registry
+    }
+
+    /// Adds a command, called what `help` names it, replacing any
+    /// existing command with the same name.
+    pub fn register<F>(&mut self, help: &'static Help, run: F)
+    where
+        F: Fn(&mut App, &str) -> Result<(), Box<dyn Error>> + 'static,
+    {
+        let command = Command {
+            run: Box::new(run),
+            help,
+        };
+        i18n::register(&help.about_key(), help.about);
+        self.commands.insert(String::from(help.name), command);
+    }
+
+    /// Adds strings for commands to show, in English, which translations
+    /// can then replace. See [`i18n::register`].
+    pub fn register_strings(&mut self, strings: &[(&str, &str)]) {
+        for (key, english) in strings {
+            i18n::register(key, english);
+        }
+    }
+
+    /// Adds a command which runs `transform` over a buffer, i.e.
+    /// `<name> <buffer>`.
+    pub fn register_transform(&mut self, help: &'static Help, transform: Transform) {
+        self.register(help, move |app, args| {
+            apply_transform(&mut app.editors, args, help, transform)
+        });
+    }
+
+    /// The name of every command.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.commands.keys().map(String::as_str)
+    }
+
+    /// What `help` says about the command called `name`.
+    pub fn help(&self, name: &str) -> Option<&'static Help> {
+        self.commands.get(name).map(|command| command.help)
+    }
+
+    /// Finds the command with the given name.
+    pub fn get(&self, name: &str) -> Option<&Command> {
+        self.commands.get(name)
+    }
+}
+
+/// What `help` says about the commands [`Registry::with_builtins`]
+/// writes out in place.
+const UNDO: Help = Help {
+    name: "undo",
+    usage: &["undo <buffer>"],
+    about: "Undoes the last change to `buffer`.",
+    examples: &["undo notes"],
+};
+
+const REDO: Help = Help {
+    name: "redo",
+    usage: &["redo <buffer>"],
+    about: "Makes the last change to `buffer` which was undone again.",
+    examples: &["redo notes"],
+};
+
+const UNDO_TO: Help = Help {
+    name: "undo-to",
+    usage: &["undo-to <buffer> <state>"],
+    about: "Puts `buffer` back in state `state` of its undo tree, as `undo-tree` numbers them, going back and forward through undo and redo to get there.",
+    examples: &["undo-to notes 12"],
+};
+
+/// What the built-in commands say, in English. They're registered with
+/// the catalog the same way any other commands' strings would be.
+const STRINGS: &[(&str, &str)] = &[
+    (
+        "stats.off",
+        "Typing stats are off, turn them on with `stats typing on`",
+    ),
+    ("stats.on", "Collecting typing stats"),
+    ("stats.stopped", "No longer collecting typing stats"),
+    (
+        "metrics.off",
+        "Usage metrics are off, turn them on with `metrics = true` in config.toml",
+    ),
+    ("metrics.exported", "Wrote the usage metrics to {path}"),
+    ("metrics.reset", "Reset the usage metrics"),
+    ("record.already", "Already recording to {path}"),
+    ("record.started", "Recording to {path}"),
+    ("record.saved", "Saved the recording to {path}"),
+    ("record.not-recording", "Not recording"),
+    ("announce.on", "Announcing to {target}"),
+    ("announce.off", "Not announcing"),
+    (
+        "help.commands",
+        "Commands (`help <command>` says more about one):",
+    ),
+    ("help.examples", "Examples:"),
+    ("repl.usage", "usage: {usage}"),
+    ("repl.no-option", "{command} has no {option} option"),
+    ("undo.done", "Undone"),
+    ("redo.done", "Redone"),
+    ("undo-to.moved", "Moved to state {state}"),
+    ("undo-to.no-state", "{name} has no state {state}"),
+    ("help.alias", "{name} is an alias for `{expansion}`"),
+    (
+        "help.no-command",
+        "there's no {name} command; `help` lists them",
+    ),
+    ("source.already", "{path} is already being sourced"),
+    ("source.no-command", "there's no {name} command"),
+    ("source.failed", "{path}:{line}: {error}"),
+    ("source.failures", "{count} command(s) in {path} failed"),
+    (
+        "open.read-only-steal",
+        "--read-only and --steal don't go together",
+    ),
+    ("open.no-matches", "Nothing matches {pattern}"),
+    (
+        "open.confirm",
+        "Open all {count} files matching {pattern}? (y/n)",
+    ),
+    ("open.nothing", "Nothing opened"),
+    (
+        "open.matching",
+        "Opened {opened} buffer(s) for the {count} file(s) matching {pattern}",
+    ),
+    ("session.none", "No saved sessions"),
+    (
+        "session.saved",
+        "Saved {count} buffer(s) as the session {name}",
+    ),
+    ("session.already-open", "{name} is already open"),
+    (
+        "session.loaded",
+        "Loaded {count} buffer(s) from the session {name}",
+    ),
+    ("session.deleted", "Deleted the session {name}"),
+    ("repl.no-buffer", "No buffer named '{name}'"),
+    (
+        "session.locked",
+        "{name} is locked, {error}, so it's read-only",
+    ),
+    ("open.split-itself", "{name} can't be split with itself"),
+    ("search.invalid", "invalid pattern: {error}"),
+    ("dedup.removed", "Removed {count} duplicate line(s)"),
+    ("sort.sorted", "Sorted {count} line(s)"),
+    ("reverse.reversed", "Reversed {count} line(s)"),
+    (
+        "case.no-word",
+        "nothing's selected in {name}, and its cursor isn't on a word",
+    ),
+    ("case.changed", "Changed to {case} case"),
+    (
+        "join.no-line",
+        "{name} has no line {line} with one after it to join",
+    ),
+    ("join.joined", "Joined lines {line} and {next}"),
+    ("reflow.zero-width", "the width has to be at least 1"),
+    (
+        "reflow.no-paragraph",
+        "the cursor in {name} isn't in a paragraph",
+    ),
+    (
+        "reflow.reflowed",
+        "Reflowed lines {first} to {last} into {count} line(s)",
+    ),
+    ("filter.copied", "Copied {count} line(s) into {name}"),
+    ("pipe.piped", "Piped {name} through {command}"),
+    (
+        "insert.inserted",
+        "Put {bytes} byte(s) from {command} into {name}",
+    ),
+    ("blame.on", "Showing who last changed each line of {name}"),
+    ("blame.off", "Not showing blame for {name}"),
+    ("jump.no-line", "{name} has no line {line}"),
+    (
+        "jump.no-source",
+        "line {line} of {name} doesn't say where it came from",
+    ),
+    ("goto.moved", "Moved to line {line}, column {col} of {name}"),
+    (
+        "memory.trimmed",
+        "Forgot the oldest {count} undo state(s) of {name}",
+    ),
+    (
+        "memory.total",
+        "{count} buffer(s), {total} in all. Undo histories are kept under {limit} each.",
+    ),
+    ("repl.no-file", "{name} has no file"),
+    ("repl.buffer-exists", "There's already a buffer named '{name}'"),
+    ("repl.deleted", "Deleted {path}"),
+    ("repl.moved", "Moved {from} to {to}"),
+    ("repl.snapshots-not-moved", "Couldn't move the snapshots of {name}: {error}"),
+    ("set.no-mode", "there's no {mode} mode; try default or vim"),
+    ("set.no-eol", "there are no {eol} line endings; try lf or crlf"),
+    ("set.eol", "{name} is written with {eol} line endings from now on"),
+    ("readonly.locked", "{name} is locked, {error}. `open {name} --steal` takes the lock"),
+    ("readonly.off", "{name} can be edited"),
+    ("alias.none", "No aliases"),
+    ("alias.not-alias", "{name} isn't an alias"),
+    ("alias.bad-name", "{name} can't be an alias; use letters, digits, - and _"),
+    ("alias.set", "{name} now runs {expansion}"),
+    ("unalias.removed", "Took out the alias {name}"),
+    ("play.no-keys", "{path} has no keys to play"),
+    ("write.no-file", "{name} has no file yet, give it a path"),
+    ("write.snapshot-failed", "Couldn't snapshot {name}: {error}"),
+    ("deletefile.unsaved", "{name} has unsaved edits, write or undo them first"),
+    ("move.exists", "{path} already exists"),
+    ("close.confirm", "{name} has unsaved changes. Close it anyway? (y/n)"),
+    ("close.nothing", "Nothing closed"),
+    ("close.closed", "Closed {name}"),
+    ("quit.confirm", "{name} has unsaved changes. Quit anyway? (y/n)"),
+    ("quit.confirm-many", "{names} have unsaved changes. Quit anyway? (y/n)"),
+    ("rename.renamed", "Renamed {name} to {new}"),
+    ("rename.recovery-not-moved", "Couldn't move the recovery file of {name}: {error}"),
+    ("recover.no-file", "{name} has no file, so no swap file"),
+    ("recover.no-swap", "{name} has no swap file"),
+    ("recover.recovered", "Recovered {name} from {path}. Write it to keep what was recovered"),
+    ("merge.unchanged", "{name} hasn't changed on disk, there's nothing to merge"),
+    ("merge.clean", "Merged cleanly, `write {name}` to save it"),
+    ("merge.conflicts", "Merged with {count} conflict(s), marked with <<<<<<< and >>>>>>>"),
+    ("timeline.none", "No snapshots of {name} yet"),
+    ("timeline.snapshot", "{number}: {age} ago, +{added} -{removed} line(s) since"),
+    ("timeline.no-snapshot", "{name} has no snapshot {number}"),
+    ("timeline.restored", "Restored {name} to snapshot {number}"),
+    ("autorevert.on", "{name} reloads when its file changes"),
+    ("autorevert.off", "{name} doesn't reload when its file changes"),
+    ("encoding.unknown", "there's no {encoding} encoding, try {names}"),
+    ("encoding.shown", "{name} is in {encoding}"),
+    ("encoding.hex-unsaved", "{name} has to be saved to a file before it can go in or out of hex"),
+    ("stress.passed", "{steps} steps with seed {seed}: the buffer matched the model"),
+    ("copy-block.bad-columns", "{first}..{last} is not a range of columns"),
+    ("copy-block.copied", "Copied a block of {count} line(s)"),
+    ("clips.from-one", "clips are numbered from 1"),
+    ("clips.missing", "there's no clip {number}"),
+    ("clips.used", "Ctrl+V pastes {clip}"),
+    ("clips.none", "No clips"),
+    ("registers.none", "No registers"),
+    ("paste.not-register", "'{register}' is not a register; they're named a to z"),
+    ("paste.empty", "there's nothing in that register"),
+    ("replace.made", "Made {count} replacement(s)"),
+    ("replace-all.none", "No matches"),
+    ("replace-all.found", "Found {hunks} change(s) in {files} file(s), review them in {name} then run `apply-replace {name}`"),
+    ("apply-replace.not-preview", "{name} isn't a replace-all preview"),
+    ("apply-replace.changed", "Changed {count} file(s)"),
+    ("apply-replace.unsaved", "{name} has unsaved changes, so save or close it first"),
+    ("repl.bad-lines", "{start}..{end} is not a range of lines in this buffer"),
+    ("repl.lines-syntax", "a range of lines is written as `start end`"),
+    ("open.dangling", "{name} is a link to something that isn't there"),
+    ("open.already-open", "{name} is already open as {existing}"),
+    ("open.huge", "{name} is huge, so it's read-only and loaded a part at a time"),
+    ("open.locked", "{name} is locked, {error}. Open it with --read-only, or --steal the lock"),
+    ("open.mixed-eol", "{name} ends its lines more than one way. Saving it makes them all {eol}; `set eol {name} lf` or `crlf` picks which"),
+    ("open.not-a-file", "{name} isn't a file or a directory"),
+    ("open.read-only", "Opening {name} read-only"),
+    ("undo-tree.drawn", "Drew the undo tree into {tree}, go to a state with `undo-to {name} <state>`"),
+    ("log.no-home", "There's no log, as there's no home directory"),
+];
+
+/// Runs a single line typed at the REPL.
+pub fn run_command(cmd: &str, registry: &Registry, app: &mut App) -> Result<(), Box<dyn Error>> {
+    // An alias stands for the start of a command, and whatever's typed
+    // after it follows on. Aliases aren't looked for in what they stand
+    // for, so one can't go round in circles.
+    let expanded = app.aliases.get(first_word(cmd)).map(|expansion| {
+        let rest = cmd[first_word(cmd).len()..].trim();
+        format!("{} {}", expansion, rest).trim_end().to_string()
+    });
+    let cmd = expanded.as_deref().unwrap_or(cmd);
+    let name = first_word(cmd);
+    let args = cmd[name.len()..].trim();
+
+    let Some(command) = registry.get(name) else {
+        println!("{}", tr("repl.unknown-command", &[]));
+        return Ok(());
+    };
+    if let Some(metrics) = &mut app.session.metrics {
+        metrics.count_command(name);
+    }
+    let started = Instant::now();
+    let result = (command.run)(app, args);
+    let elapsed_ms = started.elapsed().as_millis() as u64;
+    match &result {
+        Ok(()) => tracing::info!(command = name, args, elapsed_ms, "ran command"),
+        Err(err) => {
+            tracing::warn!(command = name, args, elapsed_ms, error = %err, "command failed")
+        }
+    }
+    result
+}
+
+const HELP: Help = Help {
+    name: "help",
+    usage: &["help [command]"],
+    about: "Lists every command, with what it does. With a command, says how it's typed, all of what it does, and gives examples.",
+    examples: &["help", "help replace"],
+};
+
+fn show_help(app: &mut App, args: &str) -> Result<(), Box<dyn Error>> {
+    let registry = Rc::clone(&app.registry);
+    let name = match args.split_whitespace().collect::<Vec<_>>()[..] {
+        [] => {
+            let mut names: Vec<&str> = registry.names().collect();
+            names.sort();
+            let width = names.iter().map(|name| name.len()).max().unwrap_or(0);
+            println!("{}", tr("help.commands", &[]));
+            for name in names {
+                if let Some(help) = registry.help(name) {
+                    println!("  {:width$}  {}", name, help.summary());
+                }
+            }
+            return Ok(());
+        }
+        [name] => name,
+        _ => return Err(usage("help [command]")),
+    };
+    // Like `run_command`, an alias is only expanded the once, so one
+    // standing for itself, or for another alias, can't go round in
+    // circles.
+    let expansion = app.aliases.get(name).cloned();
+    let command = match &expansion {
+        Some(expansion) => {
+            println!(
+                "{}",
+                tr("help.alias", &[("name", &name), ("expansion", expansion)])
+            );
+            first_word(expansion)
+        }
+        None => name,
+    };
+    let Some(help) = registry.help(command) else {
+        return Err(tr("help.no-command", &[("name", &command)]).into());
+    };
+    for usage in help.usage {
+        println!("{}", usage);
+    }
+    println!();
+    println!("{}", lines::reflow(&help.description(), 72));
+    if !help.examples.is_empty() {
+        println!();
+        println!("{}", tr("help.examples", &[]));
+        for example in help.examples {
+            println!("  {}", example);
+        }
+    }
+    Ok(())
+}
+
+const SOURCE: Help = Help {
+    name: "source",
+    usage: &["source <path> [--keep-going]"],
+    about: "Runs each line of the file at `path` as a command, as if it had been typed, which is handy for opening and setting up a lot of buffers at once. Blank lines, and lines starting with `#`, are skipped. It stops at the first command that fails, or isn't one, saying which line of the file it was on, unless it's told to `--keep-going`, when every failure is reported and the rest of the file still runs.",
+    examples: &["source ~/setup.ws03", "source project.ws03 --keep-going"],
+};
+
+fn source_script(app: &mut App, args: &str) -> Result<(), Box<dyn Error>> {
+    let (words, flags) = split_flags(args);
+    let [path] = words[..] else {
+        return Err(usage("source <path> [--keep-going]"));
+    };
+    let mut keep_going = false;
+    for flag in flags {
+        match flag {
+            "--keep-going" => keep_going = true,
+            _ => return Err(no_option("source", flag)),
+        }
+    }
+    let file = paths::expand(path)?;
+    let script = fs::read_to_string(&file).map_err(|err| format!("{}: {}", path, err))?;
+    // A script which sources itself, however indirectly, would never end.
+    let canonical = fs::canonicalize(&file)?;
+    if app.sourcing.contains(&canonical) {
+        return Err(tr("source.already", &[("path", &path)]).into());
+    }
+
+    let registry = Rc::clone(&app.registry);
+    app.sourcing.push(canonical);
+    let mut failed = 0;
+    for (number, line) in script.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        // At the prompt, a command that isn't one is just pointed out,
+        // but in a script it's as good as failing.
+        let name = first_word(line);
+        let result = match registry.get(name).is_some() || app.aliases.contains_key(name) {
+            true => run_command(line, &registry, app),
+            false => Err(tr("source.no-command", &[("name", &name)]).into()),
+        };
+        if let Err(err) = result {
+            let line = number + 1;
+            let err = tr(
+                "source.failed",
+                &[("path", &path), ("line", &line), ("error", &err)],
+            );
+            if !keep_going {
+                app.sourcing.pop();
+                return Err(err.into());
+            }
+            println!("{}", tr("repl.error", &[("error", &err)]));
+            failed += 1;
+        }
+        if app.quit {
+            break;
+        }
+    }
+    app.sourcing.pop();
+    match failed {
+        0 => Ok(()),
+        _ => Err(tr("source.failures", &[("count", &failed), ("path", &path)]).into()),
+    }
+}
+
+const OPEN: Help = Help {
+    name: "open",
+    usage: &["open [name] [--read-only|--readonly] [--steal] [--hex]", "open --split <a> <b> [--stacked]"],
+    about: "Opens the buffer called `name`, or a new one. If there's no such buffer but there is a file by that name, the file is loaded and locked. With `--read-only` (or `--readonly`), it can't be edited and isn't locked, so it can be opened while another BuffeRS holds the lock, which can also be taken over with `--steal`. A name with wildcards, like `src/**/*.rs`, opens every file it matches. With `--hex`, the file is shown as a hex dump, as files which aren't text are anyway. With `--split`, `a` and `b` are opened side by side, or one above the other with `--stacked`.",
+    examples: &["open notes.md", "open src/**/*.rs", "open --split main.rs lib.rs"],
+};
+
+fn open_editor(app: &mut App, args: &str) -> Result<(), Box<dyn Error>> {
+    let (words, flags) = split_flags(args);
+    if flags.contains(&"--split") {
+        let [first, second] = words[..] else {
+            return Err(usage("open --split <a> <b> [--stacked]"));
+        };
+        return run_split(app, first, second, flags.contains(&"--stacked"));
+    }
+    let name = words.first().copied();
+    let read_only = flags.contains(&"--read-only") || flags.contains(&"--readonly");
+    let lock_policy = match (read_only, flags.contains(&"--steal")) {
+        (true, true) => return Err(tr("open.read-only-steal", &[]).into()),
+        (true, false) => LockPolicy::ReadOnly,
+        (false, true) => LockPolicy::Steal,
+        (false, false) => LockPolicy::Refuse,
+    };
+    if let Some(pattern) = name.filter(|name| project::is_glob(name)) {
+        return open_matching(app, pattern, lock_policy);
+    }
+    if flags.contains(&"--hex") {
+        let editor = fetch_editor(app, name, lock_policy)?;
+        reencode(editor, Encoding::Hex)?;
+        let name = editor.name.clone();
+        return run_editor(app, Some(&name), lock_policy);
+    }
+    run_editor(app, name, lock_policy)
+}
+
+/// How many files a glob can open before `open` asks first.
+const GLOB_CONFIRM: usize = 20;
+
+/// Opens every file matching `pattern` as its own buffer, as `open` does
+/// for one, but without going into the editor.
+fn open_matching(
+    app: &mut App,
+    pattern: &str,
+    lock_policy: LockPolicy,
+) -> Result<(), Box<dyn Error>> {
+    let files = project::glob(pattern)?;
+    if files.is_empty() {
+        return Err(tr("open.no-matches", &[("pattern", &pattern)]).into());
+    }
+    if files.len() > GLOB_CONFIRM {
+        let count = files.len();
+        let question = tr("open.confirm", &[("count", &count), ("pattern", &pattern)]);
+        if !confirm(app, &question, false)? {
+            println!("{}", tr("open.nothing", &[]));
+            return Ok(());
+        }
+    }
+    let before = app.editors.len();
+    for file in &files {
+        if let Err(err) = fetch_editor(app, Some(file), lock_policy) {
+            println!("{}", tr("repl.error", &[("error", &err)]));
+        }
+    }
+    let opened = app.editors.len() - before;
+    let count = files.len();
+    println!(
+        "{}",
+        tr(
+            "open.matching",
+            &[
+                ("opened", &opened),
+                ("count", &count),
+                ("pattern", &pattern)
+            ]
+        )
+    );
+    Ok(())
+}
+
+/// Asks `question` at the REPL, and waits for a yes or a no. Once the
+/// input's run out, there's nobody to answer, so it's `otherwise`.
+fn confirm(app: &mut App, question: &str, otherwise: bool) -> io::Result<bool> {
+    print!("{} ", question);
+    io::stdout().flush()?;
+    let answer = match app.answers.pop_front() {
+        Some(answer) => answer,
+        None => {
+            let mut answer = String::new();
+            if io::stdin().read_line(&mut answer)? == 0 {
+                return Ok(otherwise);
+            }
+            answer
+        }
+    };
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// The error for a command given the wrong arguments, showing how it's
+/// used.
+fn usage(syntax: &str) -> Box<dyn Error> {
+    tr("repl.usage", &[("usage", &syntax)]).into()
+}
+
+/// The error for a buffer that isn't open.
+fn no_buffer(name: &str) -> String {
+    tr("repl.no-buffer", &[("name", &name)])
+}
+
+/// The error for a buffer which isn't backed by a file.
+fn no_file(name: &str) -> String {
+    tr("repl.no-file", &[("name", &name)])
+}
+
+/// The error for giving a buffer a name another one has.
+fn buffer_exists(name: &str) -> String {
+    tr("repl.buffer-exists", &[("name", &name)])
+}
+
+/// What's said when a buffer's snapshots couldn't follow it to its new
+/// name.
+fn snapshots_not_moved(name: &str, err: &dyn Error) -> String {
+    tr(
+        "repl.snapshots-not-moved",
+        &[("name", &name), ("error", &err)],
+    )
+}
+
+/// The error for a flag `command` doesn't have.
+fn no_option(command: &str, flag: &str) -> Box<dyn Error> {
+    tr(
+        "repl.no-option",
+        &[("command", &command), ("option", &flag)],
+    )
+    .into()
+}
+
+const SESSION: Help = Help {
+    name: "session",
+    usage: &["session [save <name>|load <name>|delete <name>]"],
+    about: "Saves the open buffers as a named session, opens the buffers saved in one, or deletes one. With nothing after it, lists the saved sessions.",
+    examples: &["session save work", "session load work"],
+};
+
+fn manage_sessions(app: &mut App, args: &str) -> Result<(), Box<dyn Error>> {
+    match args.split_whitespace().collect::<Vec<_>>()[..] {
+        [] => {
+            let names = sessions::list()?;
+            if names.is_empty() {
+                println!("{}", tr("session.none", &[]));
+            }
+            for name in names {
+                println!("{}", name);
+            }
+        }
+        ["save", name] => {
+            let session = save_session(app);
+            sessions::save(name, &session)?;
+            let count = session.buffers.len();
+            println!(
+                "{}",
+                tr("session.saved", &[("count", &count), ("name", &name)])
+            );
+        }
+        ["load", name] => {
+            let session = sessions::load(name)?;
+            let mut loaded = 0;
+            for saved in &session.buffers {
+                if app.editors.contains_key(&saved.name) {
+                    println!("{}", tr("session.already-open", &[("name", &saved.name)]));
+                    continue;
+                }
+                match restore_buffer(app, saved) {
+                    Ok(editor) => {
+                        app.editors.insert(saved.name.clone(), editor);
+                        loaded += 1;
+                    }
+                    Err(err) => println!("{}: {}", saved.name, err),
+                }
+            }
+            println!(
+                "{}",
+                tr("session.loaded", &[("count", &loaded), ("name", &name)])
+            );
+        }
+        ["delete", name] => {
+            sessions::delete(name)?;
+            println!("{}", tr("session.deleted", &[("name", &name)]));
+        }
+        _ => return Err(usage("session [save <name>|load <name>|delete <name>]")),
+    }
+    Ok(())
+}
+
+/// Every open buffer, as a session. Directory buffers and the dashboard
+/// are left out, since they're just listings.
+fn save_session(app: &App) -> SavedSession {
+    let mut buffers: Vec<SavedBuffer> = app
+        .editors
+        .values()
+        .filter(|editor| editor.directory.is_none() && editor.dashboard.is_none())
+        .map(|editor| {
+            let file = editor.buffer.file().map(String::from);
+            let unsaved = editor.buffer.is_dirty() || file.is_none();
+            SavedBuffer {
+                name: editor.name.clone(),
+                file,
+                text: unsaved.then(|| String::from(editor.buffer.text())),
+                cursor: editor.buffer.cursor(),
+                viewport: (editor.viewport.x, editor.viewport.y),
+            }
+        })
+        .collect();
+    buffers.sort_by(|a, b| a.name.cmp(&b.name));
+    SavedSession { buffers }
+}
+
+/// Opens a buffer as it was when its session was saved. Its file is
+/// locked, as `open` would, or it's read-only if someone else has it.
+fn restore_buffer(app: &App, saved: &SavedBuffer) -> Result<BufferEditor, Box<dyn Error>> {
+    let mut buffer = match &saved.file {
+        Some(file) => {
+            let mut buffer = Buffer::from_file(file)?;
+            if let Some(text) = &saved.text {
+                buffer.set_text(text);
+            }
+            buffer
+        }
+        None => Buffer::from_text(None, saved.text.clone().unwrap_or_default()),
+    };
+    buffer.set_cursor(saved.cursor);
+    let mut editor = BufferEditor::new(saved.name.clone(), buffer, Rc::clone(&app.config));
+    editor.viewport = ViewportLocation {
+        x: saved.viewport.0,
+        y: saved.viewport.1,
+    };
+    if let Some(file) = &saved.file {
+        match Lock::acquire(Path::new(file)) {
+            Ok(lock) => editor.lock = Some(lock),
+            Err(err @ LockError::Held(_)) => {
+                println!(
+                    "{}",
+                    tr("session.locked", &[("name", &saved.name), ("error", &err)])
+                );
+                editor.read_only = true;
+            }
+            Err(err) => return Err(err.into()),
+        }
+    }
+    Ok(editor)
+}
+
+/// What to do when a file being opened is locked by someone else.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LockPolicy {
+    Refuse,
+    ReadOnly,
+    Steal,
+}
+
+fn run_editor(
+    app: &mut App,
+    name: Option<&str>,
+    lock_policy: LockPolicy,
+) -> Result<(), Box<dyn Error>> {
+    let mut name = name.map(String::from);
+    let mut lock_policy = lock_policy;
+    loop {
+        // The session follows whichever editor is open.
+        let session = mem::take(&mut app.session);
+        let editor = match fetch_editor(app, name.as_deref(), lock_policy) {
+            Ok(editor) => editor,
+            Err(err) => {
+                app.session = session;
+                return Err(err);
+            }
+        };
+
+        editor.session = session;
+        let buffer = editor.name.clone();
+        // Every buffer gets a tab, in order by name.
+        let mut names: Vec<String> = app.editors.keys().cloned().collect();
+        names.sort();
+        let active = names.iter().position(|name| *name == buffer).unwrap();
+        let editors = names
+            .iter()
+            .map(|name| app.editors.remove(name).unwrap())
+            .collect();
+        let mut tabs = Tabs::new(editors, active);
+        let started = Instant::now();
+        // Ctrl+C copies, rather than quitting, and Esc closes the editor.
+        let settings = GameSettings::new()
+            .tick_duration(app.config.tick())
+            .quit_event(None);
+        let result = run_game(&mut tabs, settings);
+        let active = tabs.active();
+        let mut editors = tabs.into_editors();
+        let elapsed_ms = started.elapsed().as_millis() as u64;
+        tracing::debug!(buffer = %editors[active].name, elapsed_ms, "closed the editor");
+        let session = mem::take(&mut editors[active].session);
+        // The editor may have asked to switch to another buffer, like a
+        // directory buffer opening a file.
+        let next = editors[active].open_next.take();
+        for mut editor in editors {
+            if let Some(command) = editor.then_run.take() {
+                app.queued.push_back(command);
+            }
+            app.editors.insert(editor.name.clone(), editor);
+        }
+        app.session = session;
+        result?;
+
+        match next {
+            Some(next) => name = Some(next),
+            None => return Ok(()),
+        }
+        lock_policy = LockPolicy::Refuse;
+    }
+}
+
+/// Opens `first` and `second` split across the screen, with the keys
+/// in `first`.
+fn run_split(
+    app: &mut App,
+    first: &str,
+    second: &str,
+    stacked: bool,
+) -> Result<(), Box<dyn Error>> {
+    // A buffer opened by its file's path goes by the name it already had.
+    let first = fetch_editor(app, Some(first), LockPolicy::Refuse)?
+        .name
+        .clone();
+    let second = fetch_editor(app, Some(second), LockPolicy::Refuse)?
+        .name
+        .clone();
+    if first == second {
+        return Err(tr("open.split-itself", &[("name", &first)]).into());
+    }
+    let mut editor = app.editors.remove(&first).unwrap();
+    editor.session = mem::take(&mut app.session);
+    let mut split = Split::new(editor, app.editors.remove(&second).unwrap(), stacked);
+
+    let started = Instant::now();
+    let settings = GameSettings::new()
+        .tick_duration(app.config.tick())
+        .quit_event(None);
+    let result = run_game(&mut split, settings);
+    let elapsed_ms = started.elapsed().as_millis() as u64;
+    tracing::debug!(%first, %second, elapsed_ms, "closed the split");
+    let [mut first, second] = split.into_editors();
+    app.session = mem::take(&mut first.session);
+    for mut editor in [first, second] {
+        editor.pane = None;
+        if let Some(command) = editor.then_run.take() {
+            app.queued.push_back(command);
+        }
+        // Switching buffers waits until the split's closed.
+        if let Some(next) = editor.open_next.take() {
+            app.queued.push_back(format!("open {}", next));
+        }
+        app.editors.insert(editor.name.clone(), editor);
+    }
+    result?;
+    Ok(())
+}
+
+const SEARCH: Help = Help {
+    name: "search",
+    usage: &["search [-i] <pattern>"],
+    about: "Prints every line, in every buffer, which matches the regular expression `pattern`, as `buffer:line:col: text`. With `-i`, case is ignored.",
+    examples: &["search -i todo"],
+};
+
+fn search_buffers(app: &mut App, args: &str) -> Result<(), Box<dyn Error>> {
+    let (ignore_case, pattern) = match args.split_once(char::is_whitespace) {
+        Some(("-i", pattern)) => (true, pattern.trim_start()),
+        None if args == "-i" => (true, ""),
+        _ => (false, args),
+    };
+    if pattern.is_empty() {
+        return Err(usage("search [-i] <pattern>"));
+    }
+    let regex = RegexBuilder::new(pattern)
+        .case_insensitive(ignore_case)
+        .build()
+        .map_err(|err| tr("search.invalid", &[("error", &err)]))?;
+    for result in search::search_buffers(&app.editors, &regex)? {
+        println!("{}", result);
+    }
+    Ok(())
+}
+
+const NOHL: Help = Help {
+    name: "nohl",
+    usage: &["nohl"],
+    about: "Stops highlighting what Ctrl+F found, in every buffer.",
+    examples: &[],
+};
+
+fn clear_highlights(app: &mut App, args: &str) -> Result<(), Box<dyn Error>> {
+    if !args.is_empty() {
+        return Err(usage("nohl"));
+    }
+    for editor in app.editors.values_mut() {
+        editor.find = None;
+    }
+    Ok(())
+}
+
+/// Replaces the selection in the buffer named in `args`, or all of its
+/// text if nothing's selected, with the result of `transform`.
+fn apply_transform(
+    editors: &mut HashMap<String, BufferEditor>,
+    args: &str,
+    help: &Help,
+    transform: Transform,
+) -> Result<(), Box<dyn Error>> {
+    let [name] = args.split_whitespace().collect::<Vec<_>>()[..] else {
+        return Err(usage(help.usage[0]));
+    };
+    let buffer = &mut editable_editor(editors, name)?.buffer;
+    match buffer.selection() {
+        Some(range) => {
+            let text = transform(&buffer.slice(range.clone()))?;
+            buffer.clear_selection();
+            buffer.replace_range(range.clone(), &text);
+            buffer.set_cursor(range.start);
+        }
+        None => {
+            let text = transform(buffer.text())?;
+            buffer.set_text(&text);
+        }
+    }
+    Ok(())
+}
+
+const DEDUP: Help = Help {
+    name: "dedup",
+    usage: &["dedup <buffer> [start end] [--global] [--keep-last]"],
+    about: "Removes duplicated lines. Without `--global`, only duplicates that are next to each other are removed. `uniq` is another name for it.",
+    examples: &["dedup words --global"],
+};
+
+const UNIQ: Help = Help {
+    name: "uniq",
+    usage: &["uniq <buffer> [start end] [--global] [--keep-last]"],
+    about: "Removes duplicated lines. Without `--global`, only duplicates that are next to each other are removed. It's another name for `dedup`.",
+    examples: &["uniq words 1 20"],
+};
+
+/// Runs `dedup`, or `uniq`, which `help` is about.
+fn dedup_lines(app: &mut App, args: &str, help: &Help) -> Result<(), Box<dyn Error>> {
+    let editors = &mut app.editors;
+    let (words, flags) = split_flags(args);
+    let name = words.first().ok_or_else(|| usage(help.usage[0]))?;
+    let (mut mode, mut keep_last) = (DedupMode::Adjacent, false);
+    for flag in flags {
+        match flag {
+            "--global" => mode = DedupMode::Global,
+            "--keep-last" => keep_last = true,
+            _ => return Err(no_option(help.name, flag)),
+        }
+    }
+    let buffer = &mut editable_editor(editors, name)?.buffer;
+    let range = parse_line_range(buffer, &words[1..])?;
+
+    let span = buffer.line_span(range);
+    let (text, removed) = lines::dedup(&buffer.text()[span.clone()], mode, keep_last);
+    if removed > 0 {
+        buffer.replace_range(span, &text);
+    }
+
+    println!("{}", tr("dedup.removed", &[("count", &removed)]));
+    Ok(())
+}
+
+const SORT: Help = Help {
+    name: "sort",
+    usage: &["sort <buffer> [start end] [--reverse] [--numeric] [--ignore-case]"],
+    about: "Sorts lines, by the number each starts with when it's `--numeric`.",
+    examples: &["sort names", "sort scores 2 40 --numeric --reverse"],
+};
+
+fn sort_lines(app: &mut App, args: &str) -> Result<(), Box<dyn Error>> {
+    let (words, flags) = split_flags(args);
+    let name = words.first().ok_or_else(|| {
+        usage("sort <buffer> [start end] [--reverse] [--numeric] [--ignore-case]")
+    })?;
+    let mut options = SortOptions::default();
+    for flag in flags {
+        match flag {
+            "--reverse" => options.reverse = true,
+            "--numeric" => options.numeric = true,
+            "--ignore-case" => options.ignore_case = true,
+            _ => return Err(no_option("sort", flag)),
+        }
+    }
+    let buffer = &mut editable_editor(&mut app.editors, name)?.buffer;
+    let range = parse_line_range(buffer, &words[1..])?;
+    let span = buffer.line_span(range.clone());
+    let text = lines::sort(&buffer.text()[span.clone()], options);
+    if text != buffer.text()[span.clone()] {
+        buffer.replace_range(span, &text);
+    }
+    println!("{}", tr("sort.sorted", &[("count", &range.len())]));
+    Ok(())
+}
+
+const REVERSE: Help = Help {
+    name: "reverse",
+    usage: &["reverse <buffer> [start end]"],
+    about: "Puts lines in the opposite order.",
+    examples: &["reverse log 1 10"],
+};
+
+fn reverse_lines(app: &mut App, args: &str) -> Result<(), Box<dyn Error>> {
+    let (words, flags) = split_flags(args);
+    let name = words
+        .first()
+        .ok_or_else(|| usage("reverse <buffer> [start end]"))?;
+    if let Some(flag) = flags.first() {
+        return Err(no_option("reverse", flag));
+    }
+    let buffer = &mut editable_editor(&mut app.editors, name)?.buffer;
+    let range = parse_line_range(buffer, &words[1..])?;
+    let span = buffer.line_span(range.clone());
+    let text = lines::reverse(&buffer.text()[span.clone()]);
+    if text != buffer.text()[span.clone()] {
+        buffer.replace_range(span, &text);
+    }
+    println!("{}", tr("reverse.reversed", &[("count", &range.len())]));
+    Ok(())
+}
+
+const CASE: Help = Help {
+    name: "case",
+    usage: &["case <buffer> <upper|lower|title|toggle|snake|camel>"],
+    about: "Changes the case of what's selected in `buffer`, or the word its cursor is on. `snake` and `camel` turn identifiers from `camelCase` into `snake_case` and back.",
+    examples: &["case notes upper"],
+};
+
+fn change_case(app: &mut App, args: &str) -> Result<(), Box<dyn Error>> {
+    let names: Vec<&str> = CASES.iter().map(|(_, name)| *name).collect();
+    let syntax = || usage(&format!("case <buffer> <{}>", names.join("|")));
+    let (name, case) = match args.split_whitespace().collect::<Vec<_>>()[..] {
+        [name, case] => (name, Case::by_name(case).ok_or_else(syntax)?),
+        _ => return Err(syntax()),
+    };
+    let editor = editable_editor(&mut app.editors, name)?;
+    if !editor.change_case(case) {
+        return Err(tr("case.no-word", &[("name", &name)]).into());
+    }
+    println!("{}", tr("case.changed", &[("case", &case.name())]));
+    Ok(())
+}
+
+const JOIN: Help = Help {
+    name: "join",
+    usage: &["join <buffer> <line>"],
+    about: "Joins line `line` of `buffer` and the one after it, with a single space between them.",
+    examples: &["join notes 3"],
+};
+
+fn join_lines(app: &mut App, args: &str) -> Result<(), Box<dyn Error>> {
+    let (name, line) = match args.split_whitespace().collect::<Vec<_>>()[..] {
+        [name, line] => (name, line.parse::<usize>()?),
+        _ => return Err(usage("join <buffer> <line>")),
+    };
+    let buffer = &mut editable_editor(&mut app.editors, name)?.buffer;
+    if !line
+        .checked_sub(1)
+        .is_some_and(|index| buffer.join_line(index))
+    {
+        return Err(tr("join.no-line", &[("name", &name), ("line", &line)]).into());
+    }
+    println!(
+        "{}",
+        tr("join.joined", &[("line", &line), ("next", &(line + 1))])
+    );
+    Ok(())
+}
+
+const REFLOW: Help = Help {
+    name: "reflow",
+    usage: &["reflow <buffer> <width>"],
+    about: "Wraps the paragraph the cursor is in again, so its lines are no longer than `width`.",
+    examples: &["reflow notes 72"],
+};
+
+fn reflow_paragraph(app: &mut App, args: &str) -> Result<(), Box<dyn Error>> {
+    let (name, width) = match args.split_whitespace().collect::<Vec<_>>()[..] {
+        [name, width] => (name, width.parse::<usize>()?),
+        _ => return Err(usage("reflow <buffer> <width>")),
+    };
+    if width == 0 {
+        return Err(tr("reflow.zero-width", &[]).into());
+    }
+    let buffer = &mut editable_editor(&mut app.editors, name)?.buffer;
+    let lines = lines::paragraph(buffer.text(), buffer.cursor_line());
+    let span = buffer.line_span(lines.clone());
+    let text = lines::reflow(&buffer.text()[span.clone()], width);
+    if text.trim().is_empty() {
+        return Err(tr("reflow.no-paragraph", &[("name", &name)]).into());
+    }
+    let start = span.start;
+    buffer.replace_range(span, &text);
+    buffer.set_cursor(start);
+    println!(
+        "{}",
+        tr(
+            "reflow.reflowed",
+            &[
+                ("first", &(lines.start + 1)),
+                ("last", &lines.end),
+                ("count", &text.lines().count())
+            ]
+        )
+    );
+    Ok(())
+}
+
+const FILTER: Help = Help {
+    name: "filter",
+    usage: &["filter [-v] <pattern> [buffer]"],
+    about: "Copies every line containing `pattern` (or with `-v`, every line that doesn't) into a new scratch buffer. Each copied line is prefixed with `buffer:line:`, so `jump` can find where it came from. Without a buffer, every buffer is filtered.",
+    examples: &["filter -v DEBUG app.log"],
+};
+
+fn filter_lines(app: &mut App, args: &str) -> Result<(), Box<dyn Error>> {
+    let editors = &mut app.editors;
+    let mut words: Vec<&str> = args.split_whitespace().collect();
+    let invert = words.first() == Some(&"-v");
+    if invert {
+        words.remove(0);
+    }
+    let (pattern, names) = match words.split_first() {
+        Some((pattern, [])) => {
+            let mut names: Vec<&str> = editors.keys().map(String::as_str).collect();
+            names.sort();
+            (*pattern, names)
+        }
+        Some((pattern, [name])) => (*pattern, vec![*name]),
+        _ => return Err(usage("filter [-v] <pattern> [buffer]")),
+    };
+
+    let mut scratch = String::new();
+    let mut count = 0;
+    for name in names {
+        let buffer = &editors.get(name).ok_or_else(|| no_buffer(name))?.buffer;
+        for (number, line) in lines::filter(buffer.text(), pattern, invert) {
+            scratch.push_str(&format!("{}:{}: {}\n", name, number, line));
+            count += 1;
+        }
+    }
+
+    let scratch_name = fresh_name(editors, "filter");
+    println!(
+        "{}",
+        tr(
+            "filter.copied",
+            &[("count", &count), ("name", &scratch_name)]
+        )
+    );
+    let editor = BufferEditor::new(
+        scratch_name.clone(),
+        Buffer::from_text(None, scratch),
+        Rc::clone(&app.config),
+    );
+    app.editors.insert(scratch_name, editor);
+    Ok(())
+}
+
+const PIPE: Help = Help {
+    name: "pipe",
+    usage: &["pipe <buffer> <command>"],
+    about: "Runs the shell command `command` with the text of `buffer` as its input, and replaces the text with what it prints, as one edit which undo takes back. If the command fails, the buffer's left as it was. In the editor, Alt+! does the same to the selection, or the whole buffer if nothing's selected, and in vim's normal mode `!` followed by a motion pipes the lines it moves over, so `!!` pipes the cursor's line.",
+    examples: &["pipe notes sort -u", "pipe data jq ."],
+};
+
+fn pipe_buffer(app: &mut App, args: &str) -> Result<(), Box<dyn Error>> {
+    let name = first_word(args);
+    let command = args[name.len()..].trim();
+    if name.is_empty() || command.is_empty() {
+        return Err(usage("pipe <buffer> <command>"));
+    }
+    let buffer = &mut editable_editor(&mut app.editors, name)?.buffer;
+    let output = pipe::run(command, buffer.text())?;
+    if output != buffer.text() {
+        buffer.replace_range(0..buffer.text().len(), &output);
+    }
+    println!(
+        "{}",
+        tr("pipe.piped", &[("name", &name), ("command", &command)])
+    );
+    Ok(())
+}
+
+const INSERT: Help = Help {
+    name: "insert",
+    usage: &["insert <buffer> !<command>"],
+    about: "Runs the shell command `command` and puts what it prints in at the cursor in `buffer`, as one edit which undo takes back. Unless the cursor's at the start of a line, the newline at the end of the output is left off, so `date` goes in as part of the line. Anything the command prints to standard error is shown as well. In the editor, Alt+R asks for a command and does the same.",
+    examples: &["insert notes !date", "insert todo !git log --oneline -5"],
+};
+
+fn insert_output(app: &mut App, args: &str) -> Result<(), Box<dyn Error>> {
+    let name = first_word(args);
+    let command = args[name.len()..].trim().strip_prefix('!').map(str::trim);
+    let (false, Some(command)) = (name.is_empty(), command.filter(|c| !c.is_empty())) else {
+        return Err(usage("insert <buffer> !<command>"));
+    };
+    let editor = editable_editor(&mut app.editors, name)?;
+    let before = editor.buffer.text().len();
+    let stderr = editor.insert_output(command)?;
+    if let Some(stderr) = stderr {
+        print!("{}", stderr);
+    }
+    let bytes = editor.buffer.text().len() - before;
+    println!(
+        "{}",
+        tr(
+            "insert.inserted",
+            &[("bytes", &bytes), ("command", &command), ("name", &name)]
+        )
+    );
+    Ok(())
+}
+
+const BLAME: Help = Help {
+    name: "blame",
+    usage: &["blame <buffer>"],
+    about: "Shows the commit, author and date that last changed each line of `buffer`, down the left of it, or stops showing them. The buffer's file has to be in a git repository. It's worked out for the text as it is when it's turned on, so lines that haven't been committed yet say so. In the editor, Alt+B does the same.",
+    examples: &["blame main"],
+};
+
+fn show_blame(app: &mut App, args: &str) -> Result<(), Box<dyn Error>> {
+    let (words, _) = split_flags(args);
+    let [name] = words[..] else {
+        return Err(usage("blame <buffer>"));
+    };
+    match get_editor(&mut app.editors, name)?.toggle_blame()? {
+        true => println!("{}", tr("blame.on", &[("name", &name)])),
+        false => println!("{}", tr("blame.off", &[("name", &name)])),
+    }
+    Ok(())
+}
+
+const JUMP: Help = Help {
+    name: "jump",
+    usage: &["jump <buffer> <line>"],
+    about: "When line `line` of `buffer` starts with `name:number:` (as the lines made by `filter` do), opens `name` scrolled to line `number`. Unlike `goto`, `line` is where to read the place from, not the place itself.",
+    examples: &["jump filtered 4"],
+};
+
+fn jump_to_line(app: &mut App, args: &str) -> Result<(), Box<dyn Error>> {
+    let target_name = follow_reference(&mut app.editors, args)?;
+    run_editor(app, Some(&target_name), LockPolicy::Refuse)
+}
+
+/// Moves the cursor to where the `name:number:` line `jump` was given
+/// points, without opening it, and returns which buffer that's in.
+fn follow_reference(
+    editors: &mut HashMap<String, BufferEditor>,
+    args: &str,
+) -> Result<String, Box<dyn Error>> {
+    let (name, line) = match args.split_whitespace().collect::<Vec<_>>()[..] {
+        [name, line] => (name, line.parse::<usize>()?),
+        _ => return Err(usage("jump <buffer> <line>")),
+    };
+    let buffer = &get_editor(editors, name)?.buffer;
+    let target = line
+        .checked_sub(1)
+        .and_then(|index| buffer.line(index))
+        .ok_or_else(|| tr("jump.no-line", &[("name", &name), ("line", &line)]))?;
+
+    let mut parts = target.splitn(3, ':');
+    let (target_name, target_line) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(target_name), Some(target_line), Some(_)) => {
+            (String::from(target_name), target_line.parse::<usize>()?)
+        }
+        _ => return Err(tr("jump.no-source", &[("name", &name), ("line", &line)]).into()),
+    };
+
+    let editor = get_editor(editors, &target_name)?;
+    let line_index = target_line.saturating_sub(1);
+    let line_start = editor.buffer.line_span(line_index..line_index).start;
+    editor.buffer.set_cursor(line_start);
+    editor.viewport.y = line_index as i32;
+    Ok(target_name)
+}
+
+const GOTO: Help = Help {
+    name: "goto",
+    usage: &["goto <buffer> <line>[:<column>]"],
+    about: "Moves the cursor in `buffer` to `line`, and `column` on it, scrolled so the line's at the top when it's next opened. Past the end goes to the end. To follow a `name:number:` line to where it points, use `jump`.",
+    examples: &["goto main.rs 120:8"],
+};
+
+fn goto_line(app: &mut App, args: &str) -> Result<(), Box<dyn Error>> {
+    let (name, position) = args.trim().split_once(' ').unwrap_or((args.trim(), ""));
+    if name.is_empty() || position.trim().is_empty() {
+        return Err(usage("goto <buffer> <line>[:<column>]"));
+    }
+    let (line, col) = goto::parse(position)?;
+    let editor = get_editor(&mut app.editors, name)?;
+    let row = editor.goto(line, col);
+    editor.viewport = ViewportLocation {
+        x: 0,
+        y: row as i32,
+    };
+    let position = editor.buffer.cursor_position();
+    println!(
+        "{}",
+        tr(
+            "goto.moved",
+            &[
+                ("line", &(editor.first_line() + position.line + 1)),
+                ("col", &(position.col + 1)),
+                ("name", &name)
+            ]
+        )
+    );
+    Ok(())
+}
+
+const MEMORY: Help = Help {
+    name: "memory",
+    usage: &["memory [--trim]"],
+    about: "Shows roughly how much memory each buffer takes up: its text, the text as last saved, and its undo history. With `--trim`, undo histories over `undo_limit` are cut down to it first.",
+    examples: &[],
+};
+
+fn show_memory(app: &mut App, args: &str) -> Result<(), Box<dyn Error>> {
+    let (_, flags) = split_flags(args);
+    let mut names: Vec<String> = app.editors.keys().cloned().collect();
+    names.sort();
+    if flags.contains(&"--trim") {
+        for name in &names {
+            let buffer = &mut get_editor(&mut app.editors, name)?.buffer;
+            let forgotten = buffer.trim_history(app.config.undo_limit);
+            if forgotten > 0 {
+                println!(
+                    "{}",
+                    tr("memory.trimmed", &[("count", &forgotten), ("name", name)])
+                );
+            }
+        }
+    }
+
+    println!(
+        "{:<20} {:>10} {:>10} {:>10} {:>10} {:>8} {:>10}",
+        "buffer", "text", "index", "saved", "undo", "states", "total"
+    );
+    let mut total = 0;
+    for name in &names {
+        let memory = app.editors[name].buffer.memory();
+        total += memory.total();
+        println!(
+            "{:<20} {:>10} {:>10} {:>10} {:>10} {:>8} {:>10}",
+            name,
+            size(memory.text),
+            size(memory.index),
+            size(memory.saved_text),
+            size(memory.history),
+            memory.states,
+            size(memory.total())
+        );
+    }
+    println!(
+        "{}",
+        tr(
+            "memory.total",
+            &[
+                ("count", &names.len()),
+                ("total", &size(total)),
+                ("limit", &size(app.config.undo_limit))
+            ]
+        )
+    );
+    Ok(())
+}
+
+const UNDO_TREE: Help = Help {
+    name: "undo-tree",
+    usage: &["undo-tree <buffer>"],
+    about: "Copies a drawing of every state `buffer` has been in into a new scratch buffer, so any of them can be found and gone back to with `undo-to`, including ones that a plain undo stack would have lost.",
+    examples: &["undo-tree notes"],
+};
+
+fn show_undo_tree(app: &mut App, args: &str) -> Result<(), Box<dyn Error>> {
+    let name = match args.split_whitespace().collect::<Vec<_>>()[..] {
+        [name] => name,
+        _ => return Err(usage("undo-tree <buffer>")),
+    };
+    let tree = get_editor(&mut app.editors, name)?.buffer.undo_tree();
+    let scratch_name = fresh_name(&app.editors, "undotree");
+    println!(
+        "{}",
+        tr(
+            "undo-tree.drawn",
+            &[("tree", &scratch_name), ("name", &name)]
+        )
+    );
+    let editor = BufferEditor::new(
+        scratch_name.clone(),
+        Buffer::from_text(None, tree),
+        Rc::clone(&app.config),
+    );
+    app.editors.insert(scratch_name, editor);
+    Ok(())
+}
+
+const STATS: Help = Help {
+    name: "stats",
+    usage: &["stats typing [on|off]"],
+    about: "Shows the typing statistics for this session, or turns collecting them on or off. They're off until asked for.",
+    examples: &["stats typing on"],
+};
+
+fn show_stats(app: &mut App, args: &str) -> Result<(), Box<dyn Error>> {
+    match args.split_whitespace().collect::<Vec<_>>()[..] {
+        ["typing"] => match &app.session.typing_stats {
+            Some(stats) => println!("{}", stats.summary()),
+            None => println!("{}", tr("stats.off", &[])),
+        },
+        ["typing", "on"] => {
+            if app.session.typing_stats.is_none() {
+                app.session.typing_stats = Some(TypingStats::new());
+            }
+            println!("{}", tr("stats.on", &[]));
+        }
+        ["typing", "off"] => {
+            app.session.typing_stats = None;
+            println!("{}", tr("stats.stopped", &[]));
+        }
+        _ => return Err(usage("stats typing [on|off]")),
+    }
+    Ok(())
+}
+
+const LOG: Help = Help {
+    name: "log",
+    usage: &["log"],
+    about: "Opens the log in a read-only buffer called `log`, which keeps up with the end of the log as more is written.",
+    examples: &[],
+};
+
+fn show_log(app: &mut App, _args: &str) -> Result<(), Box<dyn Error>> {
+    let path = logging::path().ok_or_else(|| tr("log.no-home", &[]))?;
+    let path = path.to_string_lossy().into_owned();
+    if !app.editors.contains_key("log") {
+        let buffer = match Buffer::from_file(&path) {
+            Ok(buffer) => buffer,
+            Err(err) if err.kind() == ErrorKind::NotFound => {
+                Buffer::from_text(Some(path.clone()), String::new())
+            }
+            Err(err) => return Err(format!("{}: {}", path, err).into()),
+        };
+        let mut editor = BufferEditor::new(String::from("log"), buffer, Rc::clone(&app.config));
+        editor.read_only = true;
+        editor.auto_revert = true;
+        app.editors.insert(String::from("log"), editor);
+    }
+    get_editor(&mut app.editors, "log")?.follow = true;
+    run_editor(app, Some("log"), LockPolicy::Refuse)
+}
+
+const METRICS: Help = Help {
+    name: "metrics",
+    usage: &["metrics [export <path>|reset]"],
+    about: "Shows how often each command and key has been used, writes the counts out as JSON, or starts them again. Counting is turned on with `metrics = true` in the config.",
+    examples: &["metrics export metrics.json"],
+};
+
+fn show_metrics(app: &mut App, args: &str) -> Result<(), Box<dyn Error>> {
+    let Some(metrics) = &mut app.session.metrics else {
+        println!("{}", tr("metrics.off", &[]));
+        return Ok(());
+    };
+    match args.split_whitespace().collect::<Vec<_>>()[..] {
+        [] => print!("{}", metrics.summary()),
+        ["export", path] => {
+            let path = paths::expand(path)?;
+            fs::write(&path, metrics.to_json())?;
+            println!("{}", tr("metrics.exported", &[("path", &path.display())]));
+        }
+        ["reset"] => {
+            *metrics = Metrics::default();
+            println!("{}", tr("metrics.reset", &[]));
+        }
+        _ => return Err(usage("metrics [export <path>|reset]")),
+    }
+    Ok(())
+}
+
+const CLOCK: Help = Help {
+    name: "clock",
+    usage: &["clock [wall|fixed [ms-per-tick]]"],
+    about: "Shows or switches the clock that snapshots, file watching and typing stats go by. A fixed clock only moves on as the editor ticks, so a scripted session plays out the same every time.",
+    examples: &["clock fixed 50"],
+};
+
+fn set_clock(app: &mut App, args: &str) -> Result<(), Box<dyn Error>> {
+    // Either way the time carries on from where it is, so nothing sees
+    // it jump backwards.
+    let now = app.session.clock.now();
+    match args.split_whitespace().collect::<Vec<_>>()[..] {
+        [] => {}
+        ["wall"] => app.session.clock = Clock::wall(now),
+        ["fixed"] => app.session.clock = Clock::fixed(now, app.config.tick()),
+        ["fixed", millis] => {
+            let tick = Duration::from_millis(millis.parse()?);
+            app.session.clock = Clock::fixed(now, tick);
+        }
+        _ => return Err(usage("clock [wall|fixed [ms-per-tick]]")),
+    }
+    println!("{}", app.session.clock.describe());
+    Ok(())
+}
+
+const TUTOR: Help = Help {
+    name: "tutor",
+    usage: &["tutor [--restart]"],
+    about:
+        "Opens the tutorial, where it was left, or from the first lesson again with `--restart`.",
+    examples: &["tutor --restart"],
+};
+
+fn open_tutor(app: &mut App, args: &str) -> Result<(), Box<dyn Error>> {
+    match args {
+        "" if app.editors.contains_key("tutor") => {}
+        "" | "--restart" => {
+            let editor = BufferEditor::tutor(Rc::clone(&app.config));
+            app.editors.insert(String::from("tutor"), editor);
+        }
+        _ => return Err(usage("tutor [--restart]")),
+    }
+    run_editor(app, Some("tutor"), LockPolicy::Refuse)
+}
+
+const DASHBOARD: Help = Help {
+    name: "dashboard",
+    usage: &["dashboard"],
+    about: "Shows the dashboard BuffeRS starts on, with the files opened recently and the saved sessions, either of which Enter opens.",
+    examples: &[],
+};
+
+fn open_dashboard(app: &mut App, args: &str) -> Result<(), Box<dyn Error>> {
+    if !args.is_empty() {
+        return Err(usage("dashboard"));
+    }
+    let editor =
+        BufferEditor::dashboard(Rc::clone(&app.config), &recent::list(), &sessions::list()?);
+    app.editors.insert(String::from("dashboard"), editor);
+    run_editor(app, Some("dashboard"), LockPolicy::Refuse)
+}
+
+const RECORD: Help = Help {
+    name: "record",
+    usage: &["record start <path>", "record stop"],
+    about: "Records what the editor shows, and the keys pressed in it, into an asciinema cast file. Only time spent in the editor is recorded.",
+    examples: &["record start demo.cast", "record stop"],
+};
+
+fn record_session(app: &mut App, args: &str) -> Result<(), Box<dyn Error>> {
+    match args.split_whitespace().collect::<Vec<_>>()[..] {
+        ["start", path] => {
+            if let Some(recording) = &app.session.recording {
+                return Err(tr("record.already", &[("path", &recording.path().display())]).into());
+            }
+            let path = paths::expand(path)?;
+            let recording = Recording::start(&path, app.session.clock.now())?;
+            println!("{}", tr("record.started", &[("path", &path.display())]));
+            app.session.recording = Some(recording);
+        }
+        ["stop"] => match app.session.recording.take() {
+            Some(recording) => {
+                let path = recording.finish()?;
+                println!("{}", tr("record.saved", &[("path", &path.display())]));
+            }
+            None => println!("{}", tr("record.not-recording", &[])),
+        },
+        _ => return Err(usage("record start <path> | record stop")),
+    }
+    Ok(())
+}
+
+const ANNOUNCE: Help = Help {
+    name: "announce",
+    usage: &["announce [stderr|<path>|off]"],
+    about: "Turns screen-reader mode on, saying what happens in the editor to standard error or a file, or turns it off. With nothing after it, says where it's announcing to.",
+    examples: &["announce /tmp/announce.txt"],
+};
+
+fn set_announce(app: &mut App, args: &str) -> Result<(), Box<dyn Error>> {
+    match args {
+        "" => {}
+        "off" => app.session.announcer = None,
+        target => {
+            let announcer = Announcer::open(&paths::expand(target)?)
+                .map_err(|err| format!("{}: {}", target, err))?;
+            app.session.announcer = Some(announcer);
+        }
+    }
+    match &app.session.announcer {
+        Some(announcer) => println!("{}", tr("announce.on", &[("target", &announcer.target())])),
+        None => println!("{}", tr("announce.off", &[])),
+    }
+    Ok(())
+}
+
+const THEME: Help = Help {
+    name: "theme",
+    usage: &["theme [name]"],
+    about: "Switches every editor to the theme called `name`. With no name, lists the themes there are, marking the one in use.",
+    examples: &["theme solarized"],
+};
+
+fn set_theme(app: &mut App, args: &str) -> Result<(), Box<dyn Error>> {
+    match args {
+        "" => {
+            for theme in THEMES {
+                let marker = if *theme == app.session.theme {
+                    '*'
+                } else {
+                    ' '
+                };
+                println!("{} {}", marker, theme.name);
+            }
+        }
+        name => {
+            app.session.theme =
+                Theme::by_name(name).ok_or_else(|| tr("theme.unknown", &[("theme", &name)]))?;
+        }
+    }
+    Ok(())
+}
+
+const SET: Help = Help {
+    name: "set",
+    usage: &["set [number|nonumber|autoindent on|off|mode default|mode vim|theme <name>]", "set [trim_trailing_whitespace|insert_final_newline] [on|off]", "set eol <buffer> [lf|crlf]", "set readonly <buffer> [on|off]"],
+    about: "Turns line numbers or indenting new lines on or off in every editor, switches between the default way of editing and vim's modes, or changes the theme, like `theme` does. `trim_trailing_whitespace` and `insert_final_newline` tidy buffers up as they're saved, as an edit of their own which undo takes back. With nothing after it, says how they're set. `eol` shows or changes the line endings one buffer's file is written with, converting it the next time it's saved, and `readonly` whether the buffer can be edited.",
+    examples: &["set number", "set mode vim", "set trim_trailing_whitespace on", "set eol notes crlf"],
+};
+
+fn set_option(app: &mut App, args: &str) -> Result<(), Box<dyn Error>> {
+    match args.split_whitespace().collect::<Vec<_>>()[..] {
+        [] => {
+            let number = if app.session.line_numbers {
+                "number"
+            } else {
+                "nonumber"
+            };
+            println!("{}", number);
+            let autoindent = if app.session.autoindent { "on" } else { "off" };
+            println!("autoindent {}", autoindent);
+            let on_off = |on| if on { "on" } else { "off" };
+            println!(
+                "trim_trailing_whitespace {}",
+                on_off(app.session.trim_trailing_whitespace)
+            );
+            println!(
+                "insert_final_newline {}",
+                on_off(app.session.insert_final_newline)
+            );
+            println!("mode {}", app.session.editing_mode.name());
+            println!("theme {}", app.session.theme.name);
+        }
+        ["number"] => app.session.line_numbers = true,
+        ["nonumber"] => app.session.line_numbers = false,
+        ["autoindent", "on"] => app.session.autoindent = true,
+        ["autoindent", "off"] => app.session.autoindent = false,
+        ["trim_trailing_whitespace", "on"] => app.session.trim_trailing_whitespace = true,
+        ["trim_trailing_whitespace", "off"] => app.session.trim_trailing_whitespace = false,
+        ["insert_final_newline", "on"] => app.session.insert_final_newline = true,
+        ["insert_final_newline", "off"] => app.session.insert_final_newline = false,
+        ["mode", mode] => {
+            app.session.editing_mode =
+                EditingMode::by_name(mode).ok_or_else(|| tr("set.no-mode", &[("mode", &mode)]))?;
+        }
+        ["theme", name] => set_theme(app, name)?,
+        ["eol", name] => println!("{}", get_editor(&mut app.editors, name)?.buffer.eol()),
+        ["eol", name, eol] => {
+            let eol = LineEnding::by_name(eol).ok_or_else(|| tr("set.no-eol", &[("eol", &eol)]))?;
+            let editor = get_editor(&mut app.editors, name)?;
+            editor.buffer.set_eol(eol);
+            println!("{}", tr("set.eol", &[("name", &name), ("eol", &eol)]));
+        }
+        ["readonly", name] => set_read_only(app, name, None)?,
+        ["readonly", name, "on"] => set_read_only(app, name, Some(true))?,
+        ["readonly", name, "off"] => set_read_only(app, name, Some(false))?,
+        _ => {
+            return Err(usage(
+                "set [number|nonumber|mode default|mode vim|theme <name>], \
+                 set [autoindent|trim_trailing_whitespace|insert_final_newline] [on|off], \
+                 set eol <buffer> [lf|crlf] or set readonly <buffer> [on|off]",
+            ))
+        }
+    }
+    Ok(())
+}
+
+/// Shows, or changes, whether the buffer called `name` is read-only.
+fn set_read_only(app: &mut App, name: &str, setting: Option<bool>) -> Result<(), Box<dyn Error>> {
+    let editor = get_editor(&mut app.editors, name)?;
+    match setting {
+        Some(true) => editor.read_only = true,
+        Some(false) => {
+            // It wasn't locked while it couldn't be written.
+            if let (Some(file), None) = (editor.buffer.file(), &editor.lock) {
+                let lock = Lock::acquire(Path::new(file)).map_err(|err| match err {
+                    LockError::Held(_) => {
+                        tr("readonly.locked", &[("name", &name), ("error", &err)])
+                    }
+                    err => format!("{}: {}", name, err),
+                })?;
+                editor.lock = Some(lock);
+            }
+            editor.read_only = false;
+        }
+        None => {}
+    }
+    match editor.read_only {
+        true => println!("{}", tr("editor.read-only", &[("name", &name)])),
+        false => println!("{}", tr("readonly.off", &[("name", &name)])),
+    }
+    Ok(())
+}
+
+const WRAP: Help = Help {
+    name: "wrap",
+    usage: &["wrap <buffer> on|off"],
+    about: "Turns soft wrapping on or off for a buffer, so long lines carry on along the rows under them instead of off the side of the screen.",
+    examples: &["wrap notes on"],
+};
+
+fn set_wrap(app: &mut App, args: &str) -> Result<(), Box<dyn Error>> {
+    let (name, wrap) = match args.split_whitespace().collect::<Vec<_>>()[..] {
+        [name, "on"] => (name, true),
+        [name, "off"] => (name, false),
+        _ => return Err(usage("wrap <buffer> on|off")),
+    };
+    get_editor(&mut app.editors, name)?.wrap = wrap;
+    Ok(())
+}
+
+const ALIAS: Help = Help {
+    name: "alias",
+    usage: &["alias [name] [expansion]"],
+    about: "Makes `name` short for `expansion` at the `>>` prompt, so after `alias rg search -i`, `rg foo` runs `search -i foo`. Aliases are kept in the `[aliases]` table of the config file, so they're there the next time too. With just a name, says what it stands for, and with nothing, lists every alias.",
+    examples: &["alias o open", "alias rg search -i"],
+};
+
+fn set_alias(app: &mut App, args: &str) -> Result<(), Box<dyn Error>> {
+    let name = first_word(args);
+    let expansion = args[name.len()..].trim();
+    match (name, expansion) {
+        ("", _) if app.aliases.is_empty() => println!("{}", tr("alias.none", &[])),
+        ("", _) => {
+            for (name, expansion) in &app.aliases {
+                println!("{} = {}", name, expansion);
+            }
+        }
+        (name, "") => match app.aliases.get(name) {
+            Some(expansion) => println!("{} = {}", name, expansion),
+            None => println!("{}", tr("alias.not-alias", &[("name", &name)])),
+        },
+        (name, expansion) => {
+            let word = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_';
+            if !name.chars().all(word) {
+                return Err(tr("alias.bad-name", &[("name", &name)]).into());
+            }
+            config::save_alias(name, Some(expansion))?;
+            app.aliases
+                .insert(String::from(name), String::from(expansion));
+            println!(
+                "{}",
+                tr("alias.set", &[("name", &name), ("expansion", &expansion)])
+            );
+        }
+    }
+    Ok(())
+}
+
+const UNALIAS: Help = Help {
+    name: "unalias",
+    usage: &["unalias <name>"],
+    about: "Takes the alias `name` out, and out of the config file.",
+    examples: &["unalias rg"],
+};
+
+fn remove_alias(app: &mut App, args: &str) -> Result<(), Box<dyn Error>> {
+    let name = match args.split_whitespace().collect::<Vec<_>>()[..] {
+        [name] => name,
+        _ => return Err(usage("unalias <name>")),
+    };
+    if !app.aliases.contains_key(name) {
+        return Err(tr("alias.not-alias", &[("name", &name)]).into());
+    }
+    config::save_alias(name, None)?;
+    app.aliases.remove(name);
+    println!("{}", tr("unalias.removed", &[("name", &name)]));
+    Ok(())
+}
+
+const KEYS: Help = Help {
+    name: "keys",
+    usage: &["keys"],
+    about: "Lists the editor's key bindings, as they're pressed on the keyboard layout in the config, with whatever `keys.toml` changed.",
+    examples: &[],
+};
+
+fn show_keys(app: &mut App, _args: &str) -> Result<(), Box<dyn Error>> {
+    for (key, action) in Keymap::for_config(&app.config).bindings() {
+        println!("{:<12} {}", key.to_string(), action.name());
+    }
+    Ok(())
+}
+
+const PLAY: Help = Help {
+    name: "play",
+    usage: &["play <path> [buffer] [--speed=N]"],
+    about: "Opens `buffer` (or a new one) and presses the keys recorded in the cast file at `path`, at `N` times the speed they were pressed.",
+    examples: &["play demo.cast scratch --speed=2"],
+};
+
+fn play_recording(app: &mut App, args: &str) -> Result<(), Box<dyn Error>> {
+    let (words, flags) = split_flags(args);
+    let (path, name) = match words[..] {
+        [path] => (path, None),
+        [path, name] => (path, Some(String::from(name))),
+        _ => return Err(usage("play <path> [buffer] [--speed=N]")),
+    };
+    let mut speed = 1.0;
+    for flag in flags {
+        match flag.strip_prefix("--speed=") {
+            Some(value) => speed = value.parse()?,
+            None => return Err(no_option("play", flag)),
+        }
+    }
+
+    let mut keys =
+        record::read_keys(&paths::expand(path)?).map_err(|err| format!("{}: {}", path, err))?;
+    // Recordings usually end with the Esc that closed the editor, which
+    // would close this one before there was a chance to look.
+    let esc = GameEvent::from(SimpleEvent::Just(KeyCode::Esc));
+    if keys.last().is_some_and(|(_, key)| *key == esc) {
+        keys.pop();
+    }
+    if keys.is_empty() {
+        return Err(tr("play.no-keys", &[("path", &path)]).into());
+    }
+
+    let name = match name {
+        Some(name) => name,
+        // A buffer just for the recording is a scratch one, with no file.
+        None => {
+            let name = fresh_name(&app.editors, "playback");
+            let editor = BufferEditor::new(name.clone(), Buffer::new(None), Rc::clone(&app.config));
+            app.editors.insert(name.clone(), editor);
+            name
+        }
+    };
+    fetch_editor(app, Some(&name), LockPolicy::Refuse)?.playback = Some(Playback::new(keys, speed));
+    run_editor(app, Some(&name), LockPolicy::Refuse)
+}
+
+const WRITE: Help = Help {
+    name: "write",
+    usage: &["write <buffer> [path] [--sudo] [--overwrite]"],
+    about: "Saves `buffer` to its file, or to `path`, which then becomes its file. If the file can't be written for lack of permission, `--sudo` writes it through the config's `privileged_helper` instead. If the file was changed by something else since it was loaded, it's only written over with `--overwrite`. With `set trim_trailing_whitespace` or `set insert_final_newline` on, the buffer's tidied up first.",
+    examples: &["write notes", "write notes ~/notes.md", "write hosts --sudo"],
+};
+
+fn write_buffer(app: &mut App, args: &str) -> Result<(), Box<dyn Error>> {
+    let (words, flags) = split_flags(args);
+    let (name, path) = match words[..] {
+        [name] => (name, None),
+        [name, path] => (name, Some(path)),
+        _ => return Err(usage("write <buffer> [path] [--sudo] [--overwrite]")),
+    };
+    let editor = editable_editor(&mut app.editors, name)?;
+    let file = match (path, editor.buffer.file()) {
+        (Some(path), _) => paths::resolve(path)?,
+        (None, Some(file)) => PathBuf::from(file),
+        (None, None) => return Err(tr("write.no-file", &[("name", &name)]).into()),
+    };
+    editor.tidy(
+        app.session.trim_trailing_whitespace,
+        app.session.insert_final_newline,
+    );
+    editor.save_to(
+        &file,
+        flags.contains(&"--sudo"),
+        flags.contains(&"--overwrite"),
+    )?;
+    println!("{}", tr("editor.saved", &[("path", &file.display())]));
+    if let Err(err) = editor.take_snapshot() {
+        println!(
+            "{}",
+            tr("write.snapshot-failed", &[("name", &name), ("error", &err)])
+        );
+    }
+    Ok(())
+}
+
+const DELETEFILE: Help = Help {
+    name: "deletefile",
+    usage: &["deletefile <buffer> [--force]"],
+    about:
+        "Closes `buffer` and moves its file to the trash, or with `--force`, deletes it for good.",
+    examples: &["deletefile old.txt"],
+};
+
+fn delete_file(app: &mut App, args: &str) -> Result<(), Box<dyn Error>> {
+    let (words, flags) = split_flags(args);
+    let [name] = words[..] else {
+        return Err(usage("deletefile <buffer> [--force]"));
+    };
+    let editor = editable_editor(&mut app.editors, name)?;
+    let file = PathBuf::from(editor.buffer.file().ok_or_else(|| no_file(name))?);
+    if editor.buffer.is_dirty() {
+        return Err(tr("deletefile.unsaved", &[("name", &name)]).into());
+    }
+
+    // The buffer's only closed once the file's gone, so it's still open
+    // if that fails. Its lock goes then, so it's not left next to where
+    // the file was.
+    let message = match flags.contains(&"--force") {
+        true => {
+            fs::remove_file(&file)?;
+            tr("repl.deleted", &[("path", &file.display())])
+        }
+        false => {
+            let trashed = trash::move_to_trash(&file)?;
+            tr(
+                "repl.moved",
+                &[("from", &file.display()), ("to", &trashed.display())],
+            )
+        }
+    };
+    drop(editor.lock.take());
+    app.editors.remove(name);
+    println!("{}", message);
+    Ok(())
+}
+
+const MOVE: Help = Help {
+    name: "move",
+    usage: &["move <buffer> <newpath>"],
+    about: "Renames `buffer`'s file, and the buffer along with it. Its undo history and snapshots come too.",
+    examples: &["move notes docs/notes.md"],
+};
+
+fn move_file(app: &mut App, args: &str) -> Result<(), Box<dyn Error>> {
+    let [name, new_name] = args.split_whitespace().collect::<Vec<_>>()[..] else {
+        return Err(usage("move <buffer> <newpath>"));
+    };
+    if app.editors.contains_key(new_name) {
+        return Err(buffer_exists(new_name).into());
+    }
+    let editor = editable_editor(&mut app.editors, name)?;
+    let file = PathBuf::from(editor.buffer.file().ok_or_else(|| no_file(name))?);
+    let new_file = paths::resolve(new_name)?;
+    if new_file.exists() {
+        return Err(tr("move.exists", &[("path", &new_file.display())]).into());
+    }
+
+    // The new place is locked first, so a file which another BuffeRS
+    // has open there isn't moved over.
+    let lock = Lock::acquire(&new_file).map_err(|err| match err {
+        LockError::Held(_) => tr("open.locked", &[("name", &new_name), ("error", &err)]),
+        err => format!("{}: {}", new_name, err),
+    })?;
+    paths::move_file(&file, &new_file).map_err(|err| format!("{}: {}", name, err))?;
+    let mut editor = app.editors.remove(name).unwrap();
+    editor.remove_recovery();
+    editor.buffer.set_file(new_file.display().to_string());
+    editor.watch = Some(FileWatch::new(&new_file));
+    editor.lock = Some(lock);
+    editor.name = String::from(new_name);
+    if let Err(err) = snapshot::rename(name, new_name) {
+        println!("{}", snapshots_not_moved(name, &err));
+    }
+    app.editors.insert(String::from(new_name), editor);
+    println!(
+        "{}",
+        tr(
+            "repl.moved",
+            &[("from", &file.display()), ("to", &new_file.display())]
+        )
+    );
+    Ok(())
+}
+
+const LS: Help = Help {
+    name: "ls",
+    usage: &["ls"],
+    about: "Lists the open buffers, how many lines each has, whether it has unsaved changes, and its file.",
+    examples: &[],
+};
+
+fn list_buffers(app: &mut App, args: &str) -> Result<(), Box<dyn Error>> {
+    if !args.is_empty() {
+        return Err(usage("ls"));
+    }
+    for line in buffer_list(app) {
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+/// A line for each open buffer, in order by name, as `ls` shows them.
+fn buffer_list(app: &App) -> Vec<String> {
+    let mut names: Vec<&String> = app.editors.keys().collect();
+    names.sort();
+    names
+        .into_iter()
+        .map(|name| {
+            let buffer = &app.editors[name].buffer;
+            let modified = match buffer.is_dirty() {
+                true => tr("status.modified", &[]),
+                false => String::new(),
+            };
+            let line = format!(
+                "{:<20} {:>8} {:<3} {}",
+                name,
+                buffer.len_lines(),
+                modified,
+                buffer.file().unwrap_or("")
+            );
+            String::from(line.trim_end())
+        })
+        .collect()
+}
+
+const CLOSE: Help = Help {
+    name: "close",
+    usage: &["close <buffer> [--force]"],
+    about: "Drops a buffer, letting go of its file. If it has unsaved changes, it's only closed once that's been confirmed, or with `--force`.",
+    examples: &["close notes --force"],
+};
+
+fn close_buffer(app: &mut App, args: &str) -> Result<(), Box<dyn Error>> {
+    let (words, flags) = split_flags(args);
+    let [name] = words[..] else {
+        return Err(usage("close <buffer> [--force]"));
+    };
+    let dirty = get_editor(&mut app.editors, name)?.buffer.is_dirty();
+    if dirty && !flags.contains(&"--force") {
+        let question = tr("close.confirm", &[("name", &name)]);
+        if !confirm(app, &question, false)? {
+            println!("{}", tr("close.nothing", &[]));
+            return Ok(());
+        }
+    }
+    // Its changes are gone, so there's nothing left to recover.
+    if let Some(editor) = app.editors.remove(name) {
+        editor.remove_recovery();
+    }
+    app.pending_replacements.remove(name);
+    println!("{}", tr("close.closed", &[("name", &name)]));
+    Ok(())
+}
+
+const QUIT: Help = Help {
+    name: "quit",
+    usage: &["quit [--force]"],
+    about: "Leaves BuffeRS, once it's been confirmed if any buffer has unsaved changes, or straight away with `--force`.",
+    examples: &[],
+};
+
+fn quit(app: &mut App, args: &str) -> Result<(), Box<dyn Error>> {
+    let (words, flags) = split_flags(args);
+    if !words.is_empty() {
+        return Err(usage("quit [--force]"));
+    }
+    app.quit = flags.contains(&"--force") || confirm_quit(app)?;
+    Ok(())
+}
+
+/// Asks whether to quit when there are unsaved changes, naming the
+/// buffers which have them. With none, there's nothing to ask.
+pub fn confirm_quit(app: &mut App) -> io::Result<bool> {
+    let mut unsaved: Vec<&str> = app
+        .editors
+        .values()
+        .filter(|editor| editor.buffer.is_dirty())
+        .map(|editor| editor.name.as_str())
+        .collect();
+    if unsaved.is_empty() {
+        return Ok(true);
+    }
+    unsaved.sort();
+    let question = match unsaved[..] {
+        [name] => tr("quit.confirm", &[("name", &name)]),
+        _ => tr("quit.confirm-many", &[("names", &unsaved.join(", "))]),
+    };
+    confirm(app, &question, true)
+}
+
+const RENAME: Help = Help {
+    name: "rename",
+    usage: &["rename <buffer> <newname>"],
+    about: "Changes the name a buffer goes by, keeping its text, history and file as they are.",
+    examples: &["rename scratch ideas"],
+};
+
+fn rename_buffer(app: &mut App, args: &str) -> Result<(), Box<dyn Error>> {
+    let [name, new_name] = args.split_whitespace().collect::<Vec<_>>()[..] else {
+        return Err(usage("rename <buffer> <newname>"));
+    };
+    if app.editors.contains_key(new_name) {
+        return Err(buffer_exists(new_name).into());
+    }
+    let mut editor = app.editors.remove(name).ok_or_else(|| no_buffer(name))?;
+    editor.name = String::from(new_name);
+    if let Err(err) = snapshot::rename(name, new_name) {
+        println!("{}", snapshots_not_moved(name, &err));
+    }
+    if let Err(err) = autosave::rename_recovery(name, new_name) {
+        println!(
+            "{}",
+            tr(
+                "rename.recovery-not-moved",
+                &[("name", &name), ("error", &err)]
+            )
+        );
+    }
+    app.editors.insert(String::from(new_name), editor);
+    if let Some(changes) = app.pending_replacements.remove(name) {
+        app.pending_replacements
+            .insert(String::from(new_name), changes);
+    }
+    println!(
+        "{}",
+        tr("rename.renamed", &[("name", &name), ("new", &new_name)])
+    );
+    Ok(())
+}
+
+const RECOVER: Help = Help {
+    name: "recover",
+    usage: &["recover <buffer> [--discard]"],
+    about: "Restores the text left in `buffer`'s swap file by a BuffeRS which didn't get to save it. The buffer's own text can still be had back with undo. With `--discard`, the swap file is deleted instead.",
+    examples: &["recover notes"],
+};
+
+fn recover_buffer(app: &mut App, args: &str) -> Result<(), Box<dyn Error>> {
+    let (words, flags) = split_flags(args);
+    let [name] = words[..] else {
+        return Err(usage("recover <buffer> [--discard]"));
+    };
+    let editor = get_editor(&mut app.editors, name)?;
+    let file = PathBuf::from(
+        editor
+            .buffer
+            .file()
+            .ok_or_else(|| tr("recover.no-file", &[("name", &name)]))?,
+    );
+    let path = swap::swap_path(&file);
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == ErrorKind::NotFound => {
+            return Err(tr("recover.no-swap", &[("name", &name)]).into())
+        }
+        Err(err) => return Err(format!("{}: {}", path.display(), err).into()),
+    };
+    editor.swap = Some(Swap::take_over(&file));
+    if flags.contains(&"--discard") {
+        fs::remove_file(&path)?;
+        println!("{}", tr("repl.deleted", &[("path", &path.display())]));
+        return Ok(());
+    }
+    check_editable(editor)?;
+    editor.buffer.set_text(&text);
+    println!(
+        "{}",
+        tr(
+            "recover.recovered",
+            &[("name", &name), ("path", &path.display())]
+        )
+    );
+    Ok(())
+}
+
+const MERGE: Help = Help {
+    name: "merge",
+    usage: &["merge <buffer>"],
+    about: "When `buffer`'s file has been changed by something else since it was loaded, combines those changes with the buffer's own. Where the two clash, both versions (and the one they started from) are left in the buffer between conflict markers to be sorted out before writing.",
+    examples: &[],
+};
+
+fn merge_file(app: &mut App, args: &str) -> Result<(), Box<dyn Error>> {
+    let name = first_word(args);
+    let editor = editable_editor(&mut app.editors, name)?;
+    let (Some(file), Some(theirs)) = (editor.buffer.file(), editor.buffer.read_file()) else {
+        return Err(no_file(name).into());
+    };
+    let theirs = theirs.map_err(|err| format!("{}: {}", file, err))?;
+    if theirs == editor.buffer.saved_text() {
+        println!("{}", tr("merge.unchanged", &[("name", &name)]));
+        return Ok(());
+    }
+
+    let (merged, conflicts) =
+        diff::merge3(editor.buffer.saved_text(), editor.buffer.text(), &theirs);
+    editor.buffer.set_text(&merged);
+    editor.buffer.set_saved_text(theirs);
+    if let Some(watch) = &mut editor.watch {
+        watch.reset();
+    }
+    match conflicts {
+        0 => println!("{}", tr("merge.clean", &[("name", &name)])),
+        n => println!("{}", tr("merge.conflicts", &[("count", &n)])),
+    }
+    Ok(())
+}
+
+const TIMELINE: Help = Help {
+    name: "timeline",
+    usage: &["timeline <buffer> [snapshot] [--restore]"],
+    about: "Lists the snapshots of `buffer`, with how much each differs from the buffer now. Given a snapshot's number, shows the difference in full, or with `--restore`, puts the buffer back how it was (as a change that can be undone).",
+    examples: &["timeline notes", "timeline notes 3 --restore"],
+};
+
+fn show_timeline(app: &mut App, args: &str) -> Result<(), Box<dyn Error>> {
+    let (words, flags) = split_flags(args);
+    let (name, number) = match words[..] {
+        [name] => (name, None),
+        [name, number] => (name, Some(number.parse::<usize>()?)),
+        _ => return Err(usage("timeline <buffer> [snapshot] [--restore]")),
+    };
+    let editor = get_editor(&mut app.editors, name)?;
+    if flags.contains(&"--restore") {
+        check_editable(editor)?;
+    }
+    let buffer = &mut editor.buffer;
+    let snapshots = snapshot::list(name)?;
+
+    let Some(number) = number else {
+        if snapshots.is_empty() {
+            println!("{}", tr("timeline.none", &[("name", &name)]));
+        }
+        for (i, snapshot) in snapshots.iter().enumerate() {
+            let age = snapshot.taken.elapsed().unwrap_or_default().as_secs();
+            let text = snapshot.text()?;
+            let changes = diff::lines(&text, buffer.text());
+            let (added, removed) = diff::stats(&changes);
+            println!(
+                "{}",
+                tr(
+                    "timeline.snapshot",
+                    &[
+                        ("number", &(i + 1)),
+                        ("age", &format_age(age)),
+                        ("added", &added),
+                        ("removed", &removed)
+                    ]
+                )
+            );
+        }
+        return Ok(());
+    };
+
+    let snapshot = number
+        .checked_sub(1)
+        .and_then(|index| snapshots.get(index))
+        .ok_or_else(|| {
+            tr(
+                "timeline.no-snapshot",
+                &[("name", &name), ("number", &number)],
+            )
+        })?;
+    let text = snapshot.text()?;
+    if flags.contains(&"--restore") {
+        buffer.set_text(&text);
+        println!(
+            "{}",
+            tr("timeline.restored", &[("name", &name), ("number", &number)])
+        );
+    } else {
+        print!("{}", diff::unified(&text, buffer.text(), 2));
+    }
+    Ok(())
+}
+
+/// Roughly how long `secs` seconds is, like `5m` or `2d`.
+fn format_age(secs: u64) -> String {
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m", secs / 60),
+        3600..=86399 => format!("{}h", secs / 3600),
+        _ => format!("{}d", secs / 86400),
+    }
+}
+
+const AUTOREVERT: Help = Help {
+    name: "autorevert",
+    usage: &["autorevert <buffer> [on|off]"],
+    about: "Shows, or changes, whether `buffer` reloads by itself when its file changes on disk. The default comes from `auto_revert` in the config.",
+    examples: &["autorevert app.log off"],
+};
+
+fn set_auto_revert(app: &mut App, args: &str) -> Result<(), Box<dyn Error>> {
+    let words: Vec<&str> = args.split_whitespace().collect();
+    let (name, setting) = match words[..] {
+        [name] => (name, None),
+        [name, "on"] => (name, Some(true)),
+        [name, "off"] => (name, Some(false)),
+        _ => return Err(usage("autorevert <buffer> [on|off]")),
+    };
+    let editor = get_editor(&mut app.editors, name)?;
+    if let Some(setting) = setting {
+        editor.auto_revert = setting;
+    }
+    match editor.auto_revert {
+        true => println!("{}", tr("autorevert.on", &[("name", &name)])),
+        false => println!("{}", tr("autorevert.off", &[("name", &name)])),
+    }
+    Ok(())
+}
+
+const ENCODING: Help = Help {
+    name: "encoding",
+    usage: &["encoding <buffer> [encoding]"],
+    about: "Shows, or changes, the encoding `buffer`'s file is read and written in. A buffer without unsaved changes is read again from its file in the new encoding, for when the one it was opened in was wrong, and otherwise the new encoding is what it's next written in.",
+    examples: &["encoding legacy.txt latin-1"],
+};
+
+fn set_encoding(app: &mut App, args: &str) -> Result<(), Box<dyn Error>> {
+    let words: Vec<&str> = args.split_whitespace().collect();
+    let (name, encoding) = match words[..] {
+        [name] => (name, None),
+        [name, encoding] => (name, Some(encoding)),
+        _ => return Err(usage("encoding <buffer> [encoding]")),
+    };
+    let editor = get_editor(&mut app.editors, name)?;
+    if let Some(encoding) = encoding {
+        let encoding = Encoding::by_name(encoding).ok_or_else(|| {
+            let names: Vec<&str> = ENCODINGS.iter().map(|(_, name)| *name).collect();
+            let names = names.join(", ");
+            tr(
+                "encoding.unknown",
+                &[("encoding", &encoding), ("names", &names)],
+            )
+        })?;
+        reencode(editor, encoding)?;
+    }
+    println!(
+        "{}",
+        tr(
+            "encoding.shown",
+            &[("name", &name), ("encoding", &editor.buffer.encoding())]
+        )
+    );
+    Ok(())
+}
+
+/// Switches `editor`'s buffer to `encoding`, reading it again from its
+/// file if it has no unsaved changes.
+fn reencode(editor: &mut BufferEditor, encoding: Encoding) -> Result<(), Box<dyn Error>> {
+    let hex = encoding == Encoding::Hex || editor.buffer.encoding() == Encoding::Hex;
+    match editor.buffer.read_file_in(encoding) {
+        Some(text) if !editor.buffer.is_dirty() => {
+            let file = editor.buffer.file().unwrap_or_default();
+            let text = text.map_err(|err| format!("{}: {}", file, err))?;
+            editor.buffer.set_encoding(encoding);
+            editor.buffer.set_text(&text);
+            editor.buffer.mark_saved();
+        }
+        // Text and a hex dump can't be written as each other.
+        _ if hex && !editor.buffer.text().is_empty() => {
+            return Err(tr("encoding.hex-unsaved", &[("name", &editor.name)]).into())
+        }
+        _ => editor.buffer.set_encoding(encoding),
+    }
+    Ok(())
+}
+
+const STRESS: Help = Help {
+    name: "stress",
+    usage: &["stress [seed] [steps]"],
+    about: "Checks the buffer against a simple model over a stream of random edits. The same seed always gives the same edits.",
+    examples: &["stress 42 1000"],
+};
+
+fn run_stress(_app: &mut App, args: &str) -> Result<(), Box<dyn Error>> {
+    let words: Vec<&str> = args.split_whitespace().collect();
+    let (seed, steps) = match words[..] {
+        [] => (0, 10_000),
+        [seed] => (seed.parse()?, 10_000),
+        [seed, steps] => (seed.parse()?, steps.parse()?),
+        _ => return Err(usage("stress [seed] [steps]")),
+    };
+    stress::run(seed, steps)?;
+    println!(
+        "{}",
+        tr("stress.passed", &[("steps", &steps), ("seed", &seed)])
+    );
+    Ok(())
+}
+
+const COPY_BLOCK: Help = Help {
+    name: "copy-block",
+    usage: &["copy-block <buffer> <first-line> <last-line> <first-col> <last-col>"],
+    about: "Copies a rectangle of text (lines and columns are 1-indexed and inclusive), which Ctrl+V then pastes as a rectangle.",
+    examples: &["copy-block table 2 10 5 12"],
+};
+
+fn copy_block(app: &mut App, args: &str) -> Result<(), Box<dyn Error>> {
+    let words: Vec<&str> = args.split_whitespace().collect();
+    let [name, lines @ .., first_col, last_col] = &words[..] else {
+        return Err(usage(
+            "copy-block <buffer> <first-line> <last-line> <first-col> <last-col>",
+        ));
+    };
+    let buffer = &get_editor(&mut app.editors, name)?.buffer;
+    let lines = parse_line_range(buffer, lines)?;
+    let (first_col, last_col): (usize, usize) = (first_col.parse()?, last_col.parse()?);
+    if first_col == 0 || first_col > last_col {
+        return Err(tr(
+            "copy-block.bad-columns",
+            &[("first", &first_col), ("last", &last_col)],
+        )
+        .into());
+    }
+
+    let clip = Clip::block(buffer.text(), lines, first_col - 1..last_col);
+    println!(
+        "{}",
+        tr(
+            "copy-block.copied",
+            &[("count", &clip.text.lines().count())]
+        )
+    );
+    app.session.clipboard.copy(clip);
+    Ok(())
+}
+
+const CLIPS: Help = Help {
+    name: "clips",
+    usage: &["clips [search <text>|use <n>|pin <n>|unpin <n>|clear]"],
+    about: "Lists everything that's been copied or cleared, newest first, or just the clips containing `text`. `use` makes clip `n` the one Ctrl+V pastes, and pinned clips are kept however many more are copied.",
+    examples: &["clips search TODO", "clips use 3"],
+};
+
+fn show_clips(app: &mut App, args: &str) -> Result<(), Box<dyn Error>> {
+    let clipboard = &mut app.session.clipboard;
+    let clip_index = |n: &str| -> Result<usize, Box<dyn Error>> {
+        match n.parse::<usize>()? {
+            0 => Err(tr("clips.from-one", &[]).into()),
+            n => Ok(n - 1),
+        }
+    };
+    let missing = |n: &str| tr("clips.missing", &[("number", &n)]);
+    let (verb, rest) = args.split_once(' ').unwrap_or((args, ""));
+    match (verb, rest.trim()) {
+        ("", _) => list_clips(clipboard, ""),
+        ("search", term) => list_clips(clipboard, term),
+        ("use", n) => {
+            let clip = clipboard.select(clip_index(n)?).ok_or_else(|| missing(n))?;
+            println!("{}", tr("clips.used", &[("clip", &clip.preview(60))]));
+        }
+        ("pin", n) => clipboard
+            .set_pinned(clip_index(n)?, true)
+            .ok_or_else(|| missing(n))?,
+        ("unpin", n) => clipboard
+            .set_pinned(clip_index(n)?, false)
+            .ok_or_else(|| missing(n))?,
+        ("clear", "") => clipboard.clear(),
+        _ => {
+            return Err(usage(
+                "clips [search <text>|use <n>|pin <n>|unpin <n>|clear]",
+            ))
+        }
+    }
+    Ok(())
+}
+
+/// Prints the clips containing `term`, numbered, with pinned ones
+/// marked with a `*`.
+fn list_clips(clipboard: &Clipboard, term: &str) {
+    let mut listed = 0;
+    for (i, clip) in clipboard.clips().iter().enumerate() {
+        if clip.text.contains(term) {
+            let pin = if clip.pinned { '*' } else { ' ' };
+            println!("{:>3}{} {}", i + 1, pin, clip.preview(60));
+            listed += 1;
+        }
+    }
+    if listed == 0 {
+        println!("{}", tr("clips.none", &[]));
+    }
+}
+
+const REGISTERS: Help = Help {
+    name: "registers",
+    usage: &["registers"],
+    about: "Lists what's in the default register, which is the clip Ctrl+V pastes, and in each named register with anything in it.",
+    examples: &[],
+};
+
+fn show_registers(app: &mut App, args: &str) -> Result<(), Box<dyn Error>> {
+    if !args.is_empty() {
+        return Err(usage("registers"));
+    }
+    let session = &app.session;
+    let default = session.clipboard.current().map(|clip| ('"', clip));
+    let registers: Vec<(char, &Clip)> = default
+        .into_iter()
+        .chain(session.registers.iter())
+        .collect();
+    if registers.is_empty() {
+        println!("{}", tr("registers.none", &[]));
+    }
+    for (name, clip) in registers {
+        println!("\"{} {}", name, clip.preview(60));
+    }
+    Ok(())
+}
+
+const PASTE: Help = Help {
+    name: "paste",
+    usage: &["paste <buffer> [register]"],
+    about: "Pastes the default register, or the register named `a` to `z`, at the buffer's cursor.",
+    examples: &["paste notes a"],
+};
+
+fn paste_register(app: &mut App, args: &str) -> Result<(), Box<dyn Error>> {
+    let (name, register) = match args.split_whitespace().collect::<Vec<_>>()[..] {
+        [name] => (name, None),
+        [name, register] => {
+            match register.trim_start_matches('"').chars().collect::<Vec<_>>()[..] {
+                [register] if Registers::is_name(register) => (name, Some(register)),
+                _ => return Err(tr("paste.not-register", &[("register", &register)]).into()),
+            }
+        }
+        _ => return Err(usage("paste <buffer> [register]")),
+    };
+    let clip = match register {
+        Some(register) => app.session.registers.get(register),
+        None => app.session.clipboard.current(),
+    };
+    let text = clip.ok_or_else(|| tr("paste.empty", &[]))?.text.clone();
+    editable_editor(&mut app.editors, name)?
+        .buffer
+        .insert(&text);
+    Ok(())
+}
+
+const REPLACE: Help = Help {
+    name: "replace",
+    usage: &["replace <buffer> <pattern> <replacement> [--all] [--preserve-case] [--regex]"],
+    about: "Replaces the first occurrence of `pattern`, or every one with `--all`, and says how many it replaced. With `--preserve-case`, matching ignores case and each replacement takes on the case of what it replaced. With `--regex`, the pattern is a regular expression and the replacement can use its capture groups, as `$1` or `${name}`.",
+    examples: &["replace notes colour color --all", "replace notes colou?r shade --all --regex"],
+};
+
+fn replace_text(app: &mut App, args: &str) -> Result<(), Box<dyn Error>> {
+    let (words, flags) = split_flags(args);
+    let [name, pattern, replacement] = words[..] else {
+        return Err(usage(
+            "replace <buffer> <pattern> <replacement> [--all] [--preserve-case] [--regex]",
+        ));
+    };
+    let options = ReplaceOptions {
+        preserve_case: flags.contains(&"--preserve-case"),
+        regex: flags.contains(&"--regex"),
+        first_only: !flags.contains(&"--all"),
+    };
+
+    let buffer = &mut editable_editor(&mut app.editors, name)?.buffer;
+    let (text, count) = replace::replace(buffer.text(), pattern, replacement, options)?;
+    buffer.set_text(&text);
+    println!("{}", tr("replace.made", &[("count", &count)]));
+    Ok(())
+}
+
+const REPLACE_ALL: Help = Help {
+    name: "replace-all",
+    usage: &["replace-all <pattern> <replacement> [--preserve-case] [--regex]"],
+    about: "Like `replace`, but over every file under the current directory, whether or not it's open. Nothing is changed yet: the changes are listed in a new preview buffer, where a hunk can be rejected by changing its `[x]` to `[ ]`, before `apply-replace` makes them.",
+    examples: &["replace-all getUser fetchUser"],
+};
+
+fn replace_in_project(app: &mut App, args: &str) -> Result<(), Box<dyn Error>> {
+    let (words, flags) = split_flags(args);
+    let [pattern, replacement] = words[..] else {
+        return Err(usage(
+            "replace-all <pattern> <replacement> [--preserve-case] [--regex]",
+        ));
+    };
+    let options = ReplaceOptions {
+        preserve_case: flags.contains(&"--preserve-case"),
+        regex: flags.contains(&"--regex"),
+        first_only: false,
+    };
+
+    let changes = project::plan(Path::new("."), pattern, replacement, options)?;
+    let hunks: usize = changes.iter().map(|change| change.hunks.len()).sum();
+    if hunks == 0 {
+        println!("{}", tr("replace-all.none", &[]));
+        return Ok(());
+    }
+
+    let preview_name = fresh_name(&app.editors, "replace");
+    println!(
+        "{}",
+        tr(
+            "replace-all.found",
+            &[
+                ("hunks", &hunks),
+                ("files", &changes.len()),
+                ("name", &preview_name)
+            ]
+        )
+    );
+    let preview = Buffer::from_text(None, project::preview(&changes));
+    let mut editor = BufferEditor::new(preview_name.clone(), preview, Rc::clone(&app.config));
+    editor.shows_changes = true;
+    app.editors.insert(preview_name.clone(), editor);
+    app.pending_replacements.insert(preview_name, changes);
+    Ok(())
+}
+
+const APPLY_REPLACE: Help = Help {
+    name: "apply-replace",
+    usage: &["apply-replace <preview>"],
+    about: "Makes the changes still ticked in a `replace-all` preview, then opens every file that changed as a buffer.",
+    examples: &["apply-replace replace-preview"],
+};
+
+fn apply_replacements(app: &mut App, args: &str) -> Result<(), Box<dyn Error>> {
+    let name = first_word(args);
+    let changes = app
+        .pending_replacements
+        .get(name)
+        .ok_or_else(|| tr("apply-replace.not-preview", &[("name", &name)]))?;
+    let accepted = project::accepted(get_editor(&mut app.editors, name)?.buffer.text());
+    check_replaceable(&app.editors, changes, &accepted)?;
+    let updates = project::apply(changes, &accepted, app.config.backups())?;
+
+    app.pending_replacements.remove(name);
+    app.editors.remove(name);
+    println!(
+        "{}",
+        tr("apply-replace.changed", &[("count", &updates.len())])
+    );
+    for (path, text) in updates {
+        let file = paths::canonical(&path)?;
+        match find_by_file(&app.editors, &file).cloned() {
+            Some(existing) => {
+                let editor = get_editor(&mut app.editors, &existing)?;
+                editor.buffer.set_text(&text);
+                editor.buffer.mark_saved();
+                if let Some(watch) = &mut editor.watch {
+                    watch.reset();
+                }
+            }
+            None => {
+                let name = path.display().to_string();
+                let buffer = Buffer::from_text(Some(file.display().to_string()), text);
+                let mut editor = BufferEditor::new(name.clone(), buffer, Rc::clone(&app.config));
+                // It was just changed, so it's hopefully not open elsewhere,
+                // but if it is the lock is left with whoever has it.
+                editor.lock = Lock::acquire(&file).ok();
+                app.editors.insert(name, editor);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Refuses to replace a file which is open in a buffer with unsaved
+/// changes, which replacing it would lose, or in one which can't be
+/// edited. Every such buffer is named, not just the first.
+fn check_replaceable(
+    editors: &HashMap<String, BufferEditor>,
+    changes: &[project::FileChange],
+    accepted: &[bool],
+) -> Result<(), Box<dyn Error>> {
+    let mut hunk = 0;
+    let mut problems = Vec::new();
+    for change in changes {
+        let hunks = hunk..hunk + change.hunks.len();
+        hunk = hunks.end;
+        if !hunks.into_iter().any(|i| accepted.get(i) == Some(&true)) {
+            continue;
+        }
+        let Some(name) = paths::canonical(&change.path)
+            .ok()
+            .and_then(|file| find_by_file(editors, &file))
+        else {
+            continue;
+        };
+        let editor = &editors[name];
+        if let Err(err) = check_editable(editor) {
+            problems.push(err.to_string());
+        } else if editor.buffer.is_dirty() {
+            problems.push(tr("apply-replace.unsaved", &[("name", &name)]));
+        }
+    }
+    match problems.is_empty() {
+        true => Ok(()),
+        false => Err(problems.join("; ").into()),
+    }
+}
+
+/// Finds the editor for the buffer called `name`.
+fn get_editor<'a>(
+    editors: &'a mut HashMap<String, BufferEditor>,
+    name: &str,
+) -> Result<&'a mut BufferEditor, Box<dyn Error>> {
+    editors.get_mut(name).ok_or_else(|| no_buffer(name).into())
+}
+
+/// Like [`get_editor`], but only if the buffer can be edited: not one
+/// that's read-only, nor a huge file, which only has part of its text
+/// loaded at a time.
+fn editable_editor<'a>(
+    editors: &'a mut HashMap<String, BufferEditor>,
+    name: &str,
+) -> Result<&'a mut BufferEditor, Box<dyn Error>> {
+    let editor = get_editor(editors, name)?;
+    check_editable(editor)?;
+    Ok(editor)
+}
+
+/// Refuses to go on when `editor`'s buffer can't be edited.
+fn check_editable(editor: &BufferEditor) -> Result<(), Box<dyn Error>> {
+    match editor.read_only || editor.huge.is_some() {
+        true => Err(tr("editor.read-only", &[("name", &editor.name)]).into()),
+        false => Ok(()),
+    }
+}
+
+/// Splits a command's arguments into plain words, and `--flags`.
+fn split_flags(args: &str) -> (Vec<&str>, Vec<&str>) {
+    args.split_whitespace()
+        .partition(|word| !word.starts_with("--"))
+}
+
+/// Parses an optional `start end` pair of (1-indexed, inclusive) line
+/// numbers into a range of line indexes. No arguments means every line.
+fn parse_line_range(buffer: &Buffer, args: &[&str]) -> Result<Range<usize>, Box<dyn Error>> {
+    let line_count = buffer.len_lines();
+    match args {
+        [] => Ok(0..line_count),
+        [start, end] => {
+            let start: usize = start.parse()?;
+            let end: usize = end.parse()?;
+            if start == 0 || start > end || start > line_count {
+                return Err(tr("repl.bad-lines", &[("start", &start), ("end", &end)]).into());
+            }
+            Ok(start - 1..end.min(line_count))
+        }
+        _ => Err(tr("repl.lines-syntax", &[]).into()),
+    }
+}
+
+fn first_word(s: &str) -> &str {
+    let bytes = s.as_bytes();
+
+    for (i, &item) in bytes.iter().enumerate() {
+        if item == b' ' {
+            return &s[0..i];
+        }
+    }
+
+    s
+}
+
+/// The name of the buffer backed by `file`, if it's open. `file`
+/// should be [`paths::canonical`], as buffers' files are.
+fn find_by_file<'a>(editors: &'a HashMap<String, BufferEditor>, file: &Path) -> Option<&'a String> {
+    editors
+        .iter()
+        .find(|(_, editor)| editor.buffer.file().map(Path::new) == Some(file))
+        .map(|(name, _)| name)
+}
+
+/// Picks a name like `prefix_0` that no open buffer is using.
+fn fresh_name(editors: &HashMap<String, BufferEditor>, prefix: &str) -> String {
+    // Not the most bullet proof strat, but it'll do
+    (0..)
+        .map(|uuid| format!("{}_{}", prefix, uuid))
+        .find(|name| !editors.contains_key(name))
+        .unwrap()
+}
+
+/// Finds the editor called `name`, or creates it: from the file called
+/// `name` if there is one, as a listing if it's a directory, or empty if
+/// there's nothing by that name. Anything else is an error.
+fn fetch_editor<'a>(
+    app: &'a mut App,
+    name: Option<&str>,
+    lock_policy: LockPolicy,
+) -> Result<&'a mut BufferEditor, Box<dyn Error>> {
+    let buffer_name: String = match name {
+        Some(name) => String::from(name),
+        // New buffer w/ random name
+        None => fresh_name(&app.editors, "buffer"),
+    };
+    if app.editors.contains_key(&buffer_name) {
+        let editor = get_editor(&mut app.editors, &buffer_name)?;
+        editor.read_only |= lock_policy == LockPolicy::ReadOnly;
+        return Ok(editor);
+    }
+
+    let path = paths::expand(&buffer_name)?;
+    // Only a name with nothing there makes a blank buffer. Anything which
+    // is there but can't be read is an error, rather than a blank buffer
+    // which would overwrite it when saved.
+    let metadata = match fs::metadata(&path) {
+        Ok(metadata) => Some(metadata),
+        Err(err) if err.kind() == ErrorKind::NotFound && path.is_symlink() => {
+            return Err(tr("open.dangling", &[("name", &buffer_name)]).into())
+        }
+        Err(err) if err.kind() == ErrorKind::NotFound => None,
+        Err(err) => return Err(format!("{}: {}", buffer_name, err).into()),
+    };
+    if metadata.as_ref().is_some_and(|metadata| metadata.is_dir()) {
+        let directory = Directory::read(&paths::canonical(&path)?)?;
+        let mut editor = BufferEditor::new(
+            buffer_name.clone(),
+            Buffer::new(None),
+            Rc::clone(&app.config),
+        );
+        editor.show_directory(directory);
+        return Ok(app.editors.entry(buffer_name).or_insert(editor));
+    }
+
+    let editor = match metadata {
+        Some(metadata) if metadata.is_file() => {
+            // However the file was written, if it's already open, that's
+            // the buffer to use.
+            let path = paths::canonical(&path)?;
+            if let Some(existing) = find_by_file(&app.editors, &path) {
+                println!(
+                    "{}",
+                    tr(
+                        "open.already-open",
+                        &[("name", &buffer_name), ("existing", existing)]
+                    )
+                );
+                let existing = existing.clone();
+                let editor = get_editor(&mut app.editors, &existing)?;
+                editor.read_only |= lock_policy == LockPolicy::ReadOnly;
+                return Ok(editor);
+            }
+
+            if metadata.len() >= app.config.huge_file_bytes() {
+                println!("{}", tr("open.huge", &[("name", &buffer_name)]));
+                let editor = BufferEditor::huge(buffer_name.clone(), &path, Rc::clone(&app.config))
+                    .map_err(|err| format!("{}: {}", buffer_name, err))?;
+                return Ok(app.editors.entry(buffer_name).or_insert(editor));
+            }
+            // A buffer which won't be written to doesn't need the lock.
+            let (lock, read_only) = match lock_policy {
+                LockPolicy::ReadOnly => (None, true),
+                _ => match Lock::acquire(&path) {
+                    Ok(lock) => (Some(lock), false),
+                    Err(LockError::Held(_)) if lock_policy == LockPolicy::Steal => {
+                        (Some(Lock::steal(&path)?), false)
+                    }
+                    Err(err @ LockError::Held(_)) => {
+                        return Err(
+                            tr("open.locked", &[("name", &buffer_name), ("error", &err)]).into(),
+                        )
+                    }
+                    Err(err) => return Err(format!("{}: {}", buffer_name, err).into()),
+                },
+            };
+            let buffer = Buffer::from_file(&path.to_string_lossy())
+                .map_err(|err| format!("{}: {}", buffer_name, err))?;
+            if let Err(err) = recent::add(&path.to_string_lossy()) {
+                tracing::warn!(error = %err, "couldn't update the recent files");
+            }
+            if buffer.mixed_eol() {
+                let eol = buffer.eol();
+                println!(
+                    "{}",
+                    tr("open.mixed-eol", &[("name", &buffer_name), ("eol", &eol)])
+                );
+            }
+            let mut editor = BufferEditor::new(buffer_name.clone(), buffer, Rc::clone(&app.config));
+            editor.lock = lock;
+            editor.read_only = read_only;
+            editor
+        }
+        Some(_) => return Err(tr("open.not-a-file", &[("name", &buffer_name)]).into()),
+        None => {
+            // A name that was asked for is where the buffer's saved, so the
+            // file's made then without asking where to put it.
+            let file = name.and_then(|_| paths::canonical(&path).ok());
+            let buffer = Buffer::new(file.as_ref().map(|file| file.display().to_string()));
+            let mut editor = BufferEditor::new(buffer_name.clone(), buffer, Rc::clone(&app.config));
+            editor.read_only = lock_policy == LockPolicy::ReadOnly;
+            if let Some(file) = file.filter(|_| !editor.read_only) {
+                editor.lock = Lock::acquire(&file).ok();
+            }
+            editor
+        }
+    };
+    if editor.read_only {
+        println!("{}", tr("open.read-only", &[("name", &buffer_name)]));
+    }
+    Ok(app.editors.entry(buffer_name).or_insert(editor))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    use crate::harness::{app, command, ctrl, open, Harness, TempFile};
+
+    /// Writes `script` to a file of its own for `source` to run.
+    fn script(name: &str, script: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "buffers-source-{}-{}.ws03",
+            name,
+            std::process::id()
+        ));
+        std::fs::write(&path, script).unwrap();
+        path
+    }
+
+    #[test]
+    fn source_runs_each_command_skipping_comments_and_blank_lines() {
+        let path = script("skips", "# line numbers on\n\n   \nset number\n");
+        let mut app = app();
+        command(&mut app, &format!("source {}", path.display())).unwrap();
+        assert!(app.session.line_numbers);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn source_stops_at_the_first_failure_unless_it_keeps_going() {
+        let path = script("fails", "set number\nundo nope\nset nonumber\n");
+        let path = path.display();
+        let mut app = app();
+        let err = command(&mut app, &format!("source {}", path)).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("{}:2: No buffer named 'nope'", path)
+        );
+        assert!(app.session.line_numbers);
+
+        let err = command(&mut app, &format!("source {} --keep-going", path)).unwrap_err();
+        assert_eq!(err.to_string(), format!("1 command(s) in {} failed", path));
+        assert!(!app.session.line_numbers);
+        std::fs::remove_file(path.to_string()).unwrap();
+    }
+
+    #[test]
+    fn source_fails_on_a_command_there_isnt() {
+        let path = script("unknown", "\nfrobnicate now\n");
+        let mut app = app();
+        let err = command(&mut app, &format!("source {}", path.display())).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("{}:2: there's no frobnicate command", path.display())
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn a_script_cannot_source_itself() {
+        let path =
+            std::env::temp_dir().join(format!("buffers-source-self-{}.ws03", std::process::id()));
+        std::fs::write(&path, format!("source {}\n", path.display())).unwrap();
+        let mut app = app();
+        let err = command(&mut app, &format!("source {}", path.display())).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("{0}:1: {0} is already being sourced", path.display())
+        );
+        assert!(app.sourcing.is_empty());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn changing_line_endings_leaves_the_buffer_unsaved() {
+        let file = TempFile::new("mixed", "one\r\ntwo\r\nthree\n");
+        let mut app = app();
+        let editor = file.editor("mixed", Rc::clone(&app.config));
+        assert!(editor.buffer.mixed_eol());
+        assert_eq!(editor.buffer.eol(), LineEnding::CrLf);
+        app.editors.insert(String::from("mixed"), editor);
+
+        command(&mut app, "set eol mixed crlf").unwrap();
+        assert!(!app.editors["mixed"].buffer.is_dirty());
+        command(&mut app, "set eol mixed lf").unwrap();
+        assert!(app.editors["mixed"].buffer.is_dirty());
+    }
+
+    #[test]
+    fn read_only_buffers_cant_be_edited_from_the_prompt() {
+        let mut app = app();
+        open(&mut app, "notes", "b\na\n");
+        command(&mut app, "set readonly notes on").unwrap();
+        for line in [
+            "sort notes",
+            "replace notes a c",
+            "reverse notes",
+            "undo notes",
+        ] {
+            let err = command(&mut app, line).unwrap_err();
+            assert_eq!(err.to_string(), "notes is read-only");
+        }
+        command(&mut app, "set readonly notes off").unwrap();
+        command(&mut app, "sort notes").unwrap();
+        assert_eq!(app.editors["notes"].buffer.text(), "a\nb\n");
+    }
+
+    #[test]
+    fn edits_at_the_prompt_keep_to_the_undo_limit() {
+        let mut app = app();
+        app.config = Rc::new(config::Config {
+            undo_limit: 2048,
+            ..config::Config::default()
+        });
+        open(&mut app, "notes", "tick");
+        for _ in 0..50 {
+            command(&mut app, "replace notes tick tock").unwrap();
+            command(&mut app, "replace notes tock tick").unwrap();
+        }
+        let memory = app.editors["notes"].buffer.memory();
+        assert!(memory.history <= 2048);
+        assert!(memory.states < 100);
+    }
+
+    #[test]
+    fn ls_lists_every_buffer_in_order() {
+        let mut app = app();
+        assert!(buffer_list(&app).is_empty());
+        open(&mut app, "notes", "one\ntwo\n");
+        open(&mut app, "empty", "");
+        app.editors
+            .get_mut("empty")
+            .unwrap()
+            .buffer
+            .set_file(String::from("/tmp/empty.txt"));
+        let status = tr("status.modified", &[]);
+        assert_eq!(
+            buffer_list(&app),
+            [
+                format!("{:<20} {:>8} {:<3} /tmp/empty.txt", "empty", 0, ""),
+                format!("{:<20} {:>8} {}", "notes", 2, status),
+            ]
+        );
+        command(&mut app, "ls").unwrap();
+        assert_eq!(
+            command(&mut app, "ls notes").unwrap_err().to_string(),
+            "usage: ls"
+        );
+    }
+
+    #[test]
+    fn rename_keeps_the_buffer_under_its_new_name() {
+        let mut app = app();
+        open(&mut app, "scratch", "ideas");
+        open(&mut app, "notes", "");
+        command(&mut app, "rename scratch ideas").unwrap();
+        assert!(!app.editors.contains_key("scratch"));
+        let editor = &app.editors["ideas"];
+        assert_eq!(editor.name, "ideas");
+        assert_eq!(editor.buffer.text(), "ideas");
+        assert!(editor.buffer.is_dirty());
+
+        let err = command(&mut app, "rename ideas notes").unwrap_err();
+        assert_eq!(err.to_string(), "There's already a buffer named 'notes'");
+        let err = command(&mut app, "rename scratch other").unwrap_err();
+        assert_eq!(err.to_string(), "No buffer named 'scratch'");
+        let err = command(&mut app, "rename ideas").unwrap_err();
+        assert_eq!(err.to_string(), "usage: rename <buffer> <newname>");
+        assert!(app.editors.contains_key("ideas"));
+    }
+
+    #[test]
+    fn search_needs_a_pattern_which_is_a_valid_regex() {
+        let mut app = app();
+        open(&mut app, "notes", "TODO: tests\n");
+        for line in ["search", "search -i", "search -i   "] {
+            let err = command(&mut app, line).unwrap_err();
+            assert_eq!(err.to_string(), "usage: search [-i] <pattern>");
+        }
+        let err = command(&mut app, "search -i (").unwrap_err();
+        assert!(err.to_string().starts_with("invalid pattern: "), "{}", err);
+        command(&mut app, "search -i todo").unwrap();
+        command(&mut app, "search -image").unwrap();
+    }
+
+    #[test]
+    fn replacing_across_files_wont_lose_an_open_buffers_changes() {
+        let file = TempFile::new("replaced", "one\n");
+        let mut app = app();
+        let mut editor = file.editor("replaced", Rc::clone(&app.config));
+        editor.buffer.insert("unsaved ");
+        app.editors.insert(String::from("replaced"), editor);
+        let change = project::FileChange {
+            path: file.path().to_path_buf(),
+            original: String::from("one\n"),
+            hunks: vec![project::Hunk {
+                line: 0,
+                old: String::from("one"),
+                new: String::from("two"),
+            }],
+        };
+        let preview = project::preview(std::slice::from_ref(&change));
+        open(&mut app, "preview", &preview);
+        app.pending_replacements
+            .insert(String::from("preview"), vec![change]);
+
+        let err = command(&mut app, "apply-replace preview").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "replaced has unsaved changes, so save or close it first"
+        );
+        assert_eq!(fs::read_to_string(file.path()).unwrap(), "one\n");
+        command(&mut app, "set readonly replaced on").unwrap();
+        let err = command(&mut app, "apply-replace preview").unwrap_err();
+        assert_eq!(err.to_string(), "replaced is read-only");
+
+        command(&mut app, "set readonly replaced off").unwrap();
+        app.editors.get_mut("replaced").unwrap().buffer.mark_saved();
+        command(&mut app, "apply-replace preview").unwrap();
+        assert_eq!(fs::read_to_string(file.path()).unwrap(), "two\n");
+        assert_eq!(app.editors["replaced"].buffer.text(), "two\n");
+    }
+
+    #[test]
+    fn a_read_only_buffers_file_isnt_written_deleted_or_moved() {
+        let file = TempFile::new("locked", "one  \n");
+        let moved = file.path().with_extension("moved");
+        let mut app = app();
+        app.session.trim_trailing_whitespace = true;
+        let mut editor = file.editor("locked", Rc::clone(&app.config));
+        editor.read_only = true;
+        app.editors.insert(String::from("locked"), editor);
+
+        let moving = format!("move locked {}", moved.display());
+        for line in ["write locked", "deletefile locked --force", &moving] {
+            let err = command(&mut app, line).unwrap_err();
+            assert_eq!(err.to_string(), "locked is read-only");
+        }
+        assert_eq!(fs::read_to_string(file.path()).unwrap(), "one  \n");
+        assert_eq!(app.editors["locked"].buffer.text(), "one  \n");
+        assert!(!moved.exists());
+    }
+
+    #[test]
+    fn transforms_change_the_selection_or_else_the_whole_buffer() {
+        let mut app = app();
+        open(&mut app, "notes", "a b");
+        command(&mut app, "base64-encode notes").unwrap();
+        assert_eq!(app.editors["notes"].buffer.text(), "YSBi");
+
+        let buffer = &mut app.editors.get_mut("notes").unwrap().buffer;
+        buffer.set_text("keep a b keep");
+        buffer.set_cursor(5);
+        buffer.start_selection();
+        buffer.set_cursor(8);
+        command(&mut app, "url-encode notes").unwrap();
+        assert_eq!(app.editors["notes"].buffer.text(), "keep a%20b keep");
+        assert_eq!(app.editors["notes"].buffer.selection(), None);
+
+        let err = command(&mut app, "url-encode notes extra").unwrap_err();
+        assert_eq!(err.to_string(), "usage: url-encode <buffer>");
+    }
+
+    #[test]
+    fn moving_a_file_takes_its_buffer_and_history_along() {
+        let file = TempFile::new("moving", "one\n");
+        let moved = file.path().with_extension("moved");
+        let mut app = app();
+        let mut editor = file.editor("moving", Rc::clone(&app.config));
+        editor.buffer.insert("x");
+        app.editors.insert(String::from("moving"), editor);
+
+        let missing = file.path().with_extension("missing").join("moved");
+        let err = command(&mut app, &format!("move moving {}", missing.display())).unwrap_err();
+        assert!(
+            err.to_string().contains("No such file or directory"),
+            "{}",
+            err
+        );
+        assert!(file.path().exists());
+        assert!(app.editors.contains_key("moving"));
+
+        let target = moved.to_str().unwrap();
+        command(&mut app, &format!("move moving {}", target)).unwrap();
+        assert!(!file.path().exists());
+        assert_eq!(fs::read_to_string(&moved).unwrap(), "one\n");
+        assert!(!app.editors.contains_key("moving"));
+        let editor = app.editors.get_mut(target).unwrap();
+        assert_eq!(editor.name, target);
+        assert_eq!(editor.buffer.file().map(Path::new), Some(moved.as_path()));
+        assert!(editor.lock.is_some());
+        assert!(editor.buffer.undo());
+        assert_eq!(editor.buffer.text(), "one\n");
+        app.editors.clear();
+        fs::remove_file(&moved).unwrap();
+    }
+
+    #[test]
+    fn a_buffer_is_only_closed_once_its_file_is_deleted() {
+        let file = TempFile::new("deleted", "one");
+        let mut app = app();
+        let mut editor = file.editor("deleted", Rc::clone(&app.config));
+        editor.lock = Some(Lock::acquire(file.path()).unwrap());
+        app.editors.insert(String::from("deleted"), editor);
+        let lock = file.path().with_file_name(format!(
+            ".{}.buffers-lock",
+            file.path().file_name().unwrap().to_string_lossy()
+        ));
+        assert!(lock.exists());
+
+        // A directory where the file was can't be deleted as one.
+        fs::remove_file(file.path()).unwrap();
+        fs::create_dir(file.path()).unwrap();
+        assert!(command(&mut app, "deletefile deleted --force").is_err());
+        assert!(app.editors.contains_key("deleted"));
+        assert!(lock.exists());
+
+        fs::remove_dir(file.path()).unwrap();
+        fs::write(file.path(), "one").unwrap();
+        command(&mut app, "deletefile deleted --force").unwrap();
+        assert!(!file.path().exists());
+        assert!(!app.editors.contains_key("deleted"));
+        assert!(!lock.exists());
+    }
+
+    #[test]
+    fn dedup_keeps_the_first_or_last_copy_and_refuses_other_flags() {
+        let mut app = app();
+        open(&mut app, "words", "a\nb\na\nb\nc\n");
+        let err = command(&mut app, "dedup words --keep-lst").unwrap_err();
+        assert_eq!(err.to_string(), "dedup has no --keep-lst option");
+        let err = command(&mut app, "dedup").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "usage: dedup <buffer> [start end] [--global] [--keep-last]"
+        );
+        assert_eq!(app.editors["words"].buffer.text(), "a\nb\na\nb\nc\n");
+
+        command(&mut app, "dedup words 2 5 --global --keep-last").unwrap();
+        assert_eq!(app.editors["words"].buffer.text(), "a\na\nb\nc\n");
+        command(&mut app, "dedup words").unwrap();
+        assert_eq!(app.editors["words"].buffer.text(), "a\nb\nc\n");
+    }
+
+    #[test]
+    fn line_commands_refuse_flags_they_dont_have() {
+        let mut app = app();
+        open(&mut app, "lines", "b\na\n");
+        for (line, message) in [
+            ("sort lines --numbers", "sort has no --numbers option"),
+            ("reverse lines --all", "reverse has no --all option"),
+            ("uniq lines --keep-lst", "uniq has no --keep-lst option"),
+            (
+                "uniq",
+                "usage: uniq <buffer> [start end] [--global] [--keep-last]",
+            ),
+        ] {
+            let err = command(&mut app, line).unwrap_err();
+            assert_eq!(err.to_string(), message);
+        }
+        assert_eq!(app.editors["lines"].buffer.text(), "b\na\n");
+        command(&mut app, "sort lines --reverse --ignore-case").unwrap();
+        assert_eq!(app.editors["lines"].buffer.text(), "b\na\n");
+        command(&mut app, "reverse lines").unwrap();
+        assert_eq!(app.editors["lines"].buffer.text(), "a\nb\n");
+    }
+
+    #[test]
+    fn jump_goes_back_to_where_a_filtered_line_came_from() {
+        let mut app = app();
+        open(&mut app, "log", "ok\nERROR one\nok\nERROR two\n");
+        command(&mut app, "filter ERROR log").unwrap();
+        assert_eq!(
+            app.editors["filter_0"].buffer.text(),
+            "log:2: ERROR one\nlog:4: ERROR two\n"
+        );
+        let target = follow_reference(&mut app.editors, "filter_0 2").unwrap();
+        assert_eq!(target, "log");
+        let editor = &app.editors["log"];
+        assert_eq!(editor.buffer.cursor_position().line, 3);
+        assert_eq!(editor.viewport.y, 3);
+
+        command(&mut app, "filter -v ERROR log").unwrap();
+        assert_eq!(
+            app.editors["filter_1"].buffer.text(),
+            "log:1: ok\nlog:3: ok\n"
+        );
+        follow_reference(&mut app.editors, "filter_1 2").unwrap();
+        assert_eq!(app.editors["log"].buffer.cursor_position().line, 2);
+
+        let err = follow_reference(&mut app.editors, "log 1").unwrap_err();
+        assert!(err.to_string().contains("log"), "{}", err);
+    }
+
+    #[test]
+    fn a_locked_file_is_refused_opened_read_only_or_stolen() {
+        let file = TempFile::new("taken", "one");
+        let name = file.path().to_str().unwrap();
+        let lock = file.path().with_file_name(format!(
+            ".{}.buffers-lock",
+            file.path().file_name().unwrap().to_string_lossy()
+        ));
+        fs::write(&lock, "1@elsewhere").unwrap();
+        let mut app = app();
+
+        let Err(err) = fetch_editor(&mut app, Some(name), LockPolicy::Refuse) else {
+            panic!("opened a locked file");
+        };
+        assert!(
+            err.to_string().contains("process 1 on elsewhere"),
+            "{}",
+            err
+        );
+        assert!(app.editors.is_empty());
+
+        let editor = fetch_editor(&mut app, Some(name), LockPolicy::ReadOnly).unwrap();
+        assert!(editor.read_only);
+        assert!(editor.lock.is_none());
+        app.editors.clear();
+        assert_eq!(fs::read_to_string(&lock).unwrap(), "1@elsewhere");
+
+        let editor = fetch_editor(&mut app, Some(name), LockPolicy::Steal).unwrap();
+        assert!(!editor.read_only);
+        assert!(editor.lock.is_some());
+        assert_ne!(fs::read_to_string(&lock).unwrap(), "1@elsewhere");
+
+        // Closing the buffer lets go of the lock.
+        command(&mut app, &format!("close {}", name)).unwrap();
+        assert!(!lock.exists());
+    }
+
+    #[test]
+    fn undo_tree_draws_every_branch_and_undo_to_goes_back_to_one() {
+        let mut app = app();
+        open(&mut app, "notes", "a");
+        let buffer = &mut app.editors.get_mut("notes").unwrap().buffer;
+        buffer.insert("b");
+        buffer.undo();
+        buffer.insert("c");
+
+        command(&mut app, "undo-tree notes").unwrap();
+        let tree = app.editors["undotree_0"].buffer.text();
+        assert!(tree.contains("  2: inserted \"b\"\n*   3: inserted \"c\" (after 1)\n"));
+        command(&mut app, "undo-to notes 2").unwrap();
+        assert_eq!(app.editors["notes"].buffer.text(), "ab");
+        command(&mut app, "undo-to notes 9").unwrap();
+        assert_eq!(app.editors["notes"].buffer.text(), "ab");
+
+        let err = command(&mut app, "undo-tree notes extra").unwrap_err();
+        assert_eq!(err.to_string(), "usage: undo-tree <buffer>");
+        assert!(command(&mut app, "undo-to notes").is_err());
+    }
+
+    #[test]
+    fn timeline_numbers_snapshots_oldest_first() {
+        let name = format!("timeline-test-{}", std::process::id());
+        for text in ["first", "second"] {
+            snapshot::take(&name, text).unwrap();
+            // Snapshots are named for the millisecond they're taken in.
+            std::thread::sleep(Duration::from_millis(2));
+        }
+        let mut app = app();
+        open(&mut app, &name, "now");
+        let restored = command(&mut app, &format!("timeline {} 1 --restore", name));
+        let oldest = app.editors[&name].buffer.text().to_owned();
+        command(&mut app, &format!("timeline {} 2 --restore", name)).unwrap();
+        let newest = app.editors[&name].buffer.text().to_owned();
+        let third = command(&mut app, &format!("timeline {} 3", name));
+
+        let snapshots = snapshot::list(&name).unwrap();
+        fs::remove_dir_all(snapshots[0].path.parent().unwrap()).unwrap();
+        restored.unwrap();
+        assert_eq!((oldest.as_str(), newest.as_str()), ("first", "second"));
+        assert_eq!(
+            third.unwrap_err().to_string(),
+            format!("{} has no snapshot 3", name)
+        );
+    }
+
+    #[test]
+    fn a_file_opened_by_another_path_is_the_same_buffer() {
+        let file = TempFile::new("twice", "one");
+        let link = file.path().with_extension("link");
+        std::os::unix::fs::symlink(file.path(), &link).unwrap();
+        let dir = file.path().parent().unwrap();
+        let name = file.path().file_name().unwrap().to_str().unwrap();
+        let mut app = app();
+        let first = file.path().to_str().unwrap();
+        fetch_editor(&mut app, Some(first), LockPolicy::Refuse).unwrap();
+        for other in [
+            format!("{}/./{}", dir.display(), name),
+            link.display().to_string(),
+        ] {
+            let editor = fetch_editor(&mut app, Some(&other), LockPolicy::Refuse).unwrap();
+            assert_eq!(editor.name, first);
+        }
+        assert_eq!(app.editors.len(), 1);
+        app.editors.clear();
+        fs::remove_file(&link).unwrap();
+    }
+
+    #[test]
+    fn a_new_file_is_saved_where_it_was_opened() {
+        let dir = std::env::temp_dir().join(format!("buffers-new-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("notes.txt");
+        let mut app = app();
+        let name = path.to_str().unwrap();
+        let editor = fetch_editor(&mut app, Some(name), LockPolicy::Refuse).unwrap();
+        let file = editor.buffer.file().map(PathBuf::from);
+        assert_eq!(file, Some(dir.canonicalize().unwrap().join("notes.txt")));
+        assert!(editor.lock.is_some());
+        let unnamed = fetch_editor(&mut app, None, LockPolicy::Refuse).unwrap();
+        assert_eq!(unnamed.buffer.file(), None);
+
+        let mut harness = Harness::with_editor(app.editors.remove(name).unwrap());
+        harness.type_text("hello\n").run([ctrl('s')]);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "hello\n");
+        assert!(!harness.editor.buffer.is_dirty());
+        drop(harness);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn closing_an_unsaved_buffer_asks_first_unless_forced() {
+        let mut app = app();
+        open(&mut app, "saved", "");
+        open(&mut app, "notes", "unsaved");
+        command(&mut app, "close saved").unwrap();
+        assert!(!app.editors.contains_key("saved"));
+
+        app.answers.push_back(String::from("n"));
+        command(&mut app, "close notes").unwrap();
+        assert!(app.editors.contains_key("notes"));
+        app.answers.push_back(String::from("Yes"));
+        command(&mut app, "close notes").unwrap();
+        assert!(!app.editors.contains_key("notes"));
+
+        open(&mut app, "notes", "unsaved");
+        command(&mut app, "close notes --force").unwrap();
+        assert!(app.answers.is_empty());
+        assert!(!app.editors.contains_key("notes"));
+        assert!(command(&mut app, "close notes").is_err());
+    }
+
+    #[test]
+    fn quitting_with_unsaved_buffers_asks_first_unless_forced() {
+        let mut clean = app();
+        open(&mut clean, "saved", "");
+        command(&mut clean, "quit").unwrap();
+        assert!(clean.quit);
+
+        let mut unsaved = app();
+        open(&mut unsaved, "notes", "unsaved");
+        open(&mut unsaved, "todo", "unsaved");
+        unsaved.answers.push_back(String::from("no"));
+        command(&mut unsaved, "quit").unwrap();
+        assert!(!unsaved.quit);
+        unsaved.answers.push_back(String::from("y"));
+        command(&mut unsaved, "quit").unwrap();
+        assert!(unsaved.quit);
+
+        let mut forced = app();
+        open(&mut forced, "notes", "unsaved");
+        command(&mut forced, "quit --force").unwrap();
+        assert!(forced.quit);
+    }
+}
@@ -0,0 +1,264 @@
+//! Commenting lines out, and back in, with Ctrl+/. How comments are
+//! written is looked up by the file's extension, first in `comments` in
+//! the config, like:
+//!
+//! ```toml
+//! [comments]
+//! sql = "--"
+//! vue = "<!-- -->"
+//! ```
+//!
+//! and then in [`DEFAULT_COMMENTS`]. A comment which has to be closed is
+//! written with what opens it and what closes it, a space apart.
+
+use termgame::{Game, Message};
+
+use crate::buffer::{Buffer, Position};
+use crate::i18n::tr;
+use crate::BufferEditor;
+
+/// How files with each extension are commented, when the config doesn't
+/// say.
+pub const DEFAULT_COMMENTS: &[(&str, &str)] = &[
+    ("rs", "//"),
+    ("c", "//"),
+    ("h", "//"),
+    ("cpp", "//"),
+    ("hpp", "//"),
+    ("cs", "//"),
+    ("java", "//"),
+    ("kt", "//"),
+    ("go", "//"),
+    ("swift", "//"),
+    ("js", "//"),
+    ("ts", "//"),
+    ("jsx", "//"),
+    ("tsx", "//"),
+    ("scss", "//"),
+    ("py", "#"),
+    ("rb", "#"),
+    ("sh", "#"),
+    ("bash", "#"),
+    ("zsh", "#"),
+    ("pl", "#"),
+    ("r", "#"),
+    ("toml", "#"),
+    ("yaml", "#"),
+    ("yml", "#"),
+    ("conf", "#"),
+    ("sql", "--"),
+    ("lua", "--"),
+    ("hs", "--"),
+    ("html", "<!-- -->"),
+    ("xml", "<!-- -->"),
+    ("svg", "<!-- -->"),
+    ("md", "<!-- -->"),
+    ("css", "/* */"),
+];
+
+/// What a comment starts with, and what it ends with if it has to be
+/// closed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommentStyle {
+    pub open: String,
+    pub close: Option<String>,
+}
+
+impl CommentStyle {
+    /// Reads a style written as it is in the config, like `//` or
+    /// `<!-- -->`.
+    pub fn parse(style: &str) -> Option<CommentStyle> {
+        let mut parts = style.split_whitespace();
+        let open = String::from(parts.next()?);
+        let close = parts.next().map(String::from);
+        match parts.next() {
+            Some(_) => None,
+            None => Some(CommentStyle { open, close }),
+        }
+    }
+
+    /// `line` without its indent, if it's commented out.
+    fn uncommented<'a>(&self, line: &'a str) -> Option<&'a str> {
+        let code = line[indent(line)..].strip_prefix(&self.open)?;
+        let code = code.strip_prefix(' ').unwrap_or(code);
+        match &self.close {
+            Some(close) => {
+                let code = code.trim_end().strip_suffix(close.as_str())?;
+                Some(code.strip_suffix(' ').unwrap_or(code))
+            }
+            None => Some(code),
+        }
+    }
+}
+
+/// How many bytes of spaces and tabs `line` starts with. Other kinds of
+/// space, like a no-break space, are part of the line, not its indent.
+fn indent(line: &str) -> usize {
+    line.len() - line.trim_start_matches([' ', '\t']).len()
+}
+
+/// How one line was changed: at which column, and how many chars were
+/// added there, or taken away if it's negative.
+type LineChange = (usize, isize);
+
+/// `lines` commented out, or uncommented if every one of them that isn't
+/// blank already is, along with how each line changed. Blank lines are
+/// left alone, and comments all start at the shallowest indent.
+pub fn toggle(style: &CommentStyle, lines: &[&str]) -> Vec<(String, LineChange)> {
+    let code = || lines.iter().filter(|line| !line.trim().is_empty());
+    let uncomment = code().all(|line| style.uncommented(line).is_some());
+    let shallowest = code().map(|line| indent(line)).min().unwrap_or(0);
+    lines
+        .iter()
+        .map(|&line| {
+            let start = indent(line);
+            if line.trim().is_empty() {
+                return (String::from(line), (0, 0));
+            }
+            if uncomment {
+                let code = style.uncommented(line).unwrap_or(line);
+                let text = format!("{}{}", &line[..start], code);
+                let removed = line.chars().count() - text.chars().count();
+                return (text, (line[..start].chars().count(), -(removed as isize)));
+            }
+            let (before, after) = line.split_at(shallowest);
+            let mut text = format!("{}{} {}", before, style.open, after);
+            if let Some(close) = &style.close {
+                text = format!("{} {}", text, close);
+            }
+            let added = style.open.chars().count() + 1;
+            (text, (before.chars().count(), added as isize))
+        })
+        .collect()
+}
+
+/// Where `position` ends up after its line changes. Anything before
+/// where it changed stays put.
+fn shift(position: Position, (at, by): LineChange) -> Position {
+    let col = match position.col > at {
+        true => position.col.saturating_add_signed(by).max(at),
+        false => position.col,
+    };
+    Position { col, ..position }
+}
+
+impl BufferEditor {
+    /// Comments out the cursor's line, or the selected lines, or
+    /// uncomments them if they already are.
+    pub fn toggle_comment(&mut self, game: &mut Game) {
+        let Some(style) = self.config.comment_for(self.buffer.extension()) else {
+            let message = tr("editor.no-comment-style", &[("name", &self.name)]);
+            game.set_message(Some(Message::new(message)));
+            return;
+        };
+        let lines = self.buffer.selected_lines();
+        let old: Vec<&str> = lines
+            .clone()
+            .filter_map(|line| self.buffer.line(line))
+            .collect();
+        let changed = toggle(&style, &old);
+        let mut text: String = changed
+            .iter()
+            .map(|(line, _)| line.as_str())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let span = self.buffer.line_span(lines.clone());
+        if self.buffer.text()[span.clone()].ends_with('\n') {
+            text.push('\n');
+        }
+
+        // The cursor and selection stay with the text they were on.
+        let moved = |buffer: &Buffer, offset: usize| {
+            let position = buffer.position_of(offset);
+            match changed.get(position.line.wrapping_sub(lines.start)) {
+                Some(&(_, change)) => shift(position, change),
+                None => position,
+            }
+        };
+        let cursor = moved(&self.buffer, self.buffer.cursor());
+        let anchor = self
+            .buffer
+            .selection()
+            .map(|range| match range.start == self.buffer.cursor() {
+                true => range.end,
+                false => range.start,
+            })
+            .map(|offset| moved(&self.buffer, offset));
+        self.buffer.replace_range(span, &text);
+        let offset =
+            |buffer: &Buffer, position| buffer.offset_of(position).unwrap_or(buffer.text().len());
+        if let Some(anchor) = anchor {
+            self.buffer.set_cursor(offset(&self.buffer, anchor));
+            self.buffer.start_selection();
+        }
+        self.buffer.set_cursor(offset(&self.buffer, cursor));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashMap;
+    use std::rc::Rc;
+
+    use crate::config::Config;
+    use crate::harness::{ctrl, shift_key, Harness};
+
+    #[test]
+    fn lines_are_commented_and_uncommented() {
+        let style = CommentStyle::parse("//").unwrap();
+        let lines = ["fn main() {", "    run();", "", "}"];
+        let commented: Vec<String> = toggle(&style, &lines)
+            .into_iter()
+            .map(|(line, _)| line)
+            .collect();
+        assert_eq!(commented, ["// fn main() {", "//     run();", "", "// }"]);
+        let commented: Vec<&str> = commented.iter().map(String::as_str).collect();
+        let uncommented: Vec<String> = toggle(&style, &commented)
+            .into_iter()
+            .map(|(line, _)| line)
+            .collect();
+        assert_eq!(uncommented, lines);
+
+        let html = CommentStyle::parse("<!-- -->").unwrap();
+        let (line, _) = &toggle(&html, &["  <p>hi</p>"])[0];
+        assert_eq!(line, "  <!-- <p>hi</p> -->");
+        assert_eq!(toggle(&html, &[line.as_str()])[0].0, "  <p>hi</p>");
+
+        // A no-break space isn't indent, so it can't be cut through.
+        let lines = [" x", "\u{a0}y"];
+        let commented: Vec<String> = toggle(&style, &lines)
+            .into_iter()
+            .map(|(line, _)| line)
+            .collect();
+        assert_eq!(commented, ["//  x", "// \u{a0}y"]);
+    }
+
+    #[test]
+    fn ctrl_slash_comments_in_the_files_language() {
+        let config = Config {
+            comments: HashMap::from([(String::from("sql"), String::from("#"))]),
+            ..Config::default()
+        };
+        let buffer = Buffer::from_text(
+            Some(String::from("q.sql")),
+            String::from("select 1;\nselect 2;\n"),
+        );
+        let editor = BufferEditor::new(String::from("q"), buffer, Rc::new(config));
+        let mut harness = Harness::with_editor(editor);
+        harness.run([shift_key(termgame::KeyCode::Down), ctrl('/')]);
+        // The config's style wins over the default `--`.
+        assert_eq!(harness.text(), "# select 1;\nselect 2;\n");
+        assert_eq!(harness.editor.buffer.selection(), Some(0..12));
+        harness.run([ctrl('7')]);
+        assert_eq!(harness.text(), "select 1;\nselect 2;\n");
+
+        let mut harness = Harness::new("notes");
+        harness.run([ctrl('/')]);
+        assert_eq!(
+            harness.message(),
+            Some("Don't know how to write comments in test. Add its extension to `comments` in the config")
+        );
+    }
+}
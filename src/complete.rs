@@ -0,0 +1,264 @@
+//! Tab completion at the REPL.
+//!
+//! The first word is completed as a command, or an alias. After it,
+//! buffer names are completed for the commands which take a buffer, like
+//! `write`, and paths for the commands which take one, like `open`.
+//! A leading `~` or `$VAR` is expanded to find what's there, but left
+//! as it was typed. Hidden files are only offered once a `.` has been
+//! typed, unless `complete_hidden` is set in the config.
+
+use rustyline::completion::{Completer, Pair};
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::Validator;
+use rustyline::{Context, Helper};
+
+use std::fs;
+
+use crate::paths;
+use crate::transform;
+
+/// For each command which takes a path, which of its arguments (from 0,
+/// not counting `--` flags) the path is.
+const PATH_ARGUMENTS: &[(&str, usize)] = &[("open", 0), ("write", 1)];
+
+/// For each command which takes a buffer, which of its arguments the
+/// buffer is. The transforms, like `base64-encode`, all take one first
+/// as well.
+const BUFFER_ARGUMENTS: &[(&str, usize)] = &[
+    ("open", 0),
+    ("write", 0),
+    ("close", 0),
+    ("rename", 0),
+    ("move", 0),
+    ("deletefile", 0),
+    ("merge", 0),
+    ("recover", 0),
+    ("timeline", 0),
+    ("autorevert", 0),
+    ("encoding", 0),
+    ("wrap", 0),
+    ("dedup", 0),
+    ("uniq", 0),
+    ("sort", 0),
+    ("reverse", 0),
+    ("case", 0),
+    ("join", 0),
+    ("reflow", 0),
+    ("pipe", 0),
+    ("insert", 0),
+    ("blame", 0),
+    ("jump", 0),
+    ("goto", 0),
+    ("replace", 0),
+    ("copy-block", 0),
+    ("paste", 0),
+    ("undo", 0),
+    ("redo", 0),
+    ("undo-to", 0),
+    ("undo-tree", 0),
+    ("play", 1),
+];
+
+/// What the REPL's line editor asks about what's being typed.
+pub struct ReplHelper {
+    /// Whether to offer hidden files before a `.` has been typed.
+    pub show_hidden: bool,
+    /// Every command and alias.
+    pub commands: Vec<String>,
+    /// The buffers which are open, kept up to date before each line's
+    /// read.
+    pub buffers: Vec<String>,
+}
+
+impl Completer for ReplHelper {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let line = &line[..pos];
+        let Some((command, argument, start)) = argument(line) else {
+            let start = line.len() - line.trim_start().len();
+            return Ok((start, starting_with(&self.commands, &line[start..])));
+        };
+        let typed = &line[start..];
+        let mut candidates = Vec::new();
+        let takes_buffer = BUFFER_ARGUMENTS.contains(&(command, argument))
+            || (argument == 0
+                && transform::BUILTIN
+                    .iter()
+                    .any(|(help, _)| help.name == command));
+        if takes_buffer {
+            candidates.extend(starting_with(&self.buffers, typed));
+        }
+        if path_argument(line).is_some() {
+            candidates.extend(complete_path(typed, self.show_hidden));
+        }
+        Ok((start, candidates))
+    }
+}
+
+impl Hinter for ReplHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ReplHelper {}
+
+impl Validator for ReplHelper {}
+
+impl Helper for ReplHelper {}
+
+/// The command the word at the end of `line` is an argument to, which
+/// argument of it (from 0, not counting `--` flags) it is, and where it
+/// starts. `None` if the word is the command itself.
+fn argument(line: &str) -> Option<(&str, usize, usize)> {
+    let start = line.rfind(' ')? + 1;
+    let mut words = line[..start].split_whitespace();
+    let command = words.next()?;
+    let argument = words.filter(|word| !word.starts_with("--")).count();
+    Some((command, argument, start))
+}
+
+/// If the word at the end of `line` is a path, where that word starts.
+fn path_argument(line: &str) -> Option<usize> {
+    let (command, argument, start) = argument(line)?;
+    PATH_ARGUMENTS
+        .contains(&(command, argument))
+        .then_some(start)
+}
+
+/// The names `typed` could be the start of, in order.
+fn starting_with(names: &[String], typed: &str) -> Vec<Pair> {
+    let mut names: Vec<&String> = names
+        .iter()
+        .filter(|name| name.starts_with(typed))
+        .collect();
+    names.sort();
+    names.dedup();
+    names
+        .into_iter()
+        .map(|name| Pair {
+            display: name.clone(),
+            replacement: name.clone(),
+        })
+        .collect()
+}
+
+/// The files and directories `typed` could be the start of, as they'd
+/// be typed. Directories end in a `/`, so completion can carry on into
+/// them.
+pub fn complete_path(typed: &str, show_hidden: bool) -> Vec<Pair> {
+    if typed == "~" {
+        return vec![Pair {
+            display: String::from("~/"),
+            replacement: String::from("~/"),
+        }];
+    }
+    let (dir, prefix) = match typed.rfind('/') {
+        Some(i) => typed.split_at(i + 1),
+        None => ("", typed),
+    };
+    let listed = match dir {
+        "" => fs::read_dir("."),
+        dir => match paths::expand(dir) {
+            Ok(dir) => fs::read_dir(dir),
+            Err(_) => return Vec::new(),
+        },
+    };
+    let Ok(listed) = listed else {
+        return Vec::new();
+    };
+
+    let mut candidates: Vec<Pair> = listed
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let mut name = entry.file_name().into_string().ok()?;
+            if !name.starts_with(prefix)
+                || (name.starts_with('.') && !show_hidden && !prefix.starts_with('.'))
+            {
+                return None;
+            }
+            if entry.path().is_dir() {
+                name.push('/');
+            }
+            Some(Pair {
+                replacement: format!("{}{}", dir, name),
+                display: name,
+            })
+        })
+        .collect();
+    candidates.sort_by(|a, b| a.display.cmp(&b.display));
+    candidates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_path_arguments_are_completed() {
+        assert_eq!(path_argument("open sr"), Some(5));
+        assert_eq!(path_argument("open --read-only sr"), Some(17));
+        assert_eq!(path_argument("write notes ~/n"), Some(12));
+        assert_eq!(path_argument("write no"), None);
+        assert_eq!(path_argument("op"), None);
+    }
+
+    #[test]
+    fn commands_and_buffers_are_completed() {
+        let helper = ReplHelper {
+            show_hidden: false,
+            commands: vec![
+                String::from("write"),
+                String::from("wrap"),
+                String::from("w"),
+            ],
+            buffers: vec![String::from("notes"), String::from("new")],
+        };
+        let history = rustyline::history::History::new();
+        let complete = |line: &str| -> (usize, Vec<String>) {
+            let ctx = Context::new(&history);
+            let (start, pairs) = helper.complete(line, line.len(), &ctx).unwrap();
+            (
+                start,
+                pairs.into_iter().map(|pair| pair.replacement).collect(),
+            )
+        };
+        assert_eq!(
+            complete("wr"),
+            (0, vec![String::from("wrap"), String::from("write")])
+        );
+        assert_eq!(complete("  w").1, ["w", "wrap", "write"]);
+        assert_eq!(complete("write no"), (6, vec![String::from("notes")]));
+        assert_eq!(complete("base64-encode n").1, ["new", "notes"]);
+        assert_eq!(complete("search n").1, Vec::<String>::new());
+    }
+
+    #[test]
+    fn directories_are_completed_into() {
+        let dir = std::env::temp_dir().join(format!("buffers-complete-{}", std::process::id()));
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(dir.join("notes.txt"), "").unwrap();
+        fs::write(dir.join(".hidden"), "").unwrap();
+        let typed = format!("{}/", dir.display());
+
+        let names = |typed: &str, show_hidden| -> Vec<String> {
+            complete_path(typed, show_hidden)
+                .into_iter()
+                .map(|pair| pair.display)
+                .collect()
+        };
+        assert_eq!(names(&typed, false), ["notes.txt", "src/"]);
+        assert_eq!(names(&typed, true), [".hidden", "notes.txt", "src/"]);
+        assert_eq!(names(&format!("{}.", typed), false), [".hidden"]);
+        assert_eq!(
+            complete_path(&format!("{}s", typed), false)[0].replacement,
+            format!("{}src/", typed)
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,397 @@
+//! Settings read from `~/.config/buffers/config.toml`.
+
+use serde::Deserialize;
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::clock;
+use crate::comment::{CommentStyle, DEFAULT_COMMENTS};
+use crate::keymap::{Layout, Rebinding};
+use crate::save;
+use crate::vim::EditingMode;
+
+/// Everything that can be set in the config file. Anything left out of
+/// the file gets its default.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Words which are replaced as soon as they're typed, like `teh = "the"`.
+    pub abbreviations: HashMap<String, String>,
+    /// The brackets and quotes which are closed automatically as they're
+    /// typed, by filetype (i.e. file extension), like `rs = ["()", "{}"]`.
+    /// Filetypes which aren't listed use `default`.
+    pub pairs: HashMap<String, Vec<String>>,
+    /// How lines are commented out, by filetype, like `sql = "--"`, or
+    /// with what closes the comment after a space, like `html = "<!-- -->"`.
+    /// Filetypes which aren't listed use the usual style for them.
+    pub comments: HashMap<String, String>,
+    /// Short names for commands at the REPL, like `rg = "search -i"`,
+    /// which `alias` adds to.
+    pub aliases: HashMap<String, String>,
+    /// Whether buffers without unsaved edits reload by themselves when
+    /// their file changes. Can be changed for each buffer with `autorevert`.
+    pub auto_revert: bool,
+    /// Whether a copy of each file is put in `~/.local/share/buffers/backups/`
+    /// before BuffeRS overwrites it.
+    pub backups: bool,
+    /// How many backups of each file to keep.
+    pub backup_limit: usize,
+    /// The command `write --sudo` runs to write a file it doesn't have
+    /// permission to, with the file's path added on the end. It's given
+    /// the text on its standard input.
+    pub privileged_helper: Vec<String>,
+    /// Whether to count which commands and keys are used, for `metrics`.
+    pub metrics: bool,
+    /// Which language to show, like `fr` or `pt_BR`. When it's not set,
+    /// the locale comes from the environment.
+    pub locale: Option<String>,
+    /// Where screen-reader mode announces what's happening, either
+    /// `stderr` or a file. It's off when this isn't set.
+    pub announce: Option<String>,
+    /// The keyboard layout bindings are placed for, one of `qwerty`,
+    /// `dvorak`, `colemak` or `azerty`.
+    pub keyboard_layout: Layout,
+    /// Whether tab completion offers hidden files before a `.` has been
+    /// typed.
+    pub complete_hidden: bool,
+    /// How much memory each buffer's undo history can take up, in bytes,
+    /// before its oldest states are forgotten.
+    pub undo_limit: usize,
+    /// The colours to start in, which `theme` can change: `default`,
+    /// `dark`, `light`, `deuteranopia` or `protanopia`.
+    pub theme: String,
+    /// Whether BuffeRS starts on the dashboard when it's run without any
+    /// files.
+    pub dashboard: bool,
+    /// Whether line numbers are shown down the left of the editor. Can
+    /// be changed while BuffeRS is running with `set number`.
+    pub line_numbers: bool,
+    /// How keys edit the text: `default`, or `vim` for vim's normal,
+    /// insert and visual modes. Can be changed with `set mode`.
+    pub editing_mode: EditingMode,
+    /// How often the editor ticks, in milliseconds, to check files, take
+    /// snapshots and play recordings back. From 1 to 1000.
+    pub tick_ms: u64,
+    /// How many columns apart tab stops are (the tabstop), which tabs
+    /// are drawn out to. From 1 to 16.
+    pub tab_width: usize,
+    /// Whether Tab types spaces up to the next tab stop, rather than a
+    /// tab.
+    pub expand_tab: bool,
+    /// Whether Enter indents the new line as far as the one before. Can
+    /// be changed with `set autoindent`.
+    pub autoindent: bool,
+    /// Whether buffers open with long lines wrapped, which `wrap` or
+    /// Ctrl+W can change for each one.
+    pub wrap: bool,
+    /// How often buffers with unsaved changes are saved by themselves, in
+    /// seconds. Autosaving is off at 0.
+    pub autosave_secs: u64,
+    /// How often a buffer with unsaved changes has them written to its
+    /// swap file, in seconds. Swap files aren't kept at 0.
+    pub swap_secs: u64,
+    /// Whether the spaces and tabs at the ends of lines are taken off when
+    /// a buffer's saved. Can be changed with `set trim_trailing_whitespace`.
+    pub trim_trailing_whitespace: bool,
+    /// Whether a buffer's given a newline at the end when it's saved
+    /// without one. Can be changed with `set insert_final_newline`.
+    pub insert_final_newline: bool,
+    /// How big a file has to be, in megabytes, to be read a part at a time
+    /// rather than all at once. It can't be edited then.
+    pub huge_file_mb: u64,
+    /// The bindings read from `keys.toml`, which is kept apart from this
+    /// file.
+    #[serde(skip)]
+    pub keys: Vec<Rebinding>,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            abbreviations: HashMap::new(),
+            pairs: HashMap::new(),
+            comments: HashMap::new(),
+            aliases: HashMap::new(),
+            auto_revert: true,
+            backups: false,
+            backup_limit: 10,
+            privileged_helper: vec![String::from("sudo"), String::from("tee")],
+            metrics: false,
+            locale: None,
+            announce: None,
+            keyboard_layout: Layout::default(),
+            complete_hidden: false,
+            undo_limit: 16 * 1024 * 1024,
+            theme: String::from("default"),
+            dashboard: true,
+            line_numbers: false,
+            editing_mode: EditingMode::default(),
+            tick_ms: clock::TICK.as_millis() as u64,
+            tab_width: 4,
+            expand_tab: true,
+            autoindent: true,
+            wrap: false,
+            autosave_secs: 0,
+            swap_secs: 4,
+            trim_trailing_whitespace: false,
+            insert_final_newline: false,
+            huge_file_mb: 256,
+            keys: Vec::new(),
+        }
+    }
+}
+
+/// What's paired when the config file doesn't say.
+const DEFAULT_PAIRS: &[(char, char)] = &[('(', ')'), ('[', ']'), ('{', '}'), ('"', '"')];
+
+impl Config {
+    /// Reads the config file. A missing file isn't an error, it just
+    /// means every setting is left at its default.
+    pub fn load() -> Result<Config, Box<dyn Error>> {
+        let Some(path) = config_file() else {
+            return Ok(Config::default());
+        };
+        match fs::read_to_string(&path) {
+            Ok(text) => toml::from_str::<Config>(&text)
+                .map_err(|err| err.to_string())
+                .and_then(|config| config.validate().map(|_| config))
+                .map_err(|err| format!("{}: {}", path.display(), err).into()),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(Config::default()),
+            Err(err) => Err(format!("{}: {}", path.display(), err).into()),
+        }
+    }
+
+    /// Checks the things the types alone can't.
+    fn validate(&self) -> Result<(), String> {
+        for pair in self.pairs.values().flatten() {
+            if pair.chars().count() != 2 {
+                return Err(format!("\"{}\" isn't a pair of characters", pair));
+            }
+        }
+        for (name, expansion) in &self.aliases {
+            if name.is_empty() || name.contains(char::is_whitespace) {
+                return Err(format!(
+                    "\"{}\" can't be an alias, it has to be one word",
+                    name
+                ));
+            }
+            if expansion.trim().is_empty() {
+                return Err(format!("the alias {} doesn't stand for anything", name));
+            }
+        }
+        for style in self.comments.values() {
+            if CommentStyle::parse(style).is_none() {
+                return Err(format!("\"{}\" isn't a way of writing comments", style));
+            }
+        }
+        if !(1..=1000).contains(&self.tick_ms) {
+            return Err(format!(
+                "tick_ms is {}, but has to be from 1 to 1000",
+                self.tick_ms
+            ));
+        }
+        if !(1..=16).contains(&self.tab_width) {
+            return Err(format!(
+                "tab_width is {}, but has to be from 1 to 16",
+                self.tab_width
+            ));
+        }
+        Ok(())
+    }
+
+    /// How long each tick of the editor lasts.
+    pub fn tick(&self) -> Duration {
+        Duration::from_millis(self.tick_ms)
+    }
+
+    /// How big a file has to be, in bytes, to be read a part at a time.
+    pub fn huge_file_bytes(&self) -> u64 {
+        self.huge_file_mb.saturating_mul(1024 * 1024)
+    }
+
+    /// How many backups to keep of each file that's overwritten, or
+    /// `None` if backups are off.
+    pub fn backups(&self) -> Option<usize> {
+        Some(self.backup_limit).filter(|_| self.backups)
+    }
+
+    /// The `(open, close)` pairs to use for files with this extension.
+    pub fn pairs_for(&self, extension: Option<&str>) -> Vec<(char, char)> {
+        let configured = extension
+            .and_then(|extension| self.pairs.get(extension))
+            .or_else(|| self.pairs.get("default"));
+        match configured {
+            Some(pairs) => pairs
+                .iter()
+                .filter_map(|pair| {
+                    let mut chars = pair.chars();
+                    Some((chars.next()?, chars.next()?))
+                })
+                .collect(),
+            None => DEFAULT_PAIRS.to_vec(),
+        }
+    }
+}
+
+impl Config {
+    /// How lines are commented in files with this extension, if it's
+    /// known.
+    pub fn comment_for(&self, extension: Option<&str>) -> Option<CommentStyle> {
+        let extension = extension?;
+        let style = self
+            .comments
+            .get(extension)
+            .map(String::as_str)
+            .or_else(|| {
+                DEFAULT_COMMENTS
+                    .iter()
+                    .find(|(other, _)| *other == extension)
+                    .map(|(_, style)| *style)
+            })?;
+        CommentStyle::parse(style)
+    }
+}
+
+/// Makes `name` an alias for `expansion` in the config file, or with no
+/// expansion, takes the alias out. The rest of the file is left as it
+/// is, comments and all.
+pub fn save_alias(name: &str, expansion: Option<&str>) -> Result<(), Box<dyn Error>> {
+    let path = config_file().ok_or("there's nowhere to keep the config")?;
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == ErrorKind::NotFound => String::new(),
+        Err(err) => return Err(format!("{}: {}", path.display(), err).into()),
+    };
+    let text = with_alias(&text, name, expansion);
+    // Better not to write anything than a file which won't load.
+    toml::from_str::<Config>(&text)
+        .map_err(|err| err.to_string())
+        .and_then(|config| config.validate())
+        .map_err(|err| format!("{}: {}", path.display(), err))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    save::write_file(&path, text.as_bytes(), None)?;
+    Ok(())
+}
+
+/// `text`, a config file, with the line for the alias `name` in its
+/// `[aliases]` table changed to stand for `expansion`, or taken out.
+fn with_alias(text: &str, name: &str, expansion: Option<&str>) -> String {
+    let mut lines: Vec<String> = text.lines().map(String::from).collect();
+    let setting = expansion.map(|expansion| format!("{} = {}", name, toml::Value::from(expansion)));
+    match lines.iter().position(|line| line.trim() == "[aliases]") {
+        Some(header) => {
+            let end = lines[header + 1..]
+                .iter()
+                .position(|line| line.trim_start().starts_with('['))
+                .map_or(lines.len(), |i| header + 1 + i);
+            let is_alias = |line: &str| {
+                line.split_once('=')
+                    .is_some_and(|(key, _)| key.trim().trim_matches('"') == name)
+            };
+            match ((header + 1..end).find(|&i| is_alias(&lines[i])), setting) {
+                (Some(i), Some(setting)) => lines[i] = setting,
+                (Some(i), None) => {
+                    lines.remove(i);
+                }
+                (None, Some(setting)) => {
+                    // After the table's last line, rather than the blank
+                    // lines before the next table.
+                    let at = (header + 1..end)
+                        .rev()
+                        .find(|&i| !lines[i].trim().is_empty())
+                        .map_or(header + 1, |i| i + 1);
+                    lines.insert(at, setting);
+                }
+                (None, None) => {}
+            }
+        }
+        None => {
+            if let Some(setting) = setting {
+                if lines.last().is_some_and(|line| !line.trim().is_empty()) {
+                    lines.push(String::new());
+                }
+                lines.push(String::from("[aliases]"));
+                lines.push(setting);
+            }
+        }
+    }
+    let mut text = lines.join("\n");
+    text.push('\n');
+    text
+}
+
+/// Where the config file is kept.
+pub fn config_file() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("config.toml"))
+}
+
+/// The directory BuffeRS keeps its configuration in, following the XDG
+/// convention of `$XDG_CONFIG_HOME`, falling back to `~/.config`.
+pub fn config_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .map(|dir| dir.join("buffers"))
+}
+
+/// Where programs keep their data, following the XDG convention of
+/// `$XDG_DATA_HOME`, falling back to `~/.local/share`.
+pub fn data_home() -> Option<PathBuf> {
+    std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("share"))
+        })
+}
+
+/// The directory BuffeRS keeps its own data in, like snapshots.
+pub fn data_dir() -> Option<PathBuf> {
+    data_home().map(|dir| dir.join("buffers"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings_out_of_range_are_caught_before_theyre_used() {
+        let config: Config = toml::from_str("tick_ms = 50\ntab_width = 8\nwrap = true").unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.tick(), Duration::from_millis(50));
+
+        let config: Config = toml::from_str("tab_width = 0").unwrap();
+        assert_eq!(
+            config.validate(),
+            Err(String::from("tab_width is 0, but has to be from 1 to 16"))
+        );
+        assert!(toml::from_str::<Config>("tick_ms = -1").is_err());
+    }
+
+    #[test]
+    fn aliases_are_written_into_the_config_file() {
+        let text = "# Mine\nwrap = true\n";
+        let text = with_alias(text, "rg", Some("search -i"));
+        assert_eq!(
+            text,
+            "# Mine\nwrap = true\n\n[aliases]\nrg = \"search -i\"\n"
+        );
+        let text = with_alias(&text, "o", Some("open"));
+        let text = with_alias(&text, "rg", Some("search \"x\""));
+        assert_eq!(
+            text,
+            "# Mine\nwrap = true\n\n[aliases]\nrg = 'search \"x\"'\no = \"open\"\n"
+        );
+        let config: Config = toml::from_str(&text).unwrap();
+        assert_eq!(config.aliases["rg"], "search \"x\"");
+
+        let text = with_alias(&text, "rg", None);
+        assert_eq!(text, "# Mine\nwrap = true\n\n[aliases]\no = \"open\"\n");
+    }
+}
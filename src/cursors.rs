@@ -0,0 +1,229 @@
+//! Multiple cursors. Ctrl+D selects the word under the cursor, and each
+//! time after that selects the next place it's written and adds a
+//! cursor there. Typing, Backspace, Delete and Enter then happen at
+//! every cursor at once, over each one's selection if it has one, as a
+//! single undo step. The arrow keys move every cursor, and Esc, undo or
+//! redo go back to just the one.
+
+use termgame::{KeyCode, SimpleEvent};
+
+use std::ops::Range;
+
+use crate::abbrev::is_word_char;
+use crate::keymap::Action;
+use crate::words;
+use crate::BufferEditor;
+
+/// An edit made at every cursor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CursorEdit {
+    Type(char),
+    Backspace,
+    Delete,
+}
+
+impl CursorEdit {
+    /// The edit a key makes, if it's one that's made at every cursor.
+    pub fn of(action: Option<Action>, event: &SimpleEvent) -> Option<CursorEdit> {
+        match (action, event) {
+            (Some(Action::Newline), _) => Some(CursorEdit::Type('\n')),
+            (Some(Action::Backspace), _) => Some(CursorEdit::Backspace),
+            (Some(Action::Delete), _) => Some(CursorEdit::Delete),
+            (None, SimpleEvent::Just(KeyCode::Char(c))) => Some(CursorEdit::Type(*c)),
+            _ => None,
+        }
+    }
+}
+
+/// Makes `edit` at each of `cursors` in `text`, where each runs from
+/// its selection's anchor to where the cursor is. Returns the new text,
+/// and where each cursor ends up, in the same order. Cursors whose edits
+/// would overlap an earlier one's are merged into it.
+pub fn edit_all(text: &str, cursors: &[Range<usize>], edit: CursorEdit) -> (String, Vec<usize>) {
+    let mut order: Vec<usize> = (0..cursors.len()).collect();
+    order.sort_by_key(|&i| cursors[i].start.min(cursors[i].end));
+    let mut edited = String::with_capacity(text.len());
+    let mut ends = vec![0; cursors.len()];
+    let mut copied = 0;
+    for i in order {
+        let cursor = &cursors[i];
+        let selected = cursor.start.min(cursor.end)..cursor.start.max(cursor.end);
+        let range = match (edit, selected.is_empty()) {
+            (_, false) | (CursorEdit::Type(_), true) => selected,
+            (CursorEdit::Backspace, true) => {
+                let before = text[..cursor.end].chars().next_back();
+                cursor.end - before.map_or(0, char::len_utf8)..cursor.end
+            }
+            (CursorEdit::Delete, true) => {
+                let after = text[cursor.end..].chars().next();
+                cursor.end..cursor.end + after.map_or(0, char::len_utf8)
+            }
+        };
+        if range.start < copied {
+            ends[i] = edited.len();
+            continue;
+        }
+        edited.push_str(&text[copied..range.start]);
+        if let CursorEdit::Type(c) = edit {
+            edited.push(c);
+        }
+        ends[i] = edited.len();
+        copied = range.end;
+    }
+    edited.push_str(&text[copied..]);
+    (edited, ends)
+}
+
+/// The word at `offset` in `text`, if there's one there or just before.
+pub fn word_at(text: &str, offset: usize) -> Option<Range<usize>> {
+    let start = text[..offset]
+        .char_indices()
+        .rev()
+        .take_while(|&(_, c)| is_word_char(c))
+        .last()
+        .map_or(offset, |(i, _)| i);
+    let end = text[offset..]
+        .char_indices()
+        .find(|&(_, c)| !is_word_char(c))
+        .map_or(text.len(), |(i, _)| offset + i);
+    Some(start..end).filter(|word| !word.is_empty())
+}
+
+impl BufferEditor {
+    /// Selects the word under the cursor, or if something's selected,
+    /// adds a cursor at the next place it's written.
+    pub fn add_cursor(&mut self) {
+        let text = self.buffer.text();
+        let Some(selection) = self.buffer.selection() else {
+            if let Some(word) = word_at(text, self.buffer.cursor()) {
+                self.buffer.set_cursor(word.start);
+                self.buffer.start_selection();
+                self.buffer.set_cursor(word.end);
+            }
+            return;
+        };
+        let word = &text[selection.clone()];
+        let taken: Vec<usize> = self
+            .cursors
+            .iter()
+            .map(|cursor| cursor.start.min(cursor.end))
+            .chain([selection.start])
+            .collect();
+        let after = self
+            .cursors
+            .iter()
+            .map(|cursor| cursor.start.max(cursor.end))
+            .chain([selection.end])
+            .max()
+            .unwrap_or(0);
+        // Round to the top again once there are none further down.
+        let next = text[after..]
+            .match_indices(word)
+            .map(|(i, _)| after + i)
+            .chain(text.match_indices(word).map(|(i, _)| i))
+            .find(|start| !taken.contains(start));
+        if let Some(start) = next {
+            self.cursors.push(start..start + word.len());
+        }
+    }
+
+    /// Makes `edit` at every cursor.
+    pub fn edit_at_cursors(&mut self, edit: CursorEdit) {
+        let main = match self.buffer.selection() {
+            Some(selection) if selection.start == self.buffer.cursor() => {
+                selection.end..selection.start
+            }
+            Some(selection) => selection,
+            None => self.buffer.cursor()..self.buffer.cursor(),
+        };
+        let mut cursors = vec![main];
+        cursors.append(&mut self.cursors);
+        let (text, mut ends) = edit_all(self.buffer.text(), &cursors, edit);
+        self.buffer.set_text(&text);
+        self.buffer.set_cursor(ends.remove(0));
+        ends.sort();
+        ends.dedup();
+        let main = self.buffer.cursor();
+        self.cursors = ends
+            .into_iter()
+            .filter(|&end| end != main)
+            .map(|end| end..end)
+            .collect();
+    }
+
+    /// Moves every cursor but the main one, as `action` moves it.
+    pub fn move_cursors(&mut self, action: Action) {
+        let main = self.buffer.cursor();
+        for cursor in &mut self.cursors {
+            self.buffer.set_cursor(cursor.end);
+            match action {
+                Action::Left => self.buffer.move_left(),
+                Action::Right => self.buffer.move_right(),
+                Action::Up => self.buffer.move_up(),
+                Action::Down => self.buffer.move_down(),
+                Action::LineStart => self.buffer.move_home(),
+                Action::LineEnd => self.buffer.move_end(),
+                Action::WordLeft | Action::WordRight => {
+                    let (text, cursor) = (self.buffer.text(), self.buffer.cursor());
+                    let offset = match action == Action::WordRight {
+                        true => words::next_word_end(text, cursor),
+                        false => words::previous_word_start(text, cursor),
+                    };
+                    self.buffer.set_cursor(offset);
+                }
+                _ => {}
+            }
+            let end = self.buffer.cursor();
+            *cursor = end..end;
+        }
+        self.buffer.set_cursor(main);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::harness::{ctrl, Harness};
+
+    #[test]
+    fn edits_happen_at_every_cursor_and_merge_where_they_meet() {
+        let (text, ends) = edit_all("ab ab ab", &[3..5, 0..2, 6..8], CursorEdit::Type('x'));
+        assert_eq!(text, "x x x");
+        assert_eq!(ends, [3, 1, 5]);
+
+        let (text, ends) = edit_all("abc", &[1..1, 2..2], CursorEdit::Backspace);
+        assert_eq!(text, "c");
+        assert_eq!(ends, [0, 0]);
+
+        // Two cursors deleting the same char only delete it once.
+        let (text, _) = edit_all("abc", &[1..1, 1..1], CursorEdit::Delete);
+        assert_eq!(text, "ac");
+    }
+
+    #[test]
+    fn ctrl_d_adds_a_cursor_at_the_next_match() {
+        let mut harness = Harness::new("let x = x + y;\nx");
+        harness.press(KeyCode::Right).press(KeyCode::Right);
+        harness.press(KeyCode::Right).press(KeyCode::Right);
+        harness.run([ctrl('d'), ctrl('d'), ctrl('d')]);
+        harness.type_text("z");
+        assert_eq!(harness.text(), "let z = z + y;\nz");
+
+        harness.press(KeyCode::Backspace).type_text("ab");
+        assert_eq!(harness.text(), "let ab = ab + y;\nab");
+
+        // One undo takes back an edit at every cursor, and since the
+        // others could be anywhere after that, leaves just the one.
+        harness.run([ctrl('z')]);
+        assert_eq!(harness.text(), "let a = a + y;\na");
+        assert!(harness.editor.cursors.is_empty());
+
+        let mut harness = Harness::new("a a");
+        harness.run([ctrl('d'), ctrl('d')]);
+        assert_eq!(harness.editor.cursors.first(), Some(&(2..3)));
+        harness.press(KeyCode::Esc);
+        assert!(harness.editor.cursors.is_empty());
+        assert!(!harness.ended());
+    }
+}
@@ -0,0 +1,175 @@
+//! The dashboard BuffeRS starts on when it's run without any files: the
+//! files opened recently, the saved sessions, and a few tips.
+//!
+//! Up and Down move between the files and sessions, Enter opens the one
+//! under the cursor, and Esc goes on to the `>>` prompt.
+
+use termgame::{Game, KeyCode, SimpleEvent};
+
+use std::path::Path;
+use std::rc::Rc;
+
+use crate::buffer::Buffer;
+use crate::config::Config;
+use crate::dired::display_path;
+use crate::i18n::tr;
+use crate::keymap::{Action, Keymap};
+use crate::BufferEditor;
+
+/// Something on the dashboard which Enter opens.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Item {
+    File(String),
+    Session(String),
+}
+
+impl Item {
+    fn name(&self) -> &str {
+        match self {
+            Item::File(name) | Item::Session(name) => name,
+        }
+    }
+}
+
+/// What's on each line of the dashboard that can be opened.
+#[derive(Debug, Clone, Default)]
+pub struct Dashboard {
+    /// By (0-indexed) line, in order.
+    items: Vec<(usize, Item)>,
+}
+
+impl Dashboard {
+    /// The dashboard's text, listing `recent` files and `sessions`, and
+    /// what can be opened on which line.
+    fn build(recent: &[String], sessions: &[String], keymap: &Keymap) -> (String, Dashboard) {
+        let mut lines = vec![tr("dashboard.title", &[]), String::new()];
+        let mut items = Vec::new();
+        let sections = [
+            (
+                tr("dashboard.recent", &[]),
+                recent.iter().cloned().map(Item::File).collect::<Vec<_>>(),
+            ),
+            (
+                tr("dashboard.sessions", &[]),
+                sessions.iter().cloned().map(Item::Session).collect(),
+            ),
+        ];
+        for (heading, section) in sections {
+            lines.push(heading);
+            if section.is_empty() {
+                lines.push(format!("  {}", tr("dashboard.none", &[])));
+            }
+            for item in section {
+                lines.push(format!("  {}", item.name()));
+                items.push((lines.len() - 1, item));
+            }
+            lines.push(String::new());
+        }
+
+        let close = keymap
+            .key_for(Action::Close)
+            .map_or_else(String::new, |key| key.to_string());
+        lines.push(tr("dashboard.tips", &[]));
+        for tip in [
+            "dashboard.tip-open",
+            "dashboard.tip-tutor",
+            "dashboard.tip-session",
+        ] {
+            lines.push(format!("  {}", tr(tip, &[("close", &close)])));
+        }
+        let mut text = lines.join("\n");
+        text.push('\n');
+        (text, Dashboard { items })
+    }
+
+    fn item_on(&self, line: usize) -> Option<&Item> {
+        self.items
+            .iter()
+            .find(|(on, _)| *on == line)
+            .map(|(_, item)| item)
+    }
+}
+
+impl BufferEditor {
+    /// A new dashboard buffer, with the cursor on the most recent file.
+    pub fn dashboard(config: Rc<Config>, recent: &[String], sessions: &[String]) -> BufferEditor {
+        let keymap = Keymap::for_config(&config);
+        let recent: Vec<String> = recent
+            .iter()
+            .map(|file| display_path(Path::new(file)))
+            .collect();
+        let (text, dashboard) = Dashboard::build(&recent, sessions, &keymap);
+        let mut buffer = Buffer::from_text(None, text);
+        if let Some((line, _)) = dashboard.items.first() {
+            let start = buffer.line_span(*line..*line).start;
+            buffer.set_cursor(start);
+        }
+        let mut editor = BufferEditor::new(String::from("dashboard"), buffer, config);
+        editor.dashboard = Some(dashboard);
+        editor
+    }
+
+    /// Handles a key on the dashboard.
+    pub fn dashboard_event(&mut self, game: &mut Game, event: SimpleEvent) {
+        let Some(dashboard) = &self.dashboard else {
+            return;
+        };
+        let line = self.buffer.cursor_line();
+        let lines: Vec<usize> = dashboard.items.iter().map(|(line, _)| *line).collect();
+        match event {
+            SimpleEvent::Just(KeyCode::Up) => {
+                if let Some(&above) = lines.iter().rev().find(|&&other| other < line) {
+                    self.move_to_line(game, above);
+                }
+            }
+            SimpleEvent::Just(KeyCode::Down) => {
+                if let Some(&below) = lines.iter().find(|&&other| other > line) {
+                    self.move_to_line(game, below);
+                }
+            }
+            SimpleEvent::Just(KeyCode::Enter) => match dashboard.item_on(line).cloned() {
+                Some(Item::File(file)) => {
+                    self.open_next = Some(file);
+                    game.end_game();
+                }
+                Some(Item::Session(name)) => {
+                    self.then_run = Some(format!("session load {}", name));
+                    game.end_game();
+                }
+                None => {}
+            },
+            SimpleEvent::Just(KeyCode::Esc) => game.end_game(),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::harness::Harness;
+
+    #[test]
+    fn enter_opens_whatever_is_under_the_cursor() {
+        let recent = [String::from("/tmp/notes.txt"), String::from("/tmp/todo")];
+        let sessions = [String::from("work")];
+        let editor = BufferEditor::dashboard(Rc::new(Config::default()), &recent, &sessions);
+        let mut harness = Harness::with_editor(editor);
+        assert!(harness.text().contains("Saved sessions\n  work\n"));
+
+        harness.press(KeyCode::Down).press(KeyCode::Enter);
+        assert!(harness.ended());
+        assert_eq!(harness.editor.open_next.as_deref(), Some("/tmp/todo"));
+
+        let editor = BufferEditor::dashboard(Rc::new(Config::default()), &[], &sessions);
+        let mut harness = Harness::with_editor(editor);
+        assert!(harness.text().contains("Recent files\n  Nothing yet\n"));
+        // There's nothing past the last session to go down to.
+        harness.press(KeyCode::Down).press(KeyCode::Enter);
+        assert_eq!(
+            harness.editor.then_run.as_deref(),
+            Some("session load work")
+        );
+    }
+}
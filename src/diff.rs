@@ -0,0 +1,288 @@
+//! Line-by-line differences between two versions of a text.
+
+/// What happened to one line on the way from the old text to the new.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// The shortest list of line changes which turns `old` into `new`.
+pub fn lines<'a>(old: &'a str, new: &'a str) -> Vec<Change<'a>> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // Only the middle, where the texts differ, needs the full search.
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (old_middle, new_middle) = (
+        &old[prefix..old.len() - suffix],
+        &new[prefix..new.len() - suffix],
+    );
+
+    // common[i][j] is the length of the longest common subsequence of
+    // old_middle[i..] and new_middle[j..].
+    let mut common = vec![vec![0u32; new_middle.len() + 1]; old_middle.len() + 1];
+    for i in (0..old_middle.len()).rev() {
+        for j in (0..new_middle.len()).rev() {
+            common[i][j] = match old_middle[i] == new_middle[j] {
+                true => common[i + 1][j + 1] + 1,
+                false => common[i + 1][j].max(common[i][j + 1]),
+            };
+        }
+    }
+
+    let mut changes: Vec<Change> = old[..prefix]
+        .iter()
+        .map(|&line| Change::Same(line))
+        .collect();
+    let (mut i, mut j) = (0, 0);
+    while i < old_middle.len() || j < new_middle.len() {
+        if i < old_middle.len() && j < new_middle.len() && old_middle[i] == new_middle[j] {
+            changes.push(Change::Same(old_middle[i]));
+            i += 1;
+            j += 1;
+        } else if i < old_middle.len()
+            && (j == new_middle.len() || common[i + 1][j] >= common[i][j + 1])
+        {
+            changes.push(Change::Removed(old_middle[i]));
+            i += 1;
+        } else {
+            changes.push(Change::Added(new_middle[j]));
+            j += 1;
+        }
+    }
+    changes.extend(
+        old[old.len() - suffix..]
+            .iter()
+            .map(|&line| Change::Same(line)),
+    );
+    changes
+}
+
+/// How many lines were added and removed.
+pub fn stats(changes: &[Change]) -> (usize, usize) {
+    changes
+        .iter()
+        .fold((0, 0), |(added, removed), change| match change {
+            Change::Same(_) => (added, removed),
+            Change::Removed(_) => (added, removed + 1),
+            Change::Added(_) => (added + 1, removed),
+        })
+}
+
+/// Shows the differences between `old` and `new` in the style of
+/// `diff -u`, with `context` unchanged lines around each change.
+pub fn unified(old: &str, new: &str, context: usize) -> String {
+    let changes = lines(old, new);
+    let changed: Vec<usize> = changes
+        .iter()
+        .enumerate()
+        .filter(|(_, change)| !matches!(change, Change::Same(_)))
+        .map(|(i, _)| i)
+        .collect();
+
+    // Runs of changes close enough together share a hunk.
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for &i in &changed {
+        let start = i.saturating_sub(context);
+        let end = (i + context + 1).min(changes.len());
+        match hunks.last_mut() {
+            Some((_, last_end)) if start <= *last_end => *last_end = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    let mut text = String::new();
+    for (start, end) in hunks {
+        // Line numbers, 1-indexed, of the hunk's first line in each text.
+        let old_line = 1 + changes[..start]
+            .iter()
+            .filter(|change| !matches!(change, Change::Added(_)))
+            .count();
+        let new_line = 1 + changes[..start]
+            .iter()
+            .filter(|change| !matches!(change, Change::Removed(_)))
+            .count();
+        let hunk = &changes[start..end];
+        let old_len = hunk
+            .iter()
+            .filter(|change| !matches!(change, Change::Added(_)))
+            .count();
+        let new_len = hunk
+            .iter()
+            .filter(|change| !matches!(change, Change::Removed(_)))
+            .count();
+        text.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            old_line, old_len, new_line, new_len
+        ));
+        for change in hunk {
+            let (sign, line) = match change {
+                Change::Same(line) => (' ', line),
+                Change::Removed(line) => ('-', line),
+                Change::Added(line) => ('+', line),
+            };
+            text.push_str(&format!("{}{}\n", sign, line));
+        }
+    }
+    text
+}
+
+/// For each line of `old`, the line of `new` it's kept as, if it wasn't
+/// removed.
+fn kept_lines(old: &str, new: &str) -> Vec<Option<usize>> {
+    let mut kept = Vec::new();
+    let mut new_line = 0;
+    for change in lines(old, new) {
+        match change {
+            Change::Same(_) => {
+                kept.push(Some(new_line));
+                new_line += 1;
+            }
+            Change::Removed(_) => kept.push(None),
+            Change::Added(_) => new_line += 1,
+        }
+    }
+    kept
+}
+
+/// Combines two sets of changes made to `base`: `mine` and `theirs`.
+/// Where only one side changed part of the text, that change is taken;
+/// where both changed the same part differently, both versions are
+/// kept between conflict markers, along with what the base had:
+///
+/// ```text
+/// <<<<<<< mine
+/// ||||||| base
+/// =======
+/// >>>>>>> theirs
+/// ```
+///
+/// Returns the merged text, and how many conflicts it has.
+pub fn merge3(base: &str, mine: &str, theirs: &str) -> (String, usize) {
+    let base_lines: Vec<&str> = base.lines().collect();
+    let mine_lines: Vec<&str> = mine.lines().collect();
+    let theirs_lines: Vec<&str> = theirs.lines().collect();
+    let in_mine = kept_lines(base, mine);
+    let in_theirs = kept_lines(base, theirs);
+
+    // Lines of the base which neither side touched anchor the merge;
+    // everything between two anchors is settled on its own.
+    let anchors = (0..base_lines.len())
+        .filter_map(|b| Some((b, in_mine[b]?, in_theirs[b]?)))
+        .chain([(base_lines.len(), mine_lines.len(), theirs_lines.len())]);
+
+    let mut merged: Vec<&str> = Vec::new();
+    let mut conflicts = 0;
+    let (mut b, mut m, mut t) = (0, 0, 0);
+    for (next_b, next_m, next_t) in anchors {
+        let base_part = &base_lines[b..next_b];
+        let mine_part = &mine_lines[m..next_m];
+        let theirs_part = &theirs_lines[t..next_t];
+        if mine_part == base_part || mine_part == theirs_part {
+            merged.extend(theirs_part);
+        } else if theirs_part == base_part {
+            merged.extend(mine_part);
+        } else {
+            conflicts += 1;
+            merged.push("<<<<<<< mine");
+            merged.extend(mine_part);
+            merged.push("||||||| base");
+            merged.extend(base_part);
+            merged.push("=======");
+            merged.extend(theirs_part);
+            merged.push(">>>>>>> theirs");
+        }
+        if next_b < base_lines.len() {
+            merged.push(base_lines[next_b]);
+        }
+        (b, m, t) = (next_b + 1, next_m + 1, next_t + 1);
+    }
+
+    // Whether the text ends with a newline is merged like a line: it's
+    // taken from whichever side changed it.
+    let ends = |text: &str| text.ends_with('\n');
+    let newline = match ends(mine) == ends(base) {
+        true => ends(theirs),
+        false => ends(mine),
+    };
+    let mut text = merged.join("\n");
+    if !text.is_empty() && newline {
+        text.push('\n');
+    }
+    (text, conflicts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_changed_lines_are_marked() {
+        let changes = lines("a\nb\nc\n", "a\nB\nc\nd\n");
+        assert_eq!(
+            changes,
+            [
+                Change::Same("a"),
+                Change::Removed("b"),
+                Change::Added("B"),
+                Change::Same("c"),
+                Change::Added("d"),
+            ]
+        );
+        assert_eq!(stats(&changes), (2, 1));
+        assert_eq!(
+            unified("a\nb\nc\nd\ne\n", "a\nb\nC\nd\ne\n", 1),
+            "@@ -2,3 +2,3 @@\n b\n-c\n+C\n d\n"
+        );
+        assert_eq!(unified("same\n", "same\n", 3), "");
+    }
+
+    #[test]
+    fn changes_to_different_lines_merge_cleanly() {
+        let base = "one\ntwo\nthree\nfour\n";
+        let mine = "ONE\ntwo\nthree\nfour\n";
+        let theirs = "one\ntwo\nthree\nFOUR\nfive\n";
+        assert_eq!(
+            merge3(base, mine, theirs),
+            (String::from("ONE\ntwo\nthree\nFOUR\nfive\n"), 0)
+        );
+        // The same change on both sides isn't a conflict.
+        assert_eq!(merge3(base, mine, mine), (String::from(mine), 0));
+    }
+
+    #[test]
+    fn changes_to_the_same_lines_conflict() {
+        let (text, conflicts) = merge3("a\nb\nc\n", "a\nmine\nc\n", "a\ntheirs\nc\n");
+        assert_eq!(conflicts, 1);
+        assert_eq!(
+            text,
+            "a\n<<<<<<< mine\nmine\n||||||| base\nb\n=======\ntheirs\n>>>>>>> theirs\nc\n"
+        );
+    }
+
+    #[test]
+    fn a_conflict_can_be_at_the_very_end() {
+        let (text, conflicts) = merge3("a\nb\n", "a\nb\nmine\n", "a\nb\ntheirs\n");
+        assert_eq!(conflicts, 1);
+        assert_eq!(
+            text,
+            "a\nb\n<<<<<<< mine\nmine\n||||||| base\n=======\ntheirs\n>>>>>>> theirs\n"
+        );
+    }
+
+    #[test]
+    fn a_missing_last_newline_is_merged_like_a_line() {
+        let base = "a\nb\n";
+        assert_eq!(merge3(base, "a\nb", "A\nb\n"), (String::from("A\nb"), 0));
+        assert_eq!(merge3(base, "A\nb\n", "a\nb"), (String::from("A\nb"), 0));
+        assert_eq!(merge3("a", "a", "a\nb"), (String::from("a\nb"), 0));
+    }
+}
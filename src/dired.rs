@@ -0,0 +1,337 @@
+//! Directory buffers: `open` on a directory lists what's in it, and
+//! files can be opened, renamed, deleted and created from the listing.
+//!
+//! In a directory buffer:
+//! - Up and Down move between entries,
+//! - Enter opens the file, or lists the directory, under the cursor,
+//! - Backspace goes up to the parent directory,
+//! - `r` renames, `d` moves to the trash, `c` creates a file, `+`
+//!   creates a directory, and `g` refreshes the listing.
+
+use termgame::{Game, KeyCode, Message, SimpleEvent};
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::buffer::Buffer;
+use crate::dates::DateTime;
+use crate::i18n::tr;
+use crate::prompt::{Prompt, Question};
+use crate::trash;
+use crate::units;
+use crate::BufferEditor;
+
+/// One thing in a directory.
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub name: String,
+    pub is_dir: bool,
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+}
+
+/// A listing of a directory.
+#[derive(Debug, Clone)]
+pub struct Directory {
+    pub path: PathBuf,
+    /// Directories first, then files, each alphabetically. The first
+    /// entry is always `..`.
+    pub entries: Vec<Entry>,
+}
+
+/// Something in the directory waiting on an answer to a prompt.
+#[derive(Debug, Clone)]
+pub enum DirAction {
+    Rename(PathBuf),
+    Delete(PathBuf),
+    CreateFile,
+    CreateDir,
+}
+
+impl Directory {
+    /// Lists the directory at `path`.
+    pub fn read(path: &Path) -> io::Result<Directory> {
+        let mut entries = Vec::new();
+        for entry in fs::read_dir(path)? {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            entries.push(Entry {
+                name: entry.file_name().to_string_lossy().into_owned(),
+                is_dir: metadata.is_dir(),
+                size: metadata.len(),
+                modified: metadata.modified().ok(),
+            });
+        }
+        entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
+        entries.insert(
+            0,
+            Entry {
+                name: String::from(".."),
+                is_dir: true,
+                size: 0,
+                modified: None,
+            },
+        );
+        Ok(Directory {
+            path: PathBuf::from(path),
+            entries,
+        })
+    }
+
+    /// The text of the directory buffer: the directory's path, then a
+    /// line for each entry.
+    pub fn listing(&self) -> String {
+        let mut text = format!("{}:\n", self.path.display());
+        for entry in &self.entries {
+            let modified = match entry.modified {
+                Some(modified) => {
+                    let time = DateTime::utc(modified);
+                    format!(
+                        "{:04}-{:02}-{:02} {:02}:{:02}",
+                        time.year, time.month, time.day, time.hour, time.minute
+                    )
+                }
+                None => String::new(),
+            };
+            let size = match entry.is_dir {
+                true => String::new(),
+                false => units::size(entry.size as usize),
+            };
+            let slash = if entry.is_dir { "/" } else { "" };
+            text.push_str(&format!(
+                "{:>10} {:>16}  {}{}\n",
+                size, modified, entry.name, slash
+            ));
+        }
+        text
+    }
+
+    /// The entry on (0-indexed) line `line` of the listing.
+    pub fn entry_at(&self, line: usize) -> Option<&Entry> {
+        self.entries.get(line.checked_sub(1)?)
+    }
+
+    /// Does what a prompt asked for, with the answer it got. Returns
+    /// what to tell the user.
+    pub fn perform(&self, action: &DirAction, answer: &str) -> io::Result<String> {
+        match action {
+            DirAction::Rename(from) => {
+                let to = self.path.join(answer);
+                fs::rename(from, &to)?;
+                Ok(tr("dired.renamed", &[("path", &to.display())]))
+            }
+            DirAction::Delete(path) => {
+                if answer != tr("dired.yes", &[]) && !matches!(answer, "y" | "yes") {
+                    return Ok(tr("dired.not-deleted", &[]));
+                }
+                let trashed = trash::move_to_trash(path)?;
+                Ok(tr("dired.trashed", &[("path", &trashed.display())]))
+            }
+            DirAction::CreateFile => {
+                let path = self.path.join(answer);
+                fs::OpenOptions::new()
+                    .write(true)
+                    .create_new(true)
+                    .open(&path)?;
+                Ok(tr("dired.created", &[("path", &path.display())]))
+            }
+            DirAction::CreateDir => {
+                let path = self.path.join(answer);
+                fs::create_dir(&path)?;
+                Ok(tr("dired.created", &[("path", &path.display())]))
+            }
+        }
+    }
+}
+
+impl BufferEditor {
+    /// Turns this editor into a listing of `directory`.
+    pub fn show_directory(&mut self, directory: Directory) {
+        self.buffer = Buffer::from_text(None, directory.listing());
+        // Start on the first real entry, rather than `..`.
+        let first = self.buffer.line_span(2..2).start;
+        self.buffer.set_cursor(first);
+        self.directory = Some(directory);
+        self.viewport.y = 0;
+    }
+
+    /// Handles a key in a directory buffer.
+    pub fn dired_event(&mut self, game: &mut Game, event: SimpleEvent) {
+        let Some(directory) = self.directory.clone() else {
+            return;
+        };
+
+        let line = self.buffer.cursor_line();
+        let entry = directory.entry_at(line).cloned();
+        let entry_path = entry.as_ref().map(|entry| directory.path.join(&entry.name));
+        match event {
+            SimpleEvent::Just(KeyCode::Up) => self.move_to_line(game, line.saturating_sub(1)),
+            SimpleEvent::Just(KeyCode::Down) => {
+                self.move_to_line(game, (line + 1).min(directory.entries.len()))
+            }
+            SimpleEvent::Just(KeyCode::Enter) => match (entry, entry_path) {
+                (Some(entry), Some(path)) if entry.is_dir => self.refresh(game, &path),
+                (Some(_), Some(path)) => {
+                    self.open_next = Some(display_path(&path));
+                    game.end_game();
+                }
+                _ => {}
+            },
+            SimpleEvent::Just(KeyCode::Backspace) => {
+                self.refresh(game, &directory.path.join(".."));
+            }
+            SimpleEvent::Just(KeyCode::Char('g')) => self.refresh(game, &directory.path),
+            SimpleEvent::Just(KeyCode::Char('r')) => {
+                if let (Some(entry), Some(path)) = (entry, entry_path) {
+                    if entry.name != ".." {
+                        let prompt = Prompt::new(&tr("dired.rename-prompt", &[]), &entry.name);
+                        self.prompt = Some((prompt, Question::Dired(DirAction::Rename(path))));
+                    }
+                }
+            }
+            SimpleEvent::Just(KeyCode::Char('d')) => {
+                if let (Some(entry), Some(path)) = (entry, entry_path) {
+                    if entry.name != ".." {
+                        let label = tr("dired.delete-prompt", &[("name", &entry.name)]);
+                        self.prompt = Some((
+                            Prompt::new(&label, ""),
+                            Question::Dired(DirAction::Delete(path)),
+                        ));
+                    }
+                }
+            }
+            SimpleEvent::Just(KeyCode::Char('c')) => {
+                let prompt = Prompt::new(&tr("dired.new-file-prompt", &[]), "");
+                self.prompt = Some((prompt, Question::Dired(DirAction::CreateFile)));
+            }
+            SimpleEvent::Just(KeyCode::Char('+')) => {
+                let prompt = Prompt::new(&tr("dired.new-dir-prompt", &[]), "");
+                self.prompt = Some((prompt, Question::Dired(DirAction::CreateDir)));
+            }
+            SimpleEvent::Just(KeyCode::Esc) => game.end_game(),
+            _ => {}
+        }
+    }
+
+    /// Does what a prompt in a directory buffer asked for.
+    pub fn dired_answer(&mut self, game: &mut Game, action: &DirAction, answer: &str) {
+        let Some(directory) = self.directory.clone() else {
+            return;
+        };
+        let message = directory
+            .perform(action, answer)
+            .unwrap_or_else(|err| err.to_string());
+        game.set_message(Some(Message::new(message)));
+        self.refresh(game, &directory.path);
+    }
+
+    /// Lists `path` again, or instead.
+    fn refresh(&mut self, game: &mut Game, path: &Path) {
+        let path = fs::canonicalize(path).unwrap_or_else(|_| PathBuf::from(path));
+        match Directory::read(&path) {
+            Ok(directory) => {
+                self.show_directory(directory);
+                game.set_viewport(self.viewport);
+            }
+            Err(err) => {
+                let message = tr(
+                    "dired.list-failed",
+                    &[("path", &path.display()), ("error", &err)],
+                );
+                game.set_message(Some(Message::new(message)));
+            }
+        }
+    }
+
+    /// Moves the cursor to the start of `line`, scrolling to keep it in view.
+    pub fn move_to_line(&mut self, game: &mut Game, line: usize) {
+        let start = self.buffer.line_span(line..line).start;
+        self.buffer.set_cursor(start);
+        self.scroll_to_cursor(game);
+    }
+}
+
+/// How to name the buffer for `path`: relative to the current directory
+/// if it's inside it.
+pub fn display_path(path: &Path) -> String {
+    let relative = std::env::current_dir()
+        .ok()
+        .and_then(|dir| path.strip_prefix(dir).ok().map(PathBuf::from));
+    relative
+        .unwrap_or_else(|| PathBuf::from(path))
+        .display()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::harness::{self, Harness};
+
+    #[test]
+    fn directories_are_listed_first_and_can_be_entered_and_left() {
+        let dir = std::env::temp_dir().join(format!("buffers-dired-{}", std::process::id()));
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("b.txt"), "x".repeat(1536)).unwrap();
+        fs::write(dir.join("a.txt"), "one").unwrap();
+        let dir = fs::canonicalize(&dir).unwrap();
+        let directory = Directory::read(&dir).unwrap();
+        let names: Vec<&str> = directory.entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["..", "sub", "a.txt", "b.txt"]);
+        let listing = directory.listing();
+        let lines: Vec<&str> = listing.lines().collect();
+        assert_eq!(lines[0], format!("{}:", dir.display()));
+        assert!(lines[2].ends_with("  sub/"), "{}", lines[2]);
+        assert!(lines[3].starts_with("       3 B "), "{}", lines[3]);
+        assert!(lines[4].starts_with("   1.5 KiB "), "{}", lines[4]);
+        assert_eq!(directory.entry_at(3).unwrap().name, "a.txt");
+        assert!(directory.entry_at(0).is_none());
+
+        let mut editor = harness::editor("dir", "");
+        editor.show_directory(directory);
+        let mut harness = Harness::with_editor(editor);
+        assert_eq!(harness.editor.buffer.cursor_line(), 2);
+        harness.press(KeyCode::Enter);
+        let listed = &harness.editor.directory.as_ref().unwrap().path;
+        assert_eq!(listed, &dir.join("sub"));
+        harness.press(KeyCode::Backspace);
+        assert_eq!(harness.editor.directory.as_ref().unwrap().path, dir);
+
+        // Entering a file opens it instead.
+        harness.press(KeyCode::Down).press(KeyCode::Enter);
+        assert_eq!(
+            harness.editor.open_next,
+            Some(display_path(&dir.join("a.txt")))
+        );
+        assert!(harness.ended());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn files_are_created_and_renamed_from_the_listing() {
+        let dir = std::env::temp_dir().join(format!("buffers-dired-new-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let directory = Directory::read(&fs::canonicalize(&dir).unwrap()).unwrap();
+        let mut editor = harness::editor("dir", "");
+        editor.show_directory(directory);
+        let mut harness = Harness::with_editor(editor);
+
+        harness.type_text("cnew.txt\n");
+        assert!(dir.join("new.txt").is_file());
+        harness.type_text("+inner\n");
+        assert!(dir.join("inner").is_dir());
+
+        // `inner/` is listed first, so `new.txt` is the line after it.
+        harness.press(KeyCode::Down).type_text("r");
+        for _ in 0.."new.txt".len() {
+            harness.press(KeyCode::Backspace);
+        }
+        harness.type_text("renamed.txt\n");
+        assert!(dir.join("renamed.txt").is_file());
+        assert!(!dir.join("new.txt").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,197 @@
+//! Reading and writing files which aren't UTF-8.
+//!
+//! A file's encoding is worked out when it's opened: from its byte order
+//! mark if it has one, and otherwise UTF-16 if every other byte is zero,
+//! a hex dump if it doesn't look like text at all, UTF-8 if it's valid
+//! UTF-8, and Latin-1 (which anything is) if not. The buffer holds the
+//! text as UTF-8, and it's written back in the encoding it was read in,
+//! unless `encoding` says to use another.
+
+use std::fmt;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::Path;
+
+use crate::hex;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    /// UTF-8 starting with a byte order mark.
+    Utf8Bom,
+    Utf16Le,
+    Utf16Be,
+    Latin1,
+    /// Not text at all, shown as a hex dump.
+    Hex,
+}
+
+/// Every encoding, as `encoding` calls them.
+pub const ENCODINGS: &[(Encoding, &str)] = &[
+    (Encoding::Utf8, "utf-8"),
+    (Encoding::Utf8Bom, "utf-8-bom"),
+    (Encoding::Utf16Le, "utf-16le"),
+    (Encoding::Utf16Be, "utf-16be"),
+    (Encoding::Latin1, "latin-1"),
+    (Encoding::Hex, "hex"),
+];
+
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+const UTF16LE_BOM: &[u8] = &[0xFF, 0xFE];
+const UTF16BE_BOM: &[u8] = &[0xFE, 0xFF];
+
+impl Encoding {
+    pub fn name(self) -> &'static str {
+        ENCODINGS
+            .iter()
+            .find(|(encoding, _)| *encoding == self)
+            .map_or("utf-8", |(_, name)| name)
+    }
+
+    /// The encoding called `name`, ignoring case, and with Latin-1 also
+    /// going by `latin1` and `iso-8859-1`.
+    pub fn by_name(name: &str) -> Option<Encoding> {
+        let name = name.to_lowercase();
+        let name = match name.as_str() {
+            "utf8" => "utf-8",
+            "latin1" | "iso-8859-1" => "latin-1",
+            name => name,
+        };
+        ENCODINGS
+            .iter()
+            .find(|(_, other)| *other == name)
+            .map(|(encoding, _)| *encoding)
+    }
+
+    /// The encoding `bytes` are most likely in.
+    pub fn detect(bytes: &[u8]) -> Encoding {
+        if bytes.starts_with(UTF8_BOM) {
+            return Encoding::Utf8Bom;
+        }
+        if bytes.starts_with(UTF16LE_BOM) {
+            return Encoding::Utf16Le;
+        }
+        if bytes.starts_with(UTF16BE_BOM) {
+            return Encoding::Utf16Be;
+        }
+        // Mostly ASCII text in UTF-16 has a zero in every other byte,
+        // which is still valid UTF-8, but not anything anyone writes.
+        let zeros = |start: usize| bytes.iter().skip(start).step_by(2).all(|&b| b == 0);
+        let pairs = !bytes.is_empty() && bytes.len().is_multiple_of(2);
+        match std::str::from_utf8(bytes) {
+            _ if pairs && zeros(1) => Encoding::Utf16Le,
+            _ if pairs && zeros(0) => Encoding::Utf16Be,
+            _ if is_binary(bytes) => Encoding::Hex,
+            Ok(_) => Encoding::Utf8,
+            Err(_) => Encoding::Latin1,
+        }
+    }
+
+    /// Reads `bytes` as text in this encoding, without any byte order
+    /// mark.
+    pub fn decode(self, bytes: &[u8]) -> Result<String, String> {
+        let invalid = || format!("this isn't valid {}", self.name());
+        match self {
+            Encoding::Utf8 => String::from_utf8(bytes.to_vec()).map_err(|_| invalid()),
+            Encoding::Utf8Bom => {
+                let bytes = bytes.strip_prefix(UTF8_BOM).unwrap_or(bytes);
+                String::from_utf8(bytes.to_vec()).map_err(|_| invalid())
+            }
+            Encoding::Utf16Le | Encoding::Utf16Be => {
+                let (bom, from): (_, fn([u8; 2]) -> u16) = match self {
+                    Encoding::Utf16Le => (UTF16LE_BOM, u16::from_le_bytes),
+                    _ => (UTF16BE_BOM, u16::from_be_bytes),
+                };
+                let bytes = bytes.strip_prefix(bom).unwrap_or(bytes);
+                if !bytes.len().is_multiple_of(2) {
+                    return Err(invalid());
+                }
+                let units: Vec<u16> = bytes
+                    .chunks_exact(2)
+                    .map(|pair| from([pair[0], pair[1]]))
+                    .collect();
+                String::from_utf16(&units).map_err(|_| invalid())
+            }
+            Encoding::Latin1 => Ok(bytes.iter().map(|&b| char::from(b)).collect()),
+            Encoding::Hex => Ok(hex::dump(bytes)),
+        }
+    }
+
+    /// Writes `text` in this encoding, with a byte order mark for the
+    /// ones which have one.
+    pub fn encode(self, text: &str) -> Result<Vec<u8>, String> {
+        match self {
+            Encoding::Utf8 => Ok(text.as_bytes().to_vec()),
+            Encoding::Utf8Bom => Ok([UTF8_BOM, text.as_bytes()].concat()),
+            Encoding::Utf16Le => Ok(UTF16LE_BOM
+                .iter()
+                .copied()
+                .chain(text.encode_utf16().flat_map(u16::to_le_bytes))
+                .collect()),
+            Encoding::Utf16Be => Ok(UTF16BE_BOM
+                .iter()
+                .copied()
+                .chain(text.encode_utf16().flat_map(u16::to_be_bytes))
+                .collect()),
+            Encoding::Latin1 => text
+                .chars()
+                .map(|c| {
+                    u8::try_from(u32::from(c))
+                        .map_err(|_| format!("{} can't be written in latin-1", c))
+                })
+                .collect(),
+            Encoding::Hex => hex::parse(text),
+        }
+    }
+}
+
+/// Whether `bytes` look like something other than text: text never has
+/// a zero byte, and only has a few control characters, like tabs.
+fn is_binary(bytes: &[u8]) -> bool {
+    let controls = bytes
+        .iter()
+        .filter(|&&b| (b < 0x20 && !b"\t\n\r\x0c\x1b".contains(&b)) || b == 0x7f)
+        .count();
+    bytes.contains(&0) || controls * 10 > bytes.len()
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Reads the file at `path` as text in `encoding`.
+pub fn read(path: impl AsRef<Path>, encoding: Encoding) -> io::Result<String> {
+    let bytes = fs::read(path)?;
+    encoding
+        .decode(&bytes)
+        .map_err(|err| io::Error::new(ErrorKind::InvalidData, err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn files_are_read_and_written_back_in_their_own_encoding() {
+        let text = "naïve café";
+        let text_encodings = ENCODINGS
+            .iter()
+            .filter(|(encoding, _)| *encoding != Encoding::Hex);
+        for &(encoding, _) in text_encodings {
+            let bytes = encoding.encode(text).unwrap();
+            assert_eq!(Encoding::detect(&bytes), encoding);
+            assert_eq!(encoding.decode(&bytes).unwrap(), text);
+        }
+        // Without a byte order mark, UTF-16 is told by its zeros.
+        assert_eq!(Encoding::detect(b"hi"), Encoding::Utf8);
+        let bytes: Vec<u8> = "hi".encode_utf16().flat_map(u16::to_le_bytes).collect();
+        assert_eq!(Encoding::detect(&bytes), Encoding::Utf16Le);
+
+        assert!(Encoding::Latin1.encode("€").is_err());
+        assert_eq!(Encoding::detect(b"\x7fELF\x02\x01\x01\x00"), Encoding::Hex);
+        assert_eq!(Encoding::by_name("ISO-8859-1"), Some(Encoding::Latin1));
+    }
+}
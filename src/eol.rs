@@ -0,0 +1,102 @@
+//! Line endings. Whatever a file ends its lines with, the buffer holds
+//! them as `\n` while it's edited, and they're turned back into the
+//! file's own when it's written, so a file from Windows stays one.
+
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    CrLf,
+    /// Just `\r`, as old Macs wrote.
+    Cr,
+}
+
+impl LineEnding {
+    pub fn name(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "lf",
+            LineEnding::CrLf => "crlf",
+            LineEnding::Cr => "cr",
+        }
+    }
+
+    pub fn by_name(name: &str) -> Option<LineEnding> {
+        [LineEnding::Lf, LineEnding::CrLf, LineEnding::Cr]
+            .into_iter()
+            .find(|eol| eol.name().eq_ignore_ascii_case(name))
+    }
+
+    /// The line ending most of `text`'s lines end with, or `\n` if it's
+    /// all one line.
+    pub fn detect(text: &str) -> LineEnding {
+        let crlf = text.matches("\r\n").count();
+        let lf = text.matches('\n').count() - crlf;
+        let cr = text.matches('\r').count() - crlf;
+        match (crlf, cr) {
+            _ if lf >= crlf && lf >= cr => LineEnding::Lf,
+            (crlf, cr) if crlf >= cr => LineEnding::CrLf,
+            _ => LineEnding::Cr,
+        }
+    }
+
+    /// Whether `text` ends its lines more than one way, so some of them
+    /// will be written back differently.
+    pub fn is_mixed(text: &str) -> bool {
+        let crlf = text.matches("\r\n").count();
+        let lf = text.matches('\n').count() - crlf;
+        let cr = text.matches('\r').count() - crlf;
+        [crlf, lf, cr].iter().filter(|&&count| count > 0).count() > 1
+    }
+
+    /// `text` from a file with these line endings, as the buffer holds it.
+    pub fn strip(self, text: String) -> String {
+        match self {
+            LineEnding::Lf => text,
+            LineEnding::CrLf => text.replace("\r\n", "\n"),
+            LineEnding::Cr => text.replace('\r', "\n"),
+        }
+    }
+
+    /// `text` from the buffer, as it's written to a file with these line
+    /// endings.
+    pub fn restore(self, text: &str) -> String {
+        match self {
+            LineEnding::Lf => String::from(text),
+            LineEnding::CrLf => text.replace('\n', "\r\n"),
+            LineEnding::Cr => text.replace('\n', "\r"),
+        }
+    }
+}
+
+impl fmt::Display for LineEnding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_endings_are_put_back_as_they_were() {
+        for (text, eol) in [
+            ("one\ntwo\n", LineEnding::Lf),
+            ("one\r\ntwo\r\n", LineEnding::CrLf),
+            ("one\rtwo\r", LineEnding::Cr),
+            ("one", LineEnding::Lf),
+        ] {
+            assert_eq!(LineEnding::detect(text), eol);
+            let stripped = eol.strip(String::from(text));
+            assert!(!stripped.contains('\r'));
+            assert_eq!(eol.restore(&stripped), text);
+        }
+        // Mostly one kind wins.
+        assert_eq!(LineEnding::detect("a\r\nb\r\nc\n"), LineEnding::CrLf);
+        assert!(LineEnding::is_mixed("a\r\nb\r\nc\n"));
+        assert!(!LineEnding::is_mixed("a\r\nb\r\n"));
+        assert!(!LineEnding::is_mixed("one"));
+    }
+}
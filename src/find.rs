@@ -0,0 +1,185 @@
+//! Finding text from inside the editor.
+//!
+//! Ctrl+F asks what to find in the status bar, and the cursor jumps to
+//! the first match after it as each key is typed. Enter keeps the match,
+//! and Esc goes back to where the cursor was. After that, F3 goes on to
+//! the next match and Alt+F3 back to the one before, wrapping round the
+//! ends of the buffer.
+//!
+//! Every match is highlighted until Esc, or `nohl` at the `>>` prompt,
+//! clears it.
+
+use regex::Regex;
+use termgame::{Game, Message};
+
+use std::ops::Range;
+
+use crate::i18n::tr;
+use crate::prompt::{Prompt, Question};
+use crate::BufferEditor;
+
+/// What's being found, as it was typed.
+#[derive(Debug, Clone)]
+pub struct Find {
+    pub query: String,
+    regex: Regex,
+}
+
+impl Find {
+    /// Finds `query` as it's written, rather than as a pattern, since
+    /// it's matched as it's typed.
+    pub fn new(query: &str) -> Find {
+        // Escaped, the query can't be an invalid pattern.
+        Find {
+            query: String::from(query),
+            regex: Regex::new(&regex::escape(query)).unwrap(),
+        }
+    }
+
+    /// Where the first match at or after `offset` in `text` starts,
+    /// wrapping round to the top when there's none below.
+    fn next(&self, text: &str, offset: usize) -> Option<usize> {
+        self.regex
+            .find_at(text, offset)
+            .or_else(|| self.regex.find(text))
+            .map(|found| found.start())
+    }
+
+    /// Where the last match before `offset` in `text` starts, wrapping
+    /// round to the bottom when there's none above.
+    fn previous(&self, text: &str, offset: usize) -> Option<usize> {
+        let starts: Vec<usize> = self
+            .regex
+            .find_iter(text)
+            .map(|found| found.start())
+            .collect();
+        starts
+            .iter()
+            .rev()
+            .find(|&&start| start < offset)
+            .or(starts.last())
+            .copied()
+    }
+
+    /// The byte ranges of every match in `line`.
+    pub fn matches<'a>(&'a self, line: &'a str) -> impl Iterator<Item = Range<usize>> + 'a {
+        self.regex.find_iter(line).map(|found| found.range())
+    }
+}
+
+impl BufferEditor {
+    /// Asks what to find, starting from the cursor.
+    pub fn start_find(&mut self) {
+        let prompt = Prompt::new(&tr("editor.find-prompt", &[]), "");
+        let from = self.buffer.cursor();
+        self.prompt = Some((prompt, Question::Find { from }));
+    }
+
+    /// Jumps to the first match of what's been typed so far at or after
+    /// `from`, or back to `from` if there isn't one. Returns where `from`
+    /// is now, which only moves when a huge file loads other lines.
+    pub fn find_typed(&mut self, game: &mut Game, query: &str, from: usize) -> usize {
+        let start = self.file_position(from);
+        let found = match query {
+            "" => None,
+            query if self.huge.is_some() => self.find_huge(game, query, from),
+            query => Find::new(query).next(self.buffer.text(), from),
+        };
+        let from = self.offset_of(start);
+        if found.is_none() && !query.is_empty() {
+            game.set_message(Some(Message::new(tr(
+                "editor.not-found",
+                &[("query", &query)],
+            ))));
+        }
+        self.buffer.set_cursor(found.unwrap_or(from));
+        self.scroll_to_cursor(game);
+        from
+    }
+
+    /// Keeps what was found, for F3 to find again.
+    pub fn finish_find(&mut self, game: &mut Game, query: &str) {
+        self.find = Some(query).filter(|query| !query.is_empty()).map(Find::new);
+        self.scroll_to_cursor(game);
+    }
+
+    /// Moves the cursor to the next match, or the one before.
+    pub fn find_again(&mut self, game: &mut Game, forwards: bool) {
+        let Some(find) = self.find.clone() else {
+            game.set_message(Some(Message::new(tr("editor.nothing-to-find", &[]))));
+            return;
+        };
+        let text = self.buffer.text();
+        let cursor = self.buffer.cursor();
+        // Past the match the cursor's on, so it isn't found again.
+        let after = text[cursor..]
+            .chars()
+            .next()
+            .map_or(cursor, |c| cursor + c.len_utf8());
+        let found = match forwards {
+            true if self.huge.is_some() => self.find_huge(game, &find.query, after),
+            true => find.next(text, after),
+            false => find.previous(text, cursor),
+        };
+        match found {
+            Some(offset) => self.buffer.set_cursor(offset),
+            None => {
+                let message = tr("editor.not-found", &[("query", &find.query)]);
+                game.set_message(Some(Message::new(message)));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use termgame::KeyCode;
+
+    use crate::harness::{ctrl, key, Harness};
+
+    #[test]
+    fn ctrl_f_finds_as_its_typed_and_f3_goes_on() {
+        let mut harness = Harness::new("cat\ndog\ncat dog\n");
+        harness.run([ctrl('f')]).type_text("do");
+        assert_eq!(harness.editor.buffer.cursor(), 4);
+        harness.press(KeyCode::Enter);
+        assert_eq!(
+            harness.status_line().split(" | ").nth(1),
+            Some("Ln 2, Col 1 of 3")
+        );
+
+        harness.press(KeyCode::F(3));
+        assert_eq!(harness.editor.buffer.cursor(), 12);
+        // Past the last match, it wraps round to the first.
+        harness.press(KeyCode::F(3));
+        assert_eq!(harness.editor.buffer.cursor(), 4);
+
+        // Esc goes back to where the cursor was.
+        harness
+            .run([ctrl('f')])
+            .type_text("cat d")
+            .press(KeyCode::Esc);
+        assert_eq!(harness.editor.buffer.cursor(), 4);
+        harness.run([ctrl('f'), key(KeyCode::Char('x'))]);
+        assert_eq!(harness.message(), Some("Couldn't find \"x\""));
+    }
+
+    #[test]
+    fn matches_are_highlighted_until_esc() {
+        let mut harness = Harness::new("a cat\ncat");
+        harness
+            .run([ctrl('f')])
+            .type_text("cat")
+            .press(KeyCode::Enter);
+        let theme = harness.editor.session.theme;
+        // The cursor is on the first match, so look past it.
+        assert_eq!(harness.paint_at(3, 0), Some(theme.found));
+        assert_eq!(harness.paint_at(1, 1), Some(theme.found));
+        assert_eq!(harness.paint_at(1, 0), None);
+
+        // The first Esc only clears the highlighting.
+        harness.press(KeyCode::Esc);
+        assert_eq!(harness.paint_at(1, 1), None);
+        assert!(!harness.ended());
+    }
+}
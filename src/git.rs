@@ -0,0 +1,472 @@
+//! What's changed in a buffer since its file was last committed, when
+//! it's in a git repository. A column left of any line numbers has a
+//! `+` on each line that's been added, a `~` on each that's been
+//! changed, and a `-` on the line above where lines were taken out.
+//! The file's text in `HEAD` is read the first time the editor opens,
+//! and again each time the buffer's saved or reloaded, and the marks
+//! are worked out against it again once an edit's been left alone for a
+//! moment. Alt+PageDown and Alt+PageUp jump to the next and previous
+//! run of changed lines.
+//!
+//! Alt+B, or `blame` at the `>>` prompt, shows who last changed each line
+//! and when, down the left of the buffer, and takes it away again. It's
+//! worked out for the buffer as it is when it's turned on, so lines
+//! which haven't been committed say so, and it goes as soon as the
+//! buffer's edited.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::{Duration, UNIX_EPOCH};
+
+use termgame::{Game, Message};
+
+use crate::dates::DateTime;
+use crate::diff::{self, Change};
+use crate::i18n::tr;
+use crate::pipe;
+use crate::BufferEditor;
+
+/// How a line differs from the last commit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mark {
+    Added,
+    Changed,
+    /// Lines were taken out after this one, or before it, if it's the
+    /// first.
+    Removed,
+}
+
+impl Mark {
+    /// What's drawn in the gutter.
+    pub fn symbol(self) -> char {
+        match self {
+            Mark::Added => '+',
+            Mark::Changed => '~',
+            Mark::Removed => '-',
+        }
+    }
+}
+
+/// The marks on a buffer's lines, by line index.
+pub type Marks = BTreeMap<usize, Mark>;
+
+/// How long the buffer has to be left alone after an edit before its
+/// marks are worked out again, so it isn't diffed on every key.
+const SETTLE: Duration = Duration::from_millis(500);
+
+/// A buffer's file as it was in the last commit, which the marks are
+/// worked out against.
+#[derive(Debug, Default)]
+pub struct Head {
+    /// The committed text, once it's been read, if there is any.
+    text: Option<Option<String>>,
+    /// The buffer's revision the marks were worked out for.
+    revision: usize,
+    /// A revision the marks aren't for, and when it was first seen.
+    edited: Option<(usize, Duration)>,
+}
+
+/// The text of `file` as it was in the last commit, if it's in a git
+/// repository and was committed.
+pub fn head_text(file: &Path) -> Option<String> {
+    let dir = file.parent().filter(|dir| !dir.as_os_str().is_empty());
+    let name = file.file_name()?.to_str()?;
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir.unwrap_or(Path::new(".")))
+        .arg("show")
+        .arg(format!("HEAD:./{}", name))
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    match output.status.success() {
+        true => String::from_utf8(output.stdout).ok(),
+        false => None,
+    }
+}
+
+/// How each line of `text` differs from `head`. Where lines were both
+/// taken out and put in, as many as were taken out count as changed,
+/// and the rest as added.
+pub fn marks(head: &str, text: &str) -> Marks {
+    let mut marks = Marks::new();
+    let (mut line, mut removed, mut added) = (0, 0, Vec::new());
+    let mut flush = |line: usize, removed: &mut usize, added: &mut Vec<usize>| {
+        for (i, &at) in added.iter().enumerate() {
+            let mark = match i < *removed {
+                true => Mark::Changed,
+                false => Mark::Added,
+            };
+            marks.insert(at, mark);
+        }
+        if added.is_empty() && *removed > 0 {
+            marks.entry(line.saturating_sub(1)).or_insert(Mark::Removed);
+        }
+        *removed = 0;
+        added.clear();
+    };
+    for change in diff::lines(head, text) {
+        match change {
+            Change::Same(_) => {
+                flush(line, &mut removed, &mut added);
+                line += 1;
+            }
+            Change::Removed(_) => removed += 1,
+            Change::Added(_) => {
+                added.push(line);
+                line += 1;
+            }
+        }
+    }
+    flush(line, &mut removed, &mut added);
+    marks
+}
+
+/// The widest an author's name is shown by `blame`.
+const AUTHOR_WIDTH: usize = 16;
+
+/// Who last changed a line, and in which commit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Blame {
+    pub hash: String,
+    pub author: String,
+    /// When it was committed, in seconds since the epoch.
+    pub time: u64,
+}
+
+/// Who last changed each line of `text`, which is `file` as it is now,
+/// if `file` is in a git repository.
+pub fn blame(file: &Path, text: &str) -> Result<Vec<Blame>, String> {
+    let dir = file.parent().filter(|dir| !dir.as_os_str().is_empty());
+    let name = file
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("");
+    let mut git = Command::new("git");
+    git.arg("-C").arg(dir.unwrap_or(Path::new("."))).args([
+        "blame",
+        "--line-porcelain",
+        "--contents",
+        "-",
+        "--",
+        name,
+    ]);
+    let (porcelain, _) = pipe::capture(git, "git blame", text)?;
+    Ok(parse_blame(&porcelain))
+}
+
+/// Reads what `git blame --line-porcelain` prints: for each line, the
+/// commit's hash, then headers like `author`, then the line after a tab.
+fn parse_blame(porcelain: &str) -> Vec<Blame> {
+    let mut blames = Vec::new();
+    let mut current: Option<Blame> = None;
+    for line in porcelain.lines() {
+        if line.starts_with('\t') {
+            blames.extend(current.take());
+            continue;
+        }
+        let Some(blame) = &mut current else {
+            let hash = line.split(' ').next().unwrap_or("");
+            current = Some(Blame {
+                hash: String::from(hash),
+                author: String::new(),
+                time: 0,
+            });
+            continue;
+        };
+        if let Some(author) = line.strip_prefix("author ") {
+            blame.author = String::from(author);
+        } else if let Some(time) = line.strip_prefix("author-time ") {
+            blame.time = time.parse().unwrap_or(0);
+        }
+    }
+    blames
+}
+
+/// What's shown beside each line, all as wide as each other: the short
+/// hash, the author, and the date.
+pub fn annotations(blames: &[Blame]) -> Vec<String> {
+    let width = blames
+        .iter()
+        .map(|blame| blame.author.chars().count())
+        .max()
+        .unwrap_or(0)
+        .min(AUTHOR_WIDTH);
+    blames
+        .iter()
+        .map(|blame| {
+            let hash: String = blame.hash.chars().take(8).collect();
+            let author: String = blame.author.chars().take(width).collect();
+            let date = DateTime::utc(UNIX_EPOCH + Duration::from_secs(blame.time));
+            format!(
+                "{} {:<width$} {}-{:02}-{:02} ",
+                hash, author, date.year, date.month, date.day
+            )
+        })
+        .collect()
+}
+
+/// The first line of each run of marked lines.
+fn hunks(marks: &Marks) -> impl Iterator<Item = usize> + '_ {
+    marks
+        .keys()
+        .copied()
+        .filter(|&line| line == 0 || !marks.contains_key(&(line - 1)))
+}
+
+impl BufferEditor {
+    /// Reads the buffer's file from its last commit again, and works out
+    /// how the buffer differs from it, or forgets it if that can't be
+    /// told.
+    pub fn refresh_git(&mut self) {
+        let head = match (&self.huge, self.buffer.file()) {
+            (None, Some(file)) => head_text(Path::new(file)),
+            _ => None,
+        };
+        self.git_head.text = Some(head);
+        self.mark_git();
+    }
+
+    /// Works out the marks as the editor opens: reading the committed
+    /// text the first time, and after that only when the buffer's been
+    /// edited since, like from the `>>` prompt.
+    pub fn open_git(&mut self) {
+        match self.git_head.text {
+            None => self.refresh_git(),
+            Some(_) if self.git_head.revision != self.buffer.revision() => self.mark_git(),
+            Some(_) => {}
+        }
+    }
+
+    /// Works out the marks again once the buffer's been left alone for
+    /// a moment since it was edited. Returns whether they were.
+    pub fn git_tick(&mut self, now: Duration) -> bool {
+        let revision = self.buffer.revision();
+        if !matches!(self.git_head.text, Some(Some(_))) || self.git_head.revision == revision {
+            return false;
+        }
+        match self.git_head.edited {
+            Some((seen, since)) if seen == revision => {
+                let settled = now.saturating_sub(since) >= SETTLE;
+                if settled {
+                    self.mark_git();
+                }
+                settled
+            }
+            _ => {
+                self.git_head.edited = Some((revision, now));
+                false
+            }
+        }
+    }
+
+    fn mark_git(&mut self) {
+        let head = self.git_head.text.as_ref().and_then(Option::as_deref);
+        self.git_marks = head.map(|head| marks(head, self.buffer.text()));
+        self.git_head.revision = self.buffer.revision();
+        self.git_head.edited = None;
+    }
+
+    /// Shows who last changed each line, or stops showing it. Returns
+    /// whether it's shown now.
+    pub fn toggle_blame(&mut self) -> Result<bool, String> {
+        if self.blame.take().is_some() {
+            return Ok(false);
+        }
+        let file = match (&self.huge, self.buffer.file()) {
+            (None, Some(file)) => Path::new(file),
+            _ => return Err(tr("editor.not-in-git", &[("name", &self.name)])),
+        };
+        let blames = blame(file, self.buffer.text())?;
+        self.blame = Some(annotations(&blames));
+        self.blame_revision = self.buffer.revision();
+        Ok(true)
+    }
+
+    /// Stops showing blame once the buffer's been edited, since its
+    /// lines don't line up with the text any more.
+    pub fn drop_stale_blame(&mut self) {
+        if self.blame_revision != self.buffer.revision() {
+            self.blame = None;
+        }
+    }
+
+    /// How many cells across `blame` takes, when it's shown.
+    pub fn blame_width(&self) -> usize {
+        self.blame
+            .as_ref()
+            .and_then(|blame| blame.first())
+            .map_or(0, |annotation| annotation.chars().count())
+    }
+
+    /// Turns blame on or off, for Alt+B.
+    pub fn toggle_blame_and_say(&mut self, game: &mut Game) {
+        match self.toggle_blame() {
+            Ok(_) => game.set_message(None),
+            Err(err) => game.set_message(Some(Message::new(err))),
+        }
+    }
+
+    /// How many cells across the git marks take: one, if the buffer's
+    /// file is in git.
+    pub fn git_width(&self) -> usize {
+        usize::from(self.git_marks.is_some())
+    }
+
+    /// Moves the cursor to the start of the next run of changed lines
+    /// after its own, or the one before it.
+    pub fn jump_to_hunk(&mut self, game: &mut Game, forwards: bool) {
+        let Some(marks) = &self.git_marks else {
+            let message = tr("editor.not-in-git", &[("name", &self.name)]);
+            game.set_message(Some(Message::new(message)));
+            return;
+        };
+        let line = self.buffer.cursor_line();
+        let hunk = match forwards {
+            true => hunks(marks).find(|&start| start > line),
+            false => hunks(marks).filter(|&start| start < line).last(),
+        };
+        match hunk {
+            Some(start) => {
+                let offset = self.buffer.line_span(start..start).start;
+                self.buffer.clear_selection();
+                self.buffer.set_cursor(offset);
+                self.scroll_to_cursor(game);
+            }
+            None => {
+                let message = match forwards {
+                    true => tr("editor.no-later-changes", &[]),
+                    false => tr("editor.no-earlier-changes", &[]),
+                };
+                game.set_message(Some(Message::new(message)));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use termgame::KeyCode;
+
+    use crate::harness::{alt_key, key, Harness};
+
+    #[test]
+    fn lines_are_marked_by_how_they_changed() {
+        let head = "one\ntwo\nthree\nfour\nfive\n";
+        let text = "zero\none\nTWO\nthree\nfive\n";
+        assert_eq!(
+            marks(head, text).into_iter().collect::<Vec<_>>(),
+            [(0, Mark::Added), (2, Mark::Changed), (3, Mark::Removed)]
+        );
+        assert!(marks(head, head).is_empty());
+    }
+
+    #[test]
+    fn blame_is_read_from_git_and_lined_up() {
+        let porcelain = "\
+1a2b3c4d5e6f7a8b9c0d1a2b3c4d5e6f7a8b9c0d 1 1 2
+author Ada Lovelace
+author-mail <ada@example.com>
+author-time 1700000000
+summary first
+filename notes.txt
+\tfirst line
+0000000000000000000000000000000000000000 2 2
+author Not Committed Yet
+author-time 1700086400
+filename notes.txt
+\tsecond line
+";
+        let blames = parse_blame(porcelain);
+        assert_eq!(blames.len(), 2);
+        assert_eq!(blames[0].author, "Ada Lovelace");
+        assert_eq!(
+            annotations(&blames),
+            [
+                "1a2b3c4d Ada Lovelace     2023-11-14 ",
+                "00000000 Not Committed Ye 2023-11-15 ",
+            ]
+        );
+    }
+
+    #[test]
+    fn the_committed_text_and_blame_are_read_from_git() {
+        let dir = std::env::temp_dir().join(format!("buffers-git-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("notes.txt");
+        std::fs::write(&file, "committed\n").unwrap();
+        let git = |args: &[&str]| {
+            Command::new("git")
+                .arg("-C")
+                .arg(&dir)
+                .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+                .args(args)
+                .output()
+                .unwrap()
+        };
+        git(&["init", "-q"]);
+        git(&["add", "notes.txt"]);
+        git(&["commit", "-q", "-m", "notes"]);
+        std::fs::write(&file, "changed since\n").unwrap();
+        assert_eq!(head_text(&file).as_deref(), Some("committed\n"));
+        assert_eq!(head_text(&dir.join("untracked.txt")), None);
+        let blames = blame(&file, "committed\nchanged since\n").unwrap();
+        let authors: Vec<&str> = blames.iter().map(|blame| blame.author.as_str()).collect();
+        assert_eq!(authors, ["test", "Not Committed Yet"]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn marks_are_drawn_and_alt_page_keys_jump_between_them() {
+        let mut harness = Harness::new("one\nTWO\nthree\nfour\nnew\nsix\n");
+        harness.editor.git_marks =
+            Some(marks("one\ntwo\nthree\nfour\nfive\nsix\n", harness.text()));
+        harness.editor.buffer.set_cursor(0);
+        harness.run([alt_key(KeyCode::PageDown)]);
+        assert_eq!(harness.screen_line(0), " one");
+        assert_eq!(harness.screen_line(1), "~TWO");
+        assert_eq!(harness.editor.buffer.cursor_line(), 1);
+        harness.run([alt_key(KeyCode::PageDown)]);
+        assert_eq!(harness.editor.buffer.cursor_line(), 4);
+        harness.run([alt_key(KeyCode::PageDown)]);
+        assert_eq!(harness.message(), Some("No more changes after this"));
+        harness.run([alt_key(KeyCode::PageUp)]);
+        assert_eq!(harness.editor.buffer.cursor_line(), 1);
+
+        // Blame goes left of the marks.
+        harness.editor.blame = Some(vec![String::from("a "), String::from("b ")]);
+        harness.editor.blame_revision = harness.editor.buffer.revision();
+        harness.press(KeyCode::Up);
+        assert_eq!(harness.screen_line(0), "a  one");
+        assert_eq!(harness.screen_line(1), "b ~TWO");
+        assert_eq!(harness.screen_line(2), "   three");
+
+        // An edit leaves it out of step with the lines, so it goes.
+        harness.press(KeyCode::End).press(KeyCode::Enter);
+        assert_eq!(harness.editor.blame, None);
+        assert_eq!(harness.screen_line(0), " one");
+    }
+
+    #[test]
+    fn marks_are_worked_out_again_once_an_edit_settles() {
+        let mut harness = Harness::new("one\ntwo\n");
+        harness.editor.git_head.text = Some(Some(String::from("one\ntwo\n")));
+        harness.editor.mark_git();
+        assert_eq!(harness.editor.git_marks, Some(Marks::new()));
+
+        harness.editor.buffer.set_cursor(0);
+        harness.run([key(KeyCode::Enter)]).tick(10);
+        assert_eq!(harness.editor.git_marks, Some(Marks::new()));
+        // A tick is 25ms, so half a second is 20 of them after the one
+        // which saw the edit.
+        harness.tick(11);
+        let marks = harness.editor.git_marks.clone().unwrap();
+        assert_eq!(marks.into_iter().collect::<Vec<_>>(), [(0, Mark::Added)]);
+        assert_eq!(harness.screen_line(0), "+");
+        assert_eq!(harness.screen_line(1), " one");
+    }
+}
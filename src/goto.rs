@@ -0,0 +1,104 @@
+//! Going to a line. Ctrl+G asks which in the status bar, and `goto`
+//! does the same from the `>>` prompt. A column can follow the line, as
+//! `12:5`, and a line or column past the end of the buffer goes to the
+//! end of it. Lines and columns count from 1, as the status bar does.
+//!
+//! `goto` moves within the buffer it names, without opening it. `jump`
+//! is different: it reads a `name:number:` line, like those `filter`
+//! makes, and opens the buffer that line points into.
+
+use termgame::{Game, Message};
+
+use crate::buffer::Position;
+use crate::i18n::tr;
+use crate::prompt::{Prompt, Question};
+use crate::BufferEditor;
+
+/// Reads a line, and the column on it if there is one, written as
+/// `line`, `line:column` or `line column`.
+pub fn parse(input: &str) -> Result<(usize, usize), String> {
+    let bad = || format!("{} isn't a line number", input.trim());
+    let mut parts = input
+        .split(|c: char| c == ':' || c.is_whitespace())
+        .filter(|part| !part.is_empty());
+    let line = parts.next().ok_or_else(bad)?.parse().map_err(|_| bad())?;
+    let col = match parts.next() {
+        Some(col) => col.parse().map_err(|_| bad())?,
+        None => 1,
+    };
+    match parts.next() {
+        Some(_) => Err(bad()),
+        None => Ok((line, col)),
+    }
+}
+
+impl BufferEditor {
+    /// Asks which line to go to.
+    pub fn start_goto(&mut self) {
+        let prompt = Prompt::new(&tr("editor.goto-prompt", &[]), "");
+        self.prompt = Some((prompt, Question::Goto));
+    }
+
+    /// Goes to the line typed at the prompt.
+    pub fn goto_answer(&mut self, game: &mut Game, answer: &str) {
+        match parse(answer) {
+            Ok((line, col)) => {
+                self.goto(line, col);
+                self.scroll_to_cursor(game);
+            }
+            Err(_) if answer.trim().is_empty() => {}
+            Err(_) => {
+                let message = tr("editor.not-a-line", &[("input", &answer.trim())]);
+                game.set_message(Some(Message::new(message)));
+            }
+        }
+    }
+
+    /// Moves the cursor to column `col` of line `line` of the file,
+    /// loading it first if it's in a part of a huge file which isn't.
+    /// Returns the line of the buffer the cursor ends up on.
+    pub fn goto(&mut self, line: usize, col: usize) -> usize {
+        let line = line.clamp(1, self.total_lines().max(1)) - 1;
+        self.load_lines(line);
+        let line = line
+            .saturating_sub(self.first_line())
+            .min(self.buffer.len_lines().saturating_sub(1));
+        let text = self.buffer.line(line).unwrap_or_default();
+        let col = col.saturating_sub(1).min(text.chars().count());
+        let offset = self
+            .buffer
+            .offset_of(Position { line, col })
+            .unwrap_or(self.buffer.text().len());
+        self.cursors.clear();
+        self.buffer.clear_selection();
+        self.buffer.set_cursor(offset);
+        line
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::harness::{ctrl, Harness};
+
+    #[test]
+    fn ctrl_g_goes_to_a_line_and_column() {
+        assert_eq!(parse("12"), Ok((12, 1)));
+        assert_eq!(parse(" 12:5 "), Ok((12, 5)));
+        assert_eq!(parse("12 5"), Ok((12, 5)));
+        assert!(parse("twelve").is_err());
+
+        let text: String = (1..=100).map(|n| format!("line {}\n", n)).collect();
+        let mut harness = Harness::new(&text);
+        harness.run([ctrl('g')]).type_text("60:3\n");
+        assert!(harness.status_line().contains("Ln 60, Col 3 of 100"));
+        assert!(harness.viewport().y > 0);
+
+        // Past the end goes to the end.
+        harness.run([ctrl('g')]).type_text("500:99\n");
+        assert!(harness.status_line().contains("Ln 100, Col 9 of 100"));
+        harness.run([ctrl('g')]).type_text("x\n");
+        assert_eq!(harness.message(), Some("x isn't a line number"));
+    }
+}
@@ -0,0 +1,753 @@
+//! Drives a [`BufferEditor`] without a terminal, by feeding it a script
+//! of events, so editing can be tested.
+//!
+//! The editor runs on a fixed clock, which goes on by one
+//! [`TICK`](crate::clock::TICK) each [`Harness::tick`], so anything
+//! that depends on time happens at the same tick every run.
+//!
+//! Commands typed at the `>>` prompt can be run the same way, against an
+//! [`app`] with nothing else in it.
+//!
+//! This is compiled for tests, and for anything else that wants it with
+//! the `test-harness` feature.
+
+use termgame::{
+    CharChunkMap, Controller, Game, GameEvent, KeyCode, KeyEvent, KeyModifiers, Message,
+    ViewportLocation,
+};
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use crate::buffer::Buffer;
+use crate::clipboard::SystemClipboard;
+use crate::clock::{self, Clock};
+use crate::commands::{self, Registry};
+use crate::config::Config;
+use crate::status;
+use crate::theme::Paint;
+use crate::{App, BufferEditor, Session};
+
+/// A headless editor: everything `run_game` would keep between events,
+/// without the terminal.
+pub struct Harness {
+    pub editor: BufferEditor,
+    screen: CharChunkMap,
+    viewport: ViewportLocation,
+    message: Option<String>,
+    ended: bool,
+    /// The file the editor was started on, if it's one of the harness's
+    /// own.
+    file: Option<TempFile>,
+}
+
+impl Harness {
+    /// Starts an editor on a buffer holding `text`, with the cursor at
+    /// the start and every setting at its default.
+    pub fn new(text: &str) -> Harness {
+        Harness::with_config(text, Config::default())
+    }
+
+    /// Like [`Harness::new`], with settings from `config`.
+    pub fn with_config(text: &str, config: Config) -> Harness {
+        let buffer = Buffer::from_text(None, String::from(text));
+        let editor = BufferEditor::new(String::from("test"), buffer, Rc::new(config));
+        Harness::with_editor(editor)
+    }
+
+    /// Starts an editor called `name` on a file of its own holding
+    /// `contents`, which is removed when the harness is dropped.
+    pub fn with_file(name: &str, contents: impl AsRef<[u8]>) -> Harness {
+        Harness::with_file_and_config(name, contents, Config::default())
+    }
+
+    /// Like [`Harness::with_file`], with settings from `config`.
+    pub fn with_file_and_config(name: &str, contents: impl AsRef<[u8]>, config: Config) -> Harness {
+        let file = TempFile::new(name, contents);
+        let mut harness = Harness::with_editor(file.editor(name, Rc::new(config)));
+        harness.file = Some(file);
+        harness
+    }
+
+    /// Starts `editor`, as if it had just been opened.
+    pub fn with_editor(mut editor: BufferEditor) -> Harness {
+        editor.session.clock = Clock::fixed(Duration::ZERO, clock::TICK);
+        editor.session.system_clipboard = SystemClipboard::unavailable();
+        let mut harness = Harness {
+            editor,
+            screen: CharChunkMap::new(),
+            viewport: ViewportLocation { x: 0, y: 0 },
+            message: None,
+            ended: false,
+            file: None,
+        };
+        harness.with_game(|editor, game| editor.on_start(game));
+        harness
+    }
+
+    /// Runs `f` with a [`Game`] in the state the last event left it in.
+    fn with_game(&mut self, f: impl FnOnce(&mut BufferEditor, &mut Game)) {
+        let mut game = Game::new(&mut self.screen);
+        game.set_viewport(self.viewport);
+        game.set_message(self.message.clone().map(Message::new));
+        f(&mut self.editor, &mut game);
+        self.viewport = game.get_viewport();
+        self.message = game
+            .get_message()
+            .as_ref()
+            .map(|message| message.text.clone());
+        self.ended |= game.game_will_end();
+    }
+
+    /// Feeds the editor each event in turn.
+    pub fn run(&mut self, events: impl IntoIterator<Item = GameEvent>) -> &mut Harness {
+        for event in events {
+            self.with_game(|editor, game| editor.on_event(game, event));
+        }
+        self
+    }
+
+    /// Presses a single key.
+    pub fn press(&mut self, code: KeyCode) -> &mut Harness {
+        self.run([key(code)])
+    }
+
+    /// Types each character of `text`, with `\n` as Enter.
+    pub fn type_text(&mut self, text: &str) -> &mut Harness {
+        self.run(typed(text))
+    }
+
+    /// Lets `ticks` ticks go by.
+    pub fn tick(&mut self, ticks: usize) -> &mut Harness {
+        for _ in 0..ticks {
+            self.with_game(|editor, game| editor.on_tick(game));
+        }
+        self
+    }
+
+    pub fn text(&self) -> &str {
+        self.editor.buffer.text()
+    }
+
+    /// Where the file from [`Harness::with_file`] is.
+    pub fn path(&self) -> &Path {
+        self.file.as_ref().expect("the harness has no file").path()
+    }
+
+    /// What's drawn along `row`, in buffer coordinates, without any
+    /// spaces on the end.
+    pub fn screen_line(&self, row: i32) -> String {
+        let width = 200;
+        let line: String = (0..width)
+            .map(|x| self.screen.get(x, row).map_or(' ', |cell| cell.c))
+            .collect();
+        String::from(line.trim_end())
+    }
+
+    /// How the cell at `(x, y)` is painted, if it isn't plain.
+    pub fn paint_at(&self, x: i32, y: i32) -> Option<Paint> {
+        let style = self.screen.get(x, y)?.style?;
+        Some(Paint {
+            fg: style.fg,
+            bg: style.bg,
+            font: Some(style.add_modifier).filter(|font| !font.is_empty()),
+        })
+    }
+
+    /// What the status bar says.
+    pub fn status_line(&mut self) -> String {
+        let mut row = 0;
+        self.with_game(|_, game| row = game.get_viewport().y + status::visible_size(game).1 - 1);
+        self.screen_line(row)
+    }
+
+    pub fn viewport(&self) -> ViewportLocation {
+        self.viewport
+    }
+
+    /// The message the editor last showed, if it's still up.
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+
+    /// Whether the editor has asked to close.
+    pub fn ended(&self) -> bool {
+        self.ended
+    }
+}
+
+/// An editor for a buffer called `name`, with no file, holding `text`.
+/// Unlike a [`Harness`], it's on the wall clock.
+pub fn editor(name: &str, text: &str) -> BufferEditor {
+    let buffer = Buffer::from_text(None, String::from(text));
+    let mut editor = BufferEditor::new(String::from(name), buffer, Rc::new(Config::default()));
+    editor.session.system_clipboard = SystemClipboard::unavailable();
+    editor
+}
+
+/// A file in the temp directory for a test to open, which is removed
+/// when it's dropped, even if the test fails first.
+pub struct TempFile {
+    path: PathBuf,
+}
+
+impl TempFile {
+    /// Writes `contents` to a file named after `name`. Each is a file
+    /// of its own, so tests running at once can use the same name.
+    pub fn new(name: &str, contents: impl AsRef<[u8]>) -> TempFile {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        let count = COUNT.fetch_add(1, Ordering::Relaxed);
+        let file_name = format!("buffers-{}-{}-{}", process::id(), count, name);
+        let path = std::env::temp_dir().join(file_name);
+        fs::write(&path, contents).unwrap();
+        TempFile { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// An editor called `name` for the file as it is now.
+    pub fn editor(&self, name: &str, config: Rc<Config>) -> BufferEditor {
+        let buffer = Buffer::from_file(self.path.to_str().unwrap()).unwrap();
+        BufferEditor::new(String::from(name), buffer, config)
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// What's drawn along row `y` of the first 80 columns of `screen`,
+/// without any spaces on the end.
+pub fn row(screen: &CharChunkMap, y: i32) -> String {
+    let line: String = (0..80)
+        .map(|x| screen.get(x, y).map_or(' ', |cell| cell.c))
+        .collect();
+    String::from(line.trim_end())
+}
+
+/// What's behind the `>>` prompt, as BuffeRS starts it but with every
+/// setting at its default, no buffers open, and the same fixed clock as
+/// a [`Harness`].
+pub fn app() -> App {
+    let session = Session {
+        clock: Clock::fixed(Duration::ZERO, clock::TICK),
+        system_clipboard: SystemClipboard::unavailable(),
+        ..Session::default()
+    };
+    App {
+        editors: HashMap::new(),
+        config: Rc::new(Config::default()),
+        session,
+        queued: VecDeque::new(),
+        answers: VecDeque::new(),
+        pending_replacements: HashMap::new(),
+        quit: false,
+        aliases: BTreeMap::new(),
+        registry: Rc::new(Registry::with_builtins()),
+        sourcing: Vec::new(),
+    }
+}
+
+/// Opens a buffer called `name`, with no file, and `text` typed into it,
+/// so that it's unsaved unless `text` is empty.
+pub fn open(app: &mut App, name: &str, text: &str) {
+    let mut editor = BufferEditor::new(
+        String::from(name),
+        Buffer::new(None),
+        Rc::clone(&app.config),
+    );
+    if !text.is_empty() {
+        editor.buffer.insert(text);
+    }
+    app.editors.insert(String::from(name), editor);
+}
+
+/// Runs `line` as though it had been typed at the `>>` prompt.
+pub fn command(app: &mut App, line: &str) -> Result<(), Box<dyn Error>> {
+    let registry = Rc::clone(&app.registry);
+    commands::run_command(line, &registry, app)
+}
+
+/// A key pressed on its own.
+pub fn key(code: KeyCode) -> GameEvent {
+    GameEvent::Key(KeyEvent::new(code, KeyModifiers::NONE))
+}
+
+/// A key pressed with Ctrl held.
+pub fn ctrl(c: char) -> GameEvent {
+    GameEvent::Key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL))
+}
+
+/// A key pressed with Alt held.
+pub fn alt(c: char) -> GameEvent {
+    GameEvent::Key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::ALT))
+}
+
+/// A key which isn't a character, like Down, pressed with Ctrl held.
+pub fn ctrl_key(code: KeyCode) -> GameEvent {
+    GameEvent::Key(KeyEvent::new(code, KeyModifiers::CONTROL))
+}
+
+/// A key which isn't a character, like Right, pressed with Alt held.
+pub fn alt_key(code: KeyCode) -> GameEvent {
+    GameEvent::Key(KeyEvent::new(code, KeyModifiers::ALT))
+}
+
+/// A key pressed with Shift held.
+pub fn shift_key(code: KeyCode) -> GameEvent {
+    GameEvent::Key(KeyEvent::new(code, KeyModifiers::SHIFT))
+}
+
+/// The events for typing `text`, with `\n` as Enter.
+pub fn typed(text: &str) -> Vec<GameEvent> {
+    text.chars()
+        .map(|c| match c {
+            '\n' => key(KeyCode::Enter),
+            c => key(KeyCode::Char(c)),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashMap;
+
+    use crate::buffer::Position;
+    use crate::clipboard::Clip;
+    use crate::stats::TypingStats;
+
+    #[test]
+    fn typing_inserts_at_the_cursor() {
+        let mut harness = Harness::new("world");
+        harness.type_text("hello ");
+        assert_eq!(harness.text(), "hello world");
+
+        harness.press(KeyCode::Right).type_text("!\n");
+        assert_eq!(harness.text(), "hello w!\norld");
+    }
+
+    #[test]
+    fn backspace_deletes_before_the_cursor() {
+        let mut harness = Harness::new("");
+        harness.type_text("abc").press(KeyCode::Backspace);
+        assert_eq!(harness.text(), "ab");
+
+        // At the start of the buffer there's nothing to delete.
+        let mut harness = Harness::new("abc");
+        harness.press(KeyCode::Backspace);
+        assert_eq!(harness.text(), "abc");
+    }
+
+    #[test]
+    fn brackets_are_closed_and_stepped_over() {
+        let mut harness = Harness::new("");
+        harness.type_text("f(x");
+        assert_eq!(harness.text(), "f(x)");
+        harness.type_text(")y");
+        assert_eq!(harness.text(), "f(x)y");
+    }
+
+    #[test]
+    fn backspace_in_an_empty_pair_removes_both() {
+        let mut harness = Harness::new("");
+        harness.type_text("[").press(KeyCode::Backspace);
+        assert_eq!(harness.text(), "");
+    }
+
+    #[test]
+    fn enter_keeps_the_indent_and_steps_in_after_a_bracket() {
+        let mut harness = Harness::new("");
+        harness.editor.session.autoindent = true;
+        harness.type_text("  fn f() {\nx");
+        assert_eq!(harness.text(), "  fn f() {\n      x\n  }");
+        harness.type_text("\ny");
+        assert_eq!(harness.text(), "  fn f() {\n      x\n      y\n  }");
+    }
+
+    #[test]
+    fn abbreviations_expand_when_the_word_ends() {
+        let config = Config {
+            abbreviations: HashMap::from([(String::from("teh"), String::from("the"))]),
+            ..Config::default()
+        };
+        let mut harness = Harness::with_config("", config);
+        harness.type_text("teh cat");
+        assert_eq!(harness.text(), "the cat");
+
+        // Ctrl+Q types the next key literally, so nothing expands.
+        harness.type_text(" teh").run([ctrl('q')]).type_text(" ");
+        assert_eq!(harness.text(), "the cat teh ");
+    }
+
+    #[test]
+    fn an_expansion_is_undone_in_one_step() {
+        let config = Config {
+            abbreviations: HashMap::from([(String::from("fnm"), String::from("fn main() {}"))]),
+            ..Config::default()
+        };
+        let mut harness = Harness::with_config("", config);
+        harness.type_text("fnm;");
+        assert_eq!(harness.text(), "fn main() {};");
+
+        // First the boundary that was typed, then the whole expansion.
+        harness.run([ctrl('z')]);
+        assert_eq!(harness.text(), "fn main() {}");
+        harness.run([ctrl('z')]);
+        assert_eq!(harness.text(), "fnm");
+    }
+
+    #[test]
+    fn shift_and_the_arrows_select_and_backspace_deletes_it() {
+        let mut harness = Harness::new("one two three");
+        harness.run([
+            key(KeyCode::Right),
+            key(KeyCode::Right),
+            key(KeyCode::Right),
+        ]);
+        harness.run([shift_key(KeyCode::Right), shift_key(KeyCode::Right)]);
+        assert_eq!(harness.editor.buffer.selection(), Some(3..5));
+        let theme = harness.editor.session.theme;
+        assert_eq!(harness.paint_at(4, 0), Some(theme.selection));
+
+        // An arrow on its own lets go of it.
+        harness.press(KeyCode::Left);
+        assert_eq!(harness.editor.buffer.selection(), None);
+        assert_eq!(harness.paint_at(3, 0), None);
+
+        harness.run([shift_key(KeyCode::Left), shift_key(KeyCode::Left)]);
+        harness.press(KeyCode::Backspace);
+        assert_eq!(harness.text(), "ontwo three");
+        harness.press(KeyCode::Delete);
+        assert_eq!(harness.text(), "onwo three");
+    }
+
+    #[test]
+    fn ctrl_x_cuts_the_selection_or_the_line_and_ctrl_v_pastes_it() {
+        let mut harness = Harness::new("one\ntwo\n");
+        harness.run([ctrl('x')]);
+        assert_eq!(harness.text(), "two\n");
+        harness.press(KeyCode::Down).run([ctrl('v')]);
+        assert_eq!(harness.text(), "two\none\n");
+
+        harness.run([shift_key(KeyCode::Up), ctrl('c')]);
+        assert_eq!(harness.text(), "two\none\n");
+        assert_eq!(
+            harness.editor.session.clipboard.current().unwrap().text,
+            "one\n"
+        );
+    }
+
+    #[test]
+    fn what_another_program_copied_is_pasted_until_something_is_killed() {
+        let mut harness = Harness::new("one\ntwo\n");
+        harness.editor.session.system_clipboard = SystemClipboard::in_memory("elsewhere ");
+        harness.run([ctrl('v')]);
+        assert_eq!(harness.text(), "elsewhere one\ntwo\n");
+
+        // Copying puts "elsewhere one" on the system clipboard too, but
+        // what's killed after only goes on BuffeRS's own.
+        harness.run([ctrl('c'), key(KeyCode::Down), alt('k'), ctrl('v')]);
+        assert_eq!(harness.text(), "elsewhere one\ntwo\n");
+        harness.run([ctrl('v')]);
+        assert_eq!(harness.text(), "elsewhere one\ntwo\ntwo\n");
+    }
+
+    #[test]
+    fn the_status_bar_follows_the_cursor_and_edits() {
+        let mut harness = Harness::new("one\ntwo");
+        assert_eq!(
+            harness.status_line(),
+            "test | Ln 1, Col 1 of 2 | 2 words, 7 chars"
+        );
+        harness.press(KeyCode::Down).type_text("x");
+        assert_eq!(
+            harness.status_line(),
+            "test [+] | Ln 2, Col 2 of 2 | 2 words, 8 chars"
+        );
+    }
+
+    #[test]
+    fn ctrl_up_and_down_scroll_but_never_above_the_top() {
+        let mut harness = Harness::new("one\ntwo\nthree");
+        harness.run([ctrl_key(KeyCode::Down), ctrl_key(KeyCode::Down)]);
+        assert_eq!(harness.viewport().y, 2);
+        harness.run([
+            ctrl_key(KeyCode::Up),
+            ctrl_key(KeyCode::Up),
+            ctrl_key(KeyCode::Up),
+        ]);
+        assert_eq!(harness.viewport().y, 0);
+    }
+
+    #[test]
+    fn page_up_and_down_move_the_cursor_with_the_screen() {
+        let text: String = (1..=100).map(|n| format!("line {}\n", n)).collect();
+        let mut harness = Harness::new(&text);
+        harness.press(KeyCode::PageDown);
+        let rows = harness.viewport().y;
+        assert!(rows > 0);
+        assert_eq!(harness.editor.buffer.cursor_line(), rows as usize);
+
+        harness.press(KeyCode::End);
+        assert_eq!(harness.editor.buffer.cursor_position().col, 7);
+        harness.press(KeyCode::PageUp);
+        assert_eq!(harness.viewport().y, 0);
+        assert_eq!(
+            harness.editor.buffer.cursor_position(),
+            Position { line: 0, col: 6 }
+        );
+
+        // Paging down stops with the last line at the bottom.
+        for _ in 0..10 {
+            harness.press(KeyCode::PageDown);
+        }
+        assert_eq!(harness.viewport().y, 100 - rows);
+        assert_eq!(harness.editor.buffer.cursor_line(), 99);
+    }
+
+    #[test]
+    fn the_viewport_follows_the_cursor() {
+        let text = "line\n".repeat(100);
+        let mut harness = Harness::new(&text);
+        for _ in 0..60 {
+            harness.press(KeyCode::Down);
+        }
+        let viewport = harness.viewport().y;
+        assert!(viewport > 0);
+        harness.type_text("x");
+        assert_eq!(harness.text().lines().nth(60), Some("xline"));
+        // Going back up only scrolls once the cursor's past the top.
+        for _ in 0..60 - viewport {
+            harness.press(KeyCode::Up);
+        }
+        assert_eq!(harness.viewport().y, viewport);
+        harness.press(KeyCode::Up);
+        assert_eq!(harness.viewport().y, viewport - 1);
+    }
+
+    #[test]
+    fn pasting_a_block_keeps_its_shape() {
+        let mut harness = Harness::new("ab\ncd\n");
+        harness
+            .editor
+            .session
+            .clipboard
+            .copy(Clip::block("XY\nZW", 0..2, 0..2));
+        harness.press(KeyCode::Right).run([ctrl('v')]);
+        assert_eq!(harness.text(), "aXYb\ncZWd\n");
+    }
+
+    #[test]
+    fn cleared_lines_can_be_pasted_back() {
+        let mut harness = Harness::new("");
+        harness.type_text("one").run([ctrl('u')]);
+        harness.type_text("two").run([ctrl('u'), ctrl('v')]);
+        assert_eq!(harness.text(), "two");
+        let clips = harness.editor.session.clipboard.clips();
+        assert_eq!(clips.len(), 2);
+        assert_eq!(clips[1].text, "one");
+    }
+
+    #[test]
+    fn ctrl_k_kills_to_the_end_of_the_line_and_alt_k_the_whole_line() {
+        let mut harness = Harness::new("one two\nthree\nfour\n");
+        harness.run([ctrl_key(KeyCode::Right), ctrl('k')]);
+        assert_eq!(harness.text(), "one\nthree\nfour\n");
+        // At the end of the line, it's the newline that goes.
+        harness.run([ctrl('k')]);
+        assert_eq!(harness.text(), "onethree\nfour\n");
+        let clips = harness.editor.session.clipboard.clips();
+        assert_eq!(clips[0].text, "\n");
+
+        harness.run([ctrl('r'), key(KeyCode::Char('a')), alt('k')]);
+        assert_eq!(harness.text(), "four\n");
+        assert_eq!(harness.editor.buffer.cursor(), 0);
+        let killed = harness.editor.session.registers.get('a').unwrap();
+        assert_eq!(killed.text, "onethree\n");
+    }
+
+    #[test]
+    fn alt_up_and_down_move_lines_and_ctrl_shift_d_duplicates_them() {
+        let mut harness = Harness::new("one\ntwo\nthree\n");
+        harness.press(KeyCode::Down).run([alt_key(KeyCode::Up)]);
+        assert_eq!(harness.text(), "two\none\nthree\n");
+        assert_eq!(harness.editor.buffer.cursor_line(), 0);
+        // A selection ending at the start of a line leaves that line be.
+        harness.run([shift_key(KeyCode::Down), alt_key(KeyCode::Down)]);
+        assert_eq!(harness.text(), "one\ntwo\nthree\n");
+        assert_eq!(harness.editor.buffer.selected_lines(), 1..2);
+
+        let ctrl_shift_d = KeyEvent::new(
+            KeyCode::Char('D'),
+            KeyModifiers::CONTROL | KeyModifiers::SHIFT,
+        );
+        harness.run([GameEvent::Key(ctrl_shift_d)]);
+        assert_eq!(harness.text(), "one\ntwo\ntwo\nthree\n");
+        assert_eq!(harness.editor.buffer.selected_lines(), 2..3);
+    }
+
+    #[test]
+    fn read_only_buffers_ignore_typing() {
+        let mut harness = Harness::new("fixed");
+        harness.editor.read_only = true;
+        harness.type_text("x").press(KeyCode::Backspace);
+        assert_eq!(harness.text(), "fixed");
+        assert_eq!(harness.message(), Some("test is read-only"));
+
+        // Moving about and copying still work.
+        harness.press(KeyCode::Right).run([ctrl('c')]);
+        assert_eq!(harness.editor.buffer.cursor(), 1);
+        assert_eq!(
+            harness.editor.session.clipboard.current().unwrap().text,
+            "fixed"
+        );
+    }
+
+    #[test]
+    fn typing_stats_count_keys_across_ticks() {
+        let mut harness = Harness::new("");
+        harness.editor.session.typing_stats = Some(TypingStats::new());
+        harness.type_text("ab").press(KeyCode::Left).tick(3);
+        let summary = harness
+            .editor
+            .session
+            .typing_stats
+            .as_ref()
+            .unwrap()
+            .summary();
+        assert!(summary.contains("Keystrokes:  3"), "{}", summary);
+        assert!(summary.contains("Typed chars: 2"), "{}", summary);
+    }
+
+    #[test]
+    fn typing_time_goes_by_ticks() {
+        let mut harness = Harness::new("");
+        harness.editor.session.typing_stats = Some(TypingStats::new());
+        // 80 ticks is two seconds, well inside the idle limit.
+        harness.tick(1).type_text("abc").tick(80);
+        let stats = harness.editor.session.typing_stats.as_ref().unwrap();
+        assert!(
+            stats.summary().contains("Typing time: 2s"),
+            "{}",
+            stats.summary()
+        );
+    }
+
+    #[test]
+    fn a_followed_file_is_reloaded_and_scrolled_to_the_end() {
+        let mut harness = Harness::with_file("log", "one\n");
+        harness.editor.follow = true;
+
+        let more: String = (0..40).map(|i| format!("line {}\n", i)).collect();
+        std::fs::write(harness.path(), format!("one\n{}", more)).unwrap();
+        // The file's looked at once a second.
+        harness.tick(41);
+
+        assert_eq!(harness.text(), format!("one\n{}", more));
+        assert_eq!(harness.editor.buffer.cursor(), harness.text().len());
+        assert!(harness.viewport().y > 0);
+        assert_eq!(harness.message(), None);
+    }
+
+    #[test]
+    fn ctrl_s_asks_where_to_save_a_new_buffer() {
+        let path = std::env::temp_dir().join(format!("buffers-save-{}.txt", std::process::id()));
+        let mut harness = Harness::new("");
+        harness.type_text("hello").run([ctrl('s')]);
+        assert!(harness.editor.prompt.is_some());
+        // What's typed now is the file name, not text.
+        harness.type_text(&format!("{}\n", path.display()));
+        assert_eq!(harness.text(), "hello");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "hello");
+        assert!(!harness.editor.buffer.is_dirty());
+
+        // From then on, Ctrl+S saves straight to that file.
+        harness.type_text(" world").run([ctrl('s')]);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "hello world");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn a_file_changed_under_unsaved_edits_asks_what_to_do() {
+        let mut harness = Harness::with_file("changed", "one");
+        let path = harness.path().to_path_buf();
+        harness.type_text("x");
+        std::fs::write(&path, "two").unwrap();
+        harness.tick(41);
+        assert!(harness.status_line().starts_with("changed changed on disk"));
+        harness.type_text("r\n");
+        assert_eq!(harness.text(), "two");
+        assert!(!harness.editor.buffer.is_dirty());
+
+        // Saving over a change asks too, and can write over it.
+        harness.type_text("x");
+        std::fs::write(&path, "three").unwrap();
+        harness.run([ctrl('s')]).type_text("o\n");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "twox");
+    }
+
+    #[test]
+    fn files_are_saved_with_the_line_endings_and_encoding_they_had() {
+        let mut harness = Harness::with_file("crlf", b"caf\xe9\r\nend\r\n");
+        assert_eq!(harness.text(), "café\nend\n");
+        assert!(harness.status_line().contains("latin-1 | CRLF"));
+
+        harness.type_text("x").run([ctrl('s')]);
+        assert_eq!(
+            std::fs::read(harness.path()).unwrap(),
+            b"xcaf\xe9\r\nend\r\n"
+        );
+    }
+
+    #[test]
+    fn saving_can_tidy_whitespace_as_one_undoable_edit() {
+        let mut harness = Harness::with_file("tidy", "");
+        harness.editor.session.trim_trailing_whitespace = true;
+        harness.editor.session.insert_final_newline = true;
+        harness.type_text("one  \ntwo\t").run([ctrl('s')]);
+        assert_eq!(
+            std::fs::read_to_string(harness.path()).unwrap(),
+            "one\ntwo\n"
+        );
+        assert_eq!(
+            harness.editor.buffer.cursor_position(),
+            Position { line: 1, col: 3 }
+        );
+
+        harness.run([ctrl('z')]);
+        assert_eq!(harness.text(), "one  \ntwo\t");
+        assert!(harness.editor.buffer.is_dirty());
+    }
+
+    #[test]
+    fn ctrl_z_and_ctrl_y_undo_and_redo_a_word_at_a_time() {
+        let mut harness = Harness::new("");
+        harness.type_text("one two").run([ctrl('z')]);
+        assert_eq!(harness.text(), "one ");
+        harness.run([ctrl('z'), ctrl('z')]);
+        assert_eq!(harness.text(), "");
+        assert_eq!(harness.message(), Some("Nothing to undo"));
+        harness.run([ctrl('y'), ctrl('y')]);
+        assert_eq!(harness.text(), "one two");
+    }
+
+    #[test]
+    fn escape_closes_the_editor() {
+        let mut harness = Harness::new("");
+        assert!(!harness.ended());
+        harness.press(KeyCode::Esc);
+        assert!(harness.ended());
+    }
+}
@@ -0,0 +1,84 @@
+//! What `help` says about each command, which is given to the registry
+//! along with the command itself. `help` lists every command the registry
+//! has, with the first sentence of what it does, and `help <command>`
+//! shows how it's typed, all of what it does, and examples.
+//!
+//! What a command does is registered as the string `about.<name>`, so a
+//! translation can say it in another language.
+
+use crate::i18n::tr;
+
+/// What there is to say about a command.
+#[derive(Debug)]
+pub struct Help {
+    pub name: &'static str,
+    /// How the command's typed, one way per line.
+    pub usage: &'static [&'static str],
+    /// What it does, in English.
+    pub about: &'static str,
+    pub examples: &'static [&'static str],
+}
+
+impl Help {
+    /// The key what the command does is registered under.
+    pub fn about_key(&self) -> String {
+        format!("about.{}", self.name)
+    }
+
+    /// What the command does, in the current locale.
+    pub fn description(&self) -> String {
+        tr(&self.about_key(), &[])
+    }
+
+    /// The first sentence of what the command does, to list it with.
+    pub fn summary(&self) -> String {
+        let mut description = self.description();
+        if let Some(end) = description.find(". ") {
+            description.truncate(end + 1);
+        }
+        description
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::commands::Registry;
+    use crate::harness::{app, command};
+
+    #[test]
+    fn commands_are_registered_by_the_name_their_help_gives() {
+        let registry = Registry::with_builtins();
+        for name in registry.names() {
+            assert_eq!(registry.help(name).unwrap().name, name);
+        }
+        assert!(registry.get("uniq").is_some());
+        assert!(registry.help("base64-decode").is_some());
+        assert!(registry.help("nope").is_none());
+        assert_eq!(tr("about.sort", &[]), registry.help("sort").unwrap().about);
+
+        let sort = registry.help("sort").unwrap();
+        assert_eq!(
+            sort.summary(),
+            "Sorts lines, by the number each starts with when it's `--numeric`."
+        );
+        assert_eq!(
+            registry.help("stress").unwrap().summary(),
+            "Checks the buffer against a simple model over a stream of random edits."
+        );
+    }
+
+    #[test]
+    fn an_alias_is_looked_up_only_the_once() {
+        let mut app = app();
+        app.aliases
+            .insert(String::from("ls"), String::from("ls -l"));
+        app.aliases.insert(String::from("a"), String::from("b"));
+        app.aliases.insert(String::from("b"), String::from("a"));
+        command(&mut app, "help ls").unwrap();
+        let err = command(&mut app, "help a").unwrap_err();
+        assert_eq!(err.to_string(), "there's no b command; `help` lists them");
+        assert!(command(&mut app, "help nope").is_err());
+    }
+}
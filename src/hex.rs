@@ -0,0 +1,221 @@
+//! Hex mode, for files which aren't text. The buffer holds a hex dump of
+//! the file, sixteen bytes to a line:
+//!
+//! ```text
+//! 00000000  48 65 6c 6c 6f 2c 20 77  6f 72 6c 64 0a           |Hello, world.|
+//! ```
+//!
+//! and it's read back into bytes when the file is written. The cursor
+//! moves from one hex digit to the next, and typing a hex digit writes
+//! over the one under it. Nothing else can be typed, so the dump always
+//! reads back.
+
+use termgame::{Game, KeyCode, Message, SimpleEvent};
+
+use crate::i18n::tr;
+use crate::keymap::Action;
+use crate::BufferEditor;
+
+/// How many bytes each line shows.
+const WIDTH: usize = 16;
+/// How far into a line the hex digits start, after the offset.
+const HEX_START: usize = 10;
+/// How wide the hex digits are, with the gap in the middle.
+const HEX_WIDTH: usize = WIDTH * 3 + 1;
+
+/// The hex dump of `bytes`.
+pub fn dump(bytes: &[u8]) -> String {
+    bytes
+        .chunks(WIDTH)
+        .enumerate()
+        .map(|(i, chunk)| dump_line(i * WIDTH, chunk) + "\n")
+        .collect()
+}
+
+/// The line of the dump showing `chunk`, which starts at `offset`.
+fn dump_line(offset: usize, chunk: &[u8]) -> String {
+    let mut line = format!("{:08x}  ", offset);
+    for slot in 0..WIDTH {
+        match chunk.get(slot) {
+            Some(byte) => line.push_str(&format!("{:02x} ", byte)),
+            None => line.push_str("   "),
+        }
+        if slot == WIDTH / 2 - 1 {
+            line.push(' ');
+        }
+    }
+    let ascii: String = chunk
+        .iter()
+        .map(|&byte| match byte {
+            0x20..=0x7e => char::from(byte),
+            _ => '.',
+        })
+        .collect();
+    line + " |" + &ascii + "|"
+}
+
+/// The bytes a line of the dump shows.
+fn parse_line(line: &str) -> Result<Vec<u8>, String> {
+    let digits = line
+        .get(HEX_START..)
+        .map(|rest| &rest[..rest.len().min(HEX_WIDTH)])
+        .unwrap_or_default();
+    digits
+        .split_whitespace()
+        .map(|pair| u8::from_str_radix(pair, 16).map_err(|_| format!("{} isn't a byte", pair)))
+        .collect()
+}
+
+/// Reads a hex dump back into the bytes it shows.
+pub fn parse(text: &str) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    for line in text.lines() {
+        bytes.extend(parse_line(line)?);
+    }
+    Ok(bytes)
+}
+
+/// Where on its line `nibble` (the hex digit, counting from the start
+/// of the file) is.
+fn column(nibble: usize) -> usize {
+    let slot = nibble / 2 % WIDTH;
+    HEX_START + slot * 3 + usize::from(slot >= WIDTH / 2) + nibble % 2
+}
+
+impl BufferEditor {
+    /// How many bytes the dump in the buffer shows.
+    fn hex_len(&self) -> usize {
+        let lines = self.buffer.len_lines();
+        let last = self
+            .buffer
+            .line(lines.saturating_sub(1))
+            .unwrap_or_default();
+        lines.saturating_sub(1) * WIDTH + parse_line(last).map_or(0, |bytes| bytes.len())
+    }
+
+    /// The hex digit the cursor is on, or the one before it when it's
+    /// between them.
+    fn nibble(&self) -> usize {
+        let line = self.buffer.cursor_line();
+        let col = self.buffer.cursor() - self.buffer.line_span(line..line).start;
+        let first = line * WIDTH * 2;
+        (first..first + WIDTH * 2)
+            .take_while(|&nibble| column(nibble) <= col)
+            .last()
+            .unwrap_or(first)
+    }
+
+    fn move_to_nibble(&mut self, game: &mut Game, nibble: usize) {
+        let nibble = nibble.min((self.hex_len() * 2).saturating_sub(1));
+        let line = nibble / (WIDTH * 2);
+        let start = self.buffer.line_span(line..line).start;
+        self.buffer.set_cursor(start + column(nibble));
+        self.scroll_to_cursor(game);
+    }
+
+    /// Writes `digit` over the hex digit under the cursor, and moves on
+    /// to the next.
+    fn type_nibble(&mut self, game: &mut Game, digit: u8) {
+        let nibble = self.nibble();
+        let line = nibble / (WIDTH * 2);
+        let Ok(mut bytes) = parse_line(self.buffer.line(line).unwrap_or_default()) else {
+            return;
+        };
+        let Some(byte) = bytes.get_mut(nibble / 2 % WIDTH) else {
+            return;
+        };
+        *byte = match nibble % 2 {
+            0 => (*byte & 0x0f) | digit << 4,
+            _ => (*byte & 0xf0) | digit,
+        };
+        let span = self.buffer.line_span(line..line + 1);
+        let end = span.start + self.buffer.line(line).map_or(0, str::len);
+        self.buffer
+            .replace_range(span.start..end, &dump_line(line * WIDTH, &bytes));
+        self.move_to_nibble(game, nibble + 1);
+    }
+
+    /// Handles a key in hex mode, returning whether it was dealt with.
+    /// Anything else which would change the text is turned away, so the
+    /// dump always reads back.
+    pub fn hex_event(&mut self, game: &mut Game, event: &SimpleEvent) -> bool {
+        let nibble = self.nibble();
+        match event {
+            SimpleEvent::Just(KeyCode::Left) => self.move_to_nibble(game, nibble.saturating_sub(1)),
+            SimpleEvent::Just(KeyCode::Right) => self.move_to_nibble(game, nibble + 1),
+            SimpleEvent::Just(KeyCode::Up) => {
+                self.move_to_nibble(game, nibble.saturating_sub(WIDTH * 2))
+            }
+            SimpleEvent::Just(KeyCode::Down) => {
+                // The last line may be too short to go straight down to.
+                if nibble / (WIDTH * 2) + 1 < self.buffer.len_lines() {
+                    self.move_to_nibble(game, nibble + WIDTH * 2);
+                }
+            }
+            SimpleEvent::Just(KeyCode::Char(c)) if c.is_ascii_hexdigit() && !self.read_only => {
+                let digit = c.to_digit(16).unwrap_or_default() as u8;
+                self.type_nibble(game, digit);
+            }
+            _ => {
+                let changes_text = match self.keymap.lookup(event) {
+                    // Every edit here leaves a whole dump behind, so going
+                    // back to one is safe.
+                    Some(Action::Undo | Action::Redo) => false,
+                    Some(action) => action.changes_text(),
+                    None => matches!(event, SimpleEvent::Just(KeyCode::Char(_) | KeyCode::Tab)),
+                };
+                if !changes_text || self.read_only {
+                    return false;
+                }
+                game.set_message(Some(Message::new(tr("editor.hex-only", &[]))));
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::rc::Rc;
+
+    use crate::buffer::Buffer;
+    use crate::config::Config;
+    use crate::encoding::Encoding;
+    use crate::harness::{ctrl, Harness};
+
+    #[test]
+    fn a_dump_reads_back_into_the_same_bytes() {
+        let bytes: Vec<u8> = (0..=255).chain(b"|end".iter().copied()).collect();
+        let text = dump(&bytes);
+        assert!(text.starts_with(
+            "00000000  00 01 02 03 04 05 06 07  08 09 0a 0b 0c 0d 0e 0f  |................|\n"
+        ));
+        assert!(text.contains("\n00000100  7c 65 6e 64  "));
+        assert_eq!(parse(&text).unwrap(), bytes);
+    }
+
+    #[test]
+    fn typing_hex_digits_writes_over_bytes() {
+        let mut buffer = Buffer::from_text(None, dump(b"\x00\x01binary"));
+        buffer.set_encoding(Encoding::Hex);
+        let editor = BufferEditor::new(String::from("bin"), buffer, Rc::new(Config::default()));
+        let mut harness = Harness::with_editor(editor);
+        harness.press(KeyCode::Right).type_text("f41");
+        assert_eq!(parse(harness.text()).unwrap(), b"\x0fAbinary");
+        assert!(harness.text().contains("|.Abinary|"));
+
+        harness.type_text("x");
+        assert_eq!(parse(harness.text()).unwrap(), b"\x0fAbinary");
+        assert_eq!(
+            harness.message(),
+            Some("Only hex digits can be typed in hex mode")
+        );
+
+        harness.run([ctrl('z'), ctrl('z'), ctrl('z')]);
+        assert_eq!(parse(harness.text()).unwrap(), b"\x00\x01binary");
+        harness.run([ctrl('y')]);
+        assert_eq!(parse(harness.text()).unwrap(), b"\x0f\x01binary");
+    }
+}
@@ -0,0 +1,417 @@
+//! Undo and redo for a [`crate::buffer::Buffer`].
+//!
+//! Every change to a buffer's text is recorded as an [`Edit`], which
+//! knows how to reverse itself. Edits are grouped into steps, and the
+//! steps form a tree: undo moves up it, and redo back down. Typing a
+//! word, or deleting back through one, is a single step.
+//!
+//! So that a long session's history doesn't grow forever, the oldest
+//! states are forgotten once it's over its limit, and can be with
+//! [`History::trim`]. The states left keep the numbers they had, so
+//! `undo-to` still goes where `undo-tree` said it would.
+
+use std::collections::BTreeMap;
+use std::mem;
+
+/// A single change: at byte `offset`, `removed` was replaced by `inserted`.
+#[derive(Debug, Clone)]
+pub struct Edit {
+    pub offset: usize,
+    pub removed: String,
+    pub inserted: String,
+}
+
+impl Edit {
+    /// The edit which undoes this one.
+    pub fn inverse(&self) -> Edit {
+        Edit {
+            offset: self.offset,
+            removed: self.inserted.clone(),
+            inserted: self.removed.clone(),
+        }
+    }
+
+    /// Where the byte at `offset` ends up once this edit is applied.
+    /// Anything inside the replaced text moves to the end of the new text.
+    pub fn shift(&self, offset: usize) -> usize {
+        if offset >= self.offset + self.removed.len() {
+            offset - self.removed.len() + self.inserted.len()
+        } else if offset > self.offset {
+            self.offset + self.inserted.len()
+        } else {
+            offset
+        }
+    }
+}
+
+/// One state the buffer has been in: the step which led to it from its
+/// parent, and the states which were reached from it.
+#[derive(Debug, Default)]
+struct Node {
+    parent: Option<usize>,
+    step: Vec<Edit>,
+    children: Vec<usize>,
+    /// The child that redo goes to: whichever was visited last.
+    redo_child: Option<usize>,
+}
+
+impl Node {
+    /// Roughly how much memory the node takes up.
+    fn bytes(&self) -> usize {
+        let edits: usize = self
+            .step
+            .iter()
+            .map(|edit| mem::size_of::<Edit>() + edit.removed.len() + edit.inserted.len())
+            .sum();
+        mem::size_of::<Node>() + edits + self.children.len() * mem::size_of::<usize>()
+    }
+}
+
+/// Every state one buffer has been in, as a tree. Making a change after
+/// an undo starts a new branch, rather than throwing away what could
+/// have been redone.
+#[derive(Debug)]
+pub struct History {
+    /// Every state, by number. The first is the state the buffer started
+    /// in, or the earliest one left once older ones are forgotten, and
+    /// states only ever come after their parents.
+    nodes: BTreeMap<usize, Node>,
+    /// The number the next state gets.
+    next: usize,
+    current: usize,
+    /// The total of every node's [`Node::bytes`].
+    bytes: usize,
+    /// How many bytes the history can grow to before old states are
+    /// forgotten.
+    limit: usize,
+    /// Whether the current step was made by typing, with nothing since,
+    /// like moving the cursor, to stop more typing from joining it.
+    joinable: bool,
+}
+
+impl Default for History {
+    fn default() -> History {
+        let root = Node::default();
+        History {
+            bytes: root.bytes(),
+            nodes: BTreeMap::from([(0, root)]),
+            next: 1,
+            current: 0,
+            limit: usize::MAX,
+            joinable: false,
+        }
+    }
+}
+
+impl History {
+    pub fn new() -> History {
+        History::default()
+    }
+
+    /// Sets how big the history can get before its oldest states are
+    /// forgotten.
+    pub fn set_limit(&mut self, limit: usize) {
+        self.limit = limit;
+        self.enforce_limit();
+    }
+
+    /// Records an edit that has just been applied, as its own undo step.
+    pub fn record(&mut self, edit: Edit) {
+        let id = self.next;
+        self.next += 1;
+        let node = Node {
+            parent: Some(self.current),
+            step: vec![edit],
+            ..Node::default()
+        };
+        self.bytes += node.bytes() + mem::size_of::<usize>();
+        self.nodes.insert(id, node);
+        let current = self.node_mut(self.current);
+        current.children.push(id);
+        current.redo_child = Some(id);
+        self.current = id;
+        self.joinable = false;
+        self.enforce_limit();
+    }
+
+    /// Records an edit made by typing, or deleting, a character. If it
+    /// carries on from the last step, typing more of the same word or
+    /// deleting further back through it, it's added on to that step.
+    pub fn record_typing(&mut self, edit: Edit) {
+        let joinable = self.joinable;
+        let node = self.node_mut(self.current);
+        let can_join = joinable && node.children.is_empty();
+        match node.step.as_mut_slice() {
+            [last] if can_join && continues(last, &edit) => {
+                if edit.removed.is_empty() {
+                    last.inserted.push_str(&edit.inserted);
+                } else {
+                    last.offset = edit.offset;
+                    last.removed.insert_str(0, &edit.removed);
+                }
+                self.bytes += edit.removed.len() + edit.inserted.len();
+                self.enforce_limit();
+            }
+            _ => self.record(edit),
+        }
+        self.joinable = true;
+    }
+
+    /// Makes the next typing edit start a step of its own.
+    pub fn seal(&mut self) {
+        self.joinable = false;
+    }
+
+    /// Moves to the parent of the current state, and returns the edits
+    /// which revert to it, in the order they should be applied.
+    pub fn undo(&mut self) -> Option<Vec<Edit>> {
+        self.joinable = false;
+        let node = &self.nodes[&self.current];
+        let parent = node.parent?;
+        let reverted = node.step.iter().rev().map(Edit::inverse).collect();
+        self.node_mut(parent).redo_child = Some(self.current);
+        self.current = parent;
+        Some(reverted)
+    }
+
+    /// Moves to the most recently visited child of the current state,
+    /// and returns the edits which re-apply it.
+    pub fn redo(&mut self) -> Option<Vec<Edit>> {
+        self.joinable = false;
+        let child = self.nodes[&self.current].redo_child?;
+        self.current = child;
+        Some(self.nodes[&child].step.clone())
+    }
+
+    /// Moves to `state`, wherever it is in the tree, and returns the
+    /// edits which get there: undoing back to the nearest state the two
+    /// have in common, then redoing down to `state`.
+    pub fn go_to(&mut self, state: usize) -> Option<Vec<Edit>> {
+        if !self.nodes.contains_key(&state) {
+            return None;
+        }
+        let target_path = self.path_to(state);
+        let mut edits = Vec::new();
+        while !target_path.contains(&self.current) {
+            edits.extend(self.undo()?);
+        }
+        let common = target_path.iter().position(|&id| id == self.current)?;
+        for &id in &target_path[common + 1..] {
+            self.node_mut(self.current).redo_child = Some(id);
+            edits.extend(self.redo()?);
+        }
+        Some(edits)
+    }
+
+    /// How many states there are.
+    pub fn states(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Roughly how much memory the history takes up, in bytes.
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    /// Forgets the oldest states until the history takes up no more than
+    /// `limit` bytes, or only the current state is left. Returns how many
+    /// were forgotten.
+    pub fn trim(&mut self, limit: usize) -> usize {
+        let before = self.nodes.len();
+        while self.bytes > limit && self.forget_oldest() {}
+        before - self.nodes.len()
+    }
+
+    /// Trims the history once it's over its limit. It's cut down a
+    /// quarter further than it has to be, so it's only trimmed every so
+    /// often rather than on every edit.
+    fn enforce_limit(&mut self) {
+        if self.bytes > self.limit {
+            self.trim(self.limit / 4 * 3);
+        }
+    }
+
+    /// Makes the second state on the way to the current one the first,
+    /// forgetting every state that isn't reached through it, including
+    /// any branches off the first state. Returns `false` if the current
+    /// state is the first.
+    fn forget_oldest(&mut self) -> bool {
+        let path = self.path_to(self.current);
+        let (Some(&old), Some(&root)) = (path.first(), path.get(1)) else {
+            return false;
+        };
+        let mut stack = vec![old];
+        while let Some(id) = stack.pop() {
+            let node = self.nodes.remove(&id).unwrap();
+            self.bytes -= node.bytes();
+            stack.extend(node.children.into_iter().filter(|&child| child != root));
+        }
+        // There's nothing before the first state to undo back to.
+        let first = self.node_mut(root);
+        let before = first.bytes();
+        first.parent = None;
+        first.step.clear();
+        let after = first.bytes();
+        self.bytes -= before - after;
+        true
+    }
+
+    fn node_mut(&mut self, id: usize) -> &mut Node {
+        self.nodes.get_mut(&id).unwrap()
+    }
+
+    /// The states from the first one down to `state`.
+    fn path_to(&self, state: usize) -> Vec<usize> {
+        let mut path = vec![state];
+        while let Some(parent) = self.nodes[path.last().unwrap()].parent {
+            path.push(parent);
+        }
+        path.reverse();
+        path
+    }
+
+    /// Draws the tree, one state per line, with the current state marked
+    /// by a `*`. A line of history carries straight on down the page,
+    /// and each branch off it is indented a little further.
+    pub fn tree(&self) -> String {
+        let mut text = String::new();
+        // (state, indent), with the next state to draw on top.
+        let first = self.nodes.keys().next().copied().unwrap_or(0);
+        let mut stack = vec![(first, 0)];
+        while let Some((id, indent)) = stack.pop() {
+            let node = &self.nodes[&id];
+            let marker = if id == self.current { '*' } else { ' ' };
+            let description = match node.parent {
+                None => String::from("original"),
+                Some(parent) if self.nodes[&parent].children[0] != id => {
+                    format!("{} (after {})", describe(&node.step), parent)
+                }
+                Some(_) => describe(&node.step),
+            };
+            text.push_str(&format!(
+                "{} {}{}: {}\n",
+                marker,
+                " ".repeat(indent),
+                id,
+                description
+            ));
+            // Later branches go underneath everything that follows on
+            // from the first child.
+            for (i, &child) in node.children.iter().enumerate().rev() {
+                stack.push((child, if i == 0 { indent } else { indent + 2 }));
+            }
+        }
+        text
+    }
+}
+
+/// Whether `next` is typing on from `last`, or deleting on back from
+/// it, within the same word and line.
+fn continues(last: &Edit, next: &Edit) -> bool {
+    let starts_word = |before: &str, after: &str| {
+        before.ends_with(char::is_whitespace) && !after.starts_with(char::is_whitespace)
+    };
+    match (last.removed.is_empty(), next.removed.is_empty()) {
+        (true, true) if last.inserted.is_empty() || next.inserted.is_empty() => false,
+        (true, true) => {
+            next.offset == last.offset + last.inserted.len()
+                && !next.inserted.contains('\n')
+                && !starts_word(&last.inserted, &next.inserted)
+        }
+        (false, false) => {
+            last.inserted.is_empty()
+                && next.inserted.is_empty()
+                && next.offset + next.removed.len() == last.offset
+                && !next.removed.contains('\n')
+                && !starts_word(&next.removed, &last.removed)
+        }
+        _ => false,
+    }
+}
+
+/// A short summary of what a step did.
+fn describe(step: &[Edit]) -> String {
+    let Some(edit) = step.first() else {
+        return String::from("nothing");
+    };
+    let quote = |text: &str| {
+        let mut shown: String = text.chars().take(20).collect();
+        if shown.len() < text.len() {
+            shown.push_str("...");
+        }
+        format!("{:?}", shown)
+    };
+    match (edit.removed.is_empty(), edit.inserted.is_empty()) {
+        (true, _) => format!("inserted {}", quote(&edit.inserted)),
+        (false, true) => format!("deleted {}", quote(&edit.removed)),
+        (false, false) => format!(
+            "replaced {} with {}",
+            quote(&edit.removed),
+            quote(&edit.inserted)
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn insert(offset: usize, text: &str) -> Edit {
+        Edit {
+            offset,
+            removed: String::new(),
+            inserted: String::from(text),
+        }
+    }
+
+    /// Applies `edits` to `text`, the way a buffer would.
+    fn apply(text: &mut String, edits: &[Edit]) {
+        for edit in edits {
+            text.replace_range(
+                edit.offset..edit.offset + edit.removed.len(),
+                &edit.inserted,
+            );
+        }
+    }
+
+    #[test]
+    fn a_change_after_an_undo_starts_a_branch() {
+        let mut history = History::new();
+        let mut text = String::from("ab");
+        history.record(insert(0, "a"));
+        history.record(insert(1, "b"));
+        apply(&mut text, &history.undo().unwrap());
+        history.record(insert(1, "c"));
+        apply(&mut text, &[insert(1, "c")]);
+
+        assert_eq!(history.states(), 4);
+        assert_eq!(
+            history.tree(),
+            "  0: original\n  1: inserted \"a\"\n  2: inserted \"b\"\n*   3: inserted \"c\" (after 1)\n"
+        );
+        // The branch that was undone away from is still there to jump to.
+        apply(&mut text, &history.go_to(2).unwrap());
+        assert_eq!(text, "ab");
+        apply(&mut text, &history.go_to(0).unwrap());
+        assert_eq!(text, "");
+        assert!(history.go_to(7).is_none());
+    }
+
+    #[test]
+    fn redo_follows_the_branch_last_visited() {
+        let mut history = History::new();
+        let mut text = String::from("a");
+        history.record(insert(0, "a"));
+        apply(&mut text, &history.undo().unwrap());
+        history.record(insert(0, "b"));
+        apply(&mut text, &[insert(0, "b")]);
+
+        apply(&mut text, &history.go_to(1).unwrap());
+        assert_eq!(text, "a");
+        apply(&mut text, &history.undo().unwrap());
+        assert_eq!(text, "");
+        apply(&mut text, &history.redo().unwrap());
+        assert_eq!(text, "a");
+        assert!(history.redo().is_none());
+    }
+}
@@ -0,0 +1,364 @@
+//! Files too big to read in whole: anything over `huge_file_mb` in the
+//! config. Opening one reads through it once to note where every
+//! thousandth line or so starts, and then the buffer only ever holds a
+//! couple of thousand lines around the cursor, read in again as the
+//! cursor gets near either end of them. So a log of several gigabytes
+//! opens without needing the memory for it.
+//!
+//! A huge file is read-only, read as UTF-8, and Ctrl+F and F3 search
+//! the whole file, reading through it from the cursor, though Alt+F3
+//! only goes back through the lines that are loaded. `search` at the
+//! `>>` prompt reads through all of it too, and commands there which
+//! would edit it refuse to.
+
+use regex::Regex;
+use termgame::{Game, ViewportLocation};
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Seek, SeekFrom};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::buffer::Buffer;
+use crate::config::Config;
+use crate::render::Drawn;
+use crate::search::{self, SearchResult};
+use crate::BufferEditor;
+
+/// How many lines apart the lines whose offsets are kept are.
+const INDEX_EVERY: usize = 1024;
+/// How many lines the buffer holds at a time.
+const WINDOW: usize = 2000;
+/// How close the cursor gets to either end of the lines that are loaded
+/// before more are.
+const MARGIN: usize = 200;
+
+/// A file being read a part at a time.
+#[derive(Debug)]
+pub struct HugeFile {
+    path: PathBuf,
+    /// Where every [`INDEX_EVERY`]th line starts.
+    index: Vec<u64>,
+    lines: usize,
+    /// The line the buffer's text starts at.
+    first: usize,
+}
+
+impl HugeFile {
+    /// Reads through the file at `path`, noting where its lines start.
+    pub fn open(path: &Path) -> io::Result<HugeFile> {
+        let mut reader = BufReader::with_capacity(1 << 20, File::open(path)?);
+        let mut index = vec![0];
+        let (mut lines, mut offset, mut last) = (0, 0, b'\n');
+        loop {
+            let chunk = reader.fill_buf()?;
+            if chunk.is_empty() {
+                break;
+            }
+            for (i, &byte) in chunk.iter().enumerate() {
+                if byte == b'\n' {
+                    lines += 1;
+                    if lines % INDEX_EVERY == 0 {
+                        index.push(offset + i as u64 + 1);
+                    }
+                }
+            }
+            last = chunk[chunk.len() - 1];
+            offset += chunk.len() as u64;
+            let len = chunk.len();
+            reader.consume(len);
+        }
+        // A last line without a newline is still a line.
+        if last != b'\n' {
+            lines += 1;
+        }
+        Ok(HugeFile {
+            path: PathBuf::from(path),
+            index,
+            lines,
+            first: 0,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// How many lines the whole file has.
+    pub fn lines(&self) -> usize {
+        self.lines
+    }
+
+    /// The line the buffer's text starts at.
+    pub fn first(&self) -> usize {
+        self.first
+    }
+
+    /// A reader starting at the beginning of (0-indexed) line `line`.
+    fn reader_at(&self, line: usize) -> io::Result<BufReader<File>> {
+        let mut file = File::open(&self.path)?;
+        let indexed = (line / INDEX_EVERY).min(self.index.len() - 1);
+        file.seek(SeekFrom::Start(self.index[indexed]))?;
+        let mut reader = BufReader::new(file);
+        let mut skipped = Vec::new();
+        for _ in indexed * INDEX_EVERY..line {
+            skipped.clear();
+            reader.read_until(b'\n', &mut skipped)?;
+        }
+        Ok(reader)
+    }
+
+    /// The text of the lines in `lines`, newlines and all.
+    pub fn read_lines(&self, lines: Range<usize>) -> io::Result<String> {
+        let mut text = Vec::new();
+        let mut reader = self.reader_at(lines.start)?;
+        for _ in lines {
+            if reader.read_until(b'\n', &mut text)? == 0 {
+                break;
+            }
+        }
+        Ok(String::from_utf8_lossy(&text).into_owned())
+    }
+
+    /// Where `query` is next found, as a line and a byte offset into it,
+    /// reading on from `col` bytes into (0-indexed) line `line` and round
+    /// from the top of the file.
+    pub fn find(&self, query: &str, line: usize, col: usize) -> io::Result<Option<(usize, usize)>> {
+        // The line the search starts on is looked at twice: after `col`
+        // first, and the whole of it once it's come round.
+        match self.find_in(query, line..self.lines, col)? {
+            Some(found) => Ok(Some(found)),
+            None => self.find_in(query, 0..line + 1, 0),
+        }
+    }
+
+    /// Every line of the file which `regex` matches, read through from
+    /// the top, as `search` lists them for the buffer called `name`.
+    pub fn search(&self, name: &str, regex: &Regex) -> io::Result<Vec<SearchResult>> {
+        let mut reader = self.reader_at(0)?;
+        let mut bytes = Vec::new();
+        let mut results = Vec::new();
+        for at in 0..self.lines {
+            bytes.clear();
+            if reader.read_until(b'\n', &mut bytes)? == 0 {
+                break;
+            }
+            let text = String::from_utf8_lossy(&bytes);
+            let line = text.strip_suffix('\n').unwrap_or(&text);
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            results.extend(search::search_line(name, at, line, regex));
+        }
+        Ok(results)
+    }
+
+    /// Where `query` is first found in `lines`, skipping the first `col`
+    /// bytes of the first one.
+    fn find_in(
+        &self,
+        query: &str,
+        lines: Range<usize>,
+        col: usize,
+    ) -> io::Result<Option<(usize, usize)>> {
+        let mut reader = self.reader_at(lines.start)?;
+        let mut bytes = Vec::new();
+        for at in lines.clone() {
+            bytes.clear();
+            if reader.read_until(b'\n', &mut bytes)? == 0 {
+                break;
+            }
+            let text = String::from_utf8_lossy(&bytes);
+            let after = if at == lines.start { col } else { 0 };
+            if let Some(found) = text.get(after..).and_then(|rest| rest.find(query)) {
+                return Ok(Some((at, after + found)));
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl BufferEditor {
+    /// An editor for the huge file at `path`, showing its first lines.
+    pub fn huge(name: String, path: &Path, config: Rc<Config>) -> io::Result<BufferEditor> {
+        let huge = HugeFile::open(path)?;
+        let text = huge.read_lines(0..WINDOW)?;
+        let mut editor = BufferEditor::new(name, Buffer::from_text(None, text), config);
+        editor.huge = Some(huge);
+        editor.read_only = true;
+        Ok(editor)
+    }
+
+    /// The line the buffer's text starts at, which is only ever past 0
+    /// in a huge file.
+    pub fn first_line(&self) -> usize {
+        self.huge.as_ref().map_or(0, HugeFile::first)
+    }
+
+    /// How many lines the buffer has, or its file has when only part of
+    /// it is loaded.
+    pub fn total_lines(&self) -> usize {
+        self.huge
+            .as_ref()
+            .map_or(self.buffer.len_lines(), HugeFile::lines)
+    }
+
+    /// Loads the lines around (0-indexed) line `line` of the huge file,
+    /// returning the offset into the buffer where it starts.
+    pub fn load_around(&mut self, game: &mut Game, line: usize) -> usize {
+        let moved = self.load_lines(line);
+        if moved != 0 {
+            let viewport = game.get_viewport();
+            game.set_viewport(ViewportLocation {
+                x: viewport.x,
+                y: viewport.y - moved,
+            });
+        }
+        self.offset_of((line, 0))
+    }
+
+    /// Loads the lines around (0-indexed) line `line` of the huge file,
+    /// if they aren't already, returning how many lines further into the
+    /// file the buffer starts now.
+    pub fn load_lines(&mut self, line: usize) -> i32 {
+        let Some(huge) = &mut self.huge else {
+            return 0;
+        };
+        let first = line
+            .saturating_sub(WINDOW / 2)
+            .min(huge.lines.saturating_sub(WINDOW));
+        if first == huge.first {
+            return 0;
+        }
+        let text = match huge.read_lines(first..first + WINDOW) {
+            Ok(text) => text,
+            Err(err) => {
+                tracing::warn!(buffer = %self.name, error = %err, "couldn't read more");
+                return 0;
+            }
+        };
+        let moved = first as i32 - huge.first as i32;
+        huge.first = first;
+        self.buffer = Buffer::from_text(None, text);
+        self.buffer.set_tab_width(self.config.tab_width);
+        self.drawn = Drawn::new();
+        moved
+    }
+
+    /// Loads more of a huge file if the cursor's got near either end of
+    /// what's loaded, keeping it where it was in the file.
+    pub fn page_huge(&mut self, game: &mut Game) {
+        let Some(huge) = &self.huge else {
+            return;
+        };
+        let (first, lines) = (huge.first, huge.lines);
+        let position = self.buffer.cursor_position();
+        let loaded = self.buffer.len_lines();
+        let near_top = position.line < MARGIN && first > 0;
+        let near_bottom = position.line + MARGIN > loaded && first + loaded < lines;
+        if !near_top && !near_bottom {
+            return;
+        }
+        let (line, col) = self.file_position(self.buffer.cursor());
+        self.load_around(game, line);
+        self.buffer.set_cursor(self.offset_of((line, col)));
+    }
+
+    /// Where `offset` into the buffer is in its file, as a (0-indexed)
+    /// line and a byte offset into that, which stays put while different
+    /// parts of a huge file are loaded.
+    pub fn file_position(&self, offset: usize) -> (usize, usize) {
+        let line = self.buffer.position_of(offset).line;
+        let col = offset - self.buffer.line_span(line..line).start;
+        (self.first_line() + line, col)
+    }
+
+    /// The offset into the buffer of `position` in its file, or the
+    /// nearest end of the buffer if it isn't loaded.
+    pub fn offset_of(&self, (line, col): (usize, usize)) -> usize {
+        let Some(line) = line.checked_sub(self.first_line()) else {
+            return 0;
+        };
+        let span = self.buffer.line_span(line..line + 1);
+        (span.start + col).min(span.end)
+    }
+
+    /// Finds `query` in a huge file, reading on from `from` in the
+    /// buffer, and loads the lines around it.
+    pub fn find_huge(&mut self, game: &mut Game, query: &str, from: usize) -> Option<usize> {
+        let huge = self.huge.as_ref()?;
+        let (line, col) = self.file_position(from);
+        let (line, col) = match huge.find(query, line, col) {
+            Ok(found) => found?,
+            Err(err) => {
+                tracing::warn!(buffer = %self.name, error = %err, "couldn't search");
+                return None;
+            }
+        };
+        Some(self.load_around(game, line) + col)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use termgame::KeyCode;
+
+    use crate::harness::{self, ctrl, Harness, TempFile};
+
+    #[test]
+    fn only_the_lines_around_the_cursor_are_loaded() {
+        let text: String = (1..=10_000).map(|n| format!("line {}\n", n)).collect();
+        let file = TempFile::new("huge", text);
+        let huge = HugeFile::open(file.path()).unwrap();
+        assert_eq!(huge.lines(), 10_000);
+        assert_eq!(
+            huge.read_lines(5000..5002).unwrap(),
+            "line 5001\nline 5002\n"
+        );
+        assert_eq!(huge.find("line 9999", 10, 0).unwrap(), Some((9998, 0)));
+        assert_eq!(huge.find("line 2\n", 10, 0).unwrap(), Some((1, 0)));
+
+        let editor = BufferEditor::huge(String::from("huge"), file.path(), Rc::default()).unwrap();
+        let mut harness = Harness::with_editor(editor);
+        assert!(harness.editor.buffer.len_lines() < 10_000);
+        harness.run([ctrl('f')]).type_text("line 7500\n");
+        let line = harness.editor.buffer.cursor_line();
+        assert_eq!(harness.editor.first_line() + line, 7499);
+        assert!(harness.status_line().contains("Ln 7500, Col 1 of 10000"));
+
+        // Moving past what's loaded loads more.
+        for _ in 0..1500 {
+            harness.press(KeyCode::Down);
+        }
+        let line = harness.editor.buffer.cursor_line();
+        assert_eq!(harness.editor.first_line() + line, 8999);
+        assert_eq!(harness.editor.buffer.line(line), Some("line 9000"));
+    }
+
+    #[test]
+    fn the_prompt_searches_all_of_a_huge_file_but_wont_edit_it() {
+        let text: String = (1..=5000).map(|n| format!("line {}\r\n", n)).collect();
+        let file = TempFile::new("huge", text);
+        let mut app = harness::app();
+        let editor = BufferEditor::huge(String::from("huge"), file.path(), Rc::default()).unwrap();
+        app.editors.insert(String::from("huge"), editor);
+
+        let regex = Regex::new("^line 4999$").unwrap();
+        let results = search::search_buffers(&app.editors, &regex).unwrap();
+        let shown: Vec<String> = results.iter().map(ToString::to_string).collect();
+        assert_eq!(shown, ["huge:4999:1: line 4999"]);
+
+        let loaded = String::from(app.editors["huge"].buffer.text());
+        for line in [
+            "sort huge",
+            "reverse huge",
+            "insert huge !date",
+            "undo huge",
+        ] {
+            let err = harness::command(&mut app, line).unwrap_err();
+            assert_eq!(err.to_string(), "huge is read-only");
+        }
+        assert_eq!(app.editors["huge"].buffer.text(), loaded);
+    }
+}
@@ -0,0 +1,340 @@
+//! The words BuffeRS shows, looked up by key so they can be translated.
+//!
+//! English is built in. Other languages are read from
+//! `~/.config/buffers/locales/<locale>.toml`, a table from keys to text,
+//! like `"dired.created" = "{path} créé"`. A translation only needs the
+//! strings it changes; anything it leaves out stays in English. The
+//! locale is `locale` in the config, or else taken from `LC_ALL`,
+//! `LC_MESSAGES` or `LANG`.
+//!
+//! In a string, `{name}` is replaced by the argument called `name`.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::Display;
+use std::fs;
+use std::io::ErrorKind;
+use std::sync::{OnceLock, RwLock};
+
+use crate::config::{config_dir, Config};
+
+/// Every string there is, in English.
+const ENGLISH: &[(&str, &str)] = &[
+    ("repl.welcome", "Welcome to BuffeRS. "),
+    ("repl.error", "Error: {error}"),
+    ("repl.unknown-command", "Command not recognised"),
+    ("repl.autosave-failed", "Couldn't autosave {name}: {error}"),
+    (
+        "repl.bad-config",
+        "Error in config, using the defaults: {error}",
+    ),
+    (
+        "repl.bad-keys",
+        "Error in keys.toml, using the default bindings: {error}",
+    ),
+    (
+        "repl.bad-locale",
+        "Couldn't load the {locale} translation: {error}",
+    ),
+    (
+        "metrics.bad-file",
+        "Couldn't read the usage metrics, starting again: {error}",
+    ),
+    (
+        "metrics.save-failed",
+        "Couldn't save the usage metrics: {error}",
+    ),
+    ("editor.read-only", "{name} is read-only"),
+    (
+        "editor.unsaved-changed",
+        "{name} changed on disk, but has unsaved edits so wasn't reloaded",
+    ),
+    ("editor.reload-failed", "Couldn't reload {name}: {error}"),
+    ("editor.reloaded", "Reloaded {name}, it changed on disk"),
+    (
+        "editor.changed-prompt",
+        "{name} changed on disk. (r)eload it, (o)verwrite it, or keep editing? ",
+    ),
+    (
+        "editor.kept",
+        "Kept the edits to {name}. `merge {name}` combines them with the file",
+    ),
+    ("editor.snapshot-failed", "Couldn't snapshot: {error}"),
+    ("editor.save-as-prompt", "Save as: "),
+    ("editor.saved", "Wrote {path}"),
+    ("editor.save-failed", "Couldn't save: {error}"),
+    ("save.no-helper", "no privileged_helper is set in the config"),
+    ("save.helper-failed-to-run", "couldn't run {program}: {error}"),
+    ("save.helper-stdin", "couldn't write to the helper"),
+    ("save.helper-failed", "{program} didn't manage to write {path}"),
+    ("save.partly-loaded", "{name} is only partly loaded, so it can't be written"),
+    (
+        "save.changed-on-disk",
+        "{path} changed on disk since it was loaded. `merge {name}` combines both versions, or `write {name} --overwrite` replaces it",
+    ),
+    ("save.unencodable", "{name}: {error}. `encoding {name} utf-8` can write it"),
+    (
+        "save.permission-denied",
+        "{path}: {error}. Try `write {name} --sudo` to write it with elevated permissions",
+    ),
+    ("apply-replace.wrong-count", "expected {expected} hunks in the preview but found {found}"),
+    ("apply-replace.changed-since", "{path} has changed since the preview"),
+    ("editor.autosave-failed", "Couldn't autosave: {error}"),
+    ("editor.swap-file", "{name} has a swap file left from a BuffeRS that didn't close. `recover {name}` restores what wasn't saved, or `recover {name} --discard` deletes it"),
+    ("editor.swap-failed", "Couldn't write the swap file: {error}"),
+    ("editor.not-saved", "Not saved"),
+    ("editor.hex-only", "Only hex digits can be typed in hex mode"),
+    ("editor.nothing-to-undo", "Nothing to undo"),
+    ("editor.nothing-to-redo", "Nothing to redo"),
+    ("editor.nothing-to-repeat", "Nothing to repeat yet"),
+    (
+        "editor.no-comment-style",
+        "Don't know how to write comments in {name}. Add its extension to `comments` in the config",
+    ),
+    ("editor.find-prompt", "Find: "),
+    ("editor.goto-prompt", "Go to line: "),
+    ("editor.not-a-line", "{input} isn't a line number"),
+    ("editor.not-in-git", "{name} isn't a committed file in a git repository"),
+    ("editor.no-later-changes", "No more changes after this"),
+    ("editor.no-earlier-changes", "No more changes before this"),
+    ("editor.pipe-prompt", "Pipe through: "),
+    ("editor.read-prompt", "Insert the output of: "),
+    ("editor.which-register", "Which register? (a to z)"),
+    ("editor.no-such-register", "Registers are named a to z"),
+    ("editor.which-macro", "Which macro? (a to z, or Enter for the last one)"),
+    ("editor.which-macro-times", "Play which macro {count} times?"),
+    ("editor.no-such-macro", "Macros are named a to z"),
+    ("editor.no-macros", "No macro has been recorded yet"),
+    ("editor.recording-macro", "Recording macro {name}, F5 stops"),
+    ("editor.macro-recorded", "Recorded {keys} keys as macro {name}"),
+    ("editor.macro-not-recorded", "There's no macro {name} yet"),
+    ("editor.macro-too-deep", "Macro {name} plays too many macros inside each other"),
+    ("editor.not-found", "Couldn't find \"{query}\""),
+    ("editor.nothing-to-find", "Nothing to find again, press Ctrl+F to find something"),
+    ("status.modified", "[+]"),
+    ("status.normal", "NORMAL"),
+    ("status.insert", "INSERT"),
+    ("status.visual", "VISUAL"),
+    ("status.position", "Ln {line}, Col {col} of {lines}"),
+    ("status.words", "{words} words, {chars} chars"),
+    ("status.wpm", "{wpm} wpm"),
+    ("status.recording-macro", "recording {name}"),
+    ("status.autosaved", "Autosaved {ago} ago"),
+    ("dired.rename-prompt", "Rename to: "),
+    ("dired.delete-prompt", "Move {name} to the trash? (y/n) "),
+    ("dired.new-file-prompt", "New file: "),
+    ("dired.new-dir-prompt", "New directory: "),
+    ("dired.yes", "y"),
+    ("dired.renamed", "Renamed to {path}"),
+    ("dired.trashed", "Moved to {path}"),
+    ("dired.not-deleted", "Not deleted"),
+    ("dired.created", "Created {path}"),
+    ("dired.list-failed", "Couldn't list {path}: {error}"),
+    ("play.playing", "Playing key {key}/{keys} at {speed}x"),
+    ("play.paused", "Paused at key {key}/{keys} at {speed}x"),
+    ("play.stopped", "Stopped playing"),
+    ("play.finished", "Finished playing"),
+    ("record.stopped", "Stopped recording to {path}: {error}"),
+    (
+        "tutor.intro",
+        "Welcome to BuffeRS! This buffer is a set of lessons. Do what each one\nsays, and the next will appear underneath.",
+    ),
+    ("dashboard.title", "BuffeRS"),
+    ("dashboard.recent", "Recent files"),
+    ("dashboard.sessions", "Saved sessions"),
+    ("dashboard.none", "Nothing yet"),
+    ("dashboard.tips", "Tips"),
+    (
+        "dashboard.tip-open",
+        "{close} goes to the >> prompt, where `open <file>` opens a file",
+    ),
+    (
+        "dashboard.tip-tutor",
+        "`tutor` teaches the keys, and `keys` lists every one",
+    ),
+    (
+        "dashboard.tip-session",
+        "`session save <name>` puts the open buffers away for later",
+    ),
+    ("theme.unknown", "There's no theme called {theme}"),
+    ("tutor.passed", "Lesson {lesson} done!"),
+    ("announce.failed", "Couldn't announce to {target}: {error}"),
+    ("announce.buffer", "Editing {name}"),
+    ("announce.line", "Line {line}: {text}"),
+    ("announce.blank", "blank"),
+    ("announce.end-of-line", "end of line"),
+    ("announce.space", "space"),
+    ("announce.tab", "tab"),
+    ("announce.deleted", "Deleted {char}"),
+    (
+        "repl.swap-leftovers",
+        "Swap files were left behind for {names}. Open one and `recover` it to get back what wasn't saved",
+    ),
+    ("pipe.couldnt-run", "couldn't run {command}: {error}"),
+    ("pipe.couldnt-write", "couldn't write to the command"),
+    ("pipe.unfinished", "{command} didn't finish: {error}"),
+    ("pipe.failed", "{command} failed: {error}"),
+    ("pipe.exited", "{command} failed ({status})"),
+    ("pipe.not-utf8", "{command} didn't print UTF-8"),
+];
+
+/// The strings for one locale.
+#[derive(Debug, Clone)]
+pub struct Catalog {
+    pub locale: String,
+    strings: HashMap<String, String>,
+}
+
+impl Catalog {
+    pub fn english() -> Catalog {
+        Catalog {
+            locale: String::from("en"),
+            strings: ENGLISH
+                .iter()
+                .map(|&(key, text)| (String::from(key), String::from(text)))
+                .collect(),
+        }
+    }
+
+    /// The strings for `locale`, like `fr_CA`. If there's no translation
+    /// for the whole locale, the one for just the language (`fr`) is
+    /// used, and if there's none of that either, English is.
+    pub fn load(locale: &str) -> Result<Catalog, Box<dyn Error>> {
+        let mut catalog = Catalog::english();
+        let Some(dir) = config_dir().map(|dir| dir.join("locales")) else {
+            return Ok(catalog);
+        };
+        let language = locale.split(['_', '-']).next().unwrap_or(locale);
+        for name in [locale, language] {
+            let path = dir.join(format!("{}.toml", name));
+            match fs::read_to_string(&path) {
+                Ok(text) => {
+                    let strings: HashMap<String, String> = toml::from_str(&text)
+                        .map_err(|err| format!("{}: {}", path.display(), err))?;
+                    catalog.strings.extend(strings);
+                    catalog.locale = String::from(name);
+                    return Ok(catalog);
+                }
+                Err(err) if err.kind() == ErrorKind::NotFound => {}
+                Err(err) => return Err(format!("{}: {}", path.display(), err).into()),
+            }
+        }
+        Ok(catalog)
+    }
+
+    /// Adds a string which isn't built in, in English. If the catalog
+    /// already has a translation for `key`, that's kept.
+    pub fn register(&mut self, key: &str, english: &str) {
+        self.strings
+            .entry(String::from(key))
+            .or_insert_with(|| String::from(english));
+    }
+
+    /// The string for `key`, with each `{name}` in it replaced by the
+    /// argument of that name. A key with no string is shown as it is,
+    /// so a missing string is obvious rather than blank.
+    pub fn format(&self, key: &str, args: &[(&str, &dyn Display)]) -> String {
+        let Some(template) = self.strings.get(key) else {
+            return String::from(key);
+        };
+        let mut text = String::new();
+        let mut rest = template.as_str();
+        while let Some(start) = rest.find('{') {
+            text.push_str(&rest[..start]);
+            let value = rest[start..].find('}').and_then(|end| {
+                let name = &rest[start + 1..start + end];
+                let (_, value) = args.iter().find(|(arg, _)| *arg == name)?;
+                Some((value, start + end + 1))
+            });
+            match value {
+                Some((value, after)) => {
+                    text.push_str(&value.to_string());
+                    rest = &rest[after..];
+                }
+                // Not an argument, so it's left as it is.
+                None => {
+                    text.push('{');
+                    rest = &rest[start + 1..];
+                }
+            }
+        }
+        text.push_str(rest);
+        text
+    }
+}
+
+/// Which locale to show, from the config or the environment.
+pub fn locale(config: &Config) -> String {
+    let from_env = ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.is_empty());
+    let locale = config.locale.clone().or(from_env).unwrap_or_default();
+    // Like `fr_FR.UTF-8`, where only the `fr_FR` matters here.
+    let locale = locale.split(['.', '@']).next().unwrap_or_default();
+    match locale {
+        "" | "C" | "POSIX" => String::from("en"),
+        locale => String::from(locale),
+    }
+}
+
+fn catalog() -> &'static RwLock<Catalog> {
+    static CATALOG: OnceLock<RwLock<Catalog>> = OnceLock::new();
+    CATALOG.get_or_init(|| RwLock::new(Catalog::english()))
+}
+
+/// Switches every string over to `catalog`.
+pub fn init(new: Catalog) {
+    *catalog().write().unwrap() = new;
+}
+
+/// Adds a string which isn't built in, in English. See [`Catalog::register`].
+pub fn register(key: &str, english: &str) {
+    catalog().write().unwrap().register(key, english);
+}
+
+/// The string for `key`, in the current locale. See [`Catalog::format`].
+pub fn tr(key: &str, args: &[(&str, &dyn Display)]) -> String {
+    catalog().read().unwrap().format(key, args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arguments_are_put_in_by_name() {
+        let catalog = Catalog::english();
+        assert_eq!(
+            catalog.format("status.words", &[("chars", &12), ("words", &3)]),
+            "3 words, 12 chars"
+        );
+        assert_eq!(catalog.format("no.such.key", &[]), "no.such.key");
+        // What's put in isn't looked at again.
+        assert_eq!(
+            catalog.format("editor.read-only", &[("name", &"{name}")]),
+            "{name} is read-only"
+        );
+    }
+
+    #[test]
+    fn registered_strings_dont_replace_translations() {
+        let mut catalog = Catalog::english();
+        catalog.register("plugin.hello", "Hello");
+        catalog.register("editor.read-only", "Whatever");
+        assert_eq!(catalog.format("plugin.hello", &[]), "Hello");
+        assert_eq!(
+            catalog.format("editor.read-only", &[("name", &"a")]),
+            "a is read-only"
+        );
+    }
+
+    #[test]
+    fn the_locale_comes_without_its_encoding() {
+        let config = Config {
+            locale: Some(String::from("pt_BR.UTF-8")),
+            ..Config::default()
+        };
+        assert_eq!(locale(&config), "pt_BR");
+    }
+}
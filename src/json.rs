@@ -0,0 +1,53 @@
+//! Just enough JSON for the files BuffeRS writes and reads back, which
+//! are simple enough not to need a whole JSON library.
+
+use crate::transform;
+
+/// `text` as a JSON string, quotes included. It's escaped the way the
+/// `json-escape` command escapes it.
+pub fn string(text: &str) -> String {
+    // Escaping can't fail; it's only a `Result` to be a transform.
+    let escaped = transform::json_escape(text).unwrap_or_default();
+    format!("\"{}\"", escaped)
+}
+
+/// Reads the JSON string at the start of `text`, returning it and
+/// whatever comes after it. Its escapes are read back the way the
+/// `json-unescape` command reads them, so one it doesn't know is an
+/// error, not a character.
+pub fn parse_string(text: &str) -> Option<(String, &str)> {
+    let body = text.strip_prefix('"')?;
+    let mut escaped = false;
+    let end = body.char_indices().find_map(|(i, c)| {
+        let quote = c == '"' && !escaped;
+        escaped = c == '\\' && !escaped;
+        quote.then_some(i)
+    })?;
+    let string = transform::json_unescape(&body[..end]).ok()?;
+    Some((string, &body[end + 1..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn control_characters_are_escaped_in_json() {
+        assert_eq!(string("a\"b\\\x1b[H\r\n"), r#""a\"b\\\u001b[H\r\n""#);
+    }
+
+    #[test]
+    fn strings_parse_back() {
+        let (text, rest) = parse_string(r#""a\"\u001b[A\ud83d\ude00\\", 1"#).unwrap();
+        assert_eq!(text, "a\"\x1b[A😀\\");
+        assert_eq!(rest, ", 1");
+        assert_eq!(
+            parse_string(&string("\x08\x0c\x7f")).unwrap().0,
+            "\x08\x0c\x7f"
+        );
+        // Escapes JSON doesn't have, and half a surrogate pair, aren't.
+        assert_eq!(parse_string(r#""\q""#), None);
+        assert_eq!(parse_string(r#""\ud83d""#), None);
+        assert_eq!(parse_string(r#""unclosed"#), None);
+    }
+}
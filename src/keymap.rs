@@ -0,0 +1,680 @@
+//! Which key does what in the editor.
+//!
+//! Bindings are made to actions, like `paste`, rather than written into
+//! the editor, and letters in them are looked up by where the key is on
+//! the keyboard rather than by what it types. So with `keyboard_layout
+//! = "dvorak"` in the config, paste is still on the key QWERTY has V on
+//! (which types K on Dvorak), and the bindings stay as easy to reach on
+//! every layout.
+//!
+//! Any key can be rebound in `~/.config/buffers/keys.toml`, which maps
+//! keys, as they're written by `keys`, to actions:
+//!
+//! ```toml
+//! "Ctrl+J" = "find"
+//! "Alt+Up" = "scroll-up"
+//! "Ctrl+W" = "none"
+//! ```
+//!
+//! Keys are written as they're pressed on the configured layout, and
+//! `none` unbinds one. The default bindings stay as they are unless
+//! they're rebound.
+//!
+//! Shift can't be bound, since not every terminal sends it, but where
+//! one does, Ctrl+Shift and a letter does what Ctrl+Alt and it does.
+
+use serde::Deserialize;
+use termgame::{KeyCode, SimpleEvent};
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io::ErrorKind;
+use std::str::FromStr;
+
+use crate::case::Case;
+use crate::config::{config_dir, Config};
+
+/// Something a key can be bound to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Newline,
+    Backspace,
+    /// Deletes the char after the cursor.
+    Delete,
+    /// Types the next key as-is, without expanding an abbreviation.
+    LiteralNext,
+    /// Copies the selection, or the cursor's line if nothing's selected.
+    Copy,
+    Cut,
+    Paste,
+    /// Picks a register for the next copy, cut, delete or paste.
+    Register,
+    Save,
+    /// Deletes back to the start of the line.
+    ClearLine,
+    /// Deletes on to the end of the line.
+    KillToEnd,
+    /// Deletes the whole line.
+    KillLine,
+    Close,
+    ScrollUp,
+    ScrollDown,
+    /// Scrolls a screen's width across.
+    ScrollLeft,
+    ScrollRight,
+    Left,
+    Right,
+    Up,
+    Down,
+    /// Moves to the first char on the line which isn't blank, or from
+    /// there to the start of the line.
+    LineStart,
+    LineEnd,
+    /// Scrolls a screen up or down, moving the cursor along with it.
+    PageUp,
+    PageDown,
+    /// Moves the cursor's line, or the selected lines, up or down one.
+    MoveLineUp,
+    MoveLineDown,
+    /// Copies the cursor's line, or the selected lines, to below them.
+    DuplicateLine,
+    /// Joins the next line onto the cursor's.
+    JoinLines,
+    /// Comments out the cursor's line, or the selected lines, or back in.
+    ToggleComment,
+    /// Changes the case of the selection, or the word under the cursor.
+    ChangeCase(Case),
+    /// Asks for a shell command to pipe the selection, or the whole
+    /// buffer, through.
+    Pipe,
+    /// Asks for a shell command to put the output of in at the cursor.
+    ReadCommand,
+    /// Moves to the start of the word before the cursor.
+    WordLeft,
+    /// Moves to the end of the word after the cursor.
+    WordRight,
+    /// Deletes back to the start of the word before the cursor.
+    DeleteWordBack,
+    /// Deletes on to the end of the word after the cursor.
+    DeleteWord,
+    Undo,
+    Redo,
+    /// Makes the last edit again.
+    Repeat,
+    Find,
+    FindNext,
+    FindPrevious,
+    /// Asks which line to go to.
+    Goto,
+    /// Jumps to the next run of lines changed since the last commit, or
+    /// the one before.
+    NextHunk,
+    PreviousHunk,
+    /// Shows who last changed each line, or stops.
+    Blame,
+    /// Turns soft wrapping on or off.
+    Wrap,
+    /// Jumps to the bracket which pairs with the one under the cursor.
+    MatchBracket,
+    /// Selects the word under the cursor, then adds a cursor at the next
+    /// place it's written.
+    AddCursor,
+    /// Starts recording a macro, or stops.
+    RecordMacro,
+    /// Plays a macro back.
+    PlayMacro,
+    /// Moves the keys to the other side of a split.
+    OtherPane,
+    /// Switches to the buffer in the next tab, or the one before.
+    NextBuffer,
+    PreviousBuffer,
+}
+
+impl Action {
+    /// What the action is called, as `keys` shows it.
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Newline => "newline",
+            Action::Backspace => "backspace",
+            Action::Delete => "delete",
+            Action::LiteralNext => "literal-next",
+            Action::Copy => "copy",
+            Action::Cut => "cut",
+            Action::Paste => "paste",
+            Action::Register => "register",
+            Action::Save => "save",
+            Action::ClearLine => "clear-line",
+            Action::KillToEnd => "kill-to-end",
+            Action::KillLine => "kill-line",
+            Action::Close => "close",
+            Action::ScrollUp => "scroll-up",
+            Action::ScrollDown => "scroll-down",
+            Action::ScrollLeft => "scroll-left",
+            Action::ScrollRight => "scroll-right",
+            Action::Left => "left",
+            Action::Right => "right",
+            Action::Up => "up",
+            Action::Down => "down",
+            Action::LineStart => "line-start",
+            Action::LineEnd => "line-end",
+            Action::PageUp => "page-up",
+            Action::PageDown => "page-down",
+            Action::MoveLineUp => "move-line-up",
+            Action::MoveLineDown => "move-line-down",
+            Action::DuplicateLine => "duplicate-line",
+            Action::JoinLines => "join-lines",
+            Action::ToggleComment => "toggle-comment",
+            Action::ChangeCase(case) => match case {
+                Case::Upper => "upper-case",
+                Case::Lower => "lower-case",
+                Case::Title => "title-case",
+                Case::Toggle => "toggle-case",
+                Case::Snake => "snake-case",
+                Case::Camel => "camel-case",
+            },
+            Action::Pipe => "pipe",
+            Action::ReadCommand => "read-command",
+            Action::WordLeft => "word-left",
+            Action::WordRight => "word-right",
+            Action::DeleteWordBack => "delete-word-back",
+            Action::DeleteWord => "delete-word",
+            Action::Undo => "undo",
+            Action::Redo => "redo",
+            Action::Repeat => "repeat",
+            Action::Find => "find",
+            Action::FindNext => "find-next",
+            Action::FindPrevious => "find-previous",
+            Action::Goto => "goto",
+            Action::NextHunk => "next-hunk",
+            Action::PreviousHunk => "previous-hunk",
+            Action::Blame => "blame",
+            Action::Wrap => "wrap",
+            Action::MatchBracket => "match-bracket",
+            Action::AddCursor => "add-cursor",
+            Action::RecordMacro => "record-macro",
+            Action::PlayMacro => "play-macro",
+            Action::OtherPane => "other-pane",
+            Action::NextBuffer => "next-buffer",
+            Action::PreviousBuffer => "previous-buffer",
+        }
+    }
+
+    /// The action called `name`.
+    pub fn by_name(name: &str) -> Option<Action> {
+        // Every action has a default binding.
+        DEFAULT_BINDINGS
+            .iter()
+            .map(|(_, action)| *action)
+            .find(|action| action.name() == name)
+    }
+
+    /// Whether the action edits the buffer.
+    pub fn changes_text(self) -> bool {
+        matches!(
+            self,
+            Action::Newline
+                | Action::Backspace
+                | Action::Delete
+                | Action::DeleteWordBack
+                | Action::DeleteWord
+                | Action::Cut
+                | Action::Paste
+                | Action::ClearLine
+                | Action::KillToEnd
+                | Action::KillLine
+                | Action::MoveLineUp
+                | Action::MoveLineDown
+                | Action::DuplicateLine
+                | Action::JoinLines
+                | Action::ToggleComment
+                | Action::ChangeCase(_)
+                | Action::Pipe
+                | Action::ReadCommand
+                | Action::Undo
+                | Action::Redo
+                | Action::Repeat
+        )
+    }
+}
+
+/// A key, with whichever modifiers are held. Letters are lowercase.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Key {
+    Just(KeyCode),
+    Ctrl(KeyCode),
+    Alt(KeyCode),
+    CtrlAlt(KeyCode),
+}
+
+impl Key {
+    fn from_event(event: &SimpleEvent) -> Option<Key> {
+        match event {
+            SimpleEvent::Just(code) => Some(Key::Just(*code)),
+            SimpleEvent::WithControl(code) => Some(Key::Ctrl(*code)),
+            SimpleEvent::WithAlt(code) => Some(Key::Alt(*code)),
+            SimpleEvent::WithControlAlt(code) => Some(Key::CtrlAlt(*code)),
+            SimpleEvent::ComplexEvent(_) => None,
+        }
+    }
+
+    /// The same key with its character changed by `f`. Plain characters
+    /// are left alone, since they're for typing, not bindings.
+    fn map_char(self, f: impl Fn(char) -> char) -> Key {
+        let code = |code| match code {
+            KeyCode::Char(c) => KeyCode::Char(f(c)),
+            code => code,
+        };
+        match self {
+            Key::Just(code) => Key::Just(code),
+            Key::Ctrl(c) => Key::Ctrl(code(c)),
+            Key::Alt(c) => Key::Alt(code(c)),
+            Key::CtrlAlt(c) => Key::CtrlAlt(code(c)),
+        }
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (modifiers, code) = match self {
+            Key::Just(code) => ("", code),
+            Key::Ctrl(code) => ("Ctrl+", code),
+            Key::Alt(code) => ("Alt+", code),
+            Key::CtrlAlt(code) => ("Ctrl+Alt+", code),
+        };
+        match code {
+            KeyCode::Char(' ') => write!(f, "{}Space", modifiers),
+            KeyCode::Char(c) => write!(f, "{}{}", modifiers, c.to_uppercase()),
+            KeyCode::F(n) => write!(f, "{}F{}", modifiers, n),
+            code => write!(f, "{}{:?}", modifiers, code),
+        }
+    }
+}
+
+/// The keys which can be written by name, as `keys` shows them.
+const NAMED_KEYS: &[KeyCode] = &[
+    KeyCode::Enter,
+    KeyCode::Backspace,
+    KeyCode::Delete,
+    KeyCode::Insert,
+    KeyCode::Tab,
+    KeyCode::BackTab,
+    KeyCode::Esc,
+    KeyCode::Left,
+    KeyCode::Right,
+    KeyCode::Up,
+    KeyCode::Down,
+    KeyCode::Home,
+    KeyCode::End,
+    KeyCode::PageUp,
+    KeyCode::PageDown,
+];
+
+impl FromStr for Key {
+    type Err = String;
+
+    /// Reads a key written the way it's displayed, like `Ctrl+Alt+F`,
+    /// `Alt+F3` or `Ctrl+Space`. Plain characters are for typing, so
+    /// they can't be bound.
+    fn from_str(s: &str) -> Result<Key, String> {
+        let (mut ctrl, mut alt) = (false, false);
+        let mut rest = s;
+        loop {
+            if let Some(after) = rest.strip_prefix("Ctrl+").filter(|after| !after.is_empty()) {
+                (ctrl, rest) = (true, after);
+            } else if let Some(after) = rest.strip_prefix("Alt+").filter(|after| !after.is_empty())
+            {
+                (alt, rest) = (true, after);
+            } else {
+                break;
+            }
+        }
+        let mut chars = rest.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c.to_ascii_lowercase()),
+            _ if rest == "Space" => KeyCode::Char(' '),
+            _ => match rest.strip_prefix('F').and_then(|n| n.parse().ok()) {
+                Some(n) => KeyCode::F(n),
+                None => *NAMED_KEYS
+                    .iter()
+                    .find(|code| format!("{:?}", code) == rest)
+                    .ok_or_else(|| format!("there's no key called {}", rest))?,
+            },
+        };
+        match (ctrl, alt, code) {
+            (false, false, KeyCode::Char(_)) => Err(format!("{} types, so it can't be bound", s)),
+            (false, false, code) => Ok(Key::Just(code)),
+            (true, false, code) => Ok(Key::Ctrl(code)),
+            (false, true, code) => Ok(Key::Alt(code)),
+            (true, true, code) => Ok(Key::CtrlAlt(code)),
+        }
+    }
+}
+
+/// The keyboard layouts bindings can follow.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Layout {
+    #[default]
+    Qwerty,
+    Dvorak,
+    Colemak,
+    Azerty,
+}
+
+/// The three rows of letters on QWERTY, which every other layout's
+/// rows are lined up against.
+const QWERTY: [&str; 3] = ["qwertyuiop", "asdfghjkl;", "zxcvbnm,./"];
+
+impl Layout {
+    fn rows(self) -> [&'static str; 3] {
+        match self {
+            Layout::Qwerty => QWERTY,
+            Layout::Dvorak => ["',.pyfgcrl", "aoeuidhtns", ";qjkxbmwvz"],
+            Layout::Colemak => ["qwfpgjluy;", "arstdhneio", "zxcvbkm,./"],
+            Layout::Azerty => ["azertyuiop", "qsdfghjklm", "wxcvbn,;:!"],
+        }
+    }
+
+    /// What QWERTY has on the key which types `c` on this layout.
+    fn qwerty_of(self, c: char) -> char {
+        translate(c, self.rows(), QWERTY)
+    }
+
+    /// What this layout types on the key QWERTY has `c` on.
+    fn typed_at(self, c: char) -> char {
+        translate(c, QWERTY, self.rows())
+    }
+}
+
+/// The char in the same place in `to` as `c` is in `from`.
+fn translate(c: char, from: [&str; 3], to: [&str; 3]) -> char {
+    from.iter()
+        .zip(to)
+        .find_map(|(from, to)| {
+            let i = from.chars().position(|other| other == c)?;
+            to.chars().nth(i)
+        })
+        .unwrap_or(c)
+}
+
+/// The editor's bindings, as they'd be on QWERTY.
+const DEFAULT_BINDINGS: &[(Key, Action)] = &[
+    (Key::Just(KeyCode::Enter), Action::Newline),
+    (Key::Just(KeyCode::Backspace), Action::Backspace),
+    (Key::Just(KeyCode::Delete), Action::Delete),
+    (Key::Ctrl(KeyCode::Char('q')), Action::LiteralNext),
+    (Key::Ctrl(KeyCode::Char('c')), Action::Copy),
+    (Key::Ctrl(KeyCode::Char('x')), Action::Cut),
+    (Key::Ctrl(KeyCode::Char('v')), Action::Paste),
+    (Key::Ctrl(KeyCode::Char('r')), Action::Register),
+    (Key::Ctrl(KeyCode::Char('s')), Action::Save),
+    (Key::Ctrl(KeyCode::Char('u')), Action::ClearLine),
+    (Key::Ctrl(KeyCode::Char('k')), Action::KillToEnd),
+    (Key::Alt(KeyCode::Char('k')), Action::KillLine),
+    (Key::Ctrl(KeyCode::Char('z')), Action::Undo),
+    (Key::Ctrl(KeyCode::Char('y')), Action::Redo),
+    (Key::Ctrl(KeyCode::Char('.')), Action::Repeat),
+    (Key::Ctrl(KeyCode::Char('f')), Action::Find),
+    (Key::Just(KeyCode::F(3)), Action::FindNext),
+    (Key::Alt(KeyCode::F(3)), Action::FindPrevious),
+    (Key::Ctrl(KeyCode::Char('g')), Action::Goto),
+    (Key::Alt(KeyCode::PageDown), Action::NextHunk),
+    (Key::Alt(KeyCode::PageUp), Action::PreviousHunk),
+    (Key::Alt(KeyCode::Char('b')), Action::Blame),
+    (Key::Ctrl(KeyCode::Char('w')), Action::Wrap),
+    (Key::Ctrl(KeyCode::Char(']')), Action::MatchBracket),
+    // Most terminals send Ctrl+] as Ctrl+5.
+    (Key::Ctrl(KeyCode::Char('5')), Action::MatchBracket),
+    (Key::Ctrl(KeyCode::Char('d')), Action::AddCursor),
+    // Ctrl+Shift+D too, where the terminal sends the Shift.
+    (Key::CtrlAlt(KeyCode::Char('d')), Action::DuplicateLine),
+    (Key::Alt(KeyCode::Char('j')), Action::JoinLines),
+    (Key::Ctrl(KeyCode::Char('/')), Action::ToggleComment),
+    // Most terminals send Ctrl+/ as Ctrl+7.
+    (Key::Ctrl(KeyCode::Char('7')), Action::ToggleComment),
+    (
+        Key::Alt(KeyCode::Char('u')),
+        Action::ChangeCase(Case::Upper),
+    ),
+    (
+        Key::Alt(KeyCode::Char('l')),
+        Action::ChangeCase(Case::Lower),
+    ),
+    (
+        Key::Alt(KeyCode::Char('c')),
+        Action::ChangeCase(Case::Title),
+    ),
+    (
+        Key::Alt(KeyCode::Char('t')),
+        Action::ChangeCase(Case::Toggle),
+    ),
+    (
+        Key::Alt(KeyCode::Char('s')),
+        Action::ChangeCase(Case::Snake),
+    ),
+    (
+        Key::Alt(KeyCode::Char('m')),
+        Action::ChangeCase(Case::Camel),
+    ),
+    (Key::Alt(KeyCode::Char('!')), Action::Pipe),
+    (Key::Alt(KeyCode::Char('r')), Action::ReadCommand),
+    (Key::Just(KeyCode::F(5)), Action::RecordMacro),
+    (Key::Just(KeyCode::F(6)), Action::PlayMacro),
+    (Key::Ctrl(KeyCode::Char('o')), Action::OtherPane),
+    (Key::Ctrl(KeyCode::Tab), Action::NextBuffer),
+    (Key::Ctrl(KeyCode::PageDown), Action::NextBuffer),
+    (Key::Ctrl(KeyCode::PageUp), Action::PreviousBuffer),
+    (Key::Just(KeyCode::Esc), Action::Close),
+    (Key::Just(KeyCode::Left), Action::Left),
+    (Key::Just(KeyCode::Right), Action::Right),
+    (Key::Just(KeyCode::Up), Action::Up),
+    (Key::Just(KeyCode::Down), Action::Down),
+    (Key::Ctrl(KeyCode::Up), Action::ScrollUp),
+    (Key::Ctrl(KeyCode::Down), Action::ScrollDown),
+    (Key::Just(KeyCode::Home), Action::LineStart),
+    (Key::Just(KeyCode::End), Action::LineEnd),
+    (Key::Just(KeyCode::PageUp), Action::PageUp),
+    (Key::Just(KeyCode::PageDown), Action::PageDown),
+    (Key::Alt(KeyCode::Up), Action::MoveLineUp),
+    (Key::Alt(KeyCode::Down), Action::MoveLineDown),
+    (Key::Alt(KeyCode::Left), Action::ScrollLeft),
+    (Key::Alt(KeyCode::Right), Action::ScrollRight),
+    (Key::Ctrl(KeyCode::Left), Action::WordLeft),
+    (Key::Ctrl(KeyCode::Right), Action::WordRight),
+    (Key::Ctrl(KeyCode::Backspace), Action::DeleteWordBack),
+    (Key::Ctrl(KeyCode::Delete), Action::DeleteWord),
+];
+
+/// Keys and what they're bound to.
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    layout: Layout,
+    /// By the key's place on the keyboard, as QWERTY names it.
+    bindings: HashMap<Key, Action>,
+}
+
+impl Keymap {
+    /// The default bindings, for a keyboard with `layout`.
+    pub fn new(layout: Layout) -> Keymap {
+        Keymap {
+            layout,
+            bindings: DEFAULT_BINDINGS.iter().copied().collect(),
+        }
+    }
+
+    /// The bindings `config` asks for: the defaults, for its keyboard
+    /// layout, with whatever `keys.toml` changed.
+    pub fn for_config(config: &Config) -> Keymap {
+        let mut keymap = Keymap::new(config.keyboard_layout);
+        for &(key, action) in &config.keys {
+            let key = key.map_char(|c| keymap.layout.qwerty_of(c));
+            match action {
+                Some(action) => keymap.bindings.insert(key, action),
+                None => keymap.bindings.remove(&key),
+            };
+        }
+        keymap
+    }
+
+    /// What the key pressed in `event` is bound to, if anything.
+    pub fn lookup(&self, event: &SimpleEvent) -> Option<Action> {
+        let key = Key::from_event(event)?.map_char(|c| self.layout.qwerty_of(c));
+        self.bindings.get(&key).copied()
+    }
+
+    /// What the key pressed in `event` is bound to, when Shift was held
+    /// with it too. There's no binding for Shift, so Ctrl+Shift and a
+    /// letter counts as Ctrl+Alt and the letter, which is easier to
+    /// press on terminals which send it, or as just Ctrl and the letter
+    /// if nothing's on Ctrl+Alt.
+    pub fn lookup_shifted(&self, event: &SimpleEvent) -> Option<Action> {
+        match event {
+            SimpleEvent::WithControl(KeyCode::Char(c)) => {
+                let c = KeyCode::Char(c.to_ascii_lowercase());
+                self.lookup(&SimpleEvent::WithControlAlt(c))
+                    .or_else(|| self.lookup(&SimpleEvent::WithControl(c)))
+            }
+            event => self.lookup(event),
+        }
+    }
+
+    /// Every binding, as it's pressed on this layout, in the order
+    /// they're listed in by default.
+    pub fn bindings(&self) -> Vec<(Key, Action)> {
+        let mut bindings: Vec<(Key, Action)> = self
+            .bindings
+            .iter()
+            .map(|(key, action)| (key.map_char(|c| self.layout.typed_at(c)), *action))
+            .collect();
+        bindings.sort_by_key(|(_, action)| {
+            DEFAULT_BINDINGS
+                .iter()
+                .position(|(_, default)| default == action)
+        });
+        bindings
+    }
+
+    /// The key `action` is bound to, as it's pressed on this layout.
+    pub fn key_for(&self, action: Action) -> Option<Key> {
+        self.bindings()
+            .into_iter()
+            .find(|(_, bound)| *bound == action)
+            .map(|(key, _)| key)
+    }
+}
+
+/// A key from `keys.toml`, and what it's bound to, or `None` if it's
+/// unbound.
+pub type Rebinding = (Key, Option<Action>);
+
+/// Reads the bindings in `~/.config/buffers/keys.toml`. A missing file
+/// isn't an error, it just means there aren't any.
+pub fn load_keys() -> Result<Vec<Rebinding>, Box<dyn Error>> {
+    let Some(path) = config_dir().map(|dir| dir.join("keys.toml")) else {
+        return Ok(Vec::new());
+    };
+    match fs::read_to_string(&path) {
+        Ok(text) => parse_keys(&text).map_err(|err| format!("{}: {}", path.display(), err).into()),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(Vec::new()),
+        Err(err) => Err(format!("{}: {}", path.display(), err).into()),
+    }
+}
+
+/// The bindings in a `keys.toml`.
+fn parse_keys(text: &str) -> Result<Vec<Rebinding>, String> {
+    let table: HashMap<String, String> = toml::from_str(text).map_err(|err| err.to_string())?;
+    let mut keys = Vec::new();
+    for (key, action) in table {
+        let action = match action.as_str() {
+            "none" => None,
+            name => Some(
+                Action::by_name(name)
+                    .ok_or_else(|| format!("there's no action called {}", name))?,
+            ),
+        };
+        keys.push((key.parse()?, action));
+    }
+    Ok(keys)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bindings_stay_where_they_are_on_qwerty() {
+        let dvorak = Keymap::new(Layout::Dvorak);
+        // K on Dvorak is where V is on QWERTY.
+        assert_eq!(
+            dvorak.lookup(&SimpleEvent::WithControl(KeyCode::Char('k'))),
+            Some(Action::Paste)
+        );
+        // And V is where . is.
+        assert_eq!(
+            dvorak.lookup(&SimpleEvent::WithControl(KeyCode::Char('v'))),
+            Some(Action::Repeat)
+        );
+        assert_eq!(
+            dvorak.lookup(&SimpleEvent::Just(KeyCode::Enter)),
+            Some(Action::Newline)
+        );
+        // Typing is never remapped.
+        assert_eq!(dvorak.lookup(&SimpleEvent::Just(KeyCode::Char('k'))), None);
+
+        let azerty = Keymap::new(Layout::Azerty);
+        assert_eq!(
+            azerty.lookup(&SimpleEvent::WithControl(KeyCode::Char('a'))),
+            Some(Action::LiteralNext)
+        );
+    }
+
+    #[test]
+    fn ctrl_shift_falls_back_to_ctrl() {
+        let qwerty = Keymap::new(Layout::Qwerty);
+        let shifted = |c| qwerty.lookup_shifted(&SimpleEvent::WithControl(KeyCode::Char(c)));
+        assert_eq!(shifted('D'), Some(Action::DuplicateLine));
+        assert_eq!(shifted('Z'), Some(Action::Undo));
+        assert_eq!(shifted('z'), Some(Action::Undo));
+    }
+
+    #[test]
+    fn bindings_are_shown_as_theyre_pressed() {
+        let colemak = Keymap::new(Layout::Colemak);
+        let save = colemak
+            .bindings()
+            .into_iter()
+            .find(|(_, action)| *action == Action::Save)
+            .unwrap();
+        assert_eq!(save.0.to_string(), "Ctrl+R");
+    }
+
+    #[test]
+    fn keys_toml_rebinds_keys_as_theyre_pressed() {
+        let keys = parse_keys("\"Ctrl+G\" = \"find\"\n\"Ctrl+,\" = \"none\"").unwrap();
+        let config = Config {
+            keyboard_layout: Layout::Dvorak,
+            keys,
+            ..Config::default()
+        };
+        let keymap = Keymap::for_config(&config);
+        assert_eq!(
+            keymap.lookup(&SimpleEvent::WithControl(KeyCode::Char('g'))),
+            Some(Action::Find)
+        );
+        // Ctrl+W on QWERTY is Ctrl+, on Dvorak.
+        assert_eq!(keymap.key_for(Action::Wrap), None);
+        // Everything else is left as it was.
+        assert_eq!(
+            keymap.lookup(&SimpleEvent::WithControl(KeyCode::Char('k'))),
+            Some(Action::Paste)
+        );
+
+        for key in ["Ctrl+Alt+F3", "Alt+F3", "Ctrl+Space", "PageUp", "Ctrl+Left"] {
+            assert_eq!(key.parse::<Key>().unwrap().to_string(), key);
+        }
+        assert!("X".parse::<Key>().is_err());
+        assert!(parse_keys("\"Ctrl+G\" = \"fly\"").is_err());
+    }
+}
@@ -0,0 +1,239 @@
+//! Operations that work on a buffer one line at a time.
+
+use std::collections::HashSet;
+use std::ops::Range;
+
+/// Which duplicates [`dedup`] should remove.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DedupMode {
+    /// Only remove a line if it's the same as the one next to it.
+    Adjacent,
+    /// Remove a line if it appears anywhere else in the range.
+    Global,
+}
+
+/// Removes duplicate lines from `text`. When `keep_last` is set, the
+/// last of each set of duplicates is kept rather than the first.
+///
+/// Returns the new text and how many lines were removed.
+pub fn dedup(text: &str, mode: DedupMode, keep_last: bool) -> (String, usize) {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut kept: Vec<&str> = Vec::new();
+    let mut seen = HashSet::new();
+
+    // Keeping the last copy is the same as keeping the first copy
+    // while walking the lines backwards.
+    let ordered: Box<dyn Iterator<Item = &&str>> = match keep_last {
+        true => Box::new(lines.iter().rev()),
+        false => Box::new(lines.iter()),
+    };
+    for &line in ordered {
+        let duplicate = match mode {
+            DedupMode::Adjacent => kept.last() == Some(&line),
+            DedupMode::Global => !seen.insert(line),
+        };
+        if !duplicate {
+            kept.push(line);
+        }
+    }
+    if keep_last {
+        kept.reverse();
+    }
+
+    let removed = lines.len() - kept.len();
+    (with_newline(kept.join("\n"), text), removed)
+}
+
+/// How [`sort`] orders lines.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SortOptions {
+    /// Largest first, or Z to A.
+    pub reverse: bool,
+    /// By the number each line starts with, with lines which don't start
+    /// with one first.
+    pub numeric: bool,
+    pub ignore_case: bool,
+}
+
+/// Sorts the lines of `text`. Lines which compare the same stay in the
+/// order they were in.
+pub fn sort(text: &str, options: SortOptions) -> String {
+    let mut lines: Vec<&str> = text.lines().collect();
+    let number = |line: &str| {
+        let line = line.trim_start();
+        let end = line
+            .char_indices()
+            .find(|&(i, c)| !(c.is_ascii_digit() || c == '.' || (i == 0 && c == '-')))
+            .map_or(line.len(), |(i, _)| i);
+        line[..end].parse::<f64>().ok()
+    };
+    lines.sort_by(|a, b| {
+        let order = match options {
+            SortOptions { numeric: true, .. } => number(a)
+                .partial_cmp(&number(b))
+                .unwrap_or(std::cmp::Ordering::Equal),
+            SortOptions {
+                ignore_case: true, ..
+            } => a.to_lowercase().cmp(&b.to_lowercase()),
+            _ => a.cmp(b),
+        };
+        match options.reverse {
+            true => order.reverse(),
+            false => order,
+        }
+    });
+    with_newline(lines.join("\n"), text)
+}
+
+/// The lines of `text` the other way up.
+pub fn reverse(text: &str) -> String {
+    let lines: Vec<&str> = text.lines().rev().collect();
+    with_newline(lines.join("\n"), text)
+}
+
+/// `result`, ending with a newline if `text` did.
+fn with_newline(mut result: String, text: &str) -> String {
+    if text.ends_with('\n') {
+        result.push('\n');
+    }
+    result
+}
+
+/// `text` tidied up to be saved: with the spaces and tabs at the ends of
+/// its lines taken off if `trim_trailing_whitespace` is set, and ending
+/// with a newline if `insert_final_newline` is.
+pub fn tidy(text: &str, trim_trailing_whitespace: bool, insert_final_newline: bool) -> String {
+    let mut result = match trim_trailing_whitespace {
+        true => text
+            .split('\n')
+            .map(|line| {
+                let (line, cr) = match line.strip_suffix('\r') {
+                    Some(line) => (line, "\r"),
+                    None => (line, ""),
+                };
+                format!("{}{}", line.trim_end_matches([' ', '\t']), cr)
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        false => String::from(text),
+    };
+    if insert_final_newline && !result.is_empty() && !result.ends_with('\n') {
+        result.push('\n');
+    }
+    result
+}
+
+/// The lines of `text` which contain `pattern` (or, when `invert` is set,
+/// the lines which don't), along with their 1-indexed line numbers.
+pub fn filter<'a>(text: &'a str, pattern: &str, invert: bool) -> Vec<(usize, &'a str)> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| line.contains(pattern) != invert)
+        .map(|(index, line)| (index + 1, line))
+        .collect()
+}
+
+/// The (0-indexed) lines of the paragraph line `line` of `text` is in:
+/// the lines either side of it up to a blank one. A blank line is a
+/// paragraph of its own.
+pub fn paragraph(text: &str, line: usize) -> Range<usize> {
+    let lines: Vec<&str> = text.lines().collect();
+    let blank = |line: &&str| line.trim().is_empty();
+    if lines.get(line).is_none_or(blank) {
+        return line..line + 1;
+    }
+    let start = lines[..line].iter().rposition(blank).map_or(0, |i| i + 1);
+    let end = lines[line..]
+        .iter()
+        .position(blank)
+        .map_or(lines.len(), |i| line + i);
+    start..end
+}
+
+/// Wraps the words of `text` into lines no longer than `width` chars,
+/// unless a word is longer on its own. Every line starts with the first
+/// line's indent.
+pub fn reflow(text: &str, width: usize) -> String {
+    let first = text.lines().next().unwrap_or_default();
+    let indent = &first[..first.len() - first.trim_start().len()];
+    let mut lines: Vec<String> = Vec::new();
+    let mut line = String::from(indent);
+    for word in text.split_whitespace() {
+        let len = line.chars().count();
+        if len > indent.len() && len + 1 + word.chars().count() > width {
+            lines.push(std::mem::replace(&mut line, String::from(indent)));
+        }
+        if line.len() > indent.len() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    lines.push(line);
+    with_newline(lines.join("\n"), text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_sort_by_text_or_number() {
+        let text = "b\n10 x\nA\n9 y\na\n";
+        assert_eq!(sort(text, SortOptions::default()), "10 x\n9 y\nA\na\nb\n");
+        let numeric = SortOptions {
+            numeric: true,
+            ..SortOptions::default()
+        };
+        assert_eq!(sort(text, numeric), "b\nA\na\n9 y\n10 x\n");
+        let options = SortOptions {
+            reverse: true,
+            ignore_case: true,
+            ..SortOptions::default()
+        };
+        assert_eq!(sort("a\nB\nc", options), "c\nB\na");
+        assert_eq!(reverse("one\ntwo\n"), "two\none\n");
+    }
+
+    #[test]
+    fn duplicates_are_removed_next_to_each_other_or_anywhere() {
+        let text = "a\na\nb\na\n";
+        assert_eq!(
+            dedup(text, DedupMode::Adjacent, false),
+            (String::from("a\nb\na\n"), 1)
+        );
+        assert_eq!(
+            dedup(text, DedupMode::Global, false),
+            (String::from("a\nb\n"), 2)
+        );
+        assert_eq!(
+            dedup(text, DedupMode::Global, true),
+            (String::from("b\na\n"), 2)
+        );
+        assert_eq!(
+            dedup("x\ny\nx", DedupMode::Adjacent, true),
+            (String::from("x\ny\nx"), 0)
+        );
+    }
+
+    #[test]
+    fn text_is_tidied_for_saving() {
+        let text = "one  \n\t\ntwo\t \r\nthree ";
+        assert_eq!(tidy(text, true, false), "one\n\ntwo\r\nthree");
+        assert_eq!(tidy(text, false, true), "one  \n\t\ntwo\t \r\nthree \n");
+        assert_eq!(tidy("", true, true), "");
+        assert_eq!(tidy("done\n", true, true), "done\n");
+    }
+
+    #[test]
+    fn paragraphs_are_reflowed_to_a_width() {
+        let text = "title\n\n  one two three\n  four five six seven\n\nend\n";
+        assert_eq!(paragraph(text, 3), 2..4);
+        assert_eq!(paragraph(text, 1), 1..2);
+        assert_eq!(paragraph(text, 0), 0..1);
+
+        let lines: Vec<&str> = text.lines().collect();
+        let reflowed = reflow(&(lines[2..4].join("\n") + "\n"), 12);
+        assert_eq!(reflowed, "  one two\n  three four\n  five six\n  seven\n");
+        assert_eq!(reflow("a verylongword b", 4), "a\nverylongword\nb");
+    }
+}
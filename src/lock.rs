@@ -0,0 +1,211 @@
+//! Advisory lock files, so two BuffeRS sessions don't edit the same file
+//! without knowing about each other.
+//!
+//! The lock for `notes.txt` is `.notes.txt.buffers-lock` next to it, and
+//! says which process (and on which host) holds it. Nothing stops
+//! another program from editing a locked file; it's only a warning.
+
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{self, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+/// Who holds a lock.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Owner {
+    pub pid: u32,
+    pub host: String,
+}
+
+impl Owner {
+    /// This process.
+    fn me() -> Owner {
+        Owner {
+            pid: std::process::id(),
+            host: hostname(),
+        }
+    }
+
+    fn parse(text: &str) -> Option<Owner> {
+        let (pid, host) = text.trim().split_once('@')?;
+        Some(Owner {
+            pid: pid.parse().ok()?,
+            host: String::from(host),
+        })
+    }
+
+    /// Whether the owner is a process on this host that has since
+    /// exited, in which case the lock was left behind by a crash.
+    fn is_gone(&self) -> bool {
+        self.host == hostname() && !Path::new(&format!("/proc/{}", self.pid)).exists()
+    }
+}
+
+impl fmt::Display for Owner {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}@{}", self.pid, self.host)
+    }
+}
+
+/// Why a lock couldn't be taken.
+#[derive(Debug)]
+pub enum LockError {
+    /// Someone else has it.
+    Held(Owner),
+    Io(io::Error),
+}
+
+impl fmt::Display for LockError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LockError::Held(owner) => write!(
+                f,
+                "it may be open in another BuffeRS (process {} on {})",
+                owner.pid, owner.host
+            ),
+            LockError::Io(err) => write!(f, "couldn't lock it: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for LockError {}
+
+impl From<io::Error> for LockError {
+    fn from(err: io::Error) -> LockError {
+        LockError::Io(err)
+    }
+}
+
+/// A lock this process holds on a file. It's released when dropped.
+#[derive(Debug)]
+pub struct Lock {
+    path: PathBuf,
+}
+
+impl Lock {
+    /// Locks `file`, unless someone else already has. Locks left behind
+    /// by processes which have exited are taken over.
+    pub fn acquire(file: &Path) -> Result<Lock, LockError> {
+        let path = lock_path(file);
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut lock_file) => {
+                    write!(lock_file, "{}", Owner::me())?;
+                    return Ok(Lock { path });
+                }
+                Err(err) if err.kind() == ErrorKind::AlreadyExists => {
+                    let owner = fs::read_to_string(&path)
+                        .ok()
+                        .and_then(|text| Owner::parse(&text));
+                    match owner {
+                        Some(owner) if owner == Owner::me() => return Ok(Lock { path }),
+                        Some(owner) if !owner.is_gone() => return Err(LockError::Held(owner)),
+                        // Stale or unreadable, so it's not protecting anything.
+                        _ => fs::remove_file(&path)?,
+                    }
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+
+    /// Takes the lock on `file` whoever holds it.
+    pub fn steal(file: &Path) -> io::Result<Lock> {
+        let path = lock_path(file);
+        fs::write(&path, Owner::me().to_string())?;
+        Ok(Lock { path })
+    }
+}
+
+impl Drop for Lock {
+    fn drop(&mut self) {
+        // Only remove the lock if it's still ours; it may have been stolen.
+        let ours = fs::read_to_string(&self.path)
+            .ok()
+            .and_then(|text| Owner::parse(&text))
+            .is_some_and(|owner| owner == Owner::me());
+        if ours {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// Where the lock for `file` goes.
+fn lock_path(file: &Path) -> PathBuf {
+    let name = file.file_name().unwrap_or_default().to_string_lossy();
+    file.with_file_name(format!(".{}.buffers-lock", name))
+}
+
+fn hostname() -> String {
+    fs::read_to_string("/proc/sys/kernel/hostname")
+        .ok()
+        .or_else(|| std::env::var("HOSTNAME").ok())
+        .map(|host| String::from(host.trim()))
+        .unwrap_or_else(|| String::from("localhost"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::harness::TempFile;
+
+    #[test]
+    fn a_lock_is_refused_while_its_holder_runs_and_released_when_dropped() {
+        let file = TempFile::new("locked", "");
+        let path = lock_path(file.path());
+        let lock = Lock::acquire(file.path()).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), Owner::me().to_string());
+        // This process taking it again is fine.
+        drop(Lock::acquire(file.path()).unwrap());
+        drop(lock);
+        assert!(!path.exists());
+
+        // Process 1 is always running.
+        let other = Owner {
+            pid: 1,
+            host: hostname(),
+        };
+        fs::write(&path, other.to_string()).unwrap();
+        match Lock::acquire(file.path()) {
+            Err(LockError::Held(owner)) => assert_eq!(owner, other),
+            result => panic!("{:?}", result),
+        }
+        // A process on another host can't be checked on, so it's held too.
+        fs::write(&path, format!("{}@elsewhere", std::process::id())).unwrap();
+        assert!(matches!(
+            Lock::acquire(file.path()),
+            Err(LockError::Held(_))
+        ));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn a_lock_left_by_a_process_which_exited_is_taken_over() {
+        let file = TempFile::new("stale", "");
+        let path = lock_path(file.path());
+        fs::write(&path, format!("{}@{}", u32::MAX, hostname())).unwrap();
+        let lock = Lock::acquire(file.path()).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), Owner::me().to_string());
+        drop(lock);
+
+        fs::write(&path, "not a lock").unwrap();
+        drop(Lock::acquire(file.path()).unwrap());
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn a_stolen_lock_is_left_alone_by_the_one_it_was_stolen_from() {
+        let file = TempFile::new("stolen", "");
+        let path = lock_path(file.path());
+        fs::write(&path, "1@elsewhere").unwrap();
+        let lock = Lock::steal(file.path()).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), Owner::me().to_string());
+
+        // Someone steals it back, so dropping ours mustn't remove theirs.
+        fs::write(&path, "1@elsewhere").unwrap();
+        drop(lock);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "1@elsewhere");
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,115 @@
+//! The log, at `~/.local/share/buffers/buffers.log`, which says what
+//! commands were run, how long they took, and what went wrong.
+//!
+//! When the log gets too big it's moved aside to `buffers.log.1` (and
+//! that to `buffers.log.2`, and so on), so it never takes up more than
+//! [`MAX_SIZE`] times [`KEEP`]. How much is logged is set with
+//! `BUFFERS_LOG`, as one of `error`, `warn`, `info` (the default),
+//! `debug` or `trace`.
+
+use tracing::Level;
+use tracing_subscriber::fmt::MakeWriter;
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::config::data_dir;
+
+/// How big the log grows before it's moved aside.
+pub const MAX_SIZE: u64 = 1024 * 1024;
+
+/// How many old logs are kept, besides the current one.
+pub const KEEP: usize = 3;
+
+/// Where the log is written.
+pub fn path() -> Option<PathBuf> {
+    Some(data_dir()?.join("buffers.log"))
+}
+
+/// Starts logging to the log file. Returns the file's path, or `None`
+/// if there's nowhere to put it.
+pub fn init() -> Option<PathBuf> {
+    let path = path()?;
+    fs::create_dir_all(path.parent()?).ok()?;
+    let level = std::env::var("BUFFERS_LOG")
+        .ok()
+        .and_then(|level| level.parse::<Level>().ok())
+        .unwrap_or(Level::INFO);
+    tracing_subscriber::fmt()
+        .with_writer(RollingFile { path: path.clone() })
+        .with_max_level(level)
+        .with_ansi(false)
+        .with_target(false)
+        .try_init()
+        .ok()?;
+    Some(path)
+}
+
+/// A log file which is moved aside once it's [`MAX_SIZE`].
+struct RollingFile {
+    path: PathBuf,
+}
+
+impl RollingFile {
+    /// Moves the log aside if it's too big, then opens it for appending.
+    fn open(&self) -> io::Result<File> {
+        if fs::metadata(&self.path).is_ok_and(|meta| meta.len() >= MAX_SIZE) {
+            for n in (1..KEEP).rev() {
+                let _ = fs::rename(numbered(&self.path, n), numbered(&self.path, n + 1));
+            }
+            fs::rename(&self.path, numbered(&self.path, 1))?;
+        }
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+    }
+}
+
+/// `path` with `.n` on the end.
+fn numbered(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", n));
+    PathBuf::from(name)
+}
+
+impl<'a> MakeWriter<'a> for RollingFile {
+    type Writer = Box<dyn Write>;
+
+    /// The file is opened once for each line logged, which is plenty
+    /// fast for how little is logged, and means nothing's lost if
+    /// BuffeRS crashes.
+    fn make_writer(&'a self) -> Self::Writer {
+        match self.open() {
+            Ok(file) => Box::new(file),
+            // Nothing can be done about not being able to log.
+            Err(_) => Box::new(io::sink()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_full_log_is_moved_aside() {
+        let dir = std::env::temp_dir().join(format!("buffers-log-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let log = RollingFile {
+            path: dir.join("buffers.log"),
+        };
+        fs::write(&log.path, vec![b'x'; MAX_SIZE as usize]).unwrap();
+        fs::write(numbered(&log.path, 1), "older").unwrap();
+
+        log.make_writer().write_all(b"new\n").unwrap();
+        assert_eq!(fs::read_to_string(&log.path).unwrap(), "new\n");
+        assert_eq!(
+            fs::metadata(numbered(&log.path, 1)).unwrap().len(),
+            MAX_SIZE
+        );
+        assert_eq!(fs::read_to_string(numbered(&log.path, 2)).unwrap(), "older");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
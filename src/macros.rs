@@ -0,0 +1,213 @@
+//! Keyboard macros. F5 and a letter starts recording every key pressed
+//! after it into the macro of that name, and F5 again stops. F6 and the
+//! letter presses the keys again, just as if they were typed, and digits
+//! typed before the letter play it that many times, so F6 3 a plays `a`
+//! three times. F6 and Enter plays whichever macro was recorded last.
+//!
+//! Macros belong to the session, so they're kept while switching
+//! buffers, and one recorded in one buffer can be played in another.
+
+use termgame::{Controller, Game, GameEvent, KeyCode, Message, SimpleEvent};
+
+use std::collections::BTreeMap;
+
+use crate::i18n::tr;
+use crate::registers::Registers;
+use crate::BufferEditor;
+
+/// How deep macros can play other macros, so one that plays itself
+/// doesn't go on forever.
+const MAX_DEPTH: usize = 8;
+
+/// Every macro recorded this session, and the one being recorded.
+#[derive(Debug, Default)]
+pub struct Macros {
+    named: BTreeMap<char, Vec<GameEvent>>,
+    /// The macro being recorded, and the keys pressed so far.
+    recording: Option<(char, Vec<GameEvent>)>,
+    /// The macro recorded last, which F6 and Enter plays.
+    last: Option<char>,
+}
+
+impl Macros {
+    pub fn get(&self, name: char) -> Option<&[GameEvent]> {
+        self.named.get(&name).map(Vec::as_slice)
+    }
+
+    pub fn last(&self) -> Option<char> {
+        self.last
+    }
+
+    /// The name of the macro being recorded.
+    pub fn recording(&self) -> Option<char> {
+        self.recording.as_ref().map(|(name, _)| *name)
+    }
+
+    /// Starts recording the macro `name`.
+    pub fn start(&mut self, name: char) {
+        self.recording = Some((name, Vec::new()));
+    }
+
+    /// Adds `key` to the macro being recorded, if there is one.
+    pub fn record(&mut self, key: GameEvent) {
+        if let Some((_, keys)) = &mut self.recording {
+            keys.push(key);
+        }
+    }
+
+    /// Stops recording, leaving out the key which stopped it, and returns
+    /// the macro's name and how many keys it has.
+    pub fn stop(&mut self) -> Option<(char, usize)> {
+        let (name, mut keys) = self.recording.take()?;
+        keys.pop();
+        let len = keys.len();
+        self.named.insert(name, keys);
+        self.last = Some(name);
+        Some((name, len))
+    }
+}
+
+/// What the key after F5 or F6 is for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MacroKey {
+    Record,
+    /// Playing a macro, as many times as the digits typed so far say.
+    Play {
+        count: usize,
+    },
+}
+
+impl BufferEditor {
+    /// Keeps `key` in the macro being recorded. Keys a macro presses as
+    /// it plays aren't kept, since the keys which played it are.
+    pub fn record_macro_key(&mut self, key: GameEvent) {
+        if self.replaying == 0 {
+            self.session.macros.record(key);
+        }
+    }
+
+    /// Stops recording for F5, or asks which macro to record.
+    pub fn record_macro(&mut self, game: &mut Game) {
+        let message = match self.session.macros.stop() {
+            Some((name, keys)) => tr("editor.macro-recorded", &[("name", &name), ("keys", &keys)]),
+            None => {
+                self.macro_next = Some(MacroKey::Record);
+                tr("editor.which-macro", &[])
+            }
+        };
+        game.set_message(Some(Message::new(message)));
+    }
+
+    /// Asks which macro to play, for F6.
+    pub fn play_macro(&mut self, game: &mut Game) {
+        self.macro_next = Some(MacroKey::Play { count: 0 });
+        let message = tr("editor.which-macro", &[]);
+        game.set_message(Some(Message::new(message)));
+    }
+
+    /// Handles the key pressed after F5 or F6, which names the macro.
+    pub fn macro_event(&mut self, game: &mut Game, key: MacroKey, event: &SimpleEvent) {
+        game.set_message(None);
+        let name = match (key, event) {
+            (MacroKey::Play { count }, SimpleEvent::Just(KeyCode::Char(digit)))
+                if digit.is_ascii_digit() =>
+            {
+                let digit = digit.to_digit(10).unwrap_or(0) as usize;
+                let count = count.saturating_mul(10).saturating_add(digit);
+                self.macro_next = Some(MacroKey::Play { count });
+                let message = tr("editor.which-macro-times", &[("count", &count)]);
+                game.set_message(Some(Message::new(message)));
+                return;
+            }
+            (_, SimpleEvent::Just(KeyCode::Char(name))) if Registers::is_name(*name) => *name,
+            (MacroKey::Play { .. }, SimpleEvent::Just(KeyCode::Enter)) => {
+                match self.session.macros.last() {
+                    Some(name) => name,
+                    None => {
+                        let message = tr("editor.no-macros", &[]);
+                        game.set_message(Some(Message::new(message)));
+                        return;
+                    }
+                }
+            }
+            (_, SimpleEvent::Just(KeyCode::Esc)) => return,
+            _ => {
+                let message = tr("editor.no-such-macro", &[]);
+                game.set_message(Some(Message::new(message)));
+                return;
+            }
+        };
+        match key {
+            MacroKey::Record => {
+                self.session.macros.start(name);
+                let message = tr("editor.recording-macro", &[("name", &name)]);
+                game.set_message(Some(Message::new(message)));
+            }
+            MacroKey::Play { count } => self.replay(game, name, count.max(1)),
+        }
+    }
+
+    /// Presses the keys of the macro `name` `count` times over, as if
+    /// they were typed. It stops if they close the editor.
+    fn replay(&mut self, game: &mut Game, name: char, count: usize) {
+        let Some(keys) = self.session.macros.get(name).map(<[_]>::to_vec) else {
+            let message = tr("editor.macro-not-recorded", &[("name", &name)]);
+            game.set_message(Some(Message::new(message)));
+            return;
+        };
+        if self.replaying == MAX_DEPTH {
+            let message = tr("editor.macro-too-deep", &[("name", &name)]);
+            game.set_message(Some(Message::new(message)));
+            return;
+        }
+        // Ctrl+. makes the macro's last edit again, not the key which
+        // played it.
+        self.repeat.forget();
+        self.replaying += 1;
+        'replay: for _ in 0..count {
+            for key in &keys {
+                if game.game_will_end() {
+                    break 'replay;
+                }
+                self.on_event(game, key.clone());
+            }
+        }
+        self.replaying -= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use termgame::KeyCode;
+
+    use crate::harness::{key, Harness};
+
+    #[test]
+    fn f5_records_keys_and_f6_plays_them_back() {
+        let mut harness = Harness::new("one\ntwo\nthree\nfour\n");
+        harness.press(KeyCode::F(5)).type_text("a");
+        assert!(harness.status_line().ends_with("| recording a"));
+        harness
+            .type_text("- ")
+            .run([key(KeyCode::Home), key(KeyCode::Down), key(KeyCode::F(5))]);
+        assert_eq!(harness.message(), Some("Recorded 4 keys as macro a"));
+        assert_eq!(harness.text(), "- one\ntwo\nthree\nfour\n");
+
+        harness.press(KeyCode::F(6)).type_text("a");
+        assert_eq!(harness.text(), "- one\n- two\nthree\nfour\n");
+        harness.press(KeyCode::F(6)).type_text("2\n");
+        assert_eq!(harness.text(), "- one\n- two\n- three\n- four\n");
+
+        // A macro can play another one.
+        harness.editor.buffer.set_cursor(0);
+        harness.press(KeyCode::F(5)).type_text("b");
+        harness.run([key(KeyCode::F(6))]).type_text("a");
+        harness.press(KeyCode::F(5));
+        assert_eq!(harness.text(), "- - one\n- two\n- three\n- four\n");
+        harness.press(KeyCode::F(6)).type_text("b");
+        assert_eq!(harness.text(), "- - one\n- - two\n- three\n- four\n");
+
+        harness.press(KeyCode::F(6)).type_text("z");
+        assert_eq!(harness.message(), Some("There's no macro z yet"));
+    }
+}
@@ -1,243 +1,1249 @@
+mod abbrev;
+mod announce;
+mod autosave;
+mod backup;
+mod brackets;
+mod buffer;
+mod case;
+mod clipboard;
+mod clock;
+mod commands;
+mod comment;
+mod complete;
+mod config;
+mod cursors;
+mod dashboard;
+mod dates;
+mod diff;
+mod dired;
+mod encoding;
+mod eol;
+mod find;
+mod git;
+mod goto;
+#[cfg(any(test, feature = "test-harness"))]
+#[cfg_attr(not(test), allow(dead_code))]
+mod harness;
+mod help;
+mod hex;
+mod history;
+mod huge;
+mod i18n;
+mod json;
+mod keymap;
+mod lines;
+mod lock;
+mod logging;
+mod macros;
+mod metrics;
+mod pane;
+mod paths;
+mod pipe;
+mod play;
+mod project;
+mod prompt;
+mod recent;
+mod record;
+mod registers;
+mod render;
+mod repeat;
+mod replace;
+mod rope;
+mod save;
+mod search;
+mod sessions;
+mod snapshot;
+mod split;
+mod stats;
+mod status;
+mod stress;
+mod swap;
+mod tabs;
+mod theme;
+mod transform;
+mod trash;
+mod tutor;
+mod units;
+mod vim;
+mod watch;
+mod wordcount;
+mod words;
+mod wrap;
+
 use termgame::{
-    run_game, CharChunkMap, Controller, Game, GameEvent, GameSettings, KeyCode, SimpleEvent,
+    Controller, Game, GameEvent, KeyCode, KeyModifiers, Message, SimpleEvent, ViewportLocation,
 };
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::error::Error;
-use std::time::Duration;
+use std::io::IsTerminal;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use announce::Announcer;
+use autosave::Autosave;
+use buffer::Buffer;
+use clipboard::{Clip, Clipboard, SystemClipboard};
+use clock::Clock;
+use commands::Registry;
+use complete::ReplHelper;
+use config::Config;
+use cursors::CursorEdit;
+use dashboard::Dashboard;
+use dired::{display_path, Directory};
+use encoding::Encoding;
+use eol::LineEnding;
+use find::Find;
+use huge::HugeFile;
+use i18n::tr;
+use keymap::{Action, Keymap};
+use lock::Lock;
+use macros::{MacroKey, Macros};
+use metrics::Metrics;
+use pane::Area;
+use play::Playback;
+use project::FileChange;
+use prompt::{Prompt, PromptResult, Question};
+use record::Recording;
+use registers::Registers;
+use render::Drawn;
+use repeat::Repeat;
+use snapshot::Schedule;
+use stats::TypingStats;
+use swap::Swap;
+use theme::Theme;
+use tutor::Tutor;
+use vim::{EditingMode, Mode, Vim};
+use watch::FileWatch;
+use wordcount::WordCount;
+
+/// Everything BuffeRS knows about, which REPL commands can look at and change.
+struct App {
+    editors: HashMap<String, BufferEditor>,
+    config: Rc<Config>,
+    session: Session,
+    /// Commands to run before the next one is read, as if they'd been
+    /// typed, like the files BuffeRS was started with.
+    queued: VecDeque<String>,
+    /// Answers to the questions commands ask, taken before any are read
+    /// from standard input, as `queued` is for commands.
+    answers: VecDeque<String>,
+    /// The changes `replace-all` has planned, by the name of the buffer
+    /// previewing them.
+    pending_replacements: HashMap<String, Vec<FileChange>>,
+    /// Set by `quit`, so the REPL stops once the command's done.
+    quit: bool,
+    /// What each alias `alias` made stands for.
+    aliases: BTreeMap<String, String>,
+    /// Every command, for the ones which run or describe others.
+    registry: Rc<Registry>,
+    /// The scripts `source` is running, innermost last.
+    sourcing: Vec<PathBuf>,
+}
+
+/// State which belongs to the whole session rather than any one buffer.
+/// It's lent to whichever editor is open.
+#[derive(Default)]
+struct Session {
+    /// When the user has opted in to typing statistics, the stats for this session.
+    typing_stats: Option<TypingStats>,
+    clipboard: Clipboard,
+    system_clipboard: SystemClipboard,
+    registers: Registers,
+    macros: Macros,
+    clock: Clock,
+    /// The cast file being recorded into, if `record start` was run.
+    recording: Option<Recording>,
+    /// Usage counts, when they're turned on in the config.
+    metrics: Option<Metrics>,
+    /// Where screen-reader mode says what's happening, when it's on.
+    announcer: Option<Announcer>,
+    /// What every editor is drawn in, as `theme` last set it.
+    theme: Theme,
+    /// Whether editors show line numbers, as `set number` last left it.
+    line_numbers: bool,
+    /// How keys edit, as `set mode` last left it.
+    editing_mode: EditingMode,
+    /// Whether Enter carries the line's indent onto the next, as `set
+    /// autoindent` last left it.
+    autoindent: bool,
+    /// Whether saving takes the whitespace off the ends of lines, as `set
+    /// trim_trailing_whitespace` last left it.
+    trim_trailing_whitespace: bool,
+    /// Whether saving ends the text with a newline, as `set
+    /// insert_final_newline` last left it.
+    insert_final_newline: bool,
+}
 
-/// This is a single "buffer".
-struct Buffer {
-    text: String,
-    file: Option<String>,
+/// This struct implements all the
+/// logic for how the editor should work. It
+/// implements "Controller", which defines how
+/// something should interact with the terminal.
+struct BufferEditor {
+    /// The name the buffer goes by at the REPL.
+    name: String,
+    buffer: Buffer,
+    /// Where the viewport was left, so reopening a buffer
+    /// picks up from the same place.
+    viewport: ViewportLocation,
+    /// The session, lent to this editor while it's open.
+    session: Session,
+    config: Rc<Config>,
+    keymap: Keymap,
+    /// Set by Ctrl+Q, so the next key is typed as-is, without expanding
+    /// an abbreviation.
+    literal_next: bool,
+    /// Set by Ctrl+R, so the next key names a register.
+    register_next: bool,
+    /// Whether the next key names a macro to record or play, after F5 or
+    /// F6.
+    macro_next: Option<MacroKey>,
+    /// How many macros are playing, one inside another.
+    replaying: usize,
+    /// The keys of the last edit, for Ctrl+. to make again.
+    repeat: Repeat,
+    /// Whether the last edit's being made again.
+    repeating: bool,
+    /// The register the next copy, cut, delete or paste uses, instead
+    /// of the clipboard.
+    register: Option<char>,
+    /// When the buffer is next due a snapshot.
+    snapshots: Schedule,
+    /// When the buffer was last autosaved.
+    autosave: Autosave,
+    /// The swap file unsaved changes are written to, once there are any.
+    swap: Option<Swap>,
+    /// Watches the buffer's file, if it has one, for changes made
+    /// outside BuffeRS.
+    watch: Option<FileWatch>,
+    /// Whether to reload the buffer when its file changes, as long as
+    /// there's nothing unsaved to lose.
+    auto_revert: bool,
+    /// The lock on the buffer's file, held for as long as it's open.
+    lock: Option<Lock>,
+    /// Set when someone else holds the lock, so nothing can be typed.
+    read_only: bool,
+    /// For a directory buffer, what's listed in it.
+    directory: Option<Directory>,
+    /// For the dashboard, what can be opened from it.
+    dashboard: Option<Dashboard>,
+    /// For a file too big to load, the part of it that's loaded.
+    huge: Option<HugeFile>,
+    /// A question being asked in the status bar, and what to do with
+    /// the answer.
+    prompt: Option<(Prompt, Question)>,
+    /// The buffer to open once this editor closes, when it's asked to
+    /// switch to another.
+    open_next: Option<String>,
+    /// A command to run at the REPL once this editor closes.
+    then_run: Option<String>,
+    /// A recording being played back into the buffer, if `play` was run.
+    playback: Option<Playback>,
+    /// Keeps the end of the buffer in view as its file grows, like
+    /// `tail -f`.
+    follow: bool,
+    /// For the `tutor` buffer, which lesson it's up to.
+    tutor: Option<Tutor>,
+    /// Whether the buffer is a list of changes, with `+` and `-` lines.
+    shows_changes: bool,
+    /// How each line differs from the last commit of the buffer's file,
+    /// when it's in a git repository.
+    git_marks: Option<git::Marks>,
+    /// The file as it was in the last commit, which `git_marks` are
+    /// worked out against.
+    git_head: git::Head,
+    /// Who last changed each line, shown down the left, when `blame` is
+    /// on.
+    blame: Option<Vec<String>>,
+    /// The buffer's revision `blame` was worked out for.
+    blame_revision: usize,
+    /// What's on screen, so only what changes is drawn again.
+    drawn: Drawn,
+    /// What Ctrl+F last found, for F3 to find again.
+    find: Option<Find>,
+    /// Whether long lines are wrapped onto the rows under them.
+    wrap: bool,
+    /// Where each line's rows start, when they're wrapped.
+    rows: wrap::Rows,
+    /// The mode, and any command half typed, in vim mode.
+    vim: Vim,
+    /// The cursors Ctrl+D added besides the buffer's own, each running
+    /// from its selection's anchor to the cursor.
+    cursors: Vec<Range<usize>>,
+    /// Which side of a split the editor is drawn on, when it isn't drawn
+    /// on the whole screen.
+    pane: Option<Area>,
 }
 
-impl Buffer {
-    /// This creates a new Buffer, to use it you should run:
-    /// ```rust
-    /// Buffer::new()
-    /// ```
-    fn new(file: Option<String>) -> Buffer {
-        Buffer {
-            text: String::new(),
-            file,
+impl BufferEditor {
+    fn new(name: String, mut buffer: Buffer, config: Rc<Config>) -> BufferEditor {
+        buffer.set_tab_width(config.tab_width);
+        buffer.set_undo_limit(config.undo_limit);
+        BufferEditor {
+            name,
+            snapshots: Schedule::new(buffer.revision()),
+            autosave: Autosave::default(),
+            swap: None,
+            watch: buffer.file().map(FileWatch::new),
+            auto_revert: config.auto_revert,
+            lock: None,
+            read_only: false,
+            directory: None,
+            dashboard: None,
+            huge: None,
+            prompt: None,
+            open_next: None,
+            then_run: None,
+            playback: None,
+            follow: false,
+            tutor: None,
+            shows_changes: false,
+            git_marks: None,
+            git_head: git::Head::default(),
+            blame: None,
+            blame_revision: 0,
+            drawn: Drawn::new(),
+            find: None,
+            wrap: config.wrap,
+            rows: wrap::Rows::default(),
+            vim: Vim::default(),
+            cursors: Vec::new(),
+            pane: None,
+            keymap: Keymap::for_config(&config),
+            buffer,
+            viewport: ViewportLocation { x: 0, y: 0 },
+            session: Session::default(),
+            config,
+            literal_next: false,
+            register_next: false,
+            macro_next: None,
+            replaying: 0,
+            repeat: Repeat::default(),
+            repeating: false,
+            register: None,
         }
     }
 
-    /// A [`CharChunkMap`] is how termgame stores characters.
-    /// This converts a buffer into something which can be shown on screen.
-    /// You will likely not need to change this function.
-    fn chunkmap_from_textarea(&mut self, map: &mut CharChunkMap) {
-        let (mut line, mut col) = (0, 0);
-        for c in self.text.chars() {
-            map.insert(col, line, c.into());
-            col += 1;
-            if c == '\n' {
-                line += 1;
-                col = 0;
+    /// Types a character. If it ends a word, and that word is an
+    /// abbreviation, the abbreviation is expanded first. Brackets and
+    /// quotes are closed automatically.
+    fn type_char(&mut self, c: char) {
+        if std::mem::take(&mut self.literal_next) {
+            self.buffer.insert_char(c);
+            return;
+        }
+
+        if !abbrev::is_word_char(c) {
+            // Words don't run across lines, so only the cursor's line is
+            // looked at.
+            let cursor = self.buffer.cursor();
+            let line = self.buffer.cursor_line();
+            let start = self.buffer.line_span(line..line).start;
+            let before = self.buffer.slice(start..cursor);
+            if let Some((word, expansion)) =
+                abbrev::find_expansion(&before, before.len(), &self.config.abbreviations)
+            {
+                // A single edit, so it's undone in one step.
+                self.buffer
+                    .replace_range(start + word.start..start + word.end, expansion);
             }
         }
+
+        let pairs = self.config.pairs_for(self.buffer.extension());
+        if self.buffer.char_after_cursor() == Some(c) && pairs.iter().any(|&(_, close)| close == c)
+        {
+            // Typing a closer that's already there just steps over it.
+            self.buffer.move_right();
+        } else if let Some(&(open, close)) = pairs.iter().find(|&&(open, _)| open == c) {
+            self.buffer.insert(&format!("{}{}", open, close));
+            self.buffer.move_left();
+        } else {
+            self.buffer.insert_char(c);
+        }
     }
 
-    /// Adds a char to the end of the buffer.
-    fn push_char(&mut self, c: char) {
-        self.text.push(c);
+    /// Starts a new line. With `set autoindent`, it's indented as far as
+    /// the one the cursor was on, and one step further after an opening
+    /// bracket, or a `:` in Python or YAML. Enter between a pair of
+    /// brackets puts the closer on a line of its own.
+    fn newline(&mut self) {
+        if !self.session.autoindent || self.literal_next {
+            self.type_char('\n');
+            return;
+        }
+        let line = self.buffer.cursor_line();
+        let start = self.buffer.line_span(line..line).start;
+        let before = self.buffer.slice(start..self.buffer.cursor());
+        let indent = &before[..before.len() - before.trim_start().len()];
+        let indent = String::from(indent);
+        let opens = match self.buffer.extension() {
+            Some("py" | "yaml" | "yml") => ['(', '[', '{', ':'].as_slice(),
+            _ => ['(', '[', '{'].as_slice(),
+        };
+        let opened = before.trim_end().chars().next_back();
+        let step = match self.config.expand_tab {
+            true => " ".repeat(self.config.tab_width),
+            false => String::from("\t"),
+        };
+        self.type_char('\n');
+        match opened.filter(|c| opens.contains(c)) {
+            Some(open) => {
+                self.buffer.insert(&format!("{}{}", indent, step));
+                let pairs = self.config.pairs_for(self.buffer.extension());
+                if pairs.contains(&(open, self.buffer.char_after_cursor().unwrap_or(' '))) {
+                    let cursor = self.buffer.cursor();
+                    self.buffer.insert(&format!("\n{}", indent));
+                    self.buffer.set_cursor(cursor);
+                }
+            }
+            None if !indent.is_empty() => self.buffer.insert(&indent),
+            None => {}
+        }
     }
 
-    /// Removes the last char in the buffer.
-    fn pop_char(&mut self) -> Option<char> {
-        self.text.pop()
+    /// Types a tab, or with `expand_tab`, spaces up to the next tab stop.
+    fn type_tab(&mut self) {
+        if !self.config.expand_tab {
+            self.buffer.insert_char('\t');
+            return;
+        }
+        let width = self.config.tab_width;
+        let cells = self.buffer.display_col(self.buffer.cursor_position());
+        self.buffer.insert(&" ".repeat(width - cells % width));
     }
 
-    // /// This is an example of a function that takes the Buffer as owned,
-    // /// as well as another text area; and returns a new Buffer.
-    // /// You would either need to return a `Buffer`, or be sure that
-    // /// the user will not want the `Buffer` anymore.
-    // fn example_owned(self, another_arg: Buffer) -> Buffer {
-    //     todo!()
-    // }
+    /// Deletes the character before the cursor. If that opens an empty
+    /// pair, like `(|)`, the closer goes too.
+    fn backspace(&mut self) {
+        let pairs = self.config.pairs_for(self.buffer.extension());
+        let cursor = self.buffer.cursor();
+        match (
+            self.buffer.char_before_cursor(),
+            self.buffer.char_after_cursor(),
+        ) {
+            (Some(open), Some(close)) if pairs.contains(&(open, close)) => {
+                self.buffer
+                    .replace_range(cursor - open.len_utf8()..cursor + close.len_utf8(), "");
+            }
+            _ => {
+                self.buffer.delete_back();
+            }
+        }
+    }
 
-    // /// This is an example of a function that takes the Buffer by
-    // /// mutable reference.
-    // fn example_ref_mut(&mut self, another_arg: i32) {
-    //     todo!()
-    // }
+    /// Copies the selection, or else the cursor's line, onto both
+    /// clipboards. Cutting deletes it too.
+    fn copy(&mut self, cut: bool) {
+        let range = self.buffer.selection().unwrap_or_else(|| {
+            let line = self.buffer.cursor_line();
+            self.buffer.line_span(line..line + 1)
+        });
+        if range.is_empty() {
+            return;
+        }
+        let text = self.buffer.slice(range.clone());
+        if self.register.is_none() {
+            self.session.system_clipboard.set(&text);
+        }
+        self.keep(text);
+        if cut {
+            self.buffer.replace_range(range, "");
+        }
+    }
 
-    // /// This is an example of a function that takes the Buffer by
-    // /// reference.
-    // fn example_ref(&self) -> i32 {
-    //     todo!()
-    // }
-}
+    /// Deletes from the cursor to the end of its line, or the newline
+    /// there if it's already at the end, keeping what's deleted.
+    fn kill_to_end(&mut self) {
+        let cursor = self.buffer.cursor();
+        let line = self.buffer.cursor_line();
+        let span = self.buffer.line_span(line..line + 1);
+        let end = span.start + self.buffer.line(line).map_or(0, str::len);
+        let range = match cursor == end {
+            true => cursor..span.end,
+            false => cursor..end,
+        };
+        if range.is_empty() {
+            return;
+        }
+        let killed = self.buffer.slice(range.clone());
+        self.buffer.replace_range(range, "");
+        self.keep(killed);
+    }
 
-/// This struct implements all the
-/// logic for how the editor should work. It
-/// implements "Controller", which defines how
-/// something should interact with the terminal.
-struct BufferEditor {
-    buffer: Buffer,
-}
+    /// Deletes the cursor's line, newline and all, keeping it.
+    fn kill_line(&mut self) {
+        let line = self.buffer.cursor_line();
+        let span = self.buffer.line_span(line..line + 1);
+        if span.is_empty() {
+            return;
+        }
+        let killed = self.buffer.slice(span.clone());
+        self.buffer.replace_range(span.clone(), "");
+        self.buffer.set_cursor(span.start);
+        self.keep(killed);
+    }
 
-impl Controller for BufferEditor {
-    /// This gets run once, you can probably ignore it.
-    fn on_start(&mut self, _game: &mut Game) {}
+    /// Keeps text which was copied or deleted in the register picked
+    /// with Ctrl+R, or else on the clipboard.
+    fn keep(&mut self, text: String) {
+        match self.register.take() {
+            Some(name) => self.session.registers.set(name, Clip::new(text)),
+            None => self.session.clipboard.copy(Clip::new(text)),
+        }
+    }
 
-    /// Any time there's a keypress, you'll get this
-    /// function called.
-    fn on_event(&mut self, game: &mut Game, event: GameEvent) {
-        match event.into() {
-            SimpleEvent::Just(KeyCode::Char(c)) => self.buffer.push_char(c),
-            SimpleEvent::Just(KeyCode::Enter) => self.buffer.push_char('\n'),
-            SimpleEvent::Just(KeyCode::Esc) => {
+    /// Pastes the clipboard, or the register picked with Ctrl+R, at the
+    /// cursor, over the selection if there is one. Anything copied from a
+    /// block is pasted as a block.
+    fn paste(&mut self) {
+        if let Some(name) = self.register.take() {
+            if let Some(clip) = self.session.registers.get(name).cloned() {
+                self.buffer.delete_selection();
+                self.buffer.insert(&clip.text);
+            }
+            return;
+        }
+        // Something copied in another program since is pasted first.
+        // What's only been left on the system clipboard isn't, so a kill
+        // after copying still pastes what was killed.
+        if let Some(text) = self.session.system_clipboard.changed() {
+            let current = self.session.clipboard.current().map(|clip| &clip.text);
+            if !text.is_empty() && current != Some(&text) {
+                self.session.clipboard.copy(Clip::new(text));
+            }
+        }
+        self.buffer.delete_selection();
+        match self.session.clipboard.current() {
+            Some(clip) if clip.block => self.buffer.insert_block(&clip.text),
+            Some(clip) => self.buffer.insert(&clip.text),
+            None => {}
+        }
+    }
+
+    /// Checks whether the buffer's file has changed, and if so and it's
+    /// safe to, reloads it. Returns what to tell the user, if anything.
+    fn check_file(&mut self, force: bool) -> Option<String> {
+        let watch = self.watch.as_mut()?;
+        let changed = match force {
+            true => watch.check(),
+            false => watch.poll(self.session.clock.now()),
+        };
+        if !changed {
+            return None;
+        }
+        // Edits would be lost to a reload, so it's asked about instead.
+        if self.buffer.is_dirty() || !self.auto_revert {
+            if !self.changed_on_disk() {
+                return None;
+            }
+            if self.prompt.is_some() {
+                return Some(tr("editor.unsaved-changed", &[("name", &self.name)]));
+            }
+            self.ask_changed();
+            return None;
+        }
+
+        let text = match self.buffer.read_file()? {
+            Ok(text) => text,
+            Err(err) => {
+                tracing::warn!(buffer = %self.name, error = %err, "couldn't reload");
+                return Some(tr(
+                    "editor.reload-failed",
+                    &[("name", &self.name), ("error", &err)],
+                ));
+            }
+        };
+        if text == self.buffer.text() {
+            return None;
+        }
+        // Only the part that changed is replaced, so the cursor (and the
+        // viewport, which is left alone) stay where they were.
+        self.buffer.set_text(&text);
+        self.buffer.mark_saved();
+        // A followed file is expected to change all the time.
+        match self.follow {
+            true => None,
+            false => Some(tr("editor.reloaded", &[("name", &self.name)])),
+        }
+    }
+
+    /// Moves the cursor to the end of the buffer, and scrolls so the
+    /// last lines fill the screen.
+    fn follow_end(&mut self, game: &mut Game) {
+        self.buffer.set_cursor(self.buffer.text().len());
+        // The bottom row is the status bar, and the cursor is on the
+        // line after the last newline.
+        let rows = self.visible_size(game).1 - 1;
+        let lines = self.buffer.len_lines() as i32 + 1;
+        self.viewport.y = (lines - rows).max(0);
+        game.set_viewport(self.viewport);
+    }
+
+    /// How much of the buffer is in view, as `(columns, rows)`: the
+    /// pane it's drawn in, or else the whole screen.
+    fn visible_size(&self, game: &Game) -> (i32, i32) {
+        match self.pane {
+            Some(area) => area.place(game).1,
+            None => status::visible_size(game),
+        }
+    }
+
+    /// Scrolls, if it has to, so the cursor is in view.
+    fn scroll_to_cursor(&mut self, game: &mut Game) {
+        let mut viewport = game.get_viewport();
+        // The bottom row is the status bar.
+        let (columns, rows) = self.visible_size(game);
+        let rows = rows - 1;
+        let position = self.buffer.cursor_position();
+        let line = match self.wrap {
+            true => self.cursor_row(columns.max(0) as usize) as i32,
+            false => position.line as i32,
+        };
+        if line < viewport.y {
+            viewport.y = line;
+        } else if line >= viewport.y + rows {
+            viewport.y = line - rows + 1;
+        }
+        // Past the line numbers, if they're showing, but they're only
+        // scrolled back to when the cursor is at the start of its line.
+        // Wrapped lines never need scrolling across.
+        let col = (self.text_left() + self.buffer.display_col(position)) as i32;
+        if position.col == 0 || self.wrap {
+            viewport.x = 0;
+        } else if col < viewport.x {
+            viewport.x = col;
+        } else if col >= viewport.x + columns {
+            viewport.x = col - columns + 1;
+        }
+        game.set_viewport(viewport);
+    }
+
+    /// Scrolls a screen's width across, left or right, but never past
+    /// the start of the lines.
+    fn scroll_across(&mut self, game: &mut Game, right: bool) {
+        let mut viewport = game.get_viewport();
+        let columns = self.visible_size(game).0;
+        viewport.x = match right {
+            true => viewport.x + columns,
+            false => (viewport.x - columns).max(0),
+        };
+        game.set_viewport(viewport);
+    }
+
+    /// Scrolls a screen up or down, and moves the cursor as many lines,
+    /// so it stays where it was on the screen. Scrolling down stops with
+    /// the last line at the bottom.
+    fn page(&mut self, game: &mut Game, down: bool) {
+        let mut viewport = game.get_viewport();
+        // The bottom row is the status bar.
+        let rows = (self.visible_size(game).1 - 1).max(1);
+        let last_screen = (self.buffer.len_lines() as i32 - rows).max(0);
+        viewport.y = match down {
+            true => (viewport.y + rows).min(last_screen.max(viewport.y)),
+            false => (viewport.y - rows).max(0),
+        };
+        game.set_viewport(viewport);
+        self.buffer
+            .move_lines(if down { rows } else { -rows } as isize);
+    }
+
+    /// Snapshots the buffer as it is now.
+    pub fn take_snapshot(&mut self) -> std::io::Result<()> {
+        snapshot::take(&self.name, self.buffer.text())?;
+        self.snapshots
+            .taken(self.buffer.revision(), self.session.clock.now());
+        Ok(())
+    }
+
+    /// Redraws just the status bar.
+    fn render_status(&mut self, game: &mut Game) {
+        let text = self.status_text();
+        let size = self.visible_size(game);
+        let (row, columns) = status::draw_status_bar(game, size, &text, self.session.theme.status);
+        self.drawn.status_drawn(row, columns);
+        self.record_frame(game);
+        self.announce(game);
+    }
+
+    /// What the status bar shows: the prompt, or the playback, when
+    /// there's one of those, and otherwise where the cursor is in which
+    /// buffer.
+    fn status_text(&self) -> String {
+        if let Some((prompt, _)) = &self.prompt {
+            return prompt.display();
+        }
+        if let Some(playback) = &self.playback {
+            return playback.status();
+        }
+
+        let mut name = self.name.clone();
+        if self.buffer.is_dirty() {
+            name.push(' ');
+            name.push_str(&tr("status.modified", &[]));
+        }
+        let mut sections = vec![name];
+        if self.session.editing_mode == EditingMode::Vim {
+            let mode = match self.vim.mode {
+                Mode::Normal => "status.normal",
+                Mode::Insert => "status.insert",
+                Mode::Visual => "status.visual",
+            };
+            sections.insert(0, tr(mode, &[]));
+        }
+        let file = self.buffer.file().map(Path::new);
+        if let Some(file) = file.or(self.huge.as_ref().map(HugeFile::path)) {
+            let path = display_path(file);
+            if path != self.name {
+                sections.push(path);
+            }
+        }
+        // Most files are UTF-8 and end lines with \n, so only the others
+        // say.
+        if self.buffer.encoding() != Encoding::Utf8 {
+            sections.push(self.buffer.encoding().to_string());
+        }
+        if self.buffer.eol() != LineEnding::Lf {
+            sections.push(self.buffer.eol().name().to_uppercase());
+        }
+        let position = self.buffer.cursor_position();
+        sections.push(tr(
+            "status.position",
+            &[
+                ("line", &(self.first_line() + position.line + 1)),
+                ("col", &(self.buffer.display_col(position) + 1)),
+                ("lines", &self.total_lines()),
+            ],
+        ));
+        if self.buffer.is_prose() {
+            // The selection's counted afresh instead, as it can change
+            // wholesale from one key to the next.
+            let count = match self.buffer.selection() {
+                Some(range) => WordCount::of(&self.buffer.slice(range)),
+                None => self.buffer.word_count(),
+            };
+            sections.push(tr(
+                "status.words",
+                &[("words", &count.words), ("chars", &count.chars)],
+            ));
+        }
+        if let Some(ago) = self.autosave.ago(self.session.clock.now()) {
+            let ago = autosave::describe_ago(ago);
+            sections.push(tr("status.autosaved", &[("ago", &ago)]));
+        }
+        if let Some(stats) = &self.session.typing_stats {
+            sections.push(tr("status.wpm", &[("wpm", &format!("{:.0}", stats.wpm()))]));
+        }
+        if let Some(name) = self.session.macros.recording() {
+            sections.push(tr("status.recording-macro", &[("name", &name)]));
+        }
+        sections.join(" | ")
+    }
+
+    /// Does whatever `event` does in the editor.
+    fn handle_key(&mut self, game: &mut Game, event: GameEvent) {
+        let revision = self.buffer.revision();
+        // termgame drops Shift from the keys it hands on, so it has to be
+        // looked for first.
+        let shift = matches!(
+            &event,
+            GameEvent::Key(key) if key.modifiers.contains(KeyModifiers::SHIFT)
+        );
+        let key = event.clone();
+        let event: SimpleEvent = event.into();
+        if self.playback_event(game, &event) {
+            return;
+        }
+        self.record_key(game, &event);
+        self.record_macro_key(key);
+        if let Some(metrics) = &mut self.session.metrics {
+            metrics.count_key(&event);
+        }
+        let typed = matches!(
+            event,
+            SimpleEvent::Just(KeyCode::Char(_)) | SimpleEvent::Just(KeyCode::Enter)
+        );
+        if let Some((mut prompt, question)) = self.prompt.take() {
+            match (prompt.handle(&event), question) {
+                (PromptResult::Editing, Question::Find { from }) => {
+                    let from = self.find_typed(game, &prompt.input, from);
+                    self.prompt = Some((prompt, Question::Find { from }));
+                }
+                (PromptResult::Editing, question) => self.prompt = Some((prompt, question)),
+                (PromptResult::Cancelled, Question::Find { from }) => {
+                    self.buffer.set_cursor(from);
+                    self.scroll_to_cursor(game);
+                }
+                (PromptResult::Cancelled, _) => {}
+                (PromptResult::Done(answer), question) => match question {
+                    Question::Dired(action) => self.dired_answer(game, &action, &answer),
+                    Question::SaveAs => self.save_as(game, &answer),
+                    Question::Find { .. } => self.finish_find(game, &answer),
+                    Question::Goto => self.goto_answer(game, &answer),
+                    Question::Pipe { range } => self.pipe_answer(game, range, &answer),
+                    Question::Read => self.read_answer(game, &answer),
+                    Question::Changed => self.changed_answer(game, &answer),
+                },
+            }
+            self.viewport = game.get_viewport();
+            self.render(game);
+            return;
+        }
+        if self.directory.is_some() {
+            self.dired_event(game, event);
+            self.viewport = game.get_viewport();
+            self.render(game);
+            return;
+        }
+        if self.dashboard.is_some() {
+            self.dashboard_event(game, event);
+            self.viewport = game.get_viewport();
+            self.render(game);
+            return;
+        }
+        if self.buffer.encoding() == Encoding::Hex && self.hex_event(game, &event) {
+            self.viewport = game.get_viewport();
+            self.render(game);
+            return;
+        }
+        if std::mem::take(&mut self.register_next) {
+            match event {
+                SimpleEvent::Just(KeyCode::Char(name)) if Registers::is_name(name) => {
+                    self.register = Some(name);
+                    game.set_message(None);
+                }
+                _ => {
+                    let message = tr("editor.no-such-register", &[]);
+                    game.set_message(Some(Message::new(message)));
+                }
+            }
+            self.render(game);
+            return;
+        }
+        if let Some(key) = self.macro_next.take() {
+            self.macro_event(game, key, &event);
+            self.render(game);
+            return;
+        }
+        // In vim mode, keys in normal and visual mode are commands.
+        let vim = self.session.editing_mode == EditingMode::Vim && self.vim_event(game, &event);
+        let action = match shift {
+            true => self.keymap.lookup_shifted(&event),
+            false => self.keymap.lookup(&event),
+        };
+        let action = action.filter(|_| !vim);
+        let changes_text = match action {
+            Some(action) => action.changes_text(),
+            None => !vim && matches!(event, SimpleEvent::Just(KeyCode::Char(_) | KeyCode::Tab)),
+        };
+        if self.read_only && changes_text {
+            self.register = None;
+            let message = tr("editor.read-only", &[("name", &self.name)]);
+            game.set_message(Some(Message::new(message)));
+            return;
+        }
+        // Shift and an arrow key selects as the cursor moves, and the
+        // arrow keys on their own leave the selection behind.
+        let moves = matches!(
+            action,
+            Some(
+                Action::Left
+                    | Action::Right
+                    | Action::Up
+                    | Action::Down
+                    | Action::WordLeft
+                    | Action::WordRight
+                    | Action::LineStart
+                    | Action::LineEnd
+                    | Action::PageUp
+                    | Action::PageDown
+            )
+        );
+        if moves {
+            match shift || self.vim.mode == Mode::Visual {
+                true => self.buffer.start_selection(),
+                false => self.buffer.clear_selection(),
+            }
+            if let Some(action) = action.filter(|_| !self.cursors.is_empty()) {
+                self.move_cursors(action);
+            }
+        }
+        // With more than one cursor, edits happen at all of them.
+        let edit = CursorEdit::of(action, &event).filter(|_| !vim && !self.cursors.is_empty());
+        if let Some(edit) = edit {
+            self.edit_at_cursors(edit);
+        }
+        // Undo and redo aren't edits to repeat.
+        if matches!(action, Some(Action::Undo | Action::Redo)) {
+            self.repeat.forget();
+        }
+        match (action, event) {
+            _ if vim || edit.is_some() => {}
+            (Some(Action::Newline), _) => self.newline(),
+            (Some(Action::LiteralNext), _) => self.literal_next = true,
+            (Some(Action::Register), _) => {
+                self.register_next = true;
+                game.set_message(Some(Message::new(tr("editor.which-register", &[]))));
+            }
+            (Some(Action::RecordMacro), _) => self.record_macro(game),
+            (Some(Action::PlayMacro), _) => self.play_macro(game),
+            (Some(Action::Paste), _) => self.paste(),
+            (Some(Action::Copy), _) => self.copy(false),
+            (Some(Action::Cut), _) => self.copy(true),
+            // Esc goes back to one cursor, and clears the selection and
+            // what was found, before it closes the editor.
+            (Some(Action::Close), _) if !self.cursors.is_empty() => self.cursors.clear(),
+            (Some(Action::Close), _) if self.buffer.selection().is_some() => {
+                self.buffer.clear_selection()
+            }
+            (Some(Action::Close), _) if self.find.is_some() => self.find = None,
+            (Some(Action::Close), _) => {
                 game.end_game();
             }
-            SimpleEvent::Just(KeyCode::Up) => {
+            (Some(Action::ScrollUp), _) => {
                 let mut viewport = game.get_viewport();
                 if viewport.y > 0 {
                     viewport.y -= 1;
                 }
                 game.set_viewport(viewport)
             }
-            SimpleEvent::Just(KeyCode::Down) => {
+            (Some(Action::ScrollDown), _) => {
                 let mut viewport = game.get_viewport();
                 viewport.y += 1;
                 game.set_viewport(viewport)
             }
-            SimpleEvent::WithControl(KeyCode::Char('s')) => {
-                // Implement
-                println!("Saving...");
-                // todo!()
+            (Some(Action::ScrollLeft), _) => self.scroll_across(game, false),
+            (Some(Action::ScrollRight), _) => self.scroll_across(game, true),
+            (Some(Action::Left), _) => self.buffer.move_left(),
+            (Some(Action::Right), _) => self.buffer.move_right(),
+            (Some(Action::Up), _) => self.buffer.move_up(),
+            (Some(Action::Down), _) => self.buffer.move_down(),
+            (Some(Action::LineStart), _) => self.buffer.move_home(),
+            (Some(Action::LineEnd), _) => self.buffer.move_end(),
+            (Some(Action::PageUp), _) => self.page(game, false),
+            (Some(Action::PageDown), _) => self.page(game, true),
+            (Some(Action::MoveLineUp), _) => {
+                self.buffer.move_selected_lines(true);
             }
-            // This is bad binding
-            SimpleEvent::WithControl(KeyCode::Char('f')) => {
-                // Clear entire line
+            (Some(Action::MoveLineDown), _) => {
+                self.buffer.move_selected_lines(false);
+            }
+            (Some(Action::DuplicateLine), _) => self.buffer.duplicate_selected_lines(),
+            (Some(Action::ToggleComment), _) => self.toggle_comment(game),
+            (Some(Action::ChangeCase(case)), _) => {
+                self.change_case(case);
+            }
+            (Some(Action::JoinLines), _) => {
+                self.buffer.join_line(self.buffer.cursor_line());
+            }
+            (Some(Action::WordLeft), _) => self.move_word(false),
+            (Some(Action::WordRight), _) => self.move_word(true),
+            (Some(Action::Save), _) => self.save(game),
+            (Some(Action::ClearLine), _) => {
+                let mut killed = Vec::new();
                 loop {
-                    match self.buffer.pop_char() {
+                    match self.buffer.delete_back() {
                         Some('\n') => break,
-                        Some(_) => {}
+                        Some(c) => killed.push(c),
                         None => break,
                     }
                 }
+                // What's cleared can be pasted back, or found with `clips`.
+                if !killed.is_empty() {
+                    self.keep(killed.into_iter().rev().collect());
+                }
+            }
+            (Some(Action::KillToEnd), _) => self.kill_to_end(),
+            (Some(Action::KillLine), _) => self.kill_line(),
+            (Some(Action::Backspace | Action::Delete), _) if self.buffer.selection().is_some() => {
+                let deleted = self.buffer.delete_selection().unwrap();
+                self.keep(deleted);
+            }
+            (Some(Action::Backspace), _) => self.backspace(),
+            (Some(Action::DeleteWordBack), _) => self.delete_word(false),
+            (Some(Action::DeleteWord), _) => self.delete_word(true),
+            (Some(Action::Delete), _) => {
+                self.buffer.delete_forward();
             }
-            SimpleEvent::Just(KeyCode::Backspace) => {
-                self.buffer.pop_char();
+            (Some(Action::Repeat), _) => self.repeat_edit(game, None),
+            // The other cursors could be anywhere after an undo or redo.
+            (Some(Action::Undo | Action::Redo), _) if !self.cursors.is_empty() => {
+                self.cursors.clear();
+                match action == Some(Action::Undo) {
+                    true => self.buffer.undo(),
+                    false => self.buffer.redo(),
+                };
             }
+            (Some(Action::Undo), _) if !self.buffer.undo() => {
+                game.set_message(Some(Message::new(tr("editor.nothing-to-undo", &[]))));
+            }
+            (Some(Action::Redo), _) if !self.buffer.redo() => {
+                game.set_message(Some(Message::new(tr("editor.nothing-to-redo", &[]))));
+            }
+            (Some(Action::Find), _) => self.start_find(),
+            (Some(Action::Goto), _) => self.start_goto(),
+            (Some(Action::Blame), _) => self.toggle_blame_and_say(game),
+            (Some(Action::NextHunk), _) => self.jump_to_hunk(game, true),
+            (Some(Action::PreviousHunk), _) => self.jump_to_hunk(game, false),
+            (Some(Action::Pipe), _) => self.start_pipe_selection(),
+            (Some(Action::ReadCommand), _) => self.start_read(),
+            (Some(Action::Wrap), _) => self.wrap = !self.wrap,
+            (Some(Action::MatchBracket), _) => self.jump_to_match(),
+            (Some(Action::AddCursor), _) => self.add_cursor(),
+            (Some(Action::FindNext), _) => self.find_again(game, true),
+            (Some(Action::FindPrevious), _) => self.find_again(game, false),
+            (None, SimpleEvent::Just(KeyCode::Char(c))) => self.type_char(c),
+            (None, SimpleEvent::Just(KeyCode::Tab)) => self.type_tab(),
 
             _ => {}
         }
-        let mut chunkmap = CharChunkMap::new();
-        self.buffer.chunkmap_from_textarea(&mut chunkmap);
-        game.swap_chunkmap(&mut chunkmap);
+        // A register picked with Ctrl+R is only for the key after it, or
+        // for what's selected with Shift and the arrow keys before it's
+        // copied or cut. In vim mode it's for the next command, however
+        // many keys that is.
+        if !vim && action != Some(Action::Register) && !(moves && shift) {
+            self.register = None;
+        }
+        // Scrolling is the one thing that can leave the cursor out of view.
+        if !matches!(
+            action,
+            Some(Action::ScrollUp | Action::ScrollDown | Action::ScrollLeft | Action::ScrollRight)
+        ) {
+            self.scroll_to_cursor(game);
+        }
+        if let Some(stats) = &mut self.session.typing_stats {
+            stats.record_key(
+                typed,
+                self.buffer.revision() != revision,
+                self.session.clock.now(),
+            );
+        }
+        if self.buffer.revision() != revision {
+            self.tutor_check(game);
+        }
+        self.viewport = game.get_viewport();
+        self.render(game);
     }
-
-    /// This function gets called regularly, so you can use it
-    /// for logic that's independent of key-presses like
-    /// implementing a "mouse".
-    fn on_tick(&mut self, _game: &mut Game) {}
-}
-
-fn run_command(
-    cmd: &str,
-    editors: &mut HashMap<String, BufferEditor>,
-) -> Result<(), Box<dyn Error>> {
-    let mut buffer_uiids: i32 = 0;
-
-    match first_word(cmd) {
-        "open" => run_game(
-            fetch_editor(editors, cmd, &mut buffer_uiids),
-            GameSettings::new().tick_duration(Duration::from_millis(25)),
-        )?,
-        "search" => print_buffer_searches(editors, cmd),
-        _ => println!("Command not recognised"),
-    }
-    // if cmd.starts_with("open") {
-    //     run_game(
-    //         fetch_editor(editors, cmd, &mut buffer_uiids),
-    //         GameSettings::new()
-    //             .tick_duration(Duration::from_millis(25))
-    //     )?;
-    // } else if cmd.starts_with("serach"){
-    //     println!("Command not recognised!");
-    // }
-
-    Ok(())
 }
 
-fn print_buffer_searches(editors: &HashMap<String, BufferEditor>, cmd: &str) {
-    let first_word = first_word(cmd);
-    let index_rest = first_word.len() - 1;
-    let search_term = &cmd[index_rest..];
-
-    for editor in editors.values() {
-        for line in editor.buffer.text.lines() {
-            if line.contains(search_term) {
-                println!("found: {}", line);
-            };
+impl Controller for BufferEditor {
+    /// Restores the viewport, and draws the buffer as it was left.
+    fn on_start(&mut self, game: &mut Game) {
+        game.set_viewport(self.viewport);
+        // The editor starts on an empty screen.
+        self.drawn = Drawn::new();
+        if let Some(message) = self.check_file(true).or_else(|| self.check_swap()) {
+            game.set_message(Some(Message::new(message)));
         }
+        if self.follow {
+            self.follow_end(game);
+        }
+        self.open_git();
+        if let Some(announcer) = &mut self.session.announcer {
+            announcer.reset();
+        }
+        self.render(game);
     }
-}
 
-fn first_word(s: &str) -> &str {
-    let bytes = s.as_bytes();
+    /// Any time there's a keypress, you'll get this
+    /// function called.
+    fn on_event(&mut self, game: &mut Game, event: GameEvent) {
+        let key = event.clone();
+        let revision = self.buffer.revision();
+        let mode = self.vim.mode;
+        self.handle_key(game, event);
+        self.track_edit(key, revision, mode);
+    }
 
-    for (i, &item) in bytes.iter().enumerate() {
-        if item == b' ' {
-            return &s[0..i];
+    /// This function gets called regularly, so you can use it
+    /// for logic that's independent of key-presses like
+    /// implementing a "mouse".
+    fn on_tick(&mut self, game: &mut Game) {
+        self.session.clock.tick();
+        self.tick_buffer(game);
+        self.playback_tick(game);
+        if let Some(stats) = &mut self.session.typing_stats {
+            stats.tick(self.session.clock.now());
         }
+        self.render_status(game);
     }
-
-    s
 }
 
-fn fetch_editor<'a>(
-    editors: &'a mut HashMap<String, BufferEditor>,
-    cmd: &str,
-    uuid: &mut i32,
-) -> &'a mut BufferEditor {
-    let args: Vec<&str> = cmd.split_whitespace().collect();
-    let buffer_name: String = match args.get(1) {
-        Some(name) => String::from(*name),
-        None => {
-            // New buffer w/ random name (not most bullet proof strat)
-            loop {
-                let name = format!("buffer_{}", uuid);
-                if !editors.contains_key(&name) {
-                    break name;
-                }
-                *uuid += 1;
+impl BufferEditor {
+    /// What comes round with time for the buffer, whether or not it's
+    /// showing: reloading it when its file changes, its git marks, and
+    /// snapshots, autosaves and the swap file.
+    fn tick_buffer(&mut self, game: &mut Game) {
+        let now = self.session.clock.now();
+        let revision = self.buffer.revision();
+        if let Some(message) = self.check_file(false) {
+            game.set_message(Some(Message::new(message)));
+            self.render(game);
+        }
+        if self.follow && self.buffer.revision() != revision {
+            self.follow_end(game);
+            self.render(game);
+        }
+        // There's no point keeping old copies of a file being followed.
+        if !self.follow && self.huge.is_none() && self.snapshots.is_due(self.buffer.revision(), now)
+        {
+            if let Err(err) = self.take_snapshot() {
+                tracing::warn!(buffer = %self.name, error = %err, "couldn't snapshot");
+                let message = tr("editor.snapshot-failed", &[("error", &err)]);
+                game.set_message(Some(Message::new(message)));
             }
         }
-    };
-
-    editors.entry(buffer_name).or_insert(BufferEditor {
-        buffer: Buffer::new(None),
-    })
+        if self.git_tick(now) {
+            self.render(game);
+        }
+        self.autosave_tick(game);
+        self.swap_tick(game);
+    }
 }
 
 use rustyline::error::ReadlineError;
 use rustyline::Editor;
 
 fn main() -> Result<(), Box<dyn Error>> {
-    println!("Welcome to BuffeRS. ");
+    logging::init();
+    tracing::info!("started");
 
-    let mut editors: HashMap<String, BufferEditor> = HashMap::new();
+    // The config says which language to use, so until it's been read,
+    // anything that goes wrong is said in English.
+    let config = Config::load();
+    let locale = i18n::locale(config.as_ref().unwrap_or(&Config::default()));
+    match i18n::Catalog::load(&locale) {
+        Ok(catalog) => i18n::init(catalog),
+        Err(err) => println!(
+            "{}",
+            tr("repl.bad-locale", &[("locale", &locale), ("error", &err)])
+        ),
+    }
+    println!("{}", tr("repl.welcome", &[]));
+    let mut config = config.unwrap_or_else(|err| {
+        println!("{}", tr("repl.bad-config", &[("error", &err)]));
+        Config::default()
+    });
+    match keymap::load_keys() {
+        Ok(keys) => config.keys = keys,
+        Err(err) => println!("{}", tr("repl.bad-keys", &[("error", &err)])),
+    }
+    let mut session = Session::default();
+    if config.metrics {
+        session.metrics = Some(Metrics::load().unwrap_or_else(|err| {
+            println!("{}", tr("metrics.bad-file", &[("error", &err)]));
+            Metrics::default()
+        }));
+    }
+    session.line_numbers = config.line_numbers;
+    session.editing_mode = config.editing_mode;
+    session.autoindent = config.autoindent;
+    session.trim_trailing_whitespace = config.trim_trailing_whitespace;
+    session.insert_final_newline = config.insert_final_newline;
+    match Theme::by_name(&config.theme) {
+        Some(theme) => session.theme = theme,
+        None => println!("{}", tr("theme.unknown", &[("theme", &config.theme)])),
+    }
+    if let Some(target) = &config.announce {
+        let announcer = paths::expand(target)
+            .and_then(|path| Announcer::open(&path).map_err(|err| err.to_string()));
+        match announcer {
+            Ok(announcer) => session.announcer = Some(announcer),
+            Err(err) => println!(
+                "{}",
+                tr("announce.failed", &[("target", target), ("error", &err)])
+            ),
+        }
+    }
+    let aliases = config.aliases.clone().into_iter().collect();
+    let mut app = App {
+        editors: HashMap::new(),
+        config: Rc::new(config),
+        session,
+        queued: VecDeque::new(),
+        answers: VecDeque::new(),
+        pending_replacements: HashMap::new(),
+        quit: false,
+        aliases,
+        registry: Rc::new(Registry::with_builtins()),
+        sourcing: Vec::new(),
+    };
+    // Any files on the command line are opened, one after the other.
+    // Without any, BuffeRS starts on the dashboard, unless there's no
+    // terminal to show it on.
+    let files: Vec<String> = std::env::args().skip(1).collect();
+    for file in &files {
+        app.queued.push_back(format!("open {}", file));
+    }
+    if let Ok(left) = std::env::current_dir().and_then(|dir| swap::leftovers(&dir)) {
+        if !left.is_empty() {
+            let names: Vec<String> = left.iter().map(|file| display_path(file)).collect();
+            let names = names.join(", ");
+            println!("{}", tr("repl.swap-leftovers", &[("names", &names)]));
+        }
+    }
+    if files.is_empty() && app.config.dashboard && std::io::stdin().is_terminal() {
+        app.queued.push_back(String::from("dashboard"));
+    }
+    let registry = Rc::clone(&app.registry);
 
-    // `()` can be used when no completer is required
-    let mut rl = Editor::<()>::new()?;
+    let mut rl = Editor::<ReplHelper>::new()?;
+    rl.set_helper(Some(ReplHelper {
+        show_hidden: app.config.complete_hidden,
+        commands: Vec::new(),
+        buffers: Vec::new(),
+    }));
     loop {
+        while let Some(line) = app.queued.pop_front() {
+            if let Err(err) = commands::run_command(&line, &registry, &mut app) {
+                println!("{}", tr("repl.error", &[("error", &err)]));
+            }
+        }
+        autosave::autosave_changes(&mut app);
+        if app.quit {
+            break;
+        }
+        if let Some(helper) = rl.helper_mut() {
+            let aliases = app.aliases.keys().map(String::as_str);
+            helper.commands = registry.names().chain(aliases).map(String::from).collect();
+            helper.buffers = app.editors.keys().cloned().collect();
+        }
         let readline = rl.readline(">> ");
         match readline {
             Ok(line) => {
-                run_command(&line, &mut editors)?;
+                if let Err(err) = commands::run_command(&line, &registry, &mut app) {
+                    println!("{}", tr("repl.error", &[("error", &err)]));
+                }
+                autosave::autosave_changes(&mut app);
+                if let Some(metrics) = &app.session.metrics {
+                    if let Err(err) = metrics.save() {
+                        tracing::warn!(error = %err, "couldn't save the usage metrics");
+                        println!("{}", tr("metrics.save-failed", &[("error", &err)]));
+                    }
+                }
                 rl.add_history_entry(line.as_str());
+                if app.quit {
+                    break;
+                }
+            }
+            Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => {
+                match commands::confirm_quit(&mut app) {
+                    Ok(false) => {}
+                    Ok(true) => break,
+                    Err(err) => {
+                        tracing::error!(error = %err, "couldn't ask whether to quit");
+                        break;
+                    }
+                }
             }
-            Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => break,
             Err(err) => {
+                tracing::error!(error = %err, "couldn't read a command");
                 println!("Error: {:?}", err);
                 break;
             }
         }
     }
+    tracing::info!("quit");
 
     Ok(())
 }
@@ -0,0 +1,151 @@
+//! Opt-in counts of which commands and key bindings get used, kept in
+//! `~/.local/share/buffers/metrics.toml` and never sent anywhere.
+//!
+//! Typing plain characters isn't counted, only keys which do something
+//! else, so the counts say nothing about what was written.
+
+use serde::{Deserialize, Serialize};
+use termgame::{KeyCode, SimpleEvent};
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+use crate::config::data_dir;
+use crate::json;
+
+/// How many times each command and key has been used, across every
+/// session since the counts were last reset.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Metrics {
+    pub commands: BTreeMap<String, u64>,
+    pub keys: BTreeMap<String, u64>,
+}
+
+fn path() -> Option<PathBuf> {
+    Some(data_dir()?.join("metrics.toml"))
+}
+
+impl Metrics {
+    /// Reads the counts so far, or starts from nothing if there aren't any.
+    pub fn load() -> Result<Metrics, Box<dyn Error>> {
+        let Some(path) = path() else {
+            return Ok(Metrics::default());
+        };
+        match fs::read_to_string(&path) {
+            Ok(text) => {
+                toml::from_str(&text).map_err(|err| format!("{}: {}", path.display(), err).into())
+            }
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(Metrics::default()),
+            Err(err) => Err(format!("{}: {}", path.display(), err).into()),
+        }
+    }
+
+    /// Writes the counts out, for the next session to carry on from.
+    pub fn save(&self) -> Result<(), Box<dyn Error>> {
+        let path = path().ok_or("no home directory")?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&path, toml::to_string(self)?)?;
+        Ok(())
+    }
+
+    pub fn count_command(&mut self, name: &str) {
+        *self.commands.entry(String::from(name)).or_default() += 1;
+    }
+
+    /// Counts `event`, unless it's just typing.
+    pub fn count_key(&mut self, event: &SimpleEvent) {
+        if let Some(name) = key_name(event) {
+            *self.keys.entry(name).or_default() += 1;
+        }
+    }
+
+    /// The most used commands and keys, most used first, as `metrics`
+    /// shows them.
+    pub fn summary(&self) -> String {
+        let mut summary = String::new();
+        for (title, counts) in [("Commands", &self.commands), ("Keys", &self.keys)] {
+            summary.push_str(&format!("{}:\n", title));
+            let mut counts: Vec<_> = counts.iter().collect();
+            counts.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+            if counts.is_empty() {
+                summary.push_str("  (none yet)\n");
+            }
+            for (name, count) in counts {
+                summary.push_str(&format!("  {:>6}  {}\n", count, name));
+            }
+        }
+        summary
+    }
+
+    /// The counts as a JSON object, for `metrics export`.
+    pub fn to_json(&self) -> String {
+        let object = |counts: &BTreeMap<String, u64>| {
+            let fields: Vec<String> = counts
+                .iter()
+                .map(|(name, count)| format!("    {}: {}", json::string(name), count))
+                .collect();
+            match fields.is_empty() {
+                true => String::from("{}"),
+                false => format!("{{\n{}\n  }}", fields.join(",\n")),
+            }
+        };
+        format!(
+            "{{\n  \"commands\": {},\n  \"keys\": {}\n}}\n",
+            object(&self.commands),
+            object(&self.keys)
+        )
+    }
+}
+
+/// What to call the key for `event`, like `Ctrl+S`, or `None` if it
+/// just types a character.
+fn key_name(event: &SimpleEvent) -> Option<String> {
+    let code = |code: &KeyCode| match code {
+        KeyCode::Char(' ') => String::from("Space"),
+        KeyCode::Char(c) => c.to_uppercase().to_string(),
+        KeyCode::F(n) => format!("F{}", n),
+        code => format!("{:?}", code),
+    };
+    match event {
+        SimpleEvent::Just(KeyCode::Char(_)) => None,
+        SimpleEvent::Just(key) => Some(code(key)),
+        SimpleEvent::WithControl(key) => Some(format!("Ctrl+{}", code(key))),
+        SimpleEvent::WithAlt(key) => Some(format!("Alt+{}", code(key))),
+        SimpleEvent::WithControlAlt(key) => Some(format!("Ctrl+Alt+{}", code(key))),
+        SimpleEvent::ComplexEvent(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_bindings_are_counted() {
+        let mut metrics = Metrics::default();
+        metrics.count_key(&SimpleEvent::Just(KeyCode::Char('x')));
+        metrics.count_key(&SimpleEvent::WithControl(KeyCode::Char('s')));
+        metrics.count_key(&SimpleEvent::WithControl(KeyCode::Char('s')));
+        metrics.count_key(&SimpleEvent::Just(KeyCode::Enter));
+        assert_eq!(
+            metrics.keys,
+            BTreeMap::from([(String::from("Ctrl+S"), 2), (String::from("Enter"), 1)])
+        );
+    }
+
+    #[test]
+    fn counts_export_as_json() {
+        let mut metrics = Metrics::default();
+        metrics.count_command("open");
+        assert_eq!(
+            metrics.to_json(),
+            "{\n  \"commands\": {\n    \"open\": 1\n  },\n  \"keys\": {}\n}\n"
+        );
+    }
+}
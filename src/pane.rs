@@ -0,0 +1,107 @@
+//! Editors which share the screen, like each side of a split or the
+//! buffer under the tab strip, draw on a screen of their own, and the
+//! part of that in view is copied into the area they've been given.
+
+use termgame::{CharChunkMap, Game, StyledCharacter, ViewportLocation, SCREEN_HEIGHT};
+
+use crate::status;
+use crate::BufferEditor;
+
+/// The part of the screen an editor has, when it doesn't have it all.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Area {
+    Left,
+    Right,
+    Top,
+    Bottom,
+    /// Everything under the tab strip.
+    UnderTabs,
+}
+
+impl Area {
+    /// The area's top left corner, and its size, as `(columns, rows)`.
+    /// A message takes its rows from the bottom, so the size changes as
+    /// they come and go.
+    pub fn place(self, game: &Game) -> ((i32, i32), (i32, i32)) {
+        let (columns, rows) = status::visible_size(game);
+        let left = (columns - 1) / 2;
+        // The top pane keeps its rows while a message is showing.
+        let top = (i32::from(SCREEN_HEIGHT) - 3) / 2;
+        match self {
+            Area::Left => ((0, 0), (left, rows)),
+            Area::Right => ((left + 1, 0), (columns - left - 1, rows)),
+            Area::Top => ((0, 0), (columns, top.min(rows))),
+            Area::Bottom => ((0, top + 1), (columns, (rows - top - 1).max(1))),
+            Area::UnderTabs => ((0, 1), (columns, (rows - 1).max(1))),
+        }
+    }
+}
+
+/// An editor, and the screen it draws on.
+pub struct Pane {
+    pub editor: BufferEditor,
+    screen: CharChunkMap,
+    viewport: ViewportLocation,
+}
+
+impl Pane {
+    pub fn new(editor: BufferEditor) -> Pane {
+        Pane {
+            editor,
+            screen: CharChunkMap::new(),
+            viewport: ViewportLocation { x: 0, y: 0 },
+        }
+    }
+
+    /// Runs `f` on the editor, drawing only on its own screen, for when
+    /// it isn't in view.
+    pub fn run_hidden(
+        &mut self,
+        game: &mut Game,
+        area: Area,
+        f: impl FnOnce(&mut BufferEditor, &mut Game),
+    ) {
+        self.editor.pane = Some(area);
+        let viewport = game.get_viewport();
+        game.swap_chunkmap(&mut self.screen);
+        game.set_viewport(self.viewport);
+        f(&mut self.editor, game);
+        self.viewport = game.get_viewport();
+        game.swap_chunkmap(&mut self.screen);
+        game.set_viewport(viewport);
+    }
+
+    /// Runs `f` on the editor, drawing on its own screen, then copies
+    /// what's in view of that into `area`.
+    pub fn run(
+        &mut self,
+        game: &mut Game,
+        area: Area,
+        f: impl FnOnce(&mut BufferEditor, &mut Game),
+    ) {
+        self.editor.pane = Some(area);
+        game.swap_chunkmap(&mut self.screen);
+        game.set_viewport(self.viewport);
+        f(&mut self.editor, game);
+        self.viewport = game.get_viewport();
+
+        let ((left, top), (columns, rows)) = area.place(game);
+        let viewport = self.viewport;
+        let cells: Vec<Option<StyledCharacter>> = (0..rows)
+            .flat_map(|y| (0..columns).map(move |x| (x, y)))
+            .map(|(x, y)| game.get_screen_char(viewport.x + x, viewport.y + y))
+            .collect();
+        game.swap_chunkmap(&mut self.screen);
+        game.set_viewport(ViewportLocation { x: 0, y: 0 });
+        for (i, cell) in cells.into_iter().enumerate() {
+            let (x, y) = (i as i32 % columns, i as i32 / columns);
+            game.set_screen_char(left + x, top + y, cell);
+        }
+    }
+
+    /// The editor, drawn on the whole screen again.
+    pub fn into_editor(mut self) -> BufferEditor {
+        self.editor.pane = None;
+        self.editor
+    }
+}
@@ -0,0 +1,156 @@
+//! Turning the paths people type into the files they mean.
+
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+
+/// Resolves a path as typed, like `~/notes.txt` or `$PROJECT/main.rs`:
+/// expanded, then made [`canonical`]. Everything that takes a path from
+/// the user should go through here, so they all understand the same
+/// paths.
+pub fn resolve(typed: &str) -> Result<PathBuf, Box<dyn Error>> {
+    let expanded = expand(typed)?;
+    Ok(canonical(&expanded).map_err(|err| format!("{}: {}", typed, err))?)
+}
+
+/// Expands a leading `~` to the home directory, and `$VAR` or `${VAR}`
+/// anywhere to the value of that environment variable.
+pub fn expand(typed: &str) -> Result<PathBuf, String> {
+    let home = || env::var("HOME").map_err(|_| String::from("HOME isn't set"));
+    let rest = match typed.strip_prefix('~') {
+        Some("") => return Ok(PathBuf::from(home()?)),
+        Some(rest) if rest.starts_with('/') => {
+            return Ok(PathBuf::from(format!("{}{}", home()?, expand_vars(rest)?)))
+        }
+        _ => typed,
+    };
+    Ok(PathBuf::from(expand_vars(rest)?))
+}
+
+/// Replaces each `$VAR` or `${VAR}` in `text` with the variable's value.
+fn expand_vars(text: &str) -> Result<String, String> {
+    let mut expanded = String::new();
+    let mut rest = text;
+    while let Some(dollar) = rest.find('$') {
+        expanded.push_str(&rest[..dollar]);
+        let after = &rest[dollar + 1..];
+        let (name, remainder) = match after.strip_prefix('{') {
+            Some(braced) => {
+                let end = braced
+                    .find('}')
+                    .ok_or_else(|| format!("unclosed ${{ in {}", text))?;
+                (&braced[..end], &braced[end + 1..])
+            }
+            None => {
+                let end = after
+                    .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                    .unwrap_or(after.len());
+                (&after[..end], &after[end..])
+            }
+        };
+        if name.is_empty() {
+            // A `$` that doesn't start a variable is just a `$`.
+            expanded.push('$');
+        } else {
+            expanded.push_str(&env::var(name).map_err(|_| format!("{} isn't set", name))?);
+        }
+        rest = remainder;
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+/// The one true name for `path`: absolute, with `.`, `..` and symlinks
+/// resolved, so different ways of writing the same file agree. A file
+/// which doesn't exist yet is resolved as far as its directory is.
+pub fn canonical(path: &Path) -> io::Result<PathBuf> {
+    match fs::canonicalize(path) {
+        Ok(path) => Ok(path),
+        Err(err) if err.kind() == ErrorKind::NotFound => {
+            let name = path.file_name().ok_or(err)?;
+            let parent = match path.parent() {
+                Some(parent) if !parent.as_os_str().is_empty() => parent,
+                _ => Path::new("."),
+            };
+            Ok(canonical(parent)?.join(name))
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// Moves the file at `from` to `to`, copying it across and removing the
+/// original when they're on different filesystems, which a rename can't
+/// cross. Any other reason the rename fails is returned as it is.
+pub fn move_file(from: &Path, to: &Path) -> io::Result<()> {
+    match fs::rename(from, to) {
+        Err(err) if err.kind() == ErrorKind::CrossesDevices => {
+            fs::copy(from, to)?;
+            fs::remove_file(from)
+        }
+        result => result,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::harness::TempFile;
+
+    #[test]
+    fn home_and_variables_are_expanded() {
+        let home = env::var("HOME").unwrap();
+        assert_eq!(expand("~").unwrap(), PathBuf::from(&home));
+        assert_eq!(
+            expand("~/notes").unwrap(),
+            PathBuf::from(format!("{}/notes", home))
+        );
+        assert_eq!(
+            expand("$HOME/a").unwrap(),
+            PathBuf::from(format!("{}/a", home))
+        );
+        assert_eq!(
+            expand("${HOME}x").unwrap(),
+            PathBuf::from(format!("{}x", home))
+        );
+        // Only a leading `~` is the home directory.
+        assert_eq!(expand("a/~").unwrap(), PathBuf::from("a/~"));
+        assert_eq!(expand("cost$").unwrap(), PathBuf::from("cost$"));
+
+        assert_eq!(expand("${HOME/a").unwrap_err(), "unclosed ${ in ${HOME/a");
+        let unset = "$BUFFERS_TEST_UNSET_VARIABLE";
+        assert_eq!(
+            expand(unset).unwrap_err(),
+            "BUFFERS_TEST_UNSET_VARIABLE isn't set"
+        );
+    }
+
+    #[test]
+    fn every_way_of_writing_a_file_resolves_the_same() {
+        let file = TempFile::new("same", "");
+        let real = canonical(file.path()).unwrap();
+        let dir = file.path().parent().unwrap();
+        let name = file.path().file_name().unwrap().to_str().unwrap();
+        let link = file.path().with_extension("link");
+        std::os::unix::fs::symlink(file.path(), &link).unwrap();
+        for typed in [
+            format!("{}/./{}", dir.display(), name),
+            format!(
+                "{}/../{}/{}",
+                dir.display(),
+                dir.file_name().unwrap().to_str().unwrap(),
+                name
+            ),
+            link.display().to_string(),
+        ] {
+            assert_eq!(resolve(&typed).unwrap(), real, "{}", typed);
+        }
+        fs::remove_file(&link).unwrap();
+
+        // A file that isn't there yet resolves as far as its directory.
+        let new = dir.join(".").join("not-yet");
+        assert_eq!(canonical(&new).unwrap(), real.with_file_name("not-yet"));
+    }
+}
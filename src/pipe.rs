@@ -0,0 +1,237 @@
+//! Sending text through a shell command, and putting what it prints in
+//! its place, like `pipe notes sort -u` at the `>>` prompt. In the
+//! editor, Alt+! pipes the selection, or the whole buffer if nothing's
+//! selected, and in vim's normal mode `!` is an operator, so `!!` pipes
+//! the cursor's line and `!G` every line from there down.
+//!
+//! What a command prints can be put in at the cursor too, with `insert
+//! notes !date` at the prompt or Alt+R in the editor, like vim's `:r !`.
+
+use std::io::Write;
+use std::ops::Range;
+use std::process::{Command, Stdio};
+use std::thread;
+
+use termgame::{Game, Message};
+
+use crate::i18n::tr;
+use crate::prompt::{Prompt, Question};
+use crate::BufferEditor;
+
+/// What `command` prints, run by the shell with `input` as its standard
+/// input. A command which fails says why with what it printed to
+/// standard error, or else with how it exited.
+pub fn run(command: &str, input: &str) -> Result<String, String> {
+    run_capturing(command, input).map(|(stdout, _)| stdout)
+}
+
+/// What `command` prints to standard output and to standard error, as
+/// for [`run`].
+pub fn run_capturing(command: &str, input: &str) -> Result<(String, String), String> {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+    capture(shell, command, input)
+}
+
+/// What `program` prints to standard output and to standard error, run
+/// with `input` as its standard input, and called `command` if it
+/// fails.
+pub fn capture(
+    mut program: Command,
+    command: &str,
+    input: &str,
+) -> Result<(String, String), String> {
+    let mut child = program
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| {
+            tr(
+                "pipe.couldnt-run",
+                &[("command", &command), ("error", &err)],
+            )
+        })?;
+    // The input's written from another thread, so a command which prints
+    // a lot before it's read everything can't leave both sides waiting.
+    let mut stdin = child
+        .stdin
+        .take()
+        .ok_or_else(|| tr("pipe.couldnt-write", &[]))?;
+    let input = String::from(input);
+    let writer = thread::spawn(move || stdin.write_all(input.as_bytes()));
+    let output = child
+        .wait_with_output()
+        .map_err(|err| tr("pipe.unfinished", &[("command", &command), ("error", &err)]))?;
+    // A command which doesn't read all of its input, like `head`, closes
+    // the pipe early, which is fine.
+    let _ = writer.join();
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(match stderr.lines().find(|line| !line.trim().is_empty()) {
+            Some(line) => tr(
+                "pipe.failed",
+                &[("command", &command), ("error", &line.trim())],
+            ),
+            None => tr(
+                "pipe.exited",
+                &[("command", &command), ("status", &output.status)],
+            ),
+        });
+    }
+    let stdout = String::from_utf8(output.stdout)
+        .map_err(|_| tr("pipe.not-utf8", &[("command", &command)]))?;
+    let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+    Ok((stdout, stderr))
+}
+
+/// What a command printed to standard error, on one line for the
+/// message area.
+pub fn one_line(stderr: &str) -> Option<String> {
+    let lines: Vec<&str> = stderr
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect();
+    (!lines.is_empty()).then(|| lines.join("; "))
+}
+
+impl BufferEditor {
+    /// Asks which command to pipe `range` of the buffer through.
+    pub fn start_pipe(&mut self, range: Range<usize>) {
+        let prompt = Prompt::new(&tr("editor.pipe-prompt", &[]), "");
+        self.prompt = Some((prompt, Question::Pipe { range }));
+    }
+
+    /// Asks which command to pipe the selection through, or the whole
+    /// buffer if nothing's selected.
+    pub fn start_pipe_selection(&mut self) {
+        let range = self
+            .buffer
+            .selection()
+            .unwrap_or(0..self.buffer.text().len());
+        self.start_pipe(range);
+    }
+
+    /// Replaces `range` with what the command typed at the prompt prints
+    /// when it's given that text.
+    pub fn pipe_answer(&mut self, game: &mut Game, range: Range<usize>, command: &str) {
+        // The Enter which answered isn't an edit for Ctrl+. to make again.
+        self.repeat.forget();
+        let command = command.trim();
+        if command.is_empty() {
+            return;
+        }
+        match run(command, &self.buffer.text()[range.clone()]) {
+            Ok(output) => {
+                self.buffer.clear_selection();
+                if output != self.buffer.text()[range.clone()] {
+                    self.buffer.replace_range(range.clone(), &output);
+                }
+                self.buffer.set_cursor(range.start);
+                self.scroll_to_cursor(game);
+            }
+            Err(err) => game.set_message(Some(Message::new(err))),
+        }
+    }
+
+    /// Asks which command's output to put in at the cursor.
+    pub fn start_read(&mut self) {
+        let prompt = Prompt::new(&tr("editor.read-prompt", &[]), "");
+        self.prompt = Some((prompt, Question::Read));
+    }
+
+    /// Puts in what the command typed at the prompt prints, showing
+    /// anything it printed to standard error.
+    pub fn read_answer(&mut self, game: &mut Game, command: &str) {
+        self.repeat.forget();
+        let command = command.trim();
+        if command.is_empty() {
+            return;
+        }
+        let message = match self.insert_output(command) {
+            Ok(stderr) => stderr.as_deref().and_then(one_line),
+            Err(err) => Some(err),
+        };
+        self.scroll_to_cursor(game);
+        game.set_message(message.map(Message::new));
+    }
+
+    /// Runs `command` and puts what it prints in at the cursor, which
+    /// ends up after it. Unless the cursor's at the start of a line,
+    /// the newline output usually ends with is left off, so `date` goes
+    /// in as part of the line. Returns what the command printed to
+    /// standard error, if anything.
+    pub fn insert_output(&mut self, command: &str) -> Result<Option<String>, String> {
+        let (mut stdout, stderr) = run_capturing(command, "")?;
+        let cursor = self.buffer.cursor();
+        let line_start = cursor == 0 || self.buffer.text()[..cursor].ends_with('\n');
+        if !line_start && stdout.ends_with('\n') {
+            stdout.pop();
+            if stdout.ends_with('\r') {
+                stdout.pop();
+            }
+        }
+        self.buffer.clear_selection();
+        if !stdout.is_empty() {
+            self.buffer.replace_range(cursor..cursor, &stdout);
+        }
+        self.buffer.set_cursor(cursor + stdout.len());
+        Ok((!stderr.trim().is_empty()).then_some(stderr))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use termgame::KeyCode;
+
+    use crate::harness::{alt, key, shift_key, Harness};
+    use crate::vim::EditingMode;
+
+    #[test]
+    fn text_is_replaced_by_what_the_command_prints() {
+        assert_eq!(run("sort -u", "b\na\nb\n").unwrap(), "a\nb\n");
+        assert_eq!(run("tr a-z A-Z", "shout").unwrap(), "SHOUT");
+        assert_eq!(
+            run("echo nope >&2; exit 3", "").unwrap_err(),
+            "echo nope >&2; exit 3 failed: nope"
+        );
+    }
+
+    #[test]
+    fn alt_bang_and_vim_bang_pipe_text_through_a_command() {
+        let mut harness = Harness::new("c\nb\na\nkeep\n");
+        harness
+            .run([shift_key(KeyCode::Down), shift_key(KeyCode::Down)])
+            .run([shift_key(KeyCode::Down), alt('!')])
+            .type_text("sort\n");
+        assert_eq!(harness.text(), "a\nb\nc\nkeep\n");
+        assert_eq!(harness.editor.buffer.selection(), None);
+        harness.run([alt('!')]).type_text("false\n");
+        assert_eq!(harness.message(), Some("false failed (exit status: 1)"));
+        assert_eq!(harness.text(), "a\nb\nc\nkeep\n");
+
+        let mut harness = Harness::new("one\ntwo\nthree\n");
+        harness.editor.session.editing_mode = EditingMode::Vim;
+        harness.type_text("j!!rev\n");
+        assert_eq!(harness.text(), "one\nowt\nthree\n");
+        harness.type_text("gg!jtr a-z A-Z\n");
+        assert_eq!(harness.text(), "ONE\nOWT\nthree\n");
+    }
+
+    #[test]
+    fn alt_r_puts_in_what_a_command_prints() {
+        let mut harness = Harness::new("Today is .\n");
+        harness.editor.buffer.set_cursor(9);
+        harness.run([alt('r')]).type_text("echo Monday\n");
+        assert_eq!(harness.text(), "Today is Monday.\n");
+        assert_eq!(harness.editor.buffer.cursor(), 15);
+
+        harness.run([key(KeyCode::Down), alt('r')]);
+        harness.type_text("printf 'a\\nb\\n'; echo careful >&2\n");
+        assert_eq!(harness.text(), "Today is Monday.\na\nb\n");
+        assert_eq!(harness.message(), Some("careful"));
+    }
+}
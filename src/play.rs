@@ -0,0 +1,219 @@
+//! Playing the keys of a recorded session back into a buffer, for
+//! tutorials or to reproduce a bug.
+//!
+//! While a recording plays, the keyboard controls playback instead of
+//! editing:
+//! - Space pauses and resumes,
+//! - `.` presses the next recorded key, while paused,
+//! - `+` and `-` play faster and slower,
+//! - Esc stops playing, leaving the buffer as it got to.
+//!
+//! Once it's played to the end, the buffer can be edited as normal.
+
+use termgame::{Controller, Game, GameEvent, KeyCode, Message, SimpleEvent};
+
+use std::time::Duration;
+
+use crate::i18n::tr;
+use crate::BufferEditor;
+
+/// How much faster or slower than it was recorded playback can go.
+const SPEEDS: (f64, f64) = (0.125, 16.0);
+
+/// A recording being played back.
+#[derive(Debug, Clone)]
+pub struct Playback {
+    /// Every key in the recording, with when it was pressed.
+    keys: Vec<(Duration, GameEvent)>,
+    /// The index of the next key to press.
+    next: usize,
+    /// How far into the recording playback is.
+    position: Duration,
+    speed: f64,
+    paused: bool,
+}
+
+impl Playback {
+    /// Plays `keys` at `speed` times the speed they were recorded at.
+    pub fn new(keys: Vec<(Duration, GameEvent)>, speed: f64) -> Playback {
+        Playback {
+            keys,
+            next: 0,
+            position: Duration::ZERO,
+            speed: speed.clamp(SPEEDS.0, SPEEDS.1),
+            paused: false,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next == self.keys.len()
+    }
+
+    /// Moves on by one tick, returning the keys which are due.
+    pub fn tick(&mut self, tick: Duration) -> Vec<GameEvent> {
+        if self.paused {
+            return Vec::new();
+        }
+        self.position += tick.mul_f64(self.speed);
+        let due = self.keys[self.next..]
+            .iter()
+            .take_while(|(time, _)| *time <= self.position)
+            .count();
+        self.next += due;
+        self.keys[self.next - due..self.next]
+            .iter()
+            .map(|(_, key)| key.clone())
+            .collect()
+    }
+
+    /// Skips straight to the next key.
+    pub fn step(&mut self) -> Option<GameEvent> {
+        let (time, key) = self.keys.get(self.next)?.clone();
+        self.next += 1;
+        self.position = self.position.max(time);
+        Some(key)
+    }
+
+    /// Where playback is up to, for the status bar.
+    pub fn status(&self) -> String {
+        let key = if self.paused {
+            "play.paused"
+        } else {
+            "play.playing"
+        };
+        tr(
+            key,
+            &[
+                ("key", &self.next),
+                ("keys", &self.keys.len()),
+                ("speed", &self.speed),
+            ],
+        )
+    }
+}
+
+impl BufferEditor {
+    /// Handles a key pressed while a recording is playing. Returns
+    /// `false` if the key isn't for playback, because it's finished.
+    pub fn playback_event(&mut self, game: &mut Game, event: &SimpleEvent) -> bool {
+        let Some(playback) = &mut self.playback else {
+            return false;
+        };
+        if playback.is_finished() {
+            self.playback = None;
+            return false;
+        }
+        match event {
+            SimpleEvent::Just(KeyCode::Char(' ')) => playback.paused = !playback.paused,
+            SimpleEvent::Just(KeyCode::Char('+')) => {
+                playback.speed = (playback.speed * 2.0).min(SPEEDS.1)
+            }
+            SimpleEvent::Just(KeyCode::Char('-')) => {
+                playback.speed = (playback.speed / 2.0).max(SPEEDS.0)
+            }
+            SimpleEvent::Just(KeyCode::Char('.')) if playback.paused => {
+                if let Some(key) = playback.step() {
+                    self.press_recorded(game, vec![key]);
+                }
+            }
+            SimpleEvent::Just(KeyCode::Esc) => {
+                game.set_message(Some(Message::new(tr("play.stopped", &[]))));
+                self.playback = None;
+            }
+            _ => {}
+        }
+        self.render(game);
+        true
+    }
+
+    /// Presses any recorded keys which are due by now.
+    pub fn playback_tick(&mut self, game: &mut Game) {
+        let Some(playback) = &mut self.playback else {
+            return;
+        };
+        let keys = playback.tick(self.config.tick());
+        let finished = playback.is_finished();
+        if !keys.is_empty() {
+            self.press_recorded(game, keys);
+        }
+        if finished {
+            game.set_message(Some(Message::new(tr("play.finished", &[]))));
+            self.playback = None;
+            self.render(game);
+        } else if self.playback.is_some() {
+            self.render_status(game);
+        }
+    }
+
+    /// Presses `keys`, as if they were typed.
+    fn press_recorded(&mut self, game: &mut Game, keys: Vec<GameEvent>) {
+        // Out of the way, so the keys go to the buffer and not to
+        // playback.
+        let playback = self.playback.take();
+        for key in keys {
+            self.on_event(game, key);
+        }
+        // A recorded Esc closes the editor, and playback with it.
+        if !game.game_will_end() {
+            self.playback = playback;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::harness::{key, Harness};
+
+    /// `text` typed one key every half a second.
+    fn playing(text: &str) -> Playback {
+        let keys = text
+            .chars()
+            .enumerate()
+            .map(|(i, c)| (Duration::from_millis(500 * i as u64), key(KeyCode::Char(c))))
+            .collect();
+        Playback::new(keys, 1.0)
+    }
+
+    #[test]
+    fn keys_are_pressed_when_they_were_recorded() {
+        let mut harness = Harness::new("");
+        harness.editor.playback = Some(playing("abc"));
+        harness.tick(1);
+        assert_eq!(harness.text(), "a");
+        // Half a second is 20 ticks.
+        harness.tick(19);
+        assert_eq!(harness.text(), "ab");
+        harness.tick(20);
+        assert_eq!(harness.text(), "abc");
+        assert_eq!(harness.message(), Some("Finished playing"));
+
+        // Once it's finished, typing edits again.
+        harness.type_text("d");
+        assert_eq!(harness.text(), "abcd");
+    }
+
+    #[test]
+    fn paused_playback_only_moves_a_step_at_a_time() {
+        let mut harness = Harness::new("");
+        harness.editor.playback = Some(playing("abc"));
+        harness.tick(1).press(KeyCode::Char(' ')).tick(100);
+        assert_eq!(harness.text(), "a");
+        harness.press(KeyCode::Char('.'));
+        assert_eq!(harness.text(), "ab");
+
+        // Typing while it plays doesn't go into the buffer.
+        harness.type_text("x");
+        assert_eq!(harness.text(), "ab");
+    }
+
+    #[test]
+    fn escape_stops_playing() {
+        let mut harness = Harness::new("");
+        harness.editor.playback = Some(playing("abc"));
+        harness.tick(1).press(KeyCode::Esc).tick(100);
+        assert_eq!(harness.text(), "a");
+        assert!(!harness.ended());
+    }
+}
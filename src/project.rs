@@ -0,0 +1,360 @@
+//! Search and replace across every file in the project (i.e. the
+//! current directory), rather than just the open buffers, and finding
+//! the project's files by [`glob`].
+//!
+//! Replacing happens in two steps: [`plan`] works out every change and
+//! [`preview`] shows them in a buffer, where each hunk can be ticked or
+//! unticked; then [`apply`] writes the ticked hunks out.
+
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::backup;
+use crate::i18n::tr;
+use crate::paths;
+use crate::replace::{self, ReplaceOptions};
+use crate::save;
+
+/// One changed line.
+#[derive(Debug, Clone)]
+pub struct Hunk {
+    /// The (0-indexed) line number.
+    pub line: usize,
+    pub old: String,
+    pub new: String,
+}
+
+/// Every change planned for one file.
+#[derive(Debug, Clone)]
+pub struct FileChange {
+    pub path: PathBuf,
+    /// What the file held when the changes were planned, so `apply` can
+    /// tell whether it's been changed since.
+    pub original: String,
+    pub hunks: Vec<Hunk>,
+}
+
+/// Works out what replacing `pattern` with `replacement` would do to
+/// every file under `root`. Matches are found a line at a time.
+///
+/// Hidden files and directories, `target` directories, and files that
+/// aren't UTF-8 are skipped.
+pub fn plan(
+    root: &Path,
+    pattern: &str,
+    replacement: &str,
+    options: ReplaceOptions,
+) -> Result<Vec<FileChange>, Box<dyn Error>> {
+    let mut changes = Vec::new();
+    for path in project_files(root)? {
+        // `./src/main.rs` is just `src/main.rs`.
+        let path = match path.strip_prefix(".") {
+            Ok(relative) => relative.to_path_buf(),
+            Err(_) => path,
+        };
+        let Ok(original) = fs::read_to_string(&path) else {
+            continue;
+        };
+        let mut hunks = Vec::new();
+        for (line, old) in original.lines().enumerate() {
+            let (new, count) = replace::replace(old, pattern, replacement, options)?;
+            if count > 0 {
+                hunks.push(Hunk {
+                    line,
+                    old: String::from(old),
+                    new,
+                });
+            }
+        }
+        if !hunks.is_empty() {
+            changes.push(FileChange {
+                path,
+                original,
+                hunks,
+            });
+        }
+    }
+    Ok(changes)
+}
+
+/// Every file under `root` worth searching, in a stable order.
+fn project_files(root: &Path) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let mut files = Vec::new();
+    let mut entries: Vec<_> = fs::read_dir(root)?.collect::<Result<_, _>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if name.starts_with('.') || name == "target" {
+            continue;
+        }
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            files.extend(project_files(&entry.path())?);
+        } else if file_type.is_file() {
+            files.push(entry.path());
+        }
+    }
+    Ok(files)
+}
+
+/// Whether `text` has any wildcards in it, and so is a [`glob`] pattern.
+pub fn is_glob(text: &str) -> bool {
+    text.contains(['*', '?'])
+}
+
+/// Every file matching `pattern`, like `src/**/*.rs`, written the way the
+/// pattern starts. `*` and `?` match within a name, and `**` matches any
+/// number of directories. Files are found the same way `replace-all`
+/// finds them, so hidden files and `target` are left out.
+pub fn glob(pattern: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    let segments: Vec<&str> = pattern.split('/').collect();
+    let fixed = segments
+        .iter()
+        .take_while(|segment| !is_glob(segment))
+        .count();
+    let (base, rest) = segments.split_at(fixed);
+    let base = base.join("/");
+    let root = match base.as_str() {
+        "" => PathBuf::from("."),
+        base => paths::expand(base)?,
+    };
+    if !root.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut matches = Vec::new();
+    for path in project_files(&root)? {
+        let Ok(relative) = path.strip_prefix(&root) else {
+            continue;
+        };
+        let names: Vec<String> = relative
+            .iter()
+            .map(|name| name.to_string_lossy().into_owned())
+            .collect();
+        if glob_matches(rest, &names) {
+            matches.push(match base.as_str() {
+                "" => names.join("/"),
+                base => format!("{}/{}", base, names.join("/")),
+            });
+        }
+    }
+    Ok(matches)
+}
+
+/// Whether the path made of `names` matches the pattern made of `segments`.
+fn glob_matches(segments: &[&str], names: &[String]) -> bool {
+    match (segments.split_first(), names.split_first()) {
+        (None, None) => true,
+        (Some((&"**", rest)), _) => {
+            glob_matches(rest, names) || (!names.is_empty() && glob_matches(segments, &names[1..]))
+        }
+        (Some((segment, rest)), Some((name, names))) => {
+            let segment: Vec<char> = segment.chars().collect();
+            let name: Vec<char> = name.chars().collect();
+            name_matches(&segment, &name) && glob_matches(rest, names)
+        }
+        _ => false,
+    }
+}
+
+/// Whether `name` matches `pattern`, which can have `*` and `?` in it.
+fn name_matches(pattern: &[char], name: &[char]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some(('*', rest)) => (0..=name.len()).any(|i| name_matches(rest, &name[i..])),
+        Some(('?', rest)) => !name.is_empty() && name_matches(rest, &name[1..]),
+        Some((c, rest)) => name.first() == Some(c) && name_matches(rest, &name[1..]),
+    }
+}
+
+/// Describes every hunk, each behind a `[x]` checkbox. Changing a box to
+/// `[ ]` rejects that hunk.
+pub fn preview(changes: &[FileChange]) -> String {
+    let mut text = String::new();
+    for change in changes {
+        for hunk in &change.hunks {
+            text.push_str(&format!(
+                "[x] {}:{}\n- {}\n+ {}\n",
+                change.path.display(),
+                hunk.line + 1,
+                hunk.old,
+                hunk.new
+            ));
+        }
+    }
+    text
+}
+
+/// Reads the checkboxes back out of a preview: whether each hunk, in
+/// order, was accepted.
+pub fn accepted(preview: &str) -> Vec<bool> {
+    preview
+        .lines()
+        .filter_map(|line| match line.get(..3) {
+            Some("[x]") | Some("[X]") => Some(true),
+            Some("[ ]") => Some(false),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Writes out the accepted hunks of every change. `accepted` has an
+/// entry for every hunk of every change, in order.
+///
+/// Either every file is written or none are: if any file changed on
+/// disk since the changes were planned, or can't be written, nothing
+/// is replaced, and if one can't be replaced, the ones that were are
+/// put back. With `backups`, the previous version of each file is
+/// backed up first, keeping that many backups. Returns the paths and
+/// new contents of the files which were changed.
+pub fn apply(
+    changes: &[FileChange],
+    accepted: &[bool],
+    backups: Option<usize>,
+) -> Result<Vec<(PathBuf, String)>, Box<dyn Error>> {
+    let hunk_count: usize = changes.iter().map(|change| change.hunks.len()).sum();
+    if accepted.len() != hunk_count {
+        return Err(tr(
+            "apply-replace.wrong-count",
+            &[("expected", &hunk_count), ("found", &accepted.len())],
+        )
+        .into());
+    }
+
+    let mut accepted = accepted.iter();
+    let mut updates = Vec::new();
+    let mut originals = Vec::new();
+    for change in changes {
+        let hunks: Vec<&Hunk> = change
+            .hunks
+            .iter()
+            .filter(|_| *accepted.next().unwrap())
+            .collect();
+        if hunks.is_empty() {
+            continue;
+        }
+        if fs::read_to_string(&change.path)? != change.original {
+            return Err(tr(
+                "apply-replace.changed-since",
+                &[("path", &change.path.display())],
+            )
+            .into());
+        }
+        updates.push((change.path.clone(), rewrite(&change.original, &hunks)));
+        originals.push(change.original.as_str());
+    }
+
+    // Write everything to the side first, with the permissions of the
+    // file it replaces, and back the files up, so a failure before
+    // anything's replaced leaves every real file untouched.
+    let mut written = Vec::new();
+    let staged = updates.iter().try_for_each(|(path, text)| {
+        let temp = save::temp_path(path);
+        written.push(temp.clone());
+        fs::write(&temp, text)?;
+        fs::set_permissions(&temp, fs::metadata(path)?.permissions())?;
+        if let Some(keep) = backups {
+            backup::back_up(path, keep)?;
+        }
+        Ok::<_, io::Error>(())
+    });
+    if let Err(err) = staged {
+        for temp in &written {
+            let _ = fs::remove_file(temp);
+        }
+        let path = &updates[written.len() - 1].0;
+        return Err(format!("{}: {}", path.display(), err).into());
+    }
+
+    for (i, ((path, _), temp)) in updates.iter().zip(&written).enumerate() {
+        if let Err(err) = fs::rename(temp, path) {
+            // Put back the files which were already replaced, and tidy
+            // up the ones which weren't.
+            for ((path, _), original) in updates.iter().zip(&originals).take(i) {
+                let _ = save::write_file(path, original.as_bytes(), None);
+            }
+            for temp in &written[i..] {
+                let _ = fs::remove_file(temp);
+            }
+            return Err(format!("{}: {}", path.display(), err).into());
+        }
+    }
+
+    Ok(updates)
+}
+
+/// Swaps the given hunks' lines into `original`.
+fn rewrite(original: &str, hunks: &[&Hunk]) -> String {
+    original
+        .split_inclusive('\n')
+        .enumerate()
+        .map(
+            |(line, text)| match hunks.iter().find(|hunk| hunk.line == line) {
+                Some(hunk) => {
+                    let ending = &text[text.trim_end_matches(['\r', '\n']).len()..];
+                    format!("{}{}", hunk.new, ending)
+                }
+                None => String::from(text),
+            },
+        )
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, path: &str) -> bool {
+        let segments: Vec<&str> = pattern.split('/').collect();
+        let names: Vec<String> = path.split('/').map(String::from).collect();
+        glob_matches(&segments, &names)
+    }
+
+    #[test]
+    fn globs_match_across_directories() {
+        assert!(matches("src/**/*.rs", "src/main.rs"));
+        assert!(matches("src/**/*.rs", "src/a/b/lib.rs"));
+        assert!(!matches("src/*.rs", "src/a/lib.rs"));
+        assert!(matches("*.t?t", "notes.txt"));
+        assert!(!matches("*.rs", "main.rs.bak"));
+    }
+
+    #[test]
+    fn files_keep_their_permissions_and_are_all_replaced_or_none_are() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("buffers-apply-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (a, b) = (dir.join("a.sh"), dir.join("b.txt"));
+        fs::write(&a, "echo one\n").unwrap();
+        fs::write(&b, "one\n").unwrap();
+        fs::set_permissions(&a, fs::Permissions::from_mode(0o755)).unwrap();
+        let change = |path: &Path, original: &str| FileChange {
+            path: path.to_path_buf(),
+            original: String::from(original),
+            hunks: vec![Hunk {
+                line: 0,
+                old: String::from(original.trim_end()),
+                new: original.trim_end().replace("one", "two"),
+            }],
+        };
+        let changes = [change(&a, "echo one\n"), change(&b, "one\n")];
+
+        // Nothing's replaced when one of them can't be written.
+        fs::create_dir_all(save::temp_path(&b).join("in-the-way")).unwrap();
+        assert!(apply(&changes, &[true, true], None).is_err());
+        assert_eq!(fs::read_to_string(&a).unwrap(), "echo one\n");
+        assert!(!save::temp_path(&a).exists());
+        fs::remove_dir_all(save::temp_path(&b)).unwrap();
+
+        apply(&changes, &[true, true], None).unwrap();
+        assert_eq!(fs::read_to_string(&a).unwrap(), "echo two\n");
+        assert_eq!(fs::read_to_string(&b).unwrap(), "two\n");
+        let mode = fs::metadata(&a).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o755);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
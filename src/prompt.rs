@@ -0,0 +1,75 @@
+//! A line of input typed into the status bar, for when the editor needs
+//! to ask something, like a file name.
+
+use std::ops::Range;
+
+use termgame::{KeyCode, SimpleEvent};
+
+use crate::dired::DirAction;
+
+/// A question being asked in the status bar, and the answer so far.
+#[derive(Debug, Clone)]
+pub struct Prompt {
+    pub label: String,
+    pub input: String,
+}
+
+/// What a prompt's answer is for.
+#[derive(Debug, Clone)]
+pub enum Question {
+    /// Something in a directory buffer.
+    Dired(DirAction),
+    /// The file to save a buffer which doesn't have one yet.
+    SaveAs,
+    /// What to find, starting from the offset the cursor was on.
+    Find { from: usize },
+    /// Which line to go to.
+    Goto,
+    /// Which shell command to pipe this part of the buffer through.
+    Pipe { range: Range<usize> },
+    /// Which shell command's output to put in at the cursor.
+    Read,
+    /// Whether to reload the buffer's file, or write over it, now that
+    /// something else has changed it.
+    Changed,
+}
+
+/// What a key did to a [`Prompt`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PromptResult {
+    /// Still being typed.
+    Editing,
+    /// Enter was pressed, with this answer.
+    Done(String),
+    /// Esc was pressed.
+    Cancelled,
+}
+
+impl Prompt {
+    /// Asks `label`, with `input` already typed in as a suggestion.
+    pub fn new(label: &str, input: &str) -> Prompt {
+        Prompt {
+            label: String::from(label),
+            input: String::from(input),
+        }
+    }
+
+    /// Handles a key pressed while the prompt is up.
+    pub fn handle(&mut self, event: &SimpleEvent) -> PromptResult {
+        match event {
+            SimpleEvent::Just(KeyCode::Char(c)) => self.input.push(*c),
+            SimpleEvent::Just(KeyCode::Backspace) => {
+                self.input.pop();
+            }
+            SimpleEvent::Just(KeyCode::Enter) => return PromptResult::Done(self.input.clone()),
+            SimpleEvent::Just(KeyCode::Esc) => return PromptResult::Cancelled,
+            _ => {}
+        }
+        PromptResult::Editing
+    }
+
+    /// What to show in the status bar.
+    pub fn display(&self) -> String {
+        format!("{}{}", self.label, self.input)
+    }
+}
@@ -0,0 +1,72 @@
+//! The files opened most recently, newest first, kept in
+//! `~/.local/share/buffers/recent` (one path a line) for the dashboard.
+
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::PathBuf;
+
+use crate::config::data_dir;
+
+/// How many files are remembered.
+const LIMIT: usize = 10;
+
+fn path() -> Option<PathBuf> {
+    Some(data_dir()?.join("recent"))
+}
+
+/// The recent files, newest first. If they can't be read, there just
+/// aren't any.
+pub fn list() -> Vec<String> {
+    let Some(path) = path() else {
+        return Vec::new();
+    };
+    match fs::read_to_string(&path) {
+        Ok(text) => text.lines().map(String::from).collect(),
+        Err(err) => {
+            if err.kind() != ErrorKind::NotFound {
+                tracing::warn!(path = %path.display(), error = %err, "couldn't read the recent files");
+            }
+            Vec::new()
+        }
+    }
+}
+
+/// Puts `file` at the top of the recent files.
+pub fn add(file: &str) -> io::Result<()> {
+    let Some(path) = path() else {
+        return Ok(());
+    };
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut text = updated(list(), file).join("\n");
+    text.push('\n');
+    fs::write(path, text)
+}
+
+/// `recent` with `file` moved, or added, to the top, and the oldest
+/// dropped past the limit.
+fn updated(mut recent: Vec<String>, file: &str) -> Vec<String> {
+    recent.retain(|other| other != file);
+    recent.insert(0, String::from(file));
+    recent.truncate(LIMIT);
+    recent
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reopening_a_file_moves_it_to_the_top() {
+        let recent: Vec<String> = (0..LIMIT).map(|i| format!("/{}", i)).collect();
+        let recent = updated(recent, "/3");
+        assert_eq!(recent[..2], ["/3", "/0"]);
+        assert_eq!(recent.len(), LIMIT);
+
+        let recent = updated(recent, "/new");
+        assert_eq!(recent[0], "/new");
+        assert_eq!(recent.len(), LIMIT);
+        assert!(!recent.contains(&String::from("/9")));
+    }
+}
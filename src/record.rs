@@ -0,0 +1,396 @@
+//! Recording editor sessions as asciinema (v2) cast files, so they can
+//! be played back with `asciinema play` or shared on the web.
+//!
+//! A cast file is a line of JSON describing the terminal, then a line
+//! per event: `[seconds, "o", text]` for something drawn on screen, and
+//! `[seconds, "i", text]` for a key pressed. Each frame is written as
+//! the whole screen, redrawn from the top, and keys are written as the
+//! bytes a terminal would send for them.
+//!
+//! The keys can be read back out of a cast, for `play`.
+
+use termgame::{Game, GameEvent, KeyCode, Message, SimpleEvent, SCREEN_HEIGHT, SCREEN_WIDTH};
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::iter::Peekable;
+use std::path::{Path, PathBuf};
+use std::str::Chars;
+use std::time::Duration;
+
+use crate::i18n::tr;
+use crate::json;
+use crate::BufferEditor;
+
+/// A recording in progress.
+pub struct Recording {
+    path: PathBuf,
+    out: BufWriter<File>,
+    /// When the recording started, by the session's clock.
+    started: Duration,
+    last_frame: String,
+}
+
+impl Recording {
+    /// Starts recording into a new file at `path`, as of `now`.
+    pub fn start(path: &Path, now: Duration) -> io::Result<Recording> {
+        let mut out = BufWriter::new(File::create(path)?);
+        // What's recorded is the inside of the editor's border.
+        writeln!(
+            out,
+            "{{\"version\": 2, \"width\": {}, \"height\": {}}}",
+            SCREEN_WIDTH - 2,
+            SCREEN_HEIGHT - 2
+        )?;
+        Ok(Recording {
+            path: PathBuf::from(path),
+            out,
+            started: now,
+            last_frame: String::new(),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn write_event(&mut self, now: Duration, kind: &str, data: &str) -> io::Result<()> {
+        let seconds = now.saturating_sub(self.started).as_secs_f64();
+        writeln!(
+            self.out,
+            "[{:.6}, \"{}\", {}]",
+            seconds,
+            kind,
+            json::string(data)
+        )
+    }
+
+    /// Records the screen as `rows`, with the cursor at (column, row) if
+    /// it's showing. Nothing is written if the screen hasn't changed.
+    pub fn frame(
+        &mut self,
+        now: Duration,
+        rows: &[String],
+        cursor: Option<(usize, usize)>,
+    ) -> io::Result<()> {
+        // Clear the screen, and draw it all from the top left.
+        let mut frame = String::from("\x1b[H\x1b[2J");
+        frame.push_str(&rows.join("\r\n"));
+        match cursor {
+            Some((col, row)) => frame.push_str(&format!("\x1b[{};{}H\x1b[?25h", row + 1, col + 1)),
+            None => frame.push_str("\x1b[?25l"),
+        }
+        if frame == self.last_frame {
+            return Ok(());
+        }
+        self.write_event(now, "o", &frame)?;
+        self.last_frame = frame;
+        Ok(())
+    }
+
+    /// Records `event` being pressed, if it's a key a terminal can send.
+    pub fn key(&mut self, now: Duration, event: &SimpleEvent) -> io::Result<()> {
+        match encode_key(event) {
+            Some(bytes) => self.write_event(now, "i", &bytes),
+            None => Ok(()),
+        }
+    }
+
+    /// Stops recording, and makes sure everything's been written.
+    pub fn finish(mut self) -> io::Result<PathBuf> {
+        self.out.flush()?;
+        Ok(self.path)
+    }
+}
+
+/// The bytes a terminal sends for `event`.
+pub fn encode_key(event: &SimpleEvent) -> Option<String> {
+    let key = |code: &KeyCode| -> Option<String> {
+        let bytes = match code {
+            KeyCode::Char(c) => return Some(c.to_string()),
+            KeyCode::Enter => "\r",
+            KeyCode::Tab => "\t",
+            KeyCode::Backspace => "\x7f",
+            KeyCode::Esc => "\x1b",
+            KeyCode::Up => "\x1b[A",
+            KeyCode::Down => "\x1b[B",
+            KeyCode::Right => "\x1b[C",
+            KeyCode::Left => "\x1b[D",
+            KeyCode::Home => "\x1b[H",
+            KeyCode::End => "\x1b[F",
+            KeyCode::Insert => "\x1b[2~",
+            KeyCode::Delete => "\x1b[3~",
+            KeyCode::PageUp => "\x1b[5~",
+            KeyCode::PageDown => "\x1b[6~",
+            _ => return None,
+        };
+        Some(String::from(bytes))
+    };
+    let control = |code: &KeyCode| match code {
+        KeyCode::Char(c) if c.is_ascii_alphabetic() => {
+            Some(char::from(c.to_ascii_lowercase() as u8 & 0x1f).to_string())
+        }
+        _ => None,
+    };
+    match event {
+        SimpleEvent::Just(code) => key(code),
+        SimpleEvent::WithControl(code) => control(code),
+        SimpleEvent::WithAlt(code) => key(code).map(|bytes| format!("\x1b{}", bytes)),
+        SimpleEvent::WithControlAlt(code) => control(code).map(|bytes| format!("\x1b{}", bytes)),
+        SimpleEvent::ComplexEvent(_) => None,
+    }
+}
+
+/// The keys in `bytes`, as a terminal sends them. This undoes
+/// [`encode_key`], and understands what most terminals send besides.
+pub fn decode_keys(bytes: &str) -> Vec<GameEvent> {
+    let mut chars = bytes.chars().peekable();
+    let mut keys = Vec::new();
+    while chars.peek().is_some() {
+        if let Some(key) = decode_key(&mut chars) {
+            keys.push(key.into());
+        }
+    }
+    keys
+}
+
+/// Takes one key off the front of `chars`. Returns `None` for a key
+/// this doesn't know, which is skipped.
+fn decode_key(chars: &mut Peekable<Chars>) -> Option<SimpleEvent> {
+    let c = chars.next()?;
+    if c != '\x1b' {
+        return Some(decode_byte(c));
+    }
+    if chars.next_if_eq(&'[').is_none() {
+        // Escape before another key is that key with Alt held.
+        return match chars.peek() {
+            None => Some(SimpleEvent::Just(KeyCode::Esc)),
+            Some(_) => match decode_key(chars)? {
+                SimpleEvent::Just(code) => Some(SimpleEvent::WithAlt(code)),
+                SimpleEvent::WithControl(code) => Some(SimpleEvent::WithControlAlt(code)),
+                _ => None,
+            },
+        };
+    }
+
+    let mut number = String::new();
+    while let Some(digit) = chars.next_if(|c| c.is_ascii_digit()) {
+        number.push(digit);
+    }
+    let code = match (number.as_str(), chars.next()) {
+        ("", Some('A')) => KeyCode::Up,
+        ("", Some('B')) => KeyCode::Down,
+        ("", Some('C')) => KeyCode::Right,
+        ("", Some('D')) => KeyCode::Left,
+        ("", Some('H')) | ("1", Some('~')) => KeyCode::Home,
+        ("", Some('F')) | ("4", Some('~')) => KeyCode::End,
+        ("2", Some('~')) => KeyCode::Insert,
+        ("3", Some('~')) => KeyCode::Delete,
+        ("5", Some('~')) => KeyCode::PageUp,
+        ("6", Some('~')) => KeyCode::PageDown,
+        _ => return None,
+    };
+    Some(SimpleEvent::Just(code))
+}
+
+/// The key for a single character a terminal sends.
+fn decode_byte(c: char) -> SimpleEvent {
+    match c {
+        '\r' | '\n' => SimpleEvent::Just(KeyCode::Enter),
+        '\t' => SimpleEvent::Just(KeyCode::Tab),
+        '\x7f' | '\x08' => SimpleEvent::Just(KeyCode::Backspace),
+        '\x1b' => SimpleEvent::Just(KeyCode::Esc),
+        c if (c as u32) < 0x20 => {
+            SimpleEvent::WithControl(KeyCode::Char(char::from(c as u8 + 0x60)))
+        }
+        c => SimpleEvent::Just(KeyCode::Char(c)),
+    }
+}
+
+/// Every key pressed in the cast file at `path`, with how far into the
+/// recording it was pressed.
+pub fn read_keys(path: &Path) -> io::Result<Vec<(Duration, GameEvent)>> {
+    let invalid = |line: usize| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "line {} of {} isn't part of a cast",
+                line + 1,
+                path.display()
+            ),
+        )
+    };
+    let cast = fs::read_to_string(path)?;
+    let mut lines = cast.lines().enumerate();
+    match lines.next() {
+        Some((_, header)) if header.contains("\"version\": 2") => {}
+        _ => return Err(invalid(0)),
+    }
+
+    let mut keys = Vec::new();
+    for (number, line) in lines {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let (time, kind, data) = parse_event(line).ok_or_else(|| invalid(number))?;
+        if kind == "i" {
+            let time = Duration::try_from_secs_f64(time).map_err(|_| invalid(number))?;
+            keys.extend(decode_keys(&data).into_iter().map(|key| (time, key)));
+        }
+    }
+    Ok(keys)
+}
+
+/// Splits an event line, `[time, "kind", "data"]`, into its parts.
+fn parse_event(line: &str) -> Option<(f64, String, String)> {
+    let rest = line.strip_prefix('[')?.strip_suffix(']')?;
+    let (time, rest) = rest.split_once(',')?;
+    let time = time.trim().parse().ok()?;
+    let (kind, rest) = json::parse_string(rest.trim_start())?;
+    let rest = rest.trim_start().strip_prefix(',')?;
+    let (data, rest) = json::parse_string(rest.trim_start())?;
+    rest.trim().is_empty().then_some((time, kind, data))
+}
+
+impl BufferEditor {
+    /// Adds what's on screen now to the recording, if there is one.
+    pub fn record_frame(&mut self, game: &mut Game) {
+        if self.session.recording.is_none() {
+            return;
+        }
+        let viewport = game.get_viewport();
+        let (columns, rows) = self.visible_size(game);
+        let (columns, rows) = (columns.max(0) as usize, rows.max(0) as usize);
+        let (top, left) = (viewport.y.max(0) as usize, viewport.x.max(0) as usize);
+
+        let mut screen: Vec<String> = (top..top + rows)
+            .map(|line| {
+                let text = self.buffer.line(line).unwrap_or("");
+                text.chars().skip(left).take(columns).collect()
+            })
+            .collect();
+        if let Some(last) = screen.last_mut() {
+            *last = self.status_text().chars().take(columns).collect();
+        }
+        // Messages are shown in a box under the buffer.
+        let (_, (_, message_rows)) = game.screen_size();
+        if let Some(message) = game.get_message() {
+            let mut lines = message.text.lines();
+            for _ in 0..message_rows {
+                screen.push(lines.next().unwrap_or("").chars().take(columns).collect());
+            }
+        }
+
+        let position = self.buffer.position_of(self.buffer.cursor());
+        let cursor = match (
+            position.line.checked_sub(top),
+            self.buffer.display_col(position).checked_sub(left),
+        ) {
+            (Some(row), Some(col)) if row < rows && col < columns => Some((col, row)),
+            _ => None,
+        };
+
+        let now = self.session.clock.now();
+        let recording = self.session.recording.as_mut().unwrap();
+        if let Err(err) = recording.frame(now, &screen, cursor) {
+            self.stop_recording(game, err);
+        }
+    }
+
+    /// Adds `event` to the recording, if there is one.
+    pub fn record_key(&mut self, game: &mut Game, event: &SimpleEvent) {
+        let now = self.session.clock.now();
+        let Some(recording) = &mut self.session.recording else {
+            return;
+        };
+        if let Err(err) = recording.key(now, event) {
+            self.stop_recording(game, err);
+        }
+    }
+
+    /// Gives up on a recording which couldn't be written to.
+    fn stop_recording(&mut self, game: &mut Game, err: io::Error) {
+        if let Some(recording) = self.session.recording.take() {
+            tracing::warn!(path = %recording.path().display(), error = %err, "stopped recording");
+            let message = tr(
+                "record.stopped",
+                &[("path", &recording.path().display()), ("error", &err)],
+            );
+            game.set_message(Some(Message::new(message)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::harness::Harness;
+
+    #[test]
+    fn keys_are_encoded_as_a_terminal_sends_them() {
+        assert_eq!(
+            encode_key(&SimpleEvent::Just(KeyCode::Char('é'))).as_deref(),
+            Some("é")
+        );
+        assert_eq!(
+            encode_key(&SimpleEvent::Just(KeyCode::Up)).as_deref(),
+            Some("\x1b[A")
+        );
+        assert_eq!(
+            encode_key(&SimpleEvent::WithControl(KeyCode::Char('s'))).as_deref(),
+            Some("\x13")
+        );
+        assert_eq!(
+            encode_key(&SimpleEvent::WithAlt(KeyCode::Char('x'))).as_deref(),
+            Some("\x1bx")
+        );
+    }
+
+    #[test]
+    fn a_recording_has_a_header_then_keys_and_frames() {
+        let path = std::env::temp_dir().join(format!("buffers-record-{}.cast", std::process::id()));
+        let mut harness = Harness::new("");
+        harness.editor.session.recording = Some(Recording::start(&path, Duration::ZERO).unwrap());
+        harness.tick(40).type_text("hi");
+        let recording = harness.editor.session.recording.take().unwrap();
+        let cast = std::fs::read_to_string(recording.finish().unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let lines: Vec<&str> = cast.lines().collect();
+        assert_eq!(lines[0], "{\"version\": 2, \"width\": 78, \"height\": 22}");
+        assert!(lines.contains(&"[1.000000, \"i\", \"h\"]"), "{}", cast);
+        let last = lines.last().unwrap();
+        assert!(
+            last.starts_with("[1.000000, \"o\", \"\\u001b[H\\u001b[2Jhi\\r\\n"),
+            "{}",
+            last
+        );
+    }
+
+    #[test]
+    fn keys_decode_back_to_what_was_encoded() {
+        let keys = [
+            SimpleEvent::Just(KeyCode::Char('a')),
+            SimpleEvent::Just(KeyCode::Enter),
+            SimpleEvent::Just(KeyCode::PageDown),
+            SimpleEvent::WithControl(KeyCode::Char('v')),
+            SimpleEvent::WithAlt(KeyCode::Left),
+            SimpleEvent::Just(KeyCode::Backspace),
+            // On its own, or it would be Alt held with the next key.
+            SimpleEvent::Just(KeyCode::Esc),
+        ];
+        let bytes: String = keys.iter().filter_map(encode_key).collect();
+        let keys: Vec<GameEvent> = keys.into_iter().map(GameEvent::from).collect();
+        assert_eq!(decode_keys(&bytes), keys);
+    }
+
+    #[test]
+    fn events_are_parsed_out_of_json() {
+        let (time, kind, data) = parse_event(r#"[1.5, "i", "a\"\u001b[A\ud83d\ude00"]"#).unwrap();
+        assert_eq!((time, kind.as_str()), (1.5, "i"));
+        assert_eq!(data, "a\"\x1b[A😀");
+        assert_eq!(parse_event(r#"[1.5, "i", "a"] x"#), None);
+    }
+}
@@ -0,0 +1,78 @@
+//! Named registers, `a` to `z`, which hold text apart from the
+//! clipboard. Ctrl+R and a letter picks the register the key after it
+//! uses, if that's a copy, cut, delete or paste; any other key forgets
+//! it. Without one, they use the clipboard, which is the default
+//! register.
+
+use std::collections::BTreeMap;
+
+use crate::clipboard::Clip;
+
+#[derive(Debug, Clone, Default)]
+pub struct Registers {
+    named: BTreeMap<char, Clip>,
+}
+
+impl Registers {
+    /// Whether `name` can name a register.
+    pub fn is_name(name: char) -> bool {
+        name.is_ascii_lowercase()
+    }
+
+    pub fn get(&self, name: char) -> Option<&Clip> {
+        self.named.get(&name)
+    }
+
+    /// Puts `clip` in the register `name`, replacing what was there.
+    pub fn set(&mut self, name: char, clip: Clip) {
+        self.named.insert(name, clip);
+    }
+
+    /// Every register with something in it, in order.
+    pub fn iter(&self) -> impl Iterator<Item = (char, &Clip)> {
+        self.named.iter().map(|(name, clip)| (*name, clip))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use termgame::KeyCode;
+
+    use crate::harness::{app, command, ctrl, shift_key, Harness};
+
+    #[test]
+    fn a_register_keeps_its_text_while_the_clipboard_moves_on() {
+        let mut harness = Harness::new("one\ntwo\n");
+        harness.run([ctrl('r')]).type_text("a").run([ctrl('c')]);
+        harness.press(KeyCode::Down).run([ctrl('c')]);
+        // Deleting a selection goes in a register too.
+        harness.run([ctrl('r')]).type_text("b");
+        harness.run([shift_key(KeyCode::Right), ctrl('x')]);
+
+        let session = &harness.editor.session;
+        assert_eq!(session.registers.get('a').unwrap().text, "one\n");
+        assert_eq!(session.registers.get('b').unwrap().text, "t");
+        assert_eq!(session.clipboard.current().unwrap().text, "two\n");
+
+        harness.run([ctrl('r')]).type_text("a").run([ctrl('v')]);
+        assert_eq!(harness.text(), "one\none\nwo\n");
+    }
+
+    #[test]
+    fn a_register_is_forgotten_by_a_key_which_doesnt_use_it() {
+        let mut harness = Harness::new("one\ntwo\n");
+        harness.run([ctrl('r')]).type_text("a").press(KeyCode::Down);
+        harness.run([ctrl('c')]);
+        let session = &harness.editor.session;
+        assert!(session.registers.get('a').is_none());
+        assert_eq!(session.clipboard.current().unwrap().text, "two\n");
+    }
+
+    #[test]
+    fn registers_takes_no_arguments() {
+        let mut app = app();
+        let err = command(&mut app, "registers a").unwrap_err();
+        assert_eq!(err.to_string(), "usage: registers");
+        command(&mut app, "registers").unwrap();
+    }
+}
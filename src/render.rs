@@ -0,0 +1,431 @@
+//! Drawing the buffer into the editor.
+//!
+//! What's on screen is kept between keys, and only the lines which
+//! changed are drawn again: the ones the buffer says were edited, the
+//! ones the cursor left and went to, and the one under where the status
+//! bar was. So typing into a huge file redraws a line, not the file.
+//! Only the rows in view, and a few either side, are drawn at all, and
+//! the rest are drawn as they're scrolled to.
+//!
+//! With `set number`, each line has its number down the left, right
+//! aligned, in a gutter as wide as the last line's number needs. Left of
+//! that, a buffer whose file is in git has a column for
+//! [what's changed](crate::git) since it was committed, and left of
+//! everything, with `blame` on, who last changed each line. What Ctrl+F
+//! found is highlighted wherever it's drawn.
+//!
+//! A line which goes on past either side of the screen has a `<` or `>`
+//! drawn over its last cell in view on that side, unless lines are
+//! [wrapped](crate::wrap).
+//!
+//! Any other cursors, and their selections, are drawn like the main
+//! one, though the whole buffer is redrawn while there are any.
+//!
+//! With the cursor on a bracket, the one it pairs with is highlighted,
+//! and if there isn't one the cursor is drawn in the warning colour.
+//!
+//! The selection is drawn over everything but the cursor, and tabs are
+//! drawn as spaces out to the next tab stop, `tab_width` apart.
+
+use termgame::{Game, StyledCharacter};
+
+use std::collections::BTreeSet;
+use std::ops::Range;
+
+use crate::brackets::Match;
+use crate::git::{Mark, Marks};
+use crate::wrap::Rows;
+use crate::BufferEditor;
+
+/// How many rows past the top and bottom of the screen are drawn, so a
+/// row or two of scrolling doesn't need anything drawing first.
+const MARGIN: usize = 8;
+
+/// What's been drawn, so the next draw knows what it has to redo.
+#[derive(Debug, Clone, Default)]
+pub struct Drawn {
+    /// Set when nothing's been drawn yet, like when the editor opens.
+    fresh: bool,
+    /// How many cells across were drawn on each line, so anything left
+    /// over can be cleared when a line gets shorter.
+    widths: Vec<usize>,
+    cursor_line: usize,
+    /// How wide the line numbers were, when they were shown.
+    gutter: usize,
+    /// The git marks that were drawn in the gutter.
+    marks: Option<Marks>,
+    /// What was highlighted as found.
+    found: Option<String>,
+    /// The column the viewport was scrolled across to.
+    left: i32,
+    /// How many cells across lines were wrapped to, when they were.
+    wrapped: Option<usize>,
+    /// The lines the selection was drawn across.
+    selected: Option<Range<usize>>,
+    /// The line the bracket matching the cursor's was highlighted on.
+    matched: Option<usize>,
+    /// Whether there were cursors besides the main one.
+    cursors: bool,
+    /// The row the status bar was drawn along, and the columns it took.
+    status: Option<(usize, Range<i32>)>,
+    /// The rows which are drawn as the buffer is now, around the ones
+    /// that were in view. Anything else has to be drawn when it's
+    /// scrolled to.
+    window: Range<usize>,
+}
+
+impl Drawn {
+    /// Nothing drawn yet, so everything will be.
+    pub fn new() -> Drawn {
+        Drawn {
+            fresh: true,
+            ..Drawn::default()
+        }
+    }
+
+    /// Notes that the status bar has been drawn along `row`, over
+    /// `columns`.
+    pub fn status_drawn(&mut self, row: i32, columns: Range<i32>) {
+        self.status = usize::try_from(row).ok().map(|row| (row, columns));
+    }
+}
+
+impl BufferEditor {
+    /// How many cells across the text starts, after any blame, git marks
+    /// and line numbers.
+    pub fn text_left(&self) -> usize {
+        self.blame_width()
+            + self.git_width()
+            + match self.session.line_numbers {
+                true => gutter_width(self.first_line() + self.buffer.drawn_lines()),
+                false => 0,
+            }
+    }
+
+    /// Draws whatever has changed since the buffer was last drawn, then
+    /// the status bar.
+    pub fn render(&mut self, game: &mut Game) {
+        // Wherever the cursor was moved to, a huge file has to have the
+        // lines around it loaded before they can be drawn.
+        self.page_huge(game);
+        // Blame is for the lines as they were, so an edit takes it away.
+        self.drop_stale_blame();
+        let theme = self.session.theme;
+        let first_line = self.first_line();
+        let (columns, visible_rows) = self.visible_size(game);
+        let viewport = game.get_viewport();
+        let top = viewport.y.max(0) as usize;
+        let window = top.saturating_sub(MARGIN)..top + visible_rows.max(0) as usize + MARGIN;
+        // Wrapped, a row isn't a line, so it's the rows which the edits
+        // changed that are drawn again.
+        let wrapped = match self.wrap {
+            true => Some(self.wrapped_rows(columns.max(0) as usize, window.clone())),
+            false => {
+                self.rows = Rows::default();
+                None
+            }
+        };
+        let changed = match &wrapped {
+            Some(_) => {
+                self.buffer.take_changed_lines();
+                self.rows.take_changed()
+            }
+            None => self.buffer.take_changed_lines(),
+        };
+        let lines = self.buffer.drawn_lines();
+        let gutter = self.text_left();
+        let blamed = self.blame_width();
+        let marked = blamed + self.git_width();
+        let rows = wrapped.as_ref().map_or(lines, |wrapped| wrapped.total);
+        // The rows a range of lines is drawn across.
+        let rows_of = |lines: Range<usize>| match &wrapped {
+            Some(wrapped) => wrapped.rows_of(lines),
+            None => lines,
+        };
+        let wrap_width = wrapped.as_ref().map(|_| self.rows.width());
+        let bracket = self.bracket_match();
+        let drawn = &mut self.drawn;
+        let total = rows.max(drawn.widths.len());
+        // Everything moves across when the gutter changes width, matches
+        // can be anywhere, and where lines run off the sides changes as
+        // the viewport scrolls across.
+        let found = self.find.as_ref().map(|find| find.query.clone());
+        if gutter != drawn.gutter
+            || self.git_marks != drawn.marks
+            || found != drawn.found
+            || viewport.x != drawn.left
+            || wrap_width != drawn.wrapped
+            || !self.cursors.is_empty()
+            || drawn.cursors
+        {
+            drawn.fresh = true;
+            drawn.gutter = gutter;
+            drawn.marks = self.git_marks.clone();
+            drawn.found = found;
+            drawn.left = viewport.x;
+            drawn.wrapped = wrap_width;
+            drawn.cursors = !self.cursors.is_empty();
+        }
+        let cursor = self.buffer.cursor_position();
+        let cursor_x = gutter + self.buffer.display_col(cursor);
+
+        let mut redraw = BTreeSet::new();
+        match (drawn.fresh, changed) {
+            (true, _) => redraw.extend(window.start..window.end.min(total)),
+            (false, Some(changed)) => redraw
+                .extend(changed.start.max(window.start)..changed.end.min(window.end).min(total)),
+            (false, None) => {}
+        }
+        // Rows scrolled into view since they were last drawn.
+        if !drawn.fresh {
+            let old = drawn.window.clone();
+            redraw.extend((window.start..window.end.min(total)).filter(|row| !old.contains(row)));
+        }
+        // Rows past the end are cleared wherever they are, since they'll
+        // never be drawn again.
+        redraw.extend((rows..drawn.widths.len()).filter(|&row| drawn.widths[row] > 0));
+        let cursor_line = self.buffer.cursor_line();
+        redraw.extend(rows_of(drawn.cursor_line..drawn.cursor_line + 1));
+        redraw.extend(rows_of(cursor_line..cursor_line + 1));
+        let selection = self.buffer.selection();
+        let selected = selection.as_ref().map(|selection| {
+            self.buffer.position_of(selection.start).line
+                ..self.buffer.position_of(selection.end).line + 1
+        });
+        for lines in [drawn.selected.take(), selected.clone()]
+            .into_iter()
+            .flatten()
+        {
+            redraw.extend(rows_of(lines));
+        }
+        drawn.selected = selected;
+        let matched = match bracket {
+            Some(Match::Found(offset)) => Some(offset),
+            _ => None,
+        };
+        let matched_line = matched.map(|offset| self.buffer.position_of(offset).line);
+        for line in drawn.matched.take().into_iter().chain(matched_line) {
+            redraw.extend(rows_of(line..line + 1));
+        }
+        drawn.matched = matched_line;
+        let cursor_paint = match bracket {
+            Some(Match::Unmatched) => theme.unmatched,
+            _ => theme.cursor,
+        };
+        let status = drawn.status.take();
+        if let Some((row, _)) = &status {
+            redraw.insert(*row);
+        }
+
+        drawn.widths.resize(total, 0);
+        let in_view = |row: &usize| window.contains(row) || *row >= rows;
+        for row in redraw
+            .into_iter()
+            .filter(|&row| row < total && in_view(&row))
+        {
+            let (line, part) = match &wrapped {
+                Some(wrapped) => wrapped.get(row).cloned().unwrap_or((lines, 0..0)),
+                None => (row, 0..usize::MAX),
+            };
+            let text = self.buffer.line(line);
+            let paint = text.and_then(|text| theme.line(text, self.shows_changes));
+            let found: Vec<Range<usize>> = match (&self.find, text) {
+                (Some(find), Some(text)) => find.matches(text).collect(),
+                _ => Vec::new(),
+            };
+            let start = self.buffer.line_span(line..line).start;
+            let selected = selection.clone().map_or(0..0, |selection| {
+                selection.start.saturating_sub(start)..selection.end.saturating_sub(start)
+            });
+            let cursors = &self.cursors;
+            let paint_at = |i: usize| {
+                let at = start + i;
+                let other_selected = cursors.iter().any(|cursor| {
+                    (cursor.start.min(cursor.end)..cursor.start.max(cursor.end)).contains(&at)
+                });
+                if cursors.iter().any(|cursor| cursor.end == at) {
+                    Some(theme.cursor)
+                } else if selected.contains(&i) || other_selected {
+                    Some(theme.selection)
+                } else if found.iter().any(|found| found.contains(&i)) {
+                    Some(theme.found)
+                } else if matched == Some(start + i) {
+                    Some(theme.bracket)
+                } else {
+                    paint
+                }
+            };
+            // Only the first row of a wrapped line is numbered, or marked.
+            if let Some(blame) = self.blame.as_ref().filter(|_| line < lines) {
+                let first = part.start == 0;
+                let annotation = blame.get(line).filter(|_| first);
+                let text = annotation.map_or_else(|| " ".repeat(blamed), String::clone);
+                for (x, c) in text.chars().enumerate() {
+                    let styled = StyledCharacter::new(c).style(theme.gutter.style());
+                    game.set_screen_char(x as i32, row as i32, Some(styled));
+                }
+            }
+            let marks = self.git_marks.as_ref();
+            if let Some(marks) = marks.filter(|_| line < lines) {
+                let mark = marks.get(&line).filter(|_| part.start == 0);
+                let styled = StyledCharacter::new(mark.map_or(' ', |mark| mark.symbol()));
+                let paint = match mark {
+                    Some(Mark::Added) => theme.added,
+                    Some(Mark::Changed) => theme.conflict,
+                    Some(Mark::Removed) => theme.removed,
+                    None => theme.gutter,
+                };
+                let styled = Some(styled.style(paint.style()));
+                game.set_screen_char(blamed as i32, row as i32, styled);
+            }
+            let numbers = gutter - marked;
+            if numbers > 0 && line < lines {
+                let number = match part.start {
+                    0 => format!("{:>1$} ", first_line + line + 1, numbers - 1),
+                    _ => " ".repeat(numbers),
+                };
+                for (x, c) in number.chars().enumerate() {
+                    let x = x + marked;
+                    let styled = StyledCharacter::new(c).style(theme.gutter.style());
+                    game.set_screen_char(x as i32, row as i32, Some(styled));
+                }
+            }
+            let at = (gutter, row as i32);
+            let width = self
+                .buffer
+                .draw_part(game, line, part, at, cursor_paint, paint_at);
+            if line < lines {
+                let right = viewport.x + columns - 1;
+                let mut more = Vec::new();
+                if viewport.x > 0 && width > 0 {
+                    more.push((viewport.x, '<'));
+                }
+                if width as i32 > right + 1 {
+                    more.push((right, '>'));
+                }
+                for (x, c) in more {
+                    // The cursor is never hidden behind one.
+                    if (cursor.line, cursor_x) != (line, x as usize) {
+                        let styled = StyledCharacter::new(c).style(theme.gutter.style());
+                        game.set_screen_char(x, row as i32, Some(styled));
+                    }
+                }
+            }
+            let mut old = drawn.widths[row];
+            if let Some((_, columns)) = status.as_ref().filter(|(status, _)| *status == row) {
+                old = old.max(columns.end.max(0) as usize);
+            }
+            for x in width..old {
+                game.set_screen_char(x as i32, row as i32, None);
+            }
+            drawn.widths[row] = width;
+        }
+        drawn.widths.truncate(rows);
+        drawn.window = window;
+        drawn.cursor_line = self.buffer.cursor_line();
+        drawn.fresh = false;
+        self.render_status(game);
+    }
+}
+
+/// How many cells across the line numbers take for `lines` lines: the
+/// digits in the last one, and a space before the text.
+fn gutter_width(lines: usize) -> usize {
+    lines.max(1).to_string().len() + 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use termgame::KeyCode;
+
+    use std::rc::Rc;
+
+    use crate::buffer::Buffer;
+    use crate::config::Config;
+    use crate::harness::{alt_key, Harness};
+
+    #[test]
+    fn only_whats_changed_is_redrawn_but_nothing_is_left_behind() {
+        let mut harness = Harness::new("one\ntwo\nthree");
+        assert_eq!(harness.screen_line(1), "two");
+        harness.press(KeyCode::Down).press(KeyCode::Backspace);
+        assert_eq!(harness.screen_line(0), "onetwo");
+        assert_eq!(harness.screen_line(1), "three");
+        assert_eq!(harness.screen_line(2), "");
+
+        // The cursor is drawn wherever it goes, and nowhere else.
+        harness.press(KeyCode::Down);
+        assert_eq!(harness.screen_line(0), "onetwo");
+        assert_eq!(harness.screen_line(1), "three");
+    }
+
+    #[test]
+    fn only_the_rows_in_view_are_drawn() {
+        let text: String = (1..=1000).map(|n| format!("{}\n", n)).collect();
+        let mut harness = Harness::new(&text);
+        assert_eq!(harness.screen_line(0), "1");
+        assert_eq!(harness.screen_line(500), "");
+
+        for _ in 0..500 {
+            harness.press(KeyCode::Down);
+        }
+        assert_eq!(harness.screen_line(500), "501");
+        assert_eq!(harness.screen_line(900), "");
+    }
+
+    #[test]
+    fn line_numbers_widen_the_gutter_as_the_file_grows() {
+        let buffer = Buffer::from_text(None, "a\n".repeat(8));
+        let mut editor =
+            BufferEditor::new(String::from("test"), buffer, Rc::new(Config::default()));
+        editor.session.line_numbers = true;
+        let mut harness = Harness::with_editor(editor);
+        assert_eq!(harness.screen_line(0), "1 a");
+        assert_eq!(harness.screen_line(8), "9");
+
+        // A tenth line needs another digit, so every line moves across.
+        harness.type_text("\n");
+        assert_eq!(harness.screen_line(0), " 1");
+        assert_eq!(harness.screen_line(1), " 2 a");
+        assert_eq!(harness.screen_line(8), " 9 a");
+        assert_eq!(harness.screen_line(9), "10");
+    }
+
+    #[test]
+    fn long_lines_scroll_across_and_say_they_go_on() {
+        let mut harness = Harness::new(&"x".repeat(200));
+        // The border takes a column off each side of the 80.
+        assert_eq!(harness.screen_line(0).chars().nth(77), Some('>'));
+        harness.type_text("ab");
+        assert_eq!(harness.viewport().x, 0);
+
+        harness.run([alt_key(KeyCode::Right)]);
+        assert_eq!(harness.viewport().x, 78);
+        let line: Vec<char> = harness.screen_line(0).chars().collect();
+        assert_eq!((line[78], line[79], line[155]), ('<', 'x', '>'));
+
+        // Moving the cursor brings it back into view, and it isn't
+        // covered up by the `<`.
+        harness.press(KeyCode::Left);
+        assert_eq!(harness.viewport().x, 1);
+        assert_eq!(harness.screen_line(0).chars().nth(1), Some('b'));
+    }
+
+    #[test]
+    fn tabs_are_drawn_out_to_the_next_tab_stop() {
+        let config = Config {
+            expand_tab: false,
+            ..Config::default()
+        };
+        let mut harness = Harness::with_config("a\tb", config);
+        assert_eq!(harness.screen_line(0), "a   b");
+        harness.press(KeyCode::Right).press(KeyCode::Right);
+        assert!(harness.status_line().contains("Col 5"));
+
+        harness.press(KeyCode::Tab);
+        assert_eq!(harness.text(), "a\t\tb");
+        assert_eq!(harness.screen_line(0), "a       b");
+    }
+}
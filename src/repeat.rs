@@ -0,0 +1,151 @@
+//! Repeating the last edit where the cursor is now, with Ctrl+., or `.`
+//! in vim's normal mode.
+//!
+//! An edit is kept as the keys which made it, and repeated by pressing
+//! them again. Outside vim mode, that's a run of keys which each change
+//! the text, like a word typed or a few Backspaces, up to the first key
+//! which doesn't. In vim mode, it's one command, like `dw` or `x`, along
+//! with anything typed in insert mode after it, up to Esc, so `.` after
+//! `cwfoo` changes another word to `foo`. A count before `.` repeats it
+//! that many times.
+
+use termgame::{Controller, Game, GameEvent, Message};
+
+use crate::i18n::tr;
+use crate::vim::{EditingMode, Mode};
+use crate::BufferEditor;
+
+/// The keys of the last edit, and of the one being made.
+#[derive(Debug, Default)]
+pub struct Repeat {
+    /// The keys pressed so far towards an edit.
+    keys: Vec<GameEvent>,
+    /// Whether any of `keys` has changed the text yet.
+    edited: bool,
+    /// Whether the edit can be repeated. Operating on a selection from
+    /// vim's visual mode can't, since there won't be one to operate on.
+    repeatable: bool,
+    /// The keys of the last edit.
+    last: Vec<GameEvent>,
+    /// Set when the key being handled doesn't count towards an edit,
+    /// like undo.
+    skip: bool,
+}
+
+impl Repeat {
+    /// Makes the key being handled count for nothing, dropping whatever
+    /// had been pressed towards an edit.
+    pub fn forget(&mut self) {
+        self.skip = true;
+    }
+
+    /// Ends the edit being made, which becomes the last edit if it
+    /// changed the text.
+    fn finish(&mut self) {
+        let keys = std::mem::take(&mut self.keys);
+        if std::mem::take(&mut self.edited) && self.repeatable {
+            self.last = keys;
+        }
+    }
+}
+
+impl BufferEditor {
+    /// Adds `key` to the edit being made, if it's part of one, now that
+    /// it's been handled. `revision` and `mode` are the buffer's revision
+    /// and the vim mode from before.
+    pub fn track_edit(&mut self, key: GameEvent, revision: usize, mode: Mode) {
+        if self.repeating {
+            return;
+        }
+        let repeat = &mut self.repeat;
+        if std::mem::take(&mut repeat.skip) {
+            repeat.keys.clear();
+            repeat.edited = false;
+            return;
+        }
+        let edited = self.buffer.revision() != revision;
+        let vim = self.session.editing_mode == EditingMode::Vim;
+        let pending = vim && self.vim.is_pending();
+        let inserting = vim && self.vim.mode == Mode::Insert;
+        let was_inserting = vim && mode == Mode::Insert;
+        if !edited && !pending && !inserting && !was_inserting {
+            repeat.finish();
+            return;
+        }
+        if repeat.keys.is_empty() {
+            repeat.repeatable = mode != Mode::Visual || !vim;
+        }
+        repeat.keys.push(key);
+        repeat.edited |= edited;
+        // A vim command's done once it's back in normal mode.
+        if vim && !pending && !inserting {
+            repeat.finish();
+        }
+    }
+
+    /// Makes the last edit again, `count` times over.
+    pub fn repeat_edit(&mut self, game: &mut Game, count: Option<usize>) {
+        self.repeat.finish();
+        self.repeat.forget();
+        if self.repeat.last.is_empty() {
+            let message = tr("editor.nothing-to-repeat", &[]);
+            game.set_message(Some(Message::new(message)));
+            return;
+        }
+        let keys = self.repeat.last.clone();
+        self.repeating = true;
+        'repeat: for _ in 0..count.unwrap_or(1) {
+            for key in &keys {
+                if game.game_will_end() {
+                    break 'repeat;
+                }
+                self.on_event(game, key.clone());
+            }
+        }
+        self.repeating = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use termgame::KeyCode;
+
+    use crate::harness::{ctrl, key, Harness};
+    use crate::vim::EditingMode;
+
+    #[test]
+    fn ctrl_dot_repeats_the_last_run_of_edits() {
+        let mut harness = Harness::new("one\ntwo\n");
+        harness
+            .type_text("- ")
+            .run([key(KeyCode::Down), key(KeyCode::Home), ctrl('.')]);
+        assert_eq!(harness.text(), "- one\n- two\n");
+
+        harness.run([key(KeyCode::Backspace), key(KeyCode::Backspace)]);
+        harness
+            .press(KeyCode::Up)
+            .run([key(KeyCode::Right), key(KeyCode::Right), ctrl('.')]);
+        assert_eq!(harness.text(), "one\ntwo\n");
+        // Undo isn't an edit to repeat.
+        harness.run([ctrl('z')]);
+        assert_eq!(harness.text(), "- one\ntwo\n");
+        harness.run([ctrl('.')]);
+        assert_eq!(harness.text(), "one\ntwo\n");
+    }
+
+    #[test]
+    fn dot_repeats_a_vim_command_and_what_was_typed_after_it() {
+        let mut harness = Harness::new("one two three four\n");
+        harness.editor.session.editing_mode = EditingMode::Vim;
+        harness.type_text("cwONE").press(KeyCode::Esc);
+        harness.type_text("w.");
+        assert_eq!(harness.text(), "ONE ONE three four\n");
+
+        harness.type_text("wx2.");
+        assert_eq!(harness.text(), "ONE ONE ee four\n");
+        harness.type_text("u");
+        assert_eq!(harness.text(), "ONE ONE ree four\n");
+        harness.type_text("w.");
+        assert_eq!(harness.text(), "ONE ONE ree our\n");
+    }
+}
@@ -0,0 +1,134 @@
+//! Search and replace over a buffer's text.
+
+use regex::{Captures, RegexBuilder};
+
+/// How [`replace`] should match and substitute.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ReplaceOptions {
+    /// Match regardless of case, and give each replacement the case
+    /// of the text it replaces (so `foo -> bar` turns `Foo` into `Bar`,
+    /// and `FOO` into `BAR`).
+    pub preserve_case: bool,
+    /// Treat the pattern as a regular expression, whose capture groups
+    /// the replacement can refer to as `$1` or `${name}`.
+    pub regex: bool,
+    /// Only replace the first match, rather than every one.
+    pub first_only: bool,
+}
+
+/// Replaces every match of `pattern` in `text` with `replacement`, or
+/// just the first with [`ReplaceOptions::first_only`].
+///
+/// Returns the new text, and how many replacements were made, or an
+/// error if `pattern` is an invalid regular expression.
+pub fn replace(
+    text: &str,
+    pattern: &str,
+    replacement: &str,
+    options: ReplaceOptions,
+) -> Result<(String, usize), regex::Error> {
+    if pattern.is_empty() {
+        return Ok((String::from(text), 0));
+    }
+
+    let pattern = match options.regex {
+        true => String::from(pattern),
+        false => regex::escape(pattern),
+    };
+    let regex = RegexBuilder::new(&pattern)
+        .case_insensitive(options.preserve_case)
+        .build()?;
+
+    // A limit of 0 replaces them all.
+    let limit = if options.first_only { 1 } else { 0 };
+    let mut count = 0;
+    let result = regex.replacen(text, limit, |captures: &Captures| {
+        count += 1;
+        let mut substituted = String::new();
+        match options.regex {
+            true => captures.expand(replacement, &mut substituted),
+            false => substituted.push_str(replacement),
+        }
+        match options.preserve_case {
+            true => match_case(&captures[0], &substituted),
+            false => substituted,
+        }
+    });
+
+    Ok((result.into_owned(), count))
+}
+
+/// Gives `replacement` the same case as `original`: all upper case,
+/// all lower case, or capitalised. Anything more mixed than that is
+/// left as the replacement was written.
+fn match_case(original: &str, replacement: &str) -> String {
+    let letters: Vec<char> = original.chars().filter(|c| c.is_alphabetic()).collect();
+    let Some((first, rest)) = letters.split_first() else {
+        return String::from(replacement);
+    };
+
+    if letters.iter().all(|c| c.is_uppercase()) && letters.len() > 1 {
+        replacement.to_uppercase()
+    } else if letters.iter().all(|c| c.is_lowercase()) {
+        replacement.to_lowercase()
+    } else if first.is_uppercase() && rest.iter().all(|c| c.is_lowercase()) {
+        let mut chars = replacement.chars();
+        match chars.next() {
+            Some(c) => c
+                .to_uppercase()
+                .chain(chars.flat_map(char::to_lowercase))
+                .collect(),
+            None => String::new(),
+        }
+    } else {
+        String::from(replacement)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_first_match_is_replaced_unless_asked() {
+        let first = ReplaceOptions {
+            first_only: true,
+            ..ReplaceOptions::default()
+        };
+        let replaced = replace("a cat, a cat", "cat", "dog", first).unwrap();
+        assert_eq!(replaced, (String::from("a dog, a cat"), 1));
+        let all = replace("a cat, a cat", "cat", "dog", ReplaceOptions::default()).unwrap();
+        assert_eq!(all, (String::from("a dog, a dog"), 2));
+    }
+
+    #[test]
+    fn each_replacement_takes_the_case_of_its_match() {
+        let preserving = ReplaceOptions {
+            preserve_case: true,
+            ..ReplaceOptions::default()
+        };
+        let (text, count) = replace("foo Foo FOO fOo", "foo", "bar", preserving).unwrap();
+        assert_eq!(text, "bar Bar BAR bar");
+        assert_eq!(count, 4);
+        // Without it, the case has to match and isn't changed.
+        let plain = replace("foo Foo", "foo", "Bar", ReplaceOptions::default()).unwrap();
+        assert_eq!(plain, (String::from("Bar Foo"), 1));
+    }
+
+    #[test]
+    fn regex_replacements_can_use_capture_groups() {
+        let regex = ReplaceOptions {
+            regex: true,
+            ..ReplaceOptions::default()
+        };
+        let (text, _) = replace("ada lovelace", r"(\w+) (\w+)", "$2, $1", regex).unwrap();
+        assert_eq!(text, "lovelace, ada");
+        let named = r"(?P<key>\w+)=(?P<value>\w+)";
+        let (text, count) = replace("a=1 b=2", named, "${value}=${key}", regex).unwrap();
+        assert_eq!((text.as_str(), count), ("1=a 2=b", 2));
+        // Outside regex mode, `$1` is just text and the pattern is literal.
+        let (text, _) = replace("(a)", "(a)", "$1", ReplaceOptions::default()).unwrap();
+        assert_eq!(text, "$1");
+        assert!(replace("x", "(", "y", regex).is_err());
+    }
+}
@@ -0,0 +1,321 @@
+//! The text of a buffer, kept as a list of chunks of whole lines rather
+//! than one `String`, so an edit only moves the bytes of the chunks it
+//! touches, not everything after it.
+//!
+//! Every chunk but the last ends with a newline, and the last never does
+//! (it's empty when the text ends with one), so no line is ever split
+//! across two chunks and each line can be lent out as a `&str`. Each
+//! chunk knows where its own lines start, and the rope where each chunk
+//! starts, so finding a line is two binary searches.
+
+use std::cell::OnceCell;
+use std::mem;
+use std::ops::Range;
+
+/// About how many bytes a chunk holds. A chunk is only cut after a
+/// newline, so one holding a very long line is longer.
+const CHUNK: usize = 4096;
+
+struct Chunk {
+    text: String,
+    /// The byte offset, into `text`, each line in it starts at.
+    line_starts: Vec<usize>,
+}
+
+impl Chunk {
+    fn new(text: String) -> Chunk {
+        let line_starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+            .filter(|&start| start < text.len() || start == 0)
+            .collect();
+        Chunk { text, line_starts }
+    }
+}
+
+pub struct Rope {
+    chunks: Vec<Chunk>,
+    /// The byte offset each chunk starts at.
+    starts: Vec<usize>,
+    /// The (0-indexed) line each chunk starts with.
+    first_lines: Vec<usize>,
+    len: usize,
+    lines: usize,
+    /// All of the text as one `String`, put together the first time it's
+    /// asked for after an edit.
+    flat: OnceCell<String>,
+}
+
+impl Rope {
+    pub fn new(text: String) -> Rope {
+        let mut rope = Rope {
+            chunks: split(text, true),
+            starts: Vec::new(),
+            first_lines: Vec::new(),
+            len: 0,
+            lines: 0,
+            flat: OnceCell::new(),
+        };
+        rope.reindex();
+        rope
+    }
+
+    /// How many bytes of text there are.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// How many lines start in the text: one, and another after every
+    /// newline, even one at the very end.
+    pub fn lines(&self) -> usize {
+        self.lines
+    }
+
+    /// All of the text.
+    pub fn text(&self) -> &str {
+        self.flat.get_or_init(|| {
+            self.chunks
+                .iter()
+                .map(|chunk| chunk.text.as_str())
+                .collect()
+        })
+    }
+
+    /// The bytes in `range`, which can run across chunks.
+    pub fn slice(&self, range: Range<usize>) -> String {
+        if let Some(flat) = self.flat.get() {
+            return String::from(&flat[range]);
+        }
+        let mut slice = String::with_capacity(range.len());
+        let mut chunk = self.chunk_at(range.start);
+        while slice.len() < range.len() {
+            let start = self.starts[chunk];
+            let text = &self.chunks[chunk].text;
+            let from = range.start.max(start) - start;
+            let to = (range.end - start).min(text.len());
+            slice.push_str(&text[from..to]);
+            chunk += 1;
+        }
+        slice
+    }
+
+    /// The byte offset (0-indexed) line `line` starts at.
+    pub fn line_start(&self, line: usize) -> Option<usize> {
+        if line >= self.lines {
+            return None;
+        }
+        let chunk = self.first_lines.partition_point(|&first| first <= line) - 1;
+        Some(self.starts[chunk] + self.chunks[chunk].line_starts[line - self.first_lines[chunk]])
+    }
+
+    /// The text of (0-indexed) line `line`, with its newline if it has one.
+    pub fn line(&self, line: usize) -> Option<&str> {
+        if line >= self.lines {
+            return None;
+        }
+        let chunk = self.first_lines.partition_point(|&first| first <= line) - 1;
+        let Chunk { text, line_starts } = &self.chunks[chunk];
+        let index = line - self.first_lines[chunk];
+        let end = line_starts.get(index + 1).copied().unwrap_or(text.len());
+        Some(&text[line_starts[index]..end])
+    }
+
+    /// The (0-indexed) line the byte at `offset` is on.
+    pub fn line_of(&self, offset: usize) -> usize {
+        let chunk = self.chunk_at(offset);
+        let within = offset - self.starts[chunk];
+        let line_starts = &self.chunks[chunk].line_starts;
+        self.first_lines[chunk] + line_starts.partition_point(|&start| start <= within) - 1
+    }
+
+    /// The char starting at `offset`.
+    pub fn char_after(&self, offset: usize) -> Option<char> {
+        let chunk = self.chunk_at(offset);
+        let text = &self.chunks[chunk].text;
+        text.get(offset - self.starts[chunk]..)?.chars().next()
+    }
+
+    /// The char ending at `offset`.
+    pub fn char_before(&self, offset: usize) -> Option<char> {
+        let chunk = self.chunk_at(offset.checked_sub(1)?);
+        let text = &self.chunks[chunk].text;
+        text.get(..offset - self.starts[chunk])?.chars().next_back()
+    }
+
+    /// Whether `offset` is at the start or end of a char, rather than in
+    /// the middle of one or past the end.
+    pub fn is_char_boundary(&self, offset: usize) -> bool {
+        let chunk = self.chunk_at(offset);
+        self.chunks[chunk]
+            .text
+            .is_char_boundary(offset - self.starts[chunk])
+    }
+
+    /// Replaces the bytes in `range` with `text`.
+    pub fn replace(&mut self, range: Range<usize>, text: &str) {
+        self.flat.take();
+        let first = self.chunk_at(range.start);
+        let mut last = self.chunk_at(range.end);
+        let start = self.starts[first];
+        let mut merged = String::new();
+        for chunk in &self.chunks[first..=last] {
+            merged.push_str(&chunk.text);
+        }
+        merged.replace_range(range.start - start..range.end - start, text);
+        // Chunks which have shrunk take in the one after, so editing
+        // doesn't leave lots of tiny ones behind.
+        if merged.len() < CHUNK / 4 && last + 1 < self.chunks.len() {
+            last += 1;
+            merged.push_str(&self.chunks[last].text);
+        }
+        let tail = last + 1 == self.chunks.len();
+        self.chunks.splice(first..=last, split(merged, tail));
+        self.reindex();
+    }
+
+    /// Roughly how many bytes the text takes up, and how many the index
+    /// of where its chunks and lines start does.
+    pub fn memory(&self) -> (usize, usize) {
+        let text = self
+            .chunks
+            .iter()
+            .map(|chunk| chunk.text.capacity())
+            .sum::<usize>()
+            + self.flat.get().map_or(0, String::capacity);
+        let starts = self
+            .chunks
+            .iter()
+            .map(|chunk| chunk.line_starts.capacity())
+            .sum::<usize>()
+            + self.starts.capacity()
+            + self.first_lines.capacity();
+        let index =
+            starts * mem::size_of::<usize>() + self.chunks.capacity() * mem::size_of::<Chunk>();
+        (text, index)
+    }
+
+    /// The chunk holding the byte at `offset`, or the last one for the
+    /// very end.
+    fn chunk_at(&self, offset: usize) -> usize {
+        self.starts.partition_point(|&start| start <= offset) - 1
+    }
+
+    /// Works out where each chunk starts again, after they've changed.
+    fn reindex(&mut self) {
+        self.starts.clear();
+        self.first_lines.clear();
+        let (mut len, mut lines) = (0, 0);
+        for chunk in &self.chunks {
+            self.starts.push(len);
+            self.first_lines.push(lines);
+            len += chunk.text.len();
+            lines += chunk.line_starts.len();
+        }
+        self.len = len;
+        self.lines = lines;
+    }
+}
+
+/// Cuts `text` into chunks, after newlines. Only the chunks at the end of
+/// the rope (`tail`) may end without a newline, and there's an empty one
+/// after a newline at the very end.
+fn split(text: String, tail: bool) -> Vec<Chunk> {
+    let mut chunks = Vec::new();
+    if text.len() <= CHUNK {
+        if !text.is_empty() {
+            chunks.push(Chunk::new(text));
+        }
+    } else {
+        let bytes = text.as_bytes();
+        let mut from = 0;
+        while from < text.len() {
+            let to = match bytes[from + CHUNK.min(text.len() - from) - 1..]
+                .iter()
+                .position(|&b| b == b'\n')
+            {
+                Some(i) => from + CHUNK.min(text.len() - from) + i,
+                None => text.len(),
+            };
+            chunks.push(Chunk::new(String::from(&text[from..to])));
+            from = to;
+        }
+    }
+    let ends_line = chunks.last().is_none_or(|chunk| chunk.text.ends_with('\n'));
+    if tail && ends_line {
+        chunks.push(Chunk::new(String::new()));
+    }
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Checks the rope against `text` every way it can be read.
+    fn check(rope: &Rope, text: &str) {
+        assert_eq!(rope.text(), text);
+        assert_eq!(rope.len(), text.len());
+        let mut starts = vec![0];
+        starts.extend(text.match_indices('\n').map(|(i, _)| i + 1));
+        assert_eq!(rope.lines(), starts.len());
+        for (line, &start) in starts.iter().enumerate() {
+            assert_eq!(rope.line_start(line), Some(start));
+            let end = starts.get(line + 1).copied().unwrap_or(text.len());
+            assert_eq!(rope.line(line), Some(&text[start..end]));
+            assert_eq!(rope.line_of(start), line);
+            assert_eq!(rope.line_of(end.saturating_sub(1).max(start)), line);
+        }
+        assert_eq!(rope.line_start(starts.len()), None);
+        for chunk in &rope.chunks[..rope.chunks.len() - 1] {
+            assert!(chunk.text.ends_with('\n'));
+        }
+        assert!(!rope.chunks.last().unwrap().text.ends_with('\n'));
+    }
+
+    #[test]
+    fn small_text_reads_back_the_same() {
+        for text in ["", "\n", "one", "one\n", "one\ntwo", "\n\n日本\n"] {
+            check(&Rope::new(String::from(text)), text);
+        }
+    }
+
+    #[test]
+    fn edits_across_chunks_keep_the_lines_in_place() {
+        let mut text: String = (0..2000).map(|i| format!("line {}\n", i)).collect();
+        let mut rope = Rope::new(text.clone());
+        assert!(rope.chunks.len() > 3);
+        check(&rope, &text);
+
+        let edits = [
+            (5..5, "x"),
+            (4000..9000, ""),
+            (0..0, "a\nb\nc"),
+            (100..100, &"y".repeat(10000)),
+            (text.len() - 10..text.len() - 10, "\n\n"),
+        ];
+        for (range, inserted) in edits {
+            text.replace_range(range.clone(), inserted);
+            rope.replace(range, inserted);
+            check(&rope, &text);
+        }
+        let len = text.len();
+        rope.replace(0..len, "");
+        check(&rope, "");
+        assert_eq!(rope.chunks.len(), 1);
+    }
+
+    #[test]
+    fn chars_either_side_of_a_chunk_boundary_are_found() {
+        let text: String = (0..1000).map(|i| format!("é{}\n", i)).collect();
+        let rope = Rope::new(text.clone());
+        let boundary = rope.starts[1];
+        assert_eq!(rope.char_before(boundary), Some('\n'));
+        assert_eq!(rope.char_after(boundary), Some('é'));
+        assert!(!rope.is_char_boundary(boundary + 1));
+        assert_eq!(
+            rope.slice(boundary - 2..boundary + 2),
+            text[boundary - 2..boundary + 2]
+        );
+        assert_eq!(rope.char_after(text.len()), None);
+        assert_eq!(rope.char_before(0), None);
+    }
+}
@@ -0,0 +1,321 @@
+//! Writing buffers out to their files.
+
+use std::error::Error;
+use std::fs;
+use std::io::{self, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use termgame::{Game, Message};
+
+use crate::backup;
+use crate::buffer::Position;
+use crate::i18n::tr;
+use crate::lines;
+use crate::lock::Lock;
+use crate::paths;
+use crate::prompt::{Prompt, Question};
+use crate::watch::FileWatch;
+use crate::BufferEditor;
+
+/// Replaces the file at `path` with `bytes`. The text is written to a
+/// temporary file first and then moved into place, so a failure part
+/// way through never leaves half a file behind. With `backups`, the old
+/// file is backed up first, keeping that many backups.
+pub fn write_file(path: &Path, bytes: &[u8], backups: Option<usize>) -> io::Result<()> {
+    let temp = temp_path(path);
+    let written = replace_with(path, &temp, bytes, backups);
+    // However it went wrong, the temporary file isn't left behind.
+    if written.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    written
+}
+
+/// Writes `bytes` to `temp`, and moves it into place at `path`.
+fn replace_with(path: &Path, temp: &Path, bytes: &[u8], backups: Option<usize>) -> io::Result<()> {
+    fs::write(temp, bytes)?;
+    if let Some(keep) = backups {
+        backup::back_up(path, keep)?;
+    }
+    // Keep the permissions of the file being replaced, rather than
+    // whatever the new file happened to get.
+    if let Ok(metadata) = fs::metadata(path) {
+        fs::set_permissions(temp, metadata.permissions())?;
+    }
+    fs::rename(temp, path)
+}
+
+/// Writes `bytes` to `path` by running `helper` with the path added on
+/// the end, and piping them in, as in `sudo tee <path>`. This is
+/// for files that can't be written directly.
+pub fn write_privileged(
+    path: &Path,
+    bytes: &[u8],
+    helper: &[String],
+) -> Result<(), Box<dyn Error>> {
+    let (program, args) = helper
+        .split_first()
+        .ok_or_else(|| tr("save.no-helper", &[]))?;
+    let mut child = Command::new(program)
+        .args(args)
+        .arg(path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .map_err(|err| {
+            tr(
+                "save.helper-failed-to-run",
+                &[("program", program), ("error", &err)],
+            )
+        })?;
+    child
+        .stdin
+        .take()
+        .ok_or_else(|| tr("save.helper-stdin", &[]))?
+        .write_all(bytes)?;
+    match child.wait()?.success() {
+        true => Ok(()),
+        false => Err(tr(
+            "save.helper-failed",
+            &[("program", program), ("path", &path.display())],
+        )
+        .into()),
+    }
+}
+
+/// Where `path`'s new contents are written before replacing it.
+pub fn temp_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".buffers-tmp");
+    path.with_file_name(name)
+}
+
+impl BufferEditor {
+    /// Saves the buffer to `file`, which then becomes its file. If the
+    /// file was changed by something else since it was loaded, it's only
+    /// written over with `overwrite`. With `sudo`, it's written through
+    /// the config's `privileged_helper`.
+    pub fn save_to(
+        &mut self,
+        file: &Path,
+        sudo: bool,
+        overwrite: bool,
+    ) -> Result<(), Box<dyn Error>> {
+        let name = &self.name;
+        if self.huge.is_some() {
+            return Err(tr("save.partly-loaded", &[("name", name)]).into());
+        }
+        // Someone else may have changed the file since it was loaded, and
+        // writing over it would lose their changes.
+        if self.buffer.file() == Some(&*file.to_string_lossy())
+            && !overwrite
+            && self.changed_on_disk()
+        {
+            return Err(tr(
+                "save.changed-on-disk",
+                &[("path", &file.display()), ("name", name)],
+            )
+            .into());
+        }
+
+        let bytes = self
+            .buffer
+            .encoded()
+            .map_err(|err| tr("save.unencodable", &[("name", name), ("error", &err)]))?;
+        match sudo {
+            true => write_privileged(file, &bytes, &self.config.privileged_helper)?,
+            false => match write_file(file, &bytes, self.config.backups()) {
+                Ok(()) => {}
+                Err(err) if err.kind() == ErrorKind::PermissionDenied => {
+                    return Err(tr(
+                        "save.permission-denied",
+                        &[("path", &file.display()), ("error", &err), ("name", name)],
+                    )
+                    .into())
+                }
+                Err(err) => return Err(format!("{}: {}", file.display(), err).into()),
+            },
+        }
+
+        if self.buffer.file() != Some(&*file.to_string_lossy()) {
+            if self.buffer.file().is_none() {
+                self.remove_recovery();
+            }
+            self.swap = None;
+            self.buffer.set_file(file.display().to_string());
+            self.watch = Some(FileWatch::new(file));
+            self.lock = Lock::acquire(file).ok();
+        }
+        self.buffer.mark_saved();
+        if let Some(swap) = &mut self.swap {
+            swap.remove();
+        }
+        if let Some(watch) = &mut self.watch {
+            watch.reset();
+        }
+        self.refresh_git();
+        Ok(())
+    }
+
+    /// Takes the whitespace off the ends of lines and adds a newline at
+    /// the end, as asked, before the buffer's saved. It's one edit, so
+    /// undo puts it all back. The cursor stays on the same line.
+    pub fn tidy(&mut self, trim_trailing_whitespace: bool, insert_final_newline: bool) {
+        if self.huge.is_some() {
+            return;
+        }
+        let text = self.buffer.text();
+        let tidied = lines::tidy(text, trim_trailing_whitespace, insert_final_newline);
+        if tidied == text {
+            return;
+        }
+        let cursor = self.buffer.cursor_position();
+        self.buffer.set_text(&tidied);
+        let len = self
+            .buffer
+            .line(cursor.line)
+            .map_or(0, |line| line.chars().count());
+        let position = Position {
+            col: cursor.col.min(len),
+            ..cursor
+        };
+        let offset = self.buffer.offset_of(position);
+        self.buffer
+            .set_cursor(offset.unwrap_or(self.buffer.text().len()));
+    }
+
+    /// Tidies the buffer as the session's settings say to, and saves it.
+    fn tidy_and_save(&mut self, file: &Path, overwrite: bool) -> Result<(), Box<dyn Error>> {
+        self.tidy(
+            self.session.trim_trailing_whitespace,
+            self.session.insert_final_newline,
+        );
+        self.save_to(file, false, overwrite)
+    }
+
+    /// Whether the buffer's file has been changed by something else since
+    /// it was loaded or saved.
+    pub fn changed_on_disk(&self) -> bool {
+        self.buffer
+            .read_file()
+            .is_some_and(|on_disk| on_disk.is_ok_and(|text| text != self.buffer.saved_text()))
+    }
+
+    /// Asks, in the status bar, whether to reload the file or write over
+    /// it, since something else has changed it.
+    pub fn ask_changed(&mut self) {
+        let label = tr("editor.changed-prompt", &[("name", &self.name)]);
+        self.prompt = Some((Prompt::new(&label, ""), Question::Changed));
+    }
+
+    /// Reloads the file for `r`, or writes over it for `o`. Anything
+    /// else keeps the buffer as it is.
+    pub fn changed_answer(&mut self, game: &mut Game, answer: &str) {
+        let message = match answer {
+            "r" => self.reload(),
+            "o" => match self.buffer.file().map(PathBuf::from) {
+                Some(file) => match self.tidy_and_save(&file, true) {
+                    Ok(()) => tr("editor.saved", &[("path", &file.display())]),
+                    Err(err) => tr("editor.save-failed", &[("error", &err)]),
+                },
+                None => return,
+            },
+            _ => tr("editor.kept", &[("name", &self.name)]),
+        };
+        game.set_message(Some(Message::new(message)));
+    }
+
+    /// Replaces the buffer's text with its file's, as one undoable edit.
+    fn reload(&mut self) -> String {
+        let Some(on_disk) = self.buffer.read_file() else {
+            return String::new();
+        };
+        match on_disk {
+            Ok(text) => {
+                self.buffer.set_text(&text);
+                self.buffer.mark_saved();
+                if let Some(watch) = &mut self.watch {
+                    watch.reset();
+                }
+                self.refresh_git();
+                tr("editor.reloaded", &[("name", &self.name)])
+            }
+            Err(err) => tr(
+                "editor.reload-failed",
+                &[("name", &self.name), ("error", &err)],
+            ),
+        }
+    }
+
+    /// Saves the buffer from inside the editor, for Ctrl+S. A buffer
+    /// without a file asks for one in the status bar first.
+    pub fn save(&mut self, game: &mut Game) {
+        if self.read_only {
+            let message = tr("editor.read-only", &[("name", &self.name)]);
+            game.set_message(Some(Message::new(message)));
+            return;
+        }
+        match self.buffer.file() {
+            // Saving would lose whatever else changed the file.
+            Some(_) if self.changed_on_disk() => self.ask_changed(),
+            Some(file) => {
+                let file = PathBuf::from(file);
+                self.save_and_say(game, &file);
+            }
+            None => {
+                let prompt = Prompt::new(&tr("editor.save-as-prompt", &[]), "");
+                self.prompt = Some((prompt, Question::SaveAs));
+            }
+        }
+    }
+
+    /// Saves the buffer to the file typed at the Ctrl+S prompt.
+    pub fn save_as(&mut self, game: &mut Game, typed: &str) {
+        if typed.is_empty() {
+            game.set_message(Some(Message::new(tr("editor.not-saved", &[]))));
+            return;
+        }
+        match paths::resolve(typed) {
+            Ok(file) => self.save_and_say(game, &file),
+            Err(err) => {
+                let message = tr("editor.save-failed", &[("error", &err)]);
+                game.set_message(Some(Message::new(message)));
+            }
+        }
+    }
+
+    /// Saves to `file`, and says how that went in the editor, since
+    /// printing would end up in the middle of the screen.
+    fn save_and_say(&mut self, game: &mut Game, file: &Path) {
+        let message = match self.tidy_and_save(file, false) {
+            Ok(()) => {
+                tracing::info!(buffer = %self.name, file = %file.display(), "saved");
+                match self.take_snapshot() {
+                    Ok(()) => tr("editor.saved", &[("path", &file.display())]),
+                    Err(err) => tr("editor.snapshot-failed", &[("error", &err)]),
+                }
+            }
+            Err(err) => {
+                tracing::warn!(buffer = %self.name, error = %err, "couldn't save");
+                tr("editor.save-failed", &[("error", &err)])
+            }
+        };
+        game.set_message(Some(Message::new(message)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_failed_write_leaves_no_temporary_file() {
+        let dir = std::env::temp_dir().join(format!("buffers-write-{}", std::process::id()));
+        fs::create_dir_all(dir.join("inside")).unwrap();
+        // A directory with something in it can't be replaced by a file.
+        assert!(write_file(&dir, b"text", None).is_err());
+        assert!(!temp_path(&dir).exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
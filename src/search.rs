@@ -0,0 +1,90 @@
+//! Finding lines which match a pattern, for `search`.
+
+use regex::Regex;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+
+use crate::BufferEditor;
+
+/// A line which matched, and where on it the match starts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchResult {
+    pub buffer: String,
+    /// 1-indexed, like an editor shows it.
+    pub line: usize,
+    /// 1-indexed, in chars rather than bytes.
+    pub col: usize,
+    /// The whole line, without its newline.
+    pub text: String,
+}
+
+/// Shown like `grep -n --column` would: `buffer:line:col: text`.
+impl fmt::Display for SearchResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}: {}",
+            self.buffer, self.line, self.col, self.text
+        )
+    }
+}
+
+/// Every one of `lines` which `regex` matches, with where the first
+/// match on it starts.
+pub fn search_lines<'a>(
+    buffer: &str,
+    lines: impl Iterator<Item = &'a str>,
+    regex: &Regex,
+) -> Vec<SearchResult> {
+    lines
+        .enumerate()
+        .filter_map(|(i, line)| search_line(buffer, i, line, regex))
+        .collect()
+}
+
+/// Where `regex` first matches `line`, which is (0-indexed) line `index`
+/// of `buffer`, if it does.
+pub fn search_line(buffer: &str, index: usize, line: &str, regex: &Regex) -> Option<SearchResult> {
+    let found = regex.find(line)?;
+    Some(SearchResult {
+        buffer: String::from(buffer),
+        line: index + 1,
+        col: line[..found.start()].chars().count() + 1,
+        text: String::from(line),
+    })
+}
+
+/// The matching lines in every buffer, in order of buffer name, then
+/// line. Only part of a huge file is ever loaded, so the whole of it is
+/// read through from the file instead.
+pub fn search_buffers(
+    editors: &HashMap<String, BufferEditor>,
+    regex: &Regex,
+) -> io::Result<Vec<SearchResult>> {
+    let mut names: Vec<&String> = editors.keys().collect();
+    names.sort();
+    let mut results = Vec::new();
+    for name in names {
+        let editor = &editors[name];
+        match &editor.huge {
+            Some(huge) => results.extend(huge.search(name, regex)?),
+            None => results.extend(search_lines(name, editor.buffer.lines(), regex)),
+        }
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn results_say_where_the_match_is() {
+        let regex = Regex::new("b+").unwrap();
+        let results = search_lines("notes", "abc\nxyz\nébb\n".lines(), &regex);
+        let shown: Vec<String> = results.iter().map(ToString::to_string).collect();
+        assert_eq!(shown, ["notes:1:2: abc", "notes:3:2: ébb"]);
+    }
+}
@@ -0,0 +1,127 @@
+//! Named sessions: which buffers were open, and where in them, saved in
+//! `~/.local/share/buffers/sessions/<name>.toml` so a set of buffers can
+//! be put away and picked up again later. Each project can have its own.
+//!
+//! Buffers with files are saved as the file, and are read again when
+//! the session's loaded. Anything that isn't in a file yet, like unsaved
+//! edits and scratch buffers, is saved in the session itself.
+
+use serde::{Deserialize, Serialize};
+
+use std::error::Error;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+use crate::config::data_dir;
+
+/// Every buffer in a session.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SavedSession {
+    pub buffers: Vec<SavedBuffer>,
+}
+
+/// One buffer in a session.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SavedBuffer {
+    pub name: String,
+    pub file: Option<String>,
+    /// The text, if it's different from the file's.
+    pub text: Option<String>,
+    /// The cursor, as a byte offset.
+    pub cursor: usize,
+    /// The viewport's `(x, y)`.
+    pub viewport: (i32, i32),
+}
+
+fn dir() -> Option<PathBuf> {
+    Some(data_dir()?.join("sessions"))
+}
+
+/// Where the session called `name` is kept. Names can't be paths, so
+/// every session stays in the sessions directory.
+fn path(name: &str) -> Result<PathBuf, Box<dyn Error>> {
+    if name.is_empty() || name.starts_with('.') || name.contains(['/', '\\']) {
+        return Err(format!("{} can't be the name of a session", name).into());
+    }
+    Ok(dir()
+        .ok_or("no home directory")?
+        .join(format!("{}.toml", name)))
+}
+
+/// Saves `session` as `name`, replacing any session already called that.
+pub fn save(name: &str, session: &SavedSession) -> Result<(), Box<dyn Error>> {
+    let path = path(name)?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&path, toml::to_string(session)?)?;
+    Ok(())
+}
+
+pub fn load(name: &str) -> Result<SavedSession, Box<dyn Error>> {
+    let path = path(name)?;
+    match fs::read_to_string(&path) {
+        Ok(text) => {
+            Ok(toml::from_str(&text).map_err(|err| format!("{}: {}", path.display(), err))?)
+        }
+        Err(err) if err.kind() == ErrorKind::NotFound => {
+            Err(format!("There's no session called {}", name).into())
+        }
+        Err(err) => Err(format!("{}: {}", path.display(), err).into()),
+    }
+}
+
+pub fn delete(name: &str) -> Result<(), Box<dyn Error>> {
+    match fs::remove_file(path(name)?) {
+        Ok(()) => Ok(()),
+        Err(err) if err.kind() == ErrorKind::NotFound => {
+            Err(format!("There's no session called {}", name).into())
+        }
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// The names of every saved session, in order.
+pub fn list() -> Result<Vec<String>, Box<dyn Error>> {
+    let Some(dir) = dir() else {
+        return Ok(Vec::new());
+    };
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err.into()),
+    };
+    let mut names = Vec::new();
+    for entry in entries {
+        let name = entry?.file_name().to_string_lossy().into_owned();
+        if let Some(name) = name.strip_suffix(".toml") {
+            names.push(String::from(name));
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_session_round_trips_through_toml() {
+        let session = SavedSession {
+            buffers: vec![SavedBuffer {
+                name: String::from("notes"),
+                file: None,
+                text: Some(String::from("unsaved\n")),
+                cursor: 3,
+                viewport: (0, 2),
+            }],
+        };
+        let text = toml::to_string(&session).unwrap();
+        let loaded: SavedSession = toml::from_str(&text).unwrap();
+        assert_eq!(loaded.buffers, session.buffers);
+
+        assert!(path("../escape").is_err());
+    }
+}
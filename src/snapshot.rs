@@ -0,0 +1,140 @@
+//! Copies of each buffer's text, taken every so often and kept on disk
+//! in `~/.local/share/buffers/snapshots/`, so old versions can be
+//! looked back at and restored.
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::config::data_dir;
+use crate::transform;
+
+/// How often an open buffer is snapshotted, if it's changed.
+pub const INTERVAL: Duration = Duration::from_secs(60);
+
+/// How many snapshots are kept for each buffer. Past this, the oldest
+/// are deleted.
+pub const KEEP: usize = 50;
+
+/// One snapshot on disk.
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    pub taken: SystemTime,
+    pub path: PathBuf,
+}
+
+impl Snapshot {
+    /// The text of the buffer when this was taken.
+    pub fn text(&self) -> io::Result<String> {
+        fs::read_to_string(&self.path)
+    }
+}
+
+/// Decides when an open buffer is due another snapshot. Times are read
+/// off the session's [`Clock`](crate::clock::Clock).
+#[derive(Debug)]
+pub struct Schedule {
+    /// When the last snapshot was taken, or the schedule was first
+    /// checked if there hasn't been one.
+    last_taken: Option<Duration>,
+    /// The buffer's revision when the last snapshot was taken.
+    revision: usize,
+}
+
+impl Schedule {
+    pub fn new(revision: usize) -> Schedule {
+        Schedule {
+            last_taken: None,
+            revision,
+        }
+    }
+
+    /// Whether it's been long enough, and there's been a change, since
+    /// the last snapshot.
+    pub fn is_due(&mut self, revision: usize, now: Duration) -> bool {
+        let last_taken = *self.last_taken.get_or_insert(now);
+        revision != self.revision && now.saturating_sub(last_taken) >= INTERVAL
+    }
+
+    /// Notes that a snapshot was just taken at `revision`.
+    pub fn taken(&mut self, revision: usize, now: Duration) {
+        self.last_taken = Some(now);
+        self.revision = revision;
+    }
+}
+
+/// Where the snapshots of the buffer called `name` are kept.
+fn dir_for(name: &str) -> Option<PathBuf> {
+    // Encoded, so a buffer named after a path is still one directory.
+    let name = transform::url_encode(name).ok()?;
+    Some(data_dir()?.join("snapshots").join(name))
+}
+
+/// Saves `text` as the newest snapshot of the buffer called `name`,
+/// unless it's the same as the last one, then deletes any snapshots
+/// past the newest [`KEEP`].
+pub fn take(name: &str, text: &str) -> io::Result<()> {
+    let dir = dir_for(name).ok_or_else(|| io::Error::other("no home directory"))?;
+    let snapshots = list(name)?;
+    if let Some(newest) = snapshots.last() {
+        if newest.text()? == text {
+            return Ok(());
+        }
+    }
+
+    fs::create_dir_all(&dir)?;
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    // Zero-padded, so the file names sort in the order they were taken.
+    fs::write(dir.join(format!("{:016}.txt", millis)), text)?;
+
+    let total = snapshots.len() + 1;
+    for old in snapshots.iter().take(total.saturating_sub(KEEP)) {
+        fs::remove_file(&old.path)?;
+    }
+    Ok(())
+}
+
+/// Every snapshot of the buffer called `name`, oldest first.
+pub fn list(name: &str) -> io::Result<Vec<Snapshot>> {
+    let Some(dir) = dir_for(name) else {
+        return Ok(Vec::new());
+    };
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+
+    let mut snapshots = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        let millis = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(|stem| stem.parse::<u64>().ok());
+        if let Some(millis) = millis {
+            snapshots.push(Snapshot {
+                taken: UNIX_EPOCH + Duration::from_millis(millis),
+                path,
+            });
+        }
+    }
+    snapshots.sort_by_key(|snapshot| snapshot.taken);
+    Ok(snapshots)
+}
+
+/// Moves the snapshots of the buffer called `from` over to `to`, for
+/// when a buffer is renamed.
+pub fn rename(from: &str, to: &str) -> io::Result<()> {
+    let (Some(from), Some(to)) = (dir_for(from), dir_for(to)) else {
+        return Ok(());
+    };
+    match fs::rename(from, to) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+        _ => Ok(()),
+    }
+}
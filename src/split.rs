@@ -0,0 +1,159 @@
+//! Two buffers open at once, from `open --split`: side by side, or one
+//! above the other with `--stacked`. Each has a pane of its own, which
+//! it scrolls around in as if it were the whole screen, and Ctrl+O moves
+//! the keys from one pane to the other. Esc closes both.
+//!
+//! The session goes with the keys, and only the editor which has them
+//! is ticked.
+
+use termgame::{Controller, Game, GameEvent, StyledCharacter};
+
+use std::mem;
+
+use crate::keymap::Action;
+use crate::pane::{Area, Pane};
+use crate::status;
+use crate::BufferEditor;
+
+/// Two editors sharing the screen.
+pub struct Split {
+    panes: [Pane; 2],
+    /// Which pane has the keys.
+    focus: usize,
+    stacked: bool,
+}
+
+impl Split {
+    /// Splits the screen between `first`, which has the keys and the
+    /// session, and `second`.
+    pub fn new(first: BufferEditor, second: BufferEditor, stacked: bool) -> Split {
+        Split {
+            panes: [Pane::new(first), Pane::new(second)],
+            focus: 0,
+            stacked,
+        }
+    }
+
+    /// The two editors, in the order they were split, with the session
+    /// back with the first.
+    pub fn into_editors(mut self) -> [BufferEditor; 2] {
+        if self.focus == 1 {
+            self.swap_session();
+        }
+        self.panes.map(Pane::into_editor)
+    }
+
+    fn swap_session(&mut self) {
+        let [first, second] = &mut self.panes;
+        mem::swap(&mut first.editor.session, &mut second.editor.session);
+    }
+
+    fn areas(&self) -> [Area; 2] {
+        match self.stacked {
+            false => [Area::Left, Area::Right],
+            true => [Area::Top, Area::Bottom],
+        }
+    }
+
+    /// Runs `f` on pane `i`'s editor, and copies what it draws into
+    /// the pane.
+    fn with_pane(
+        &mut self,
+        game: &mut Game,
+        i: usize,
+        f: impl FnOnce(&mut BufferEditor, &mut Game),
+    ) {
+        // The editor without the keys only borrows the session.
+        if i != self.focus {
+            self.swap_session();
+        }
+        let area = self.areas()[i];
+        self.panes[i].run(game, area, f);
+        if i != self.focus {
+            self.swap_session();
+        }
+        self.draw_divider(game);
+    }
+
+    /// Draws the line between the panes.
+    fn draw_divider(&self, game: &mut Game) {
+        let (columns, rows) = status::visible_size(game);
+        let paint = self.panes[self.focus].editor.session.theme.gutter;
+        let ((left, top), _) = self.areas()[1].place(game);
+        let cells: Vec<(i32, i32, char)> = match self.stacked {
+            false => (0..rows).map(|y| (left - 1, y, '│')).collect(),
+            true => (0..columns).map(|x| (x, top - 1, '─')).collect(),
+        };
+        for (x, y, c) in cells {
+            let styled = StyledCharacter::new(c).style(paint.style());
+            game.set_screen_char(x, y, Some(styled));
+        }
+    }
+}
+
+impl Controller for Split {
+    /// Starts both editors, the one with the keys last, so its message
+    /// is the one that shows.
+    fn on_start(&mut self, game: &mut Game) {
+        let other = 1 - self.focus;
+        self.with_pane(game, other, |editor, game| editor.on_start(game));
+        self.with_pane(game, self.focus, |editor, game| editor.on_start(game));
+    }
+
+    fn on_event(&mut self, game: &mut Game, event: GameEvent) {
+        let action = self.panes[self.focus]
+            .editor
+            .keymap
+            .lookup(&event.clone().into());
+        if action == Some(Action::OtherPane) {
+            self.swap_session();
+            self.focus = 1 - self.focus;
+            game.set_message(None);
+            // Redrawn, so the status bar shows what the new one has.
+            self.with_pane(game, self.focus, |editor, game| editor.render(game));
+            return;
+        }
+        self.with_pane(game, self.focus, |editor, game| {
+            editor.on_event(game, event)
+        });
+    }
+
+    fn on_tick(&mut self, game: &mut Game) {
+        self.with_pane(game, self.focus, |editor, game| editor.on_tick(game));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use termgame::{CharChunkMap, KeyCode};
+
+    use crate::harness::{ctrl, editor, key, row, typed};
+
+    #[test]
+    fn keys_go_to_one_pane_and_each_scrolls_on_its_own() {
+        let first = "a\n".repeat(40);
+        let mut split = Split::new(editor("a", &first), editor("b", "b"), false);
+        let mut screen = CharChunkMap::new();
+        let mut game = Game::new(&mut screen);
+        split.on_start(&mut game);
+        for event in typed("x").into_iter().chain([ctrl('o')]).chain(typed("y")) {
+            split.on_event(&mut game, event);
+        }
+        // The second pane starts past the first and the line between.
+        drop(game);
+        assert_eq!(row(&screen, 0), format!("xa{}│yb", " ".repeat(36)));
+
+        let mut game = Game::new(&mut screen);
+        split.on_event(&mut game, ctrl('o'));
+        for _ in 0..30 {
+            split.on_event(&mut game, key(KeyCode::Down));
+        }
+        drop(game);
+        assert_eq!(row(&screen, 0), format!("a{}│yb", " ".repeat(37)));
+        let [first, second] = split.into_editors();
+        assert!(first.viewport.y > 0);
+        assert_eq!(second.viewport.y, 0);
+    }
+}
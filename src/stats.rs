@@ -0,0 +1,79 @@
+//! Opt-in statistics about how the user types.
+
+use std::time::Duration;
+
+/// If no key has been pressed for this long, the user isn't typing,
+/// so the time doesn't count towards their words per minute.
+const IDLE_AFTER: Duration = Duration::from_secs(5);
+
+/// Counts keystrokes and edits over a session of typing. Times are read
+/// off the session's [`Clock`](crate::clock::Clock).
+#[derive(Debug)]
+pub struct TypingStats {
+    keystrokes: usize,
+    chars_typed: usize,
+    edits: usize,
+    /// Time spent actively typing (i.e. ignoring idle stretches).
+    active: Duration,
+    last_key: Option<Duration>,
+    last_tick: Option<Duration>,
+}
+
+impl TypingStats {
+    pub fn new() -> TypingStats {
+        TypingStats {
+            keystrokes: 0,
+            chars_typed: 0,
+            edits: 0,
+            active: Duration::ZERO,
+            last_key: None,
+            last_tick: None,
+        }
+    }
+
+    /// Records a key being pressed. `typed` is whether it typed a
+    /// character, and `edited` is whether it changed the buffer.
+    pub fn record_key(&mut self, typed: bool, edited: bool, now: Duration) {
+        self.keystrokes += 1;
+        if typed {
+            self.chars_typed += 1;
+        }
+        if edited {
+            self.edits += 1;
+        }
+        self.last_key = Some(now);
+    }
+
+    /// Called on each tick, to count up the time spent typing.
+    pub fn tick(&mut self, now: Duration) {
+        let typing = self
+            .last_key
+            .is_some_and(|last_key| now.saturating_sub(last_key) < IDLE_AFTER);
+        if let (true, Some(last_tick)) = (typing, self.last_tick) {
+            self.active += now.saturating_sub(last_tick);
+        }
+        self.last_tick = Some(now);
+    }
+
+    /// Words (by the usual convention of five characters) per minute
+    /// of active typing.
+    pub fn wpm(&self) -> f64 {
+        let minutes = self.active.as_secs_f64() / 60.0;
+        if minutes == 0.0 {
+            return 0.0;
+        }
+        (self.chars_typed as f64 / 5.0) / minutes
+    }
+
+    /// A multi-line summary, as shown by `stats typing`.
+    pub fn summary(&self) -> String {
+        format!(
+            "Keystrokes:  {}\nTyped chars: {}\nEdits:       {}\nTyping time: {}s\nWPM:         {:.1}",
+            self.keystrokes,
+            self.chars_typed,
+            self.edits,
+            self.active.as_secs(),
+            self.wpm()
+        )
+    }
+}
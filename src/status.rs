@@ -0,0 +1,37 @@
+//! The status bar, pinned to the bottom of the editor.
+
+use termgame::{Game, StyledCharacter};
+
+use std::ops::Range;
+
+use crate::theme::Paint;
+
+/// The size of the area inside the editor's border which is currently
+/// showing, as `(columns, rows)`.
+pub fn visible_size(game: &Game) -> (i32, i32) {
+    let (width, (height, _)) = game.screen_size();
+    // The border takes a cell off each side.
+    (i32::from(width) - 2, i32::from(height) - 2)
+}
+
+/// Draws `text` as a bar along the bottom row of whatever is currently
+/// in view, `size` across and down, covering whatever was there. Returns
+/// the row, and the columns along it, which it covered.
+pub fn draw_status_bar(
+    game: &mut Game,
+    size: (i32, i32),
+    text: &str,
+    paint: Paint,
+) -> (i32, Range<i32>) {
+    let viewport = game.get_viewport();
+    let (columns, rows) = size;
+    let y = viewport.y + rows - 1;
+
+    let mut chars = text.chars();
+    for x in 0..columns {
+        let c = chars.next().unwrap_or(' ');
+        let styled = StyledCharacter::new(c).style(paint.style());
+        game.set_screen_char(viewport.x + x, y, Some(styled));
+    }
+    (y, viewport.x..viewport.x + columns)
+}
@@ -0,0 +1,275 @@
+//! Checks [`Buffer`] against a much simpler (and slower) model of what
+//! it should do, over a long stream of random edits.
+//!
+//! The stream is generated from a seed, so any failure can be replayed
+//! exactly by running the same seed again.
+
+use crate::buffer::Buffer;
+use crate::wordcount::WordCount;
+
+/// A small, fast pseudo-random number generator (SplitMix64). It's not
+/// remotely good enough for anything secret, but it's deterministic.
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng { state: seed }
+    }
+
+    pub fn next(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+
+    /// A number in `0..n`.
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+/// What the buffer should hold: a list of chars and a cursor between
+/// them, with every state kept for undo.
+struct Model {
+    chars: Vec<char>,
+    cursor: usize,
+    undo: Vec<Vec<char>>,
+    redo: Vec<Vec<char>>,
+    /// What's been deleted by deleting back, since anything else, which
+    /// the buffer undoes as one step while it's within a word.
+    deleting: Option<Vec<char>>,
+}
+
+impl Model {
+    fn edit(&mut self, range: std::ops::Range<usize>, text: &str) {
+        self.undo.push(self.chars.clone());
+        self.redo.clear();
+        let inserted: Vec<char> = text.chars().collect();
+        // A cursor after the edit moves with the text, and one inside it
+        // ends up just after what was inserted.
+        if self.cursor >= range.end {
+            self.cursor = self.cursor - range.len() + inserted.len();
+        } else if self.cursor > range.start {
+            self.cursor = range.start + inserted.len();
+        }
+        self.chars.splice(range, inserted);
+    }
+
+    /// Deletes the char before the cursor, joining the undo step of the
+    /// deletes just before it when that's still in the same word.
+    fn delete_back(&mut self) {
+        let c = self.chars[self.cursor - 1];
+        let joins = match &self.deleting {
+            Some(deleted) => c != '\n' && (!c.is_whitespace() || deleted[0].is_whitespace()),
+            None => false,
+        };
+        let mut deleted = match joins {
+            true => self.deleting.take().unwrap(),
+            false => {
+                self.undo.push(self.chars.clone());
+                self.redo.clear();
+                Vec::new()
+            }
+        };
+        deleted.insert(0, c);
+        self.cursor -= 1;
+        self.chars.remove(self.cursor);
+        self.deleting = Some(deleted);
+    }
+
+    fn text(&self) -> String {
+        self.chars.iter().collect()
+    }
+}
+
+/// Characters to type, picked to include multi-byte ones and lines.
+const ALPHABET: &[char] = &['a', 'b', ' ', '\n', 'é', '日', '_', '.'];
+
+/// Applies `steps` random operations, chosen by `seed`, to both a
+/// [`Buffer`] and the model, stopping at the first time they disagree.
+/// The error says which step went wrong and how.
+pub fn run(seed: u64, steps: usize) -> Result<(), String> {
+    let mut rng = Rng::new(seed);
+    let mut buffer = Buffer::new(None);
+    let mut model = Model {
+        chars: Vec::new(),
+        cursor: 0,
+        undo: Vec::new(),
+        redo: Vec::new(),
+        deleting: None,
+    };
+
+    for step in 0..steps {
+        let len = model.chars.len();
+        let operation = match rng.below(10) {
+            0..=2 => {
+                let text: String = (0..1 + rng.below(4))
+                    .map(|_| ALPHABET[rng.below(ALPHABET.len())])
+                    .collect();
+                buffer.insert(&text);
+                model.edit(model.cursor..model.cursor, &text);
+                format!("insert {:?}", text)
+            }
+            3 => {
+                buffer.delete_back();
+                if model.cursor > 0 {
+                    model.delete_back();
+                }
+                String::from("delete back")
+            }
+            4 => {
+                let start = rng.below(len + 1);
+                let end = start + rng.below(len - start + 1);
+                let text: String = (0..rng.below(3))
+                    .map(|_| ALPHABET[rng.below(ALPHABET.len())])
+                    .collect();
+                buffer.replace_range(
+                    byte_offset(&model.chars, start)..byte_offset(&model.chars, end),
+                    &text,
+                );
+                // A replacement which changes nothing still makes an
+                // undo step, as the buffer does.
+                model.edit(start..end, &text);
+                format!("replace {}..{} with {:?}", start, end, text)
+            }
+            5 => {
+                buffer.move_left();
+                model.cursor = model.cursor.saturating_sub(1);
+                String::from("left")
+            }
+            6 => {
+                buffer.move_right();
+                model.cursor = (model.cursor + 1).min(len);
+                String::from("right")
+            }
+            7 => {
+                let to = rng.below(len + 1);
+                buffer.set_cursor(byte_offset(&model.chars, to));
+                model.cursor = to;
+                format!("cursor to {}", to)
+            }
+            8 => {
+                let undone = buffer.undo();
+                let previous = model.undo.pop();
+                if undone != previous.is_some() {
+                    return Err(format!(
+                        "seed {}, step {}: undo returned {}",
+                        seed, step, undone
+                    ));
+                }
+                if let Some(previous) = previous {
+                    model
+                        .redo
+                        .push(std::mem::replace(&mut model.chars, previous));
+                }
+                String::from("undo")
+            }
+            _ => {
+                let redone = buffer.redo();
+                let next = model.redo.pop();
+                if redone != next.is_some() {
+                    return Err(format!(
+                        "seed {}, step {}: redo returned {}",
+                        seed, step, redone
+                    ));
+                }
+                if let Some(next) = next {
+                    model.undo.push(std::mem::replace(&mut model.chars, next));
+                }
+                String::from("redo")
+            }
+        };
+        if operation != "delete back" {
+            model.deleting = None;
+        }
+        // Where undo and redo leave the cursor is the buffer's business,
+        // so the model just goes along with it.
+        if operation == "undo" || operation == "redo" {
+            model.cursor = buffer.text()[..buffer.cursor()].chars().count();
+        }
+
+        let fail = |what: String| {
+            Err(format!(
+                "seed {}, step {} ({}): {}",
+                seed, step, operation, what
+            ))
+        };
+        let expected = model.text();
+        if buffer.text() != expected {
+            return fail(format!(
+                "text is {:?}, expected {:?}",
+                buffer.text(),
+                expected
+            ));
+        }
+        if buffer.cursor() != byte_offset(&model.chars, model.cursor) {
+            return fail(format!(
+                "cursor is at byte {}, expected char {}",
+                buffer.cursor(),
+                model.cursor
+            ));
+        }
+        if buffer.word_count() != WordCount::of(&expected) {
+            return fail(format!(
+                "word count is {:?}, expected {:?}",
+                buffer.word_count(),
+                WordCount::of(&expected)
+            ));
+        }
+        if buffer.len_chars() != model.chars.len() {
+            return fail(format!("char count is {}", buffer.len_chars()));
+        }
+        let position = buffer.position_of(buffer.cursor());
+        if buffer.offset_of(position) != Some(buffer.cursor()) {
+            return fail(format!(
+                "cursor's position {:?} doesn't lead back to it",
+                position
+            ));
+        }
+        if buffer.char_at(position)
+            != model
+                .chars
+                .get(model.cursor)
+                .filter(|&&c| c != '\n')
+                .copied()
+        {
+            return fail(format!(
+                "char at the cursor is {:?}",
+                buffer.char_at(position)
+            ));
+        }
+        if buffer.len_lines() != expected.lines().count() {
+            return fail(format!("line count is {}", buffer.len_lines()));
+        }
+    }
+    Ok(())
+}
+
+/// The byte offset of the `index`th char.
+fn byte_offset(chars: &[char], index: usize) -> usize {
+    chars[..index].iter().map(|c| c.len_utf8()).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buffer_agrees_with_the_model() {
+        for seed in 0..8 {
+            run(seed, 2_000).unwrap();
+        }
+    }
+
+    #[test]
+    fn the_same_seed_gives_the_same_stream() {
+        let (mut a, mut b) = (Rng::new(42), Rng::new(42));
+        for _ in 0..100 {
+            assert_eq!(a.next(), b.next());
+        }
+    }
+}
@@ -0,0 +1,182 @@
+//! Swap files, so unsaved changes survive a crash. While a buffer with a
+//! file has unsaved changes, its text is written every `swap_secs` (4 by
+//! default) to a swap file next to the file, `.notes.txt.swp` for
+//! `notes.txt`. Saving
+//! deletes it, and so does closing the buffer or quitting, so the only
+//! ones left are from a BuffeRS which didn't get to.
+//!
+//! Opening a file with one left over says so, wherever the file is, and
+//! until `recover` has
+//! either restored the text from it or, with `--discard`, deleted it,
+//! it isn't written over.
+
+use termgame::{Game, Message};
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::i18n::tr;
+use crate::save;
+use crate::BufferEditor;
+
+/// Where the swap file for `file` goes.
+pub fn swap_path(file: &Path) -> PathBuf {
+    let name = file.file_name().unwrap_or_default().to_string_lossy();
+    file.with_file_name(format!(".{}.swp", name))
+}
+
+/// The files in `dir` which have swap files left over.
+pub fn leftovers(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| {
+            let name = entry.ok()?.file_name();
+            let name = name.to_str()?.strip_prefix('.')?.strip_suffix(".swp")?;
+            Some(dir.join(name))
+        })
+        .filter(|file| file.is_file())
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// The swap file kept for a buffer. It's deleted when dropped, if this
+/// process wrote it.
+#[derive(Debug)]
+pub struct Swap {
+    path: PathBuf,
+    /// Set when there was one left over, which mustn't be written over.
+    leftover: bool,
+    written: bool,
+    /// When it was last written, or first checked if it hasn't been.
+    last: Option<Duration>,
+    /// The buffer's revision when it was last written.
+    revision: Option<usize>,
+}
+
+impl Swap {
+    /// The swap file for `file`, noting whether there's one left over.
+    pub fn new(file: &Path) -> Swap {
+        let path = swap_path(file);
+        Swap {
+            leftover: path.exists(),
+            path,
+            written: false,
+            last: None,
+            revision: None,
+        }
+    }
+
+    /// The swap file for `file`, taking over any that's left there.
+    pub fn take_over(file: &Path) -> Swap {
+        let mut swap = Swap::new(file);
+        swap.leftover = false;
+        swap
+    }
+
+    /// Deletes the swap file, once there's nothing unsaved in it.
+    pub fn remove(&mut self) {
+        if self.written {
+            let _ = fs::remove_file(&self.path);
+            self.written = false;
+        }
+    }
+}
+
+impl Drop for Swap {
+    fn drop(&mut self) {
+        self.remove();
+    }
+}
+
+impl BufferEditor {
+    /// Looks for a swap file left over for the buffer's file, as the
+    /// editor opens, saying what to do about one.
+    pub fn check_swap(&mut self) -> Option<String> {
+        let file = PathBuf::from(self.buffer.file()?);
+        if self.swap.is_some() || self.config.swap_secs == 0 {
+            return None;
+        }
+        let swap = self.swap.insert(Swap::new(&file));
+        swap.leftover
+            .then(|| tr("editor.swap-file", &[("name", &self.name)]))
+    }
+
+    /// Writes the buffer's swap file, if it has unsaved changes and it's
+    /// been long enough since the last time.
+    pub fn swap_tick(&mut self, game: &mut Game) {
+        let Some(file) = self.buffer.file().map(PathBuf::from) else {
+            return;
+        };
+        let every = Duration::from_secs(self.config.swap_secs);
+        if every.is_zero() || self.read_only || !self.buffer.is_dirty() {
+            return;
+        }
+        let now = self.session.clock.now();
+        let revision = self.buffer.revision();
+        let swap = self.swap.get_or_insert_with(|| Swap::new(&file));
+        let last = *swap.last.get_or_insert(now);
+        if swap.leftover || swap.revision == Some(revision) || now.saturating_sub(last) < every {
+            return;
+        }
+        swap.last = Some(now);
+        swap.revision = Some(revision);
+        match save::write_file(&swap.path, self.buffer.text().as_bytes(), None) {
+            Ok(()) => swap.written = true,
+            Err(err) => {
+                tracing::warn!(buffer = %self.name, error = %err, "couldn't write the swap file");
+                let message = tr("editor.swap-failed", &[("error", &err)]);
+                game.set_message(Some(Message::new(message)));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::rc::Rc;
+
+    use crate::config::Config;
+    use crate::harness::{ctrl, Harness, TempFile};
+
+    #[test]
+    fn unsaved_changes_are_swapped_out_until_theyre_saved() {
+        let mut harness = Harness::with_file("swap", "one");
+        harness.type_text("x").tick(161);
+        let swap = swap_path(harness.path());
+        assert_eq!(fs::read_to_string(&swap).unwrap(), "xone");
+        let leftover = leftovers(&std::env::temp_dir()).unwrap();
+        assert!(leftover.iter().any(|path| path == harness.path()));
+
+        harness.run([ctrl('s')]);
+        assert!(!swap.exists());
+    }
+
+    #[test]
+    fn a_swap_file_left_over_is_mentioned_and_kept_wherever_the_file_is() {
+        let file = TempFile::new("notes", "one");
+        let path = file.path();
+        fs::write(swap_path(path), "lost").unwrap();
+        let mut harness = Harness::with_editor(file.editor("notes", Rc::default()));
+        assert!(harness
+            .message()
+            .unwrap()
+            .starts_with("notes has a swap file left"));
+        harness.type_text("x").tick(161);
+        assert_eq!(fs::read_to_string(swap_path(path)).unwrap(), "lost");
+
+        // With `swap_secs` at 0, there's none to mention or to write.
+        let config = Config {
+            swap_secs: 0,
+            ..Config::default()
+        };
+        let mut harness = Harness::with_editor(file.editor("notes", Rc::new(config)));
+        assert_eq!(harness.message(), None);
+        fs::remove_file(swap_path(path)).unwrap();
+        harness.type_text("x").tick(161);
+        assert!(!swap_path(path).exists());
+    }
+}
@@ -0,0 +1,194 @@
+//! The tab strip along the top of the editor, with a tab for each open
+//! buffer. Ctrl+Tab or Ctrl+PageDown switches to the next one, and
+//! Ctrl+PageUp to the one before, without going back to the REPL. A
+//! buffer with unsaved changes has a `+` after its name.
+//!
+//! The session goes with whichever buffer is showing, and only that one
+//! is drawn, but every buffer is ticked, so the ones behind it still
+//! reload, autosave and so on.
+
+use termgame::{Controller, Game, GameEvent, StyledCharacter};
+
+use std::mem;
+
+use crate::keymap::Action;
+use crate::pane::{Area, Pane};
+use crate::status;
+use crate::BufferEditor;
+
+/// Every open buffer, one of them showing.
+pub struct Tabs {
+    panes: Vec<Pane>,
+    active: usize,
+}
+
+impl Tabs {
+    /// Tabs for `editors`, in order, showing the one at `active`, which
+    /// has the session.
+    pub fn new(editors: Vec<BufferEditor>, active: usize) -> Tabs {
+        Tabs {
+            panes: editors.into_iter().map(Pane::new).collect(),
+            active,
+        }
+    }
+
+    /// Which buffer is showing.
+    pub fn active(&self) -> usize {
+        self.active
+    }
+
+    pub fn into_editors(self) -> Vec<BufferEditor> {
+        self.panes.into_iter().map(Pane::into_editor).collect()
+    }
+
+    /// Shows the buffer at `to`, as it was left.
+    fn switch(&mut self, game: &mut Game, to: usize) {
+        let session = mem::take(&mut self.panes[self.active].editor.session);
+        self.active = to;
+        self.panes[to].editor.session = session;
+        game.set_message(None);
+        self.with_active(game, |editor, game| editor.on_start(game));
+    }
+
+    /// Runs `f` on the buffer that's showing, then draws the tabs over
+    /// what it drew.
+    fn with_active(&mut self, game: &mut Game, f: impl FnOnce(&mut BufferEditor, &mut Game)) {
+        self.panes[self.active].run(game, Area::UnderTabs, f);
+        self.draw_tabs(game);
+    }
+
+    /// Draws a tab for each buffer along the top row, scrolled across
+    /// far enough that the one showing is in view.
+    fn draw_tabs(&self, game: &mut Game) {
+        let columns = status::visible_size(game).0.max(0) as usize;
+        let theme = self.panes[self.active].editor.session.theme;
+        let labels: Vec<String> = self
+            .panes
+            .iter()
+            .map(|pane| match pane.editor.buffer.is_dirty() {
+                true => format!(" {}+ ", pane.editor.name),
+                false => format!(" {} ", pane.editor.name),
+            })
+            .collect();
+        let width = |tabs: &[String]| tabs.iter().map(|tab| tab.chars().count()).sum::<usize>();
+        let mut first = 0;
+        while first < self.active && width(&labels[first..=self.active]) > columns {
+            first += 1;
+        }
+        let cells = labels
+            .iter()
+            .enumerate()
+            .skip(first)
+            .flat_map(|(i, label)| {
+                let paint = match i == self.active {
+                    true => theme.status,
+                    false => theme.gutter,
+                };
+                label.chars().map(move |c| (c, paint))
+            });
+        let mut cells = cells.fuse();
+        for x in 0..columns {
+            let styled = cells
+                .next()
+                .map(|(c, paint)| StyledCharacter::new(c).style(paint.style()));
+            game.set_screen_char(x as i32, 0, styled);
+        }
+    }
+}
+
+impl Controller for Tabs {
+    fn on_start(&mut self, game: &mut Game) {
+        self.with_active(game, |editor, game| editor.on_start(game));
+    }
+
+    fn on_event(&mut self, game: &mut Game, event: GameEvent) {
+        let count = self.panes.len();
+        let action = self.panes[self.active]
+            .editor
+            .keymap
+            .lookup(&event.clone().into());
+        match action {
+            Some(Action::NextBuffer) => self.switch(game, (self.active + 1) % count),
+            Some(Action::PreviousBuffer) => self.switch(game, (self.active + count - 1) % count),
+            _ => self.with_active(game, |editor, game| editor.on_event(game, event)),
+        }
+    }
+
+    fn on_tick(&mut self, game: &mut Game) {
+        let mut session = mem::take(&mut self.panes[self.active].editor.session);
+        for (i, pane) in self.panes.iter_mut().enumerate() {
+            if i != self.active {
+                mem::swap(&mut pane.editor.session, &mut session);
+                pane.run_hidden(game, Area::UnderTabs, BufferEditor::tick_buffer);
+                mem::swap(&mut pane.editor.session, &mut session);
+            }
+        }
+        self.panes[self.active].editor.session = session;
+        self.with_active(game, |editor, game| editor.on_tick(game));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use termgame::{CharChunkMap, KeyCode};
+
+    use std::fs;
+    use std::rc::Rc;
+    use std::time::Duration;
+
+    use crate::clock::{self, Clock};
+    use crate::config::Config;
+    use crate::harness::{ctrl_key, editor, row, typed, TempFile};
+
+    #[test]
+    fn ctrl_page_down_switches_buffers_without_closing() {
+        let editors = vec![editor("a", "one"), editor("b", "two"), editor("c", "three")];
+        let mut tabs = Tabs::new(editors, 1);
+        let mut screen = CharChunkMap::new();
+        let mut game = Game::new(&mut screen);
+        tabs.on_start(&mut game);
+        tabs.on_event(&mut game, ctrl_key(KeyCode::PageDown));
+        for event in typed("x") {
+            tabs.on_event(&mut game, event);
+        }
+        // Past the last tab is back round to the first.
+        tabs.on_event(&mut game, ctrl_key(KeyCode::PageDown));
+        assert!(!game.game_will_end());
+        drop(game);
+        assert_eq!(row(&screen, 0), " a  b  c+");
+        assert_eq!(row(&screen, 1), "one");
+
+        assert_eq!(tabs.active(), 0);
+        let editors = tabs.into_editors();
+        assert_eq!(editors[2].buffer.text(), "xthree");
+        assert_eq!(editors[1].buffer.text(), "two");
+    }
+
+    #[test]
+    fn buffers_which_arent_showing_still_autosave() {
+        let file = TempFile::new("file", "one");
+        let config = Rc::new(Config {
+            autosave_secs: 1,
+            ..Config::default()
+        });
+        let mut tabs = Tabs::new(vec![file.editor("file", config), editor("other", "two")], 0);
+        tabs.panes[0].editor.session.clock = Clock::fixed(Duration::ZERO, clock::TICK);
+        let mut screen = CharChunkMap::new();
+        let mut game = Game::new(&mut screen);
+        tabs.on_start(&mut game);
+        for event in typed("x") {
+            tabs.on_event(&mut game, event);
+        }
+        tabs.on_event(&mut game, ctrl_key(KeyCode::PageDown));
+        // A tick is 25ms, so a second is 40 of them.
+        for _ in 0..41 {
+            tabs.on_tick(&mut game);
+        }
+        drop(game);
+        assert_eq!(fs::read_to_string(file.path()).unwrap(), "xone");
+        assert_eq!(row(&screen, 0), " file  other");
+        assert_eq!(row(&screen, 1), "two");
+    }
+}
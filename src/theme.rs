@@ -0,0 +1,274 @@
+//! The colours the editor is drawn in, which `theme` (or `set theme`)
+//! switches between while BuffeRS is running, and which `theme` in the
+//! config starts on.
+//!
+//! The default leaves most colours to the terminal. `dark` and `light`
+//! pick them all, for dark and light terminal backgrounds.
+//!
+//! Besides the default, there are palettes for the two commonest kinds
+//! of colour blindness, `deuteranopia` and `protanopia`, which keep
+//! added and removed lines and conflict markers apart with blues,
+//! oranges and yellows instead of red and green.
+
+use termgame::{Font, GameColor, GameStyle};
+
+/// How to draw something: its colours and font, any of which can be
+/// left as the terminal's own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Paint {
+    pub fg: Option<GameColor>,
+    pub bg: Option<GameColor>,
+    pub font: Option<Font>,
+}
+
+impl Paint {
+    const fn fg(fg: GameColor) -> Paint {
+        Paint {
+            fg: Some(fg),
+            bg: None,
+            font: None,
+        }
+    }
+
+    const fn font(font: Font) -> Paint {
+        Paint {
+            fg: None,
+            bg: None,
+            font: Some(font),
+        }
+    }
+
+    pub fn style(self) -> GameStyle {
+        GameStyle::new()
+            .color(self.fg)
+            .background_color(self.bg)
+            .font(self.font)
+    }
+}
+
+/// Everything there's a colour for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    pub name: &'static str,
+    pub cursor: Paint,
+    pub status: Paint,
+    /// Lines added and removed, in buffers which show changes, like the
+    /// preview `replace-all` makes.
+    pub added: Paint,
+    pub removed: Paint,
+    /// The `<<<<<<<`, `=======` and `>>>>>>>` lines around a conflict
+    /// left by `merge`.
+    pub conflict: Paint,
+    /// The line numbers `set number` shows.
+    pub gutter: Paint,
+    /// Matches of what Ctrl+F found.
+    pub found: Paint,
+    /// What's selected with Shift and the arrow keys.
+    pub selection: Paint,
+    /// The bracket which pairs with the one under the cursor.
+    pub bracket: Paint,
+    /// The cursor, when it's on a bracket with nothing to pair with.
+    pub unmatched: Paint,
+}
+
+/// The colours from Okabe and Ito's palette, which are told apart with
+/// any kind of colour vision.
+const BLUE: GameColor = GameColor::Rgb(0, 114, 178);
+const SKY_BLUE: GameColor = GameColor::Rgb(86, 180, 233);
+const ORANGE: GameColor = GameColor::Rgb(230, 159, 0);
+const YELLOW: GameColor = GameColor::Rgb(240, 228, 66);
+const PURPLE: GameColor = GameColor::Rgb(204, 121, 167);
+
+pub const THEMES: &[Theme] = &[
+    Theme {
+        name: "default",
+        cursor: Paint::font(Font::REVERSED),
+        status: Paint::font(Font::REVERSED),
+        added: Paint::fg(GameColor::Green),
+        removed: Paint::fg(GameColor::Red),
+        conflict: Paint::fg(GameColor::Yellow),
+        gutter: Paint::fg(GameColor::DarkGray),
+        found: Paint {
+            fg: Some(GameColor::Black),
+            bg: Some(GameColor::Yellow),
+            font: None,
+        },
+        selection: Paint::font(Font::REVERSED),
+        bracket: Paint::font(Font::BOLD.union(Font::UNDERLINED)),
+        unmatched: Paint {
+            fg: None,
+            bg: Some(GameColor::Red),
+            font: None,
+        },
+    },
+    Theme {
+        name: "dark",
+        cursor: Paint::font(Font::REVERSED),
+        status: Paint {
+            fg: Some(GameColor::White),
+            bg: Some(GameColor::DarkGray),
+            font: Some(Font::BOLD),
+        },
+        added: Paint::fg(GameColor::LightGreen),
+        removed: Paint::fg(GameColor::LightRed),
+        conflict: Paint::fg(GameColor::LightYellow),
+        gutter: Paint::fg(GameColor::DarkGray),
+        found: Paint {
+            fg: Some(GameColor::Black),
+            bg: Some(GameColor::Yellow),
+            font: None,
+        },
+        selection: Paint {
+            fg: Some(GameColor::White),
+            bg: Some(GameColor::Blue),
+            font: None,
+        },
+        bracket: Paint {
+            fg: Some(GameColor::Black),
+            bg: Some(GameColor::Cyan),
+            font: None,
+        },
+        unmatched: Paint {
+            fg: None,
+            bg: Some(GameColor::Red),
+            font: None,
+        },
+    },
+    Theme {
+        name: "light",
+        cursor: Paint::font(Font::REVERSED),
+        status: Paint {
+            fg: Some(GameColor::Black),
+            bg: Some(GameColor::Gray),
+            font: Some(Font::BOLD),
+        },
+        added: Paint::fg(GameColor::Rgb(0, 128, 0)),
+        removed: Paint::fg(GameColor::Rgb(176, 0, 0)),
+        conflict: Paint::fg(GameColor::Rgb(160, 96, 0)),
+        gutter: Paint::fg(GameColor::Gray),
+        found: Paint {
+            fg: Some(GameColor::Black),
+            bg: Some(GameColor::LightYellow),
+            font: None,
+        },
+        selection: Paint {
+            fg: Some(GameColor::Black),
+            bg: Some(GameColor::LightBlue),
+            font: None,
+        },
+        bracket: Paint {
+            fg: Some(GameColor::Black),
+            bg: Some(GameColor::LightCyan),
+            font: None,
+        },
+        unmatched: Paint {
+            fg: None,
+            bg: Some(GameColor::Red),
+            font: None,
+        },
+    },
+    Theme {
+        name: "deuteranopia",
+        cursor: Paint::font(Font::REVERSED),
+        status: Paint::font(Font::REVERSED),
+        added: Paint::fg(BLUE),
+        removed: Paint::fg(ORANGE),
+        conflict: Paint {
+            fg: Some(PURPLE),
+            bg: None,
+            font: Some(Font::BOLD),
+        },
+        gutter: Paint::fg(GameColor::DarkGray),
+        found: Paint {
+            fg: Some(GameColor::Black),
+            bg: Some(YELLOW),
+            font: None,
+        },
+        selection: Paint::font(Font::REVERSED),
+        bracket: Paint {
+            fg: Some(GameColor::Black),
+            bg: Some(SKY_BLUE),
+            font: None,
+        },
+        unmatched: Paint {
+            fg: None,
+            bg: Some(ORANGE),
+            font: None,
+        },
+    },
+    Theme {
+        name: "protanopia",
+        cursor: Paint::font(Font::REVERSED),
+        status: Paint::font(Font::REVERSED),
+        added: Paint::fg(SKY_BLUE),
+        removed: Paint::fg(YELLOW),
+        conflict: Paint {
+            fg: None,
+            bg: None,
+            font: Some(Font::BOLD.union(Font::UNDERLINED)),
+        },
+        gutter: Paint::fg(GameColor::DarkGray),
+        found: Paint {
+            fg: Some(GameColor::Black),
+            bg: Some(YELLOW),
+            font: None,
+        },
+        selection: Paint::font(Font::REVERSED),
+        bracket: Paint {
+            fg: Some(GameColor::Black),
+            bg: Some(SKY_BLUE),
+            font: None,
+        },
+        unmatched: Paint {
+            fg: None,
+            bg: Some(ORANGE),
+            font: None,
+        },
+    },
+];
+
+impl Default for Theme {
+    fn default() -> Theme {
+        THEMES[0]
+    }
+}
+
+impl Theme {
+    pub fn by_name(name: &str) -> Option<Theme> {
+        THEMES.iter().find(|theme| theme.name == name).copied()
+    }
+
+    /// How to draw `line`. Lines are only painted as added or removed
+    /// when `shows_changes`, since plenty of text starts with a `-`.
+    pub fn line(&self, line: &str, shows_changes: bool) -> Option<Paint> {
+        const MARKERS: [&str; 4] = ["<<<<<<<", "|||||||", "=======", ">>>>>>>"];
+        if MARKERS.iter().any(|marker| line.starts_with(marker)) {
+            return Some(self.conflict);
+        }
+        match line.chars().next() {
+            Some('+') if shows_changes => Some(self.added),
+            Some('-') if shows_changes => Some(self.removed),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_theme_can_tell_changes_apart() {
+        for theme in THEMES {
+            let paints = [theme.added, theme.removed, theme.conflict];
+            for (i, a) in paints.iter().enumerate() {
+                assert!(!paints[i + 1..].contains(a), "{}", theme.name);
+            }
+        }
+        let theme = Theme::default();
+        assert_eq!(theme.line("- old", true), Some(theme.removed));
+        assert_eq!(theme.line("- a list", false), None);
+        assert_eq!(theme.line("=======", false), Some(theme.conflict));
+        assert_eq!(theme.line("||||||| base", false), Some(theme.conflict));
+    }
+}
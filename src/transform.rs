@@ -0,0 +1,287 @@
+//! Text transforms, like encoding a buffer as base64.
+//!
+//! A [`Transform`] takes some text and gives back the transformed text,
+//! or a description of why that text can't be transformed. They're
+//! registered as REPL commands through [`crate::commands::Registry::register_transform`],
+//! which transform the buffer's selection, or all of it if nothing's
+//! selected.
+
+use crate::help::Help;
+
+/// A function that rewrites some text, or explains why it couldn't.
+pub type Transform = fn(&str) -> Result<String, String>;
+
+/// Every transform that ships with BuffeRS, with what `help` says about
+/// its command.
+pub const BUILTIN: &[(Help, Transform)] = &[
+    (
+        Help {
+            name: "base64-encode",
+            usage: &["base64-encode <buffer>"],
+            about: "Encodes the text of `buffer` (only what's selected, if anything is) as base64.",
+            examples: &[],
+        },
+        base64_encode,
+    ),
+    (
+        Help {
+            name: "base64-decode",
+            usage: &["base64-decode <buffer>"],
+            about: "Decodes the text of `buffer` (only what's selected, if anything is) from base64.",
+            examples: &[],
+        },
+        base64_decode,
+    ),
+    (
+        Help {
+            name: "url-encode",
+            usage: &["url-encode <buffer>"],
+            about: "Percent-encodes the text of `buffer` (only what's selected, if anything is), as it would be written in a URL.",
+            examples: &[],
+        },
+        url_encode,
+    ),
+    (
+        Help {
+            name: "url-decode",
+            usage: &["url-decode <buffer>"],
+            about: "Decodes the percent-encoding in the text of `buffer` (only what's selected, if anything is).",
+            examples: &[],
+        },
+        url_decode,
+    ),
+    (
+        Help {
+            name: "json-escape",
+            usage: &["json-escape <buffer>"],
+            about: "Escapes the text of `buffer` (only what's selected, if anything is) to be written inside a JSON string.",
+            examples: &[],
+        },
+        json_escape,
+    ),
+    (
+        Help {
+            name: "json-unescape",
+            usage: &["json-unescape <buffer>"],
+            about: "Turns the escapes in the text of `buffer` (only what's selected, if anything is), written inside a JSON string, back into what they stand for.",
+            examples: &[],
+        },
+        json_unescape,
+    ),
+];
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Encodes text as (padded, standard alphabet) base64.
+pub fn base64_encode(text: &str) -> Result<String, String> {
+    let mut out = String::new();
+    for chunk in text.as_bytes().chunks(3) {
+        let bytes = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let group = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                let index = (group >> (18 - 6 * i)) & 0b11_1111;
+                out.push(BASE64_ALPHABET[index as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Decodes base64 back into text. Whitespace is ignored, so wrapped
+/// base64 decodes fine.
+pub fn base64_decode(text: &str) -> Result<String, String> {
+    let mut bytes = Vec::new();
+    let mut group: u32 = 0;
+    let mut bits = 0;
+    let mut padding = 0;
+
+    for c in text.chars().filter(|c| !c.is_whitespace()) {
+        if c == '=' {
+            padding += 1;
+            continue;
+        }
+        if padding > 0 {
+            return Err(String::from("base64 padding in the middle of the text"));
+        }
+        let value = BASE64_ALPHABET
+            .iter()
+            .position(|&b| b as char == c)
+            .ok_or_else(|| format!("'{}' is not a base64 character", c))?;
+        group = (group << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((group >> bits) as u8);
+            group &= (1 << bits) - 1;
+        }
+    }
+    // What's left over is the padding out to a whole character, which
+    // is never a character on its own and always zero.
+    if bits >= 6 || group != 0 {
+        return Err(String::from("base64 text ends partway through a byte"));
+    }
+
+    String::from_utf8(bytes).map_err(|_| String::from("decoded base64 is not valid UTF-8"))
+}
+
+/// Percent-encodes everything except the characters RFC 3986 calls "unreserved".
+pub fn url_encode(text: &str) -> Result<String, String> {
+    let mut out = String::new();
+    for &b in text.as_bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                out.push(b as char)
+            }
+            _ => out.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    Ok(out)
+}
+
+/// Decodes `%XX` escapes. Anything else is left as it was.
+pub fn url_decode(text: &str) -> Result<String, String> {
+    let mut bytes = Vec::new();
+    let mut input = text.bytes();
+    while let Some(b) = input.next() {
+        if b != b'%' {
+            bytes.push(b);
+            continue;
+        }
+        // `from_str_radix` takes a sign too, so the digits are checked
+        // first.
+        let hex: Vec<u8> = input.by_ref().take(2).collect();
+        let escape = std::str::from_utf8(&hex)
+            .ok()
+            .filter(|h| h.len() == 2 && h.bytes().all(|b| b.is_ascii_hexdigit()))
+            .and_then(|h| u8::from_str_radix(h, 16).ok())
+            .ok_or_else(|| String::from("'%' must be followed by two hex digits"))?;
+        bytes.push(escape);
+    }
+
+    String::from_utf8(bytes).map_err(|_| String::from("decoded URL is not valid UTF-8"))
+}
+
+/// Escapes text so it can be placed between the quotes of a JSON string.
+pub fn json_escape(text: &str) -> Result<String, String> {
+    let mut out = String::new();
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\u{8}' => out.push_str("\\b"),
+            '\u{c}' => out.push_str("\\f"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    Ok(out)
+}
+
+/// The reverse of [`json_escape`]. Understands every escape JSON does,
+/// including `\uXXXX` surrogate pairs.
+pub fn json_unescape(text: &str) -> Result<String, String> {
+    let mut out = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('"') => out.push('"'),
+            Some('\\') => out.push('\\'),
+            Some('/') => out.push('/'),
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some('t') => out.push('\t'),
+            Some('b') => out.push('\u{8}'),
+            Some('f') => out.push('\u{c}'),
+            Some('u') => {
+                let high = read_hex4(&mut chars)?;
+                let code = if (0xD800..0xDC00).contains(&high) {
+                    if chars.next() != Some('\\') || chars.next() != Some('u') {
+                        return Err(String::from("unpaired surrogate in \\u escape"));
+                    }
+                    let low = read_hex4(&mut chars)?;
+                    if !(0xDC00..0xE000).contains(&low) {
+                        return Err(String::from("unpaired surrogate in \\u escape"));
+                    }
+                    0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+                } else {
+                    high
+                };
+                out.push(
+                    char::from_u32(code)
+                        .ok_or_else(|| format!("\\u{:04x} is not a valid character", code))?,
+                );
+            }
+            Some(other) => return Err(format!("unknown escape '\\{}'", other)),
+            None => return Err(String::from("text ends in the middle of an escape")),
+        }
+    }
+    Ok(out)
+}
+
+/// Reads the four hex digits following a `\u`.
+fn read_hex4(chars: &mut std::str::Chars) -> Result<u32, String> {
+    let hex: String = chars.take(4).collect();
+    if hex.chars().count() != 4 {
+        return Err(String::from("\\u must be followed by four hex digits"));
+    }
+    match hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        true => u32::from_str_radix(&hex, 16).map_err(|err| err.to_string()),
+        false => Err(format!("'{}' is not four hex digits", hex)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64_round_trips_and_rejects_what_isnt() {
+        for (text, encoded) in [("", ""), ("f", "Zg=="), ("fo", "Zm8="), ("foo", "Zm9v")] {
+            assert_eq!(base64_encode(text).unwrap(), encoded);
+            assert_eq!(base64_decode(encoded).unwrap(), text);
+        }
+        assert_eq!(base64_decode("Zm9v\nYmFy").unwrap(), "foobar");
+        assert!(base64_decode("A").is_err());
+        assert!(base64_decode("Zh==").is_err());
+        assert!(base64_decode("Zg=v").is_err());
+        assert!(base64_decode("Z!").is_err());
+    }
+
+    #[test]
+    fn url_escapes_take_exactly_two_hex_digits() {
+        assert_eq!(url_encode("a b/ü~").unwrap(), "a%20b%2F%C3%BC~");
+        assert_eq!(url_decode("a%20b%2F%C3%BC~").unwrap(), "a b/ü~");
+        assert!(url_decode("%+1").is_err());
+        assert!(url_decode("%4").is_err());
+        assert!(url_decode("%zz").is_err());
+        assert!(url_decode("%FF").is_err());
+    }
+
+    #[test]
+    fn json_escapes_round_trip() {
+        let text = "say \"hi\"\n\tback\\slash \u{1}";
+        let escaped = json_escape(text).unwrap();
+        assert_eq!(escaped, "say \\\"hi\\\"\\n\\tback\\\\slash \\u0001");
+        assert_eq!(json_unescape(&escaped).unwrap(), text);
+        assert_eq!(json_unescape("\\ud83d\\ude00").unwrap(), "😀");
+        assert!(json_unescape("\\ud83d").is_err());
+        assert!(json_unescape("\\u+123").is_err());
+        assert!(json_unescape("\\q").is_err());
+        assert!(json_unescape("\\").is_err());
+    }
+}
@@ -0,0 +1,115 @@
+//! Moving files to the trash, following the freedesktop.org trash spec,
+//! so they show up (and can be restored) in the desktop's trash can.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::config::data_home;
+use crate::dates::DateTime;
+use crate::paths;
+use crate::transform;
+
+/// Moves the file at `path` into the trash, and returns where it went.
+pub fn move_to_trash(path: &Path) -> io::Result<PathBuf> {
+    let trash = data_home()
+        .ok_or_else(|| io::Error::other("no home directory"))?
+        .join("Trash");
+    move_into(path, &trash, SystemTime::now())
+}
+
+/// Moves the file at `path` into the trash directory `trash`, saying it
+/// was deleted at `when`.
+fn move_into(path: &Path, trash: &Path, when: SystemTime) -> io::Result<PathBuf> {
+    let path = paths::canonical(path)?;
+    let (files, info) = (trash.join("files"), trash.join("info"));
+    fs::create_dir_all(&files)?;
+    fs::create_dir_all(&info)?;
+
+    // Files with the same name are all kept, as `name`, `name.2`, ...
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let trash_name = (1..)
+        .map(|n| match n {
+            1 => name.to_string(),
+            n => format!("{}.{}", name, n),
+        })
+        .find(|candidate| {
+            !files.join(candidate).exists()
+                && !info.join(format!("{}.trashinfo", candidate)).exists()
+        })
+        .unwrap();
+
+    let now = DateTime::utc(when);
+    let encoded: Vec<String> = path
+        .to_string_lossy()
+        .split('/')
+        .map(|part| transform::url_encode(part).unwrap_or_default())
+        .collect();
+    fs::write(
+        info.join(format!("{}.trashinfo", trash_name)),
+        format!(
+            "[Trash Info]\nPath={}\nDeletionDate={:04}-{:02}-{:02}T{:02}:{:02}:{:02}\n",
+            encoded.join("/"),
+            now.year,
+            now.month,
+            now.day,
+            now.hour,
+            now.minute,
+            now.second
+        ),
+    )?;
+
+    let destination = files.join(&trash_name);
+    // The trash may be on another filesystem.
+    paths::move_file(&path, &destination)?;
+    Ok(destination)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use crate::harness::TempFile;
+
+    #[test]
+    fn trashed_files_are_numbered_apart_and_say_where_they_were() {
+        let trash = std::env::temp_dir().join(format!("buffers-trash-{}", std::process::id()));
+        let when = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let first = TempFile::new("my notes", "one");
+        let name = first
+            .path()
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .into_owned();
+        let trashed = move_into(first.path(), &trash, when).unwrap();
+        assert_eq!(trashed, trash.join("files").join(&name));
+        assert_eq!(fs::read_to_string(&trashed).unwrap(), "one");
+        assert!(!first.path().exists());
+
+        let info = fs::read_to_string(trash.join("info").join(format!("{}.trashinfo", name)));
+        let dir = paths::canonical(&std::env::temp_dir()).unwrap();
+        let path = format!("{}/{}", dir.display(), name.replace(' ', "%20"));
+        assert_eq!(
+            info.unwrap(),
+            format!(
+                "[Trash Info]\nPath={}\nDeletionDate=2023-11-14T22:13:20\n",
+                path
+            )
+        );
+
+        // Another file of the same name doesn't overwrite the first.
+        fs::write(first.path(), "two").unwrap();
+        let again = move_into(first.path(), &trash, when).unwrap();
+        assert_eq!(again, trash.join("files").join(format!("{}.2", name)));
+        assert_eq!(fs::read_to_string(&trashed).unwrap(), "one");
+        assert!(trash
+            .join("info")
+            .join(format!("{}.2.trashinfo", name))
+            .exists());
+        fs::remove_dir_all(&trash).unwrap();
+    }
+}
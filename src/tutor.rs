@@ -0,0 +1,182 @@
+//! `tutor`, a lesson buffer which teaches BuffeRS by having it used.
+//!
+//! Each lesson ends in an exercise, on the lines marked with a `>`.
+//! Once the buffer shows the exercise has been done, the next lesson is
+//! added on the end. Keys are named as they are in the keymap, so the
+//! lessons are right on any keyboard layout.
+
+use termgame::{Game, Message};
+
+use std::rc::Rc;
+
+use crate::buffer::Buffer;
+use crate::config::Config;
+use crate::i18n::tr;
+use crate::keymap::Keymap;
+use crate::BufferEditor;
+
+/// One lesson, and how to tell it's been done.
+struct Lesson {
+    title: &'static str,
+    /// What the lesson says. `{action}` is replaced by the key bound to
+    /// that action.
+    text: &'static str,
+    /// Where in the text the cursor starts: just after this.
+    cursor_after: &'static str,
+    /// Whether the lesson's been done, given the text from its title on.
+    /// The last lesson has nothing to do.
+    done: Option<fn(&str) -> bool>,
+}
+
+const LESSONS: &[Lesson] = &[
+    Lesson {
+        title: "Lesson 1: moving and deleting",
+        text: "\
+The cursor is at the start of the line marked with a >. {left} and
+{right} move it along a character at a time, and {backspace} deletes the
+character before it.
+
+Move along to just after the stray X, and delete it:
+
+> The cXat sat on the mat.
+",
+        cursor_after: "> ",
+        done: Some(|lesson| lesson.contains("> The cat sat on the mat.\n")),
+    },
+    Lesson {
+        title: "Lesson 2: typing",
+        text: "\
+Typing puts characters in at the cursor. Move to just before \"fox\",
+and type the missing word, then a space:
+
+> The quick fox jumps over the lazy dog.
+",
+        cursor_after: "> ",
+        done: Some(|lesson| lesson.contains("> The quick brown fox jumps over the lazy dog.\n")),
+    },
+    Lesson {
+        title: "Lesson 3: clearing a line",
+        text: "\
+{clear-line} deletes everything from the cursor back to the start of the
+line, and joins what's left onto the line above. The cursor is at the
+end of the line below. Clear it:
+
+> delete me please",
+        cursor_after: "> delete me please",
+        done: Some(|lesson| !lesson.contains("delete me please")),
+    },
+    Lesson {
+        title: "Lesson 4: pasting",
+        text: "\
+What's cleared isn't gone. {paste} pastes it back, and `clips`, at the >>
+prompt, lists everything that's been cleared or copied. Paste what you
+just cleared onto the end of this line:
+
+> paste here: ",
+        cursor_after: "> paste here: ",
+        done: Some(|lesson| lesson.contains("delete me please")),
+    },
+    Lesson {
+        title: "Lesson 5: commands",
+        text: "\
+That's all there is to the editor, for now. {close} goes back to the >>
+prompt, where commands work on buffers: `open <file>` opens one,
+`write <buffer> <path>` saves one, and `keys` lists every key there is.
+Run `tutor` again to come back here, or `tutor --restart` to start over.
+",
+        cursor_after: "",
+        done: None,
+    },
+];
+
+/// How far through the lessons the tutor buffer is.
+#[derive(Debug, Clone, Default)]
+pub struct Tutor {
+    lesson: usize,
+}
+
+/// `lesson`, with its keys named as they're bound in `keymap`, and
+/// where in that the cursor goes.
+fn lesson_text(lesson: &Lesson, keymap: &Keymap) -> (String, usize) {
+    let mut text = format!("{}\n\n{}", lesson.title, lesson.text);
+    for (key, action) in keymap.bindings() {
+        text = text.replace(&format!("{{{}}}", action.name()), &key.to_string());
+    }
+    let cursor = text
+        .find(lesson.cursor_after)
+        .map_or(text.len(), |i| i + lesson.cursor_after.len());
+    (text, cursor)
+}
+
+impl BufferEditor {
+    /// A new tutor buffer, at the first lesson.
+    pub fn tutor(config: Rc<Config>) -> BufferEditor {
+        let keymap = Keymap::for_config(&config);
+        let (lesson, cursor) = lesson_text(&LESSONS[0], &keymap);
+        let intro = tr("tutor.intro", &[]);
+        let mut buffer = Buffer::from_text(None, format!("{}\n\n{}", intro, lesson));
+        buffer.set_cursor(intro.len() + 2 + cursor);
+        let mut editor = BufferEditor::new(String::from("tutor"), buffer, config);
+        editor.tutor = Some(Tutor::default());
+        editor
+    }
+
+    /// Moves on to the next lesson, if the one in progress has been done.
+    pub fn tutor_check(&mut self, game: &mut Game) {
+        let Some(tutor) = &mut self.tutor else {
+            return;
+        };
+        let lesson = &LESSONS[tutor.lesson];
+        let (Some(done), Some(start)) = (lesson.done, self.buffer.text().rfind(lesson.title))
+        else {
+            return;
+        };
+        if !done(&self.buffer.text()[start..]) {
+            return;
+        }
+
+        tutor.lesson += 1;
+        let passed = tr("tutor.passed", &[("lesson", &tutor.lesson)]);
+        let (next, cursor) = lesson_text(&LESSONS[tutor.lesson], &self.keymap);
+        let end = self.buffer.text().len();
+        let added = format!("\n\n{}\n\n", passed);
+        self.buffer
+            .replace_range(end..end, &format!("{}{}", added, next));
+        self.buffer.set_cursor(end + added.len() + cursor);
+        self.viewport.y = self.buffer.position_of(end + added.len()).line as i32;
+        game.set_viewport(self.viewport);
+        game.set_message(Some(Message::new(passed)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use termgame::KeyCode;
+
+    use crate::harness::{ctrl, Harness};
+
+    #[test]
+    fn doing_a_lesson_moves_on_to_the_next() {
+        let mut harness = Harness::with_editor(BufferEditor::tutor(Rc::new(Config::default())));
+        for _ in 0.."The cX".len() {
+            harness.press(KeyCode::Right);
+        }
+        harness.press(KeyCode::Backspace);
+        assert!(harness.text().contains("> The cat sat on the mat.\n"));
+        assert!(harness.text().contains("Lesson 2: typing"));
+        assert_eq!(harness.message(), Some("Lesson 1 done!"));
+
+        // The cursor starts each lesson where its exercise is.
+        for _ in 0.."The quick ".len() {
+            harness.press(KeyCode::Right);
+        }
+        harness.type_text("brown ");
+        assert!(harness.text().contains("Lesson 3"));
+        harness.run([ctrl('u')]);
+        assert!(harness.text().contains("Lesson 4"));
+        harness.run([ctrl('v')]);
+        assert!(harness.text().ends_with("Lesson 5: commands\n\nThat's all there is to the editor, for now. Esc goes back to the >>\nprompt, where commands work on buffers: `open <file>` opens one,\n`write <buffer> <path>` saves one, and `keys` lists every key there is.\nRun `tutor` again to come back here, or `tutor --restart` to start over.\n"));
+    }
+}
@@ -0,0 +1,31 @@
+//! Amounts written out for people to read, the same way wherever
+//! they're shown.
+
+/// `bytes` as a size people can read, like `1.5 MiB`.
+pub fn size(bytes: usize) -> String {
+    let mut size = bytes as f64;
+    for unit in ["B", "KiB", "MiB"] {
+        if size < 1024.0 {
+            return match unit {
+                "B" => format!("{} B", bytes),
+                unit => format!("{:.1} {}", size, unit),
+            };
+        }
+        size /= 1024.0;
+    }
+    format!("{:.1} GiB", size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sizes_are_written_in_the_largest_unit_under_1024() {
+        assert_eq!(size(0), "0 B");
+        assert_eq!(size(1023), "1023 B");
+        assert_eq!(size(1536), "1.5 KiB");
+        assert_eq!(size(5 << 20), "5.0 MiB");
+        assert_eq!(size(3 << 30), "3.0 GiB");
+    }
+}
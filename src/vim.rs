@@ -0,0 +1,611 @@
+//! Vim-style modal editing, for whoever has `editing_mode = "vim"` in
+//! the config, or runs `set mode vim`.
+//!
+//! The editor opens in normal mode, where letters move the cursor and
+//! change the text rather than being typed:
+//!
+//! - `h` `j` `k` `l` move a character or a line, `w` and `b` a word,
+//!   `0` and `$` to the start and end of the line, `gg` to the first
+//!   line and `G` to the last.
+//! - `d`, `y` and `c` delete, copy (yank) and change what the motion
+//!   after them moves over, like `dw`, or whole lines when they're
+//!   doubled, like `dd`. `!` pipes the lines the motion moves over
+//!   through a shell command it asks for, so `!!` pipes the cursor's.
+//! - A count before a motion or an operator repeats it, so `3j` moves
+//!   down three lines and `2dd` deletes two. Before `G` or `gg`, it's the
+//!   line to go to.
+//! - `i` and `a` type before and after the cursor, in insert mode, until
+//!   Esc. `v` selects as the cursor moves, in visual mode, where `d`,
+//!   `y`, `c` and `!` act on the selection.
+//! - `x` deletes the character under the cursor, `p` pastes after it,
+//!   `u` undoes, and `"` and a letter picks the register the next
+//!   delete, yank or paste uses.
+//! - `.` makes the last change again, where the cursor is now.
+//!
+//! Everything else, like Ctrl+S and the arrow keys, does what it always
+//! does. Esc closes the editor from normal mode.
+
+use serde::Deserialize;
+use termgame::{Game, KeyCode, Message, SimpleEvent};
+
+use std::ops::Range;
+
+use crate::buffer::{Buffer, Position};
+use crate::i18n::tr;
+use crate::registers::Registers;
+use crate::BufferEditor;
+
+/// How keys edit the text.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EditingMode {
+    /// Keys type, and Ctrl does everything else.
+    #[default]
+    Default,
+    Vim,
+}
+
+impl EditingMode {
+    pub fn name(self) -> &'static str {
+        match self {
+            EditingMode::Default => "default",
+            EditingMode::Vim => "vim",
+        }
+    }
+
+    pub fn by_name(name: &str) -> Option<EditingMode> {
+        [EditingMode::Default, EditingMode::Vim]
+            .into_iter()
+            .find(|mode| mode.name() == name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Mode {
+    #[default]
+    Normal,
+    Insert,
+    Visual,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Motion {
+    Left,
+    Right,
+    Up,
+    Down,
+    WordForward,
+    WordBack,
+    LineStart,
+    LineEnd,
+    /// `gg`, to the first line, or the line the count says.
+    FirstLine,
+    /// `G`, to the last line, or the line the count says.
+    LastLine,
+}
+
+impl Motion {
+    /// Whether an operator with this motion acts on whole lines.
+    fn is_linewise(self) -> bool {
+        matches!(
+            self,
+            Motion::Up | Motion::Down | Motion::FirstLine | Motion::LastLine
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Delete,
+    Yank,
+    Change,
+    /// `!`, which pipes whole lines through a shell command.
+    Filter,
+}
+
+/// What an operator acts on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    Motion(Motion),
+    /// The cursor's line and the ones under it, when the operator's
+    /// doubled.
+    Lines,
+    /// The selection, in visual mode.
+    Selection,
+}
+
+/// What the keys typed in normal or visual mode add up to. Counts are
+/// `None` when none was typed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    Move(Motion, Option<usize>),
+    Operate(Operator, Target, Option<usize>),
+    Insert,
+    Append,
+    Visual,
+    DeleteChar(Option<usize>),
+    Paste(Option<usize>),
+    Undo,
+    /// `.`, which makes the last change again.
+    Repeat(Option<usize>),
+    Register(char),
+    /// Esc, or a key which doesn't mean anything, which drops whatever
+    /// had been typed so far.
+    Cancel,
+}
+
+/// Where the keys typed so far have got to.
+#[derive(Debug, Clone, Default)]
+pub struct Vim {
+    pub mode: Mode,
+    count: Option<usize>,
+    /// An operator waiting for its motion, with the count typed before it.
+    operator: Option<(Operator, Option<usize>)>,
+    /// Set after `g`, which only means something before another `g`.
+    g: bool,
+    /// Set after `"`, so the next key names a register.
+    quote: bool,
+}
+
+impl Vim {
+    /// Adds `c` to the keys typed in normal or visual mode, and returns
+    /// what they mean once they mean something.
+    pub fn key(&mut self, c: char) -> Option<Command> {
+        if std::mem::take(&mut self.quote) {
+            return Some(self.finish(match Registers::is_name(c) {
+                true => Command::Register(c),
+                false => Command::Cancel,
+            }));
+        }
+        if std::mem::take(&mut self.g) {
+            let command = match c {
+                'g' => self.motion(Motion::FirstLine),
+                _ => Command::Cancel,
+            };
+            return Some(self.finish(command));
+        }
+        if let Some(digit) = c.to_digit(10).filter(|&d| d > 0 || self.count.is_some()) {
+            let count = self.count.unwrap_or(0).saturating_mul(10);
+            self.count = Some(count.saturating_add(digit as usize));
+            return None;
+        }
+        let operator = match c {
+            'd' => Some(Operator::Delete),
+            'y' => Some(Operator::Yank),
+            'c' => Some(Operator::Change),
+            '!' => Some(Operator::Filter),
+            _ => None,
+        };
+        let command = match (c, operator) {
+            (_, Some(operator)) if self.mode == Mode::Visual => {
+                Command::Operate(operator, Target::Selection, None)
+            }
+            (_, Some(operator)) => match self.operator {
+                Some((pending, _)) if pending == operator => {
+                    Command::Operate(operator, Target::Lines, self.take_count())
+                }
+                Some(_) => Command::Cancel,
+                None => {
+                    self.operator = Some((operator, self.count.take()));
+                    return None;
+                }
+            },
+            ('g', _) => {
+                self.g = true;
+                return None;
+            }
+            ('"', _) if self.operator.is_none() => {
+                self.quote = true;
+                return None;
+            }
+            ('h', _) => self.motion(Motion::Left),
+            ('l' | ' ', _) => self.motion(Motion::Right),
+            ('k', _) => self.motion(Motion::Up),
+            ('j', _) => self.motion(Motion::Down),
+            ('w', _) => self.motion(Motion::WordForward),
+            ('b', _) => self.motion(Motion::WordBack),
+            ('0', _) => self.motion(Motion::LineStart),
+            ('$', _) => self.motion(Motion::LineEnd),
+            ('G', _) => self.motion(Motion::LastLine),
+            _ if self.operator.is_some() => Command::Cancel,
+            ('i', _) => Command::Insert,
+            ('a', _) => Command::Append,
+            ('v', _) => Command::Visual,
+            ('x', _) if self.mode == Mode::Visual => {
+                Command::Operate(Operator::Delete, Target::Selection, None)
+            }
+            ('x', _) => Command::DeleteChar(self.count),
+            ('p', _) => Command::Paste(self.count),
+            ('u', _) => Command::Undo,
+            ('.', _) => Command::Repeat(self.count),
+            _ => Command::Cancel,
+        };
+        Some(self.finish(command))
+    }
+
+    /// Drops whatever's been typed towards a command.
+    pub fn cancel(&mut self) {
+        self.finish(Command::Cancel);
+    }
+
+    /// Whether some of a command has been typed.
+    pub fn is_pending(&self) -> bool {
+        self.count.is_some() || self.operator.is_some() || self.g || self.quote
+    }
+
+    /// `motion`, on its own or after the operator waiting for it.
+    fn motion(&mut self, motion: Motion) -> Command {
+        let count = self.take_count();
+        match self.operator {
+            Some((operator, _)) => Command::Operate(operator, Target::Motion(motion), count),
+            None => Command::Move(motion, count),
+        }
+    }
+
+    /// The count typed before the operator times the one after it, like
+    /// the six lines `2d3j` deletes.
+    fn take_count(&mut self) -> Option<usize> {
+        let before = self.operator.and_then(|(_, count)| count);
+        match (before, self.count.take()) {
+            (Some(before), Some(after)) => Some(before.saturating_mul(after)),
+            (before, after) => before.or(after),
+        }
+    }
+
+    fn finish(&mut self, command: Command) -> Command {
+        self.count = None;
+        self.operator = None;
+        self.g = false;
+        command
+    }
+}
+
+/// Whitespace, word characters and punctuation, each of which `w` and
+/// `b` treat as a word of its own.
+fn class(c: char) -> u8 {
+    match c {
+        c if c.is_whitespace() => 0,
+        c if c.is_alphanumeric() || c == '_' => 1,
+        _ => 2,
+    }
+}
+
+/// Where the next word after `offset` in `text` starts, or with `to_end`
+/// where the one at `offset` ends.
+fn word_forward(text: &str, offset: usize, to_end: bool) -> usize {
+    let mut chars = text[offset..].char_indices().peekable();
+    if let Some(&(_, first)) = chars.peek() {
+        let first = class(first);
+        while chars
+            .next_if(|&(_, c)| first != 0 && class(c) == first)
+            .is_some()
+        {}
+    }
+    if !to_end {
+        while chars.next_if(|&(_, c)| class(c) == 0).is_some() {}
+    }
+    chars.peek().map_or(text.len(), |&(i, _)| offset + i)
+}
+
+/// Where the word before `offset` in `text` starts.
+fn word_back(text: &str, offset: usize) -> usize {
+    let mut chars = text[..offset].char_indices().rev().peekable();
+    while chars.next_if(|&(_, c)| class(c) == 0).is_some() {}
+    let mut start = chars.peek().map_or(0, |&(i, _)| i);
+    if let Some(&(_, last)) = chars.peek() {
+        let last = class(last);
+        while let Some((i, _)) = chars.next_if(|&(_, c)| class(c) == last) {
+            start = i;
+        }
+    }
+    start
+}
+
+/// Where `motion` moves the cursor in `buffer` to, `count` times over.
+fn target(buffer: &Buffer, motion: Motion, count: Option<usize>) -> usize {
+    let cursor = buffer.cursor();
+    let position = buffer.cursor_position();
+    let times = count.unwrap_or(1);
+    let last_line = buffer.len_lines().saturating_sub(1);
+    let line_end = |line: usize| {
+        let start = buffer.line_span(line..line).start;
+        start + buffer.line(line).map_or(0, str::len)
+    };
+    let on_line = |line: usize, col: usize| {
+        let line = line.min(last_line);
+        let len = buffer.line(line).unwrap_or("").chars().count();
+        let col = col.min(len);
+        buffer
+            .offset_of(Position { line, col })
+            .unwrap_or(buffer.text().len())
+    };
+    let text = buffer.text();
+    match motion {
+        Motion::Left => on_line(position.line, position.col.saturating_sub(times)),
+        Motion::Right => on_line(position.line, position.col.saturating_add(times)),
+        Motion::Up => on_line(position.line.saturating_sub(times), position.col),
+        Motion::Down => on_line(position.line.saturating_add(times), position.col),
+        Motion::WordForward => (0..times).fold(cursor, |at, _| word_forward(text, at, false)),
+        Motion::WordBack => (0..times).fold(cursor, |at, _| word_back(text, at)),
+        Motion::LineStart => buffer.line_span(position.line..position.line).start,
+        Motion::LineEnd => line_end(position.line),
+        Motion::FirstLine => on_line(count.map_or(0, |line| line.saturating_sub(1)), 0),
+        Motion::LastLine => on_line(count.map_or(last_line, |line| line.saturating_sub(1)), 0),
+    }
+}
+
+impl BufferEditor {
+    /// Handles a key in normal or visual mode, or Esc in insert mode, if
+    /// it's one vim mode takes over, and returns whether it was.
+    pub fn vim_event(&mut self, game: &mut Game, event: &SimpleEvent) -> bool {
+        if self.vim.mode == Mode::Insert {
+            let esc = matches!(event, SimpleEvent::Just(KeyCode::Esc));
+            if esc {
+                self.vim.mode = Mode::Normal;
+            }
+            return esc;
+        }
+        let c = match event {
+            SimpleEvent::Just(KeyCode::Char(c)) => *c,
+            SimpleEvent::Just(KeyCode::Enter) => 'j',
+            SimpleEvent::Just(KeyCode::Backspace) => 'h',
+            SimpleEvent::Just(KeyCode::Delete) => 'x',
+            SimpleEvent::Just(KeyCode::Esc) if self.vim.mode == Mode::Visual => {
+                self.leave_visual();
+                return true;
+            }
+            // Esc drops half a command, rather than closing the editor.
+            SimpleEvent::Just(KeyCode::Esc) if self.vim.is_pending() => {
+                self.vim.cancel();
+                return true;
+            }
+            _ => return false,
+        };
+        let Some(command) = self.vim.key(c) else {
+            return true;
+        };
+        if self.read_only && changes_text(command) {
+            let message = tr("editor.read-only", &[("name", &self.name)]);
+            game.set_message(Some(Message::new(message)));
+            return true;
+        }
+        match command {
+            Command::Move(motion, count) => {
+                let to = target(&self.buffer, motion, count);
+                self.buffer.set_cursor(to);
+            }
+            Command::Operate(operator, target, count) => self.operate(operator, target, count),
+            Command::Insert => {
+                self.buffer.clear_selection();
+                self.vim.mode = Mode::Insert;
+            }
+            Command::Append => {
+                self.buffer.clear_selection();
+                if self.buffer.char_after_cursor().is_some_and(|c| c != '\n') {
+                    self.buffer.move_right();
+                }
+                self.vim.mode = Mode::Insert;
+            }
+            Command::Visual if self.vim.mode == Mode::Visual => self.leave_visual(),
+            Command::Visual => {
+                self.buffer.clear_selection();
+                self.buffer.start_selection();
+                self.vim.mode = Mode::Visual;
+            }
+            Command::DeleteChar(count) => {
+                let line_end = target(&self.buffer, Motion::LineEnd, None);
+                let to = target(&self.buffer, Motion::Right, count).min(line_end);
+                let range = self.buffer.cursor()..to;
+                if !range.is_empty() {
+                    self.cut_range(range);
+                }
+            }
+            Command::Paste(count) => {
+                for _ in 0..count.unwrap_or(1) {
+                    self.paste_after();
+                }
+            }
+            Command::Undo => {
+                self.repeat.forget();
+                if !self.buffer.undo() {
+                    game.set_message(Some(Message::new(tr("editor.nothing-to-undo", &[]))));
+                }
+            }
+            Command::Repeat(count) => self.repeat_edit(game, count),
+            Command::Register(name) => self.register = Some(name),
+            Command::Cancel => {}
+        }
+        true
+    }
+
+    /// Goes back to normal mode from visual mode, leaving the selection.
+    fn leave_visual(&mut self) {
+        self.buffer.clear_selection();
+        self.vim.mode = Mode::Normal;
+    }
+
+    /// Applies `operator` to what `target` covers.
+    fn operate(&mut self, operator: Operator, target: Target, count: Option<usize>) {
+        let cursor = self.buffer.cursor();
+        let line = self.buffer.cursor_line();
+        let range = match target {
+            Target::Selection => self.buffer.selection().unwrap_or(cursor..cursor),
+            Target::Lines => {
+                let lines = line..line + count.unwrap_or(1);
+                self.buffer.line_span(lines)
+            }
+            // `cw` changes the word, not the space after it.
+            Target::Motion(Motion::WordForward) if operator == Operator::Change => {
+                let text = self.buffer.text();
+                let times = count.unwrap_or(1);
+                let end = (1..times).fold(cursor, |at, _| word_forward(text, at, false));
+                cursor..word_forward(text, end, true)
+            }
+            Target::Motion(motion) => {
+                let to = self::target(&self.buffer, motion, count);
+                let range = cursor.min(to)..cursor.max(to);
+                match motion.is_linewise() {
+                    true => {
+                        let first = self.buffer.position_of(range.start).line;
+                        let last = self.buffer.position_of(range.end).line;
+                        self.buffer.line_span(first..last + 1)
+                    }
+                    false => range,
+                }
+            }
+        };
+        // Changing lines leaves one empty line to type on, and filtering
+        // is always of whole lines.
+        let range = match (operator, target) {
+            (Operator::Filter, Target::Motion(motion)) if !motion.is_linewise() => {
+                let first = self.buffer.position_of(range.start).line;
+                let last = self.buffer.position_of(range.end).line;
+                self.buffer.line_span(first..last + 1)
+            }
+            (Operator::Change, Target::Lines)
+                if self.buffer.text()[range.clone()].ends_with('\n') =>
+            {
+                range.start..range.end - 1
+            }
+            _ => range,
+        };
+        match operator {
+            Operator::Yank => {
+                self.select(range.clone());
+                self.copy(false);
+                self.buffer.clear_selection();
+                self.buffer.set_cursor(range.start);
+            }
+            Operator::Delete | Operator::Change => {
+                if !range.is_empty() {
+                    self.cut_range(range);
+                }
+            }
+            Operator::Filter => {
+                self.buffer.clear_selection();
+                self.start_pipe(range);
+            }
+        }
+        self.vim.mode = match operator {
+            Operator::Change => Mode::Insert,
+            Operator::Delete | Operator::Yank | Operator::Filter => Mode::Normal,
+        };
+    }
+
+    /// Selects `range`, with the cursor at its end.
+    fn select(&mut self, range: Range<usize>) {
+        self.buffer.clear_selection();
+        self.buffer.set_cursor(range.start);
+        self.buffer.start_selection();
+        self.buffer.set_cursor(range.end);
+    }
+
+    /// Deletes `range`, keeping it in the register or on the clipboard.
+    fn cut_range(&mut self, range: Range<usize>) {
+        self.select(range);
+        self.copy(true);
+    }
+
+    /// Pastes after the cursor, or under the cursor's line if what's
+    /// pasted is whole lines.
+    fn paste_after(&mut self) {
+        let text = match self.register {
+            Some(name) => self.session.registers.get(name).map(|clip| &clip.text),
+            None => self.session.clipboard.current().map(|clip| &clip.text),
+        };
+        match text.is_some_and(|text| text.ends_with('\n')) {
+            true => {
+                let line = self.buffer.cursor_line();
+                let end = self.buffer.line_span(line..line + 1).end;
+                self.buffer.set_cursor(end);
+                // The last line needs a newline to paste under.
+                let text = self.buffer.text();
+                if end == text.len() && !text.is_empty() && !text.ends_with('\n') {
+                    self.buffer.insert("\n");
+                }
+            }
+            false => {
+                if self.buffer.char_after_cursor().is_some_and(|c| c != '\n') {
+                    self.buffer.move_right();
+                }
+            }
+        }
+        self.paste();
+    }
+}
+
+/// Whether `command` edits the buffer.
+fn changes_text(command: Command) -> bool {
+    match command {
+        Command::Operate(operator, ..) => operator != Operator::Yank,
+        Command::DeleteChar(_) | Command::Paste(_) | Command::Undo | Command::Repeat(_) => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::harness::{key, Harness};
+
+    #[test]
+    fn counts_and_operators_add_up_to_commands() {
+        let mut vim = Vim::default();
+        let mut keys = |keys: &str| keys.chars().filter_map(|c| vim.key(c)).collect::<Vec<_>>();
+        assert_eq!(keys("3j"), [Command::Move(Motion::Down, Some(3))]);
+        assert_eq!(keys("0"), [Command::Move(Motion::LineStart, None)]);
+        assert_eq!(
+            keys("2d3j"),
+            [Command::Operate(
+                Operator::Delete,
+                Target::Motion(Motion::Down),
+                Some(6)
+            )]
+        );
+        assert_eq!(
+            keys("yy"),
+            [Command::Operate(Operator::Yank, Target::Lines, None)]
+        );
+        assert_eq!(keys("10G"), [Command::Move(Motion::LastLine, Some(10))]);
+        assert_eq!(keys("gg"), [Command::Move(Motion::FirstLine, None)]);
+        assert_eq!(keys("dy"), [Command::Cancel]);
+        assert_eq!(keys("\"ap"), [Command::Register('a'), Command::Paste(None)]);
+    }
+
+    #[test]
+    fn words_start_at_a_change_of_class() {
+        let text = "foo.bar  baz\nqux";
+        assert_eq!(word_forward(text, 0, false), 3);
+        assert_eq!(word_forward(text, 3, false), 4);
+        assert_eq!(word_forward(text, 4, false), 9);
+        assert_eq!(word_forward(text, 9, false), 13);
+        assert_eq!(word_forward(text, 4, true), 7);
+        assert_eq!(word_back(text, 13), 9);
+        assert_eq!(word_back(text, 9), 4);
+        assert_eq!(word_back(text, 3), 0);
+    }
+
+    #[test]
+    fn normal_mode_moves_and_operates_instead_of_typing() {
+        let mut harness = Harness::new("one two three\nfour\nfive\n");
+        harness.editor.session.editing_mode = EditingMode::Vim;
+        harness.type_text("wdw");
+        assert_eq!(harness.text(), "one three\nfour\nfive\n");
+
+        harness.type_text("jddp");
+        assert_eq!(harness.text(), "one three\nfive\nfour\n");
+        let clip = harness.editor.session.clipboard.current().unwrap();
+        assert_eq!(clip.text, "four\n");
+
+        harness.type_text("ggcwONE").press(KeyCode::Esc);
+        assert_eq!(harness.text(), "ONE three\nfive\nfour\n");
+
+        // Visual mode selects up to the cursor, as Shift does.
+        harness.type_text("0vlly$p");
+        assert_eq!(harness.text(), "ONE threeON\nfive\nfour\n");
+        assert!(!harness.ended());
+        harness.run([key(KeyCode::Esc)]);
+        assert!(harness.ended());
+    }
+}
@@ -0,0 +1,64 @@
+//! Noticing when a buffer's file is changed by something else.
+//!
+//! This just polls the file's modification time, which is cheap enough
+//! to do every second or so and needs no platform-specific watcher.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// How often the file is looked at.
+const POLL_EVERY: Duration = Duration::from_secs(1);
+
+/// Keeps an eye on one file.
+#[derive(Debug)]
+pub struct FileWatch {
+    path: PathBuf,
+    /// When the file was last modified, as of the last look.
+    modified: Option<SystemTime>,
+    /// By the session's [`Clock`](crate::clock::Clock).
+    last_poll: Option<Duration>,
+}
+
+impl FileWatch {
+    pub fn new(path: impl Into<PathBuf>) -> FileWatch {
+        let path = path.into();
+        FileWatch {
+            modified: modified(&path),
+            path,
+            last_poll: None,
+        }
+    }
+
+    /// Whether the file has been modified since the last time this
+    /// said so (or since the watch started). Only actually looks at the
+    /// file once in a while, so it's fine to call on every tick.
+    pub fn poll(&mut self, now: Duration) -> bool {
+        let last_poll = *self.last_poll.get_or_insert(now);
+        if now.saturating_sub(last_poll) < POLL_EVERY {
+            return false;
+        }
+        self.last_poll = Some(now);
+        self.check()
+    }
+
+    /// Like [`FileWatch::poll`], but always looks at the file.
+    pub fn check(&mut self) -> bool {
+        let modified = modified(&self.path);
+        // A file that's been deleted hasn't changed into anything to reload.
+        if modified.is_none() || modified == self.modified {
+            return false;
+        }
+        self.modified = modified;
+        true
+    }
+
+    /// Forgets any change made up to now, like one BuffeRS made itself.
+    pub fn reset(&mut self) {
+        self.modified = modified(&self.path);
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
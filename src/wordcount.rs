@@ -0,0 +1,102 @@
+//! Word and character counts which are kept up to date as a buffer is
+//! edited, rather than re-counted from scratch every keystroke.
+
+use crate::history::Edit;
+
+/// The number of words and characters in some text.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WordCount {
+    pub words: usize,
+    pub chars: usize,
+}
+
+impl WordCount {
+    /// Counts all of `text`.
+    pub fn of(text: &str) -> WordCount {
+        WordCount {
+            words: word_starts(None, text, None),
+            chars: text.chars().count(),
+        }
+    }
+
+    /// Updates the count for `edit`, which is about to be applied to text
+    /// where `before` comes just before what it removes, and `after` just
+    /// after.
+    ///
+    /// Only the edited region and the characters either side of it are
+    /// looked at, since they're all that can change where words start.
+    pub fn update(&mut self, before: Option<char>, after: Option<char>, edit: &Edit) {
+        self.words -= word_starts(before, &edit.removed, after);
+        self.words += word_starts(before, &edit.inserted, after);
+        self.chars -= edit.removed.chars().count();
+        self.chars += edit.inserted.chars().count();
+    }
+}
+
+/// Counts how many words start in `region` or at `next`, given `prev`
+/// comes just before `region`.
+fn word_starts(prev: Option<char>, region: &str, next: Option<char>) -> usize {
+    let mut count = 0;
+    let mut after_space = prev.is_none_or(char::is_whitespace);
+    for c in region.chars().chain(next) {
+        if after_space && !c.is_whitespace() {
+            count += 1;
+        }
+        after_space = c.is_whitespace();
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use termgame::KeyCode;
+
+    use crate::harness::{shift_key, Harness};
+
+    /// Applies `edit` to `text`, checking the running count against one
+    /// counted from scratch.
+    fn check(text: &str, offset: usize, removed: &str, inserted: &str) -> WordCount {
+        let mut count = WordCount::of(text);
+        let edit = Edit {
+            offset,
+            removed: String::from(removed),
+            inserted: String::from(inserted),
+        };
+        let end = offset + removed.len();
+        count.update(
+            text[..offset].chars().next_back(),
+            text[end..].chars().next(),
+            &edit,
+        );
+        let edited = format!("{}{}{}", &text[..offset], inserted, &text[end..]);
+        assert_eq!(count, WordCount::of(&edited), "{:?}", edited);
+        count
+    }
+
+    #[test]
+    fn edits_which_join_words_count_one_fewer() {
+        assert_eq!(check("one two", 3, " ", "").words, 1);
+        assert_eq!(check("one two three", 3, " two ", "-").words, 1);
+        assert_eq!(check("a\nb", 1, "\n", "").words, 1);
+    }
+
+    #[test]
+    fn edits_which_split_words_count_one_more() {
+        assert_eq!(check("onetwo", 3, "", " ").words, 2);
+        assert_eq!(check("onetwo", 3, "", "\n\n").words, 2);
+        assert_eq!(check("one", 1, "n", " x ").words, 3);
+        assert_eq!(check("", 0, "", "é b").chars, 3);
+    }
+
+    #[test]
+    fn the_status_bar_counts_the_selection_when_there_is_one() {
+        let mut harness = Harness::new("one two three\n");
+        assert!(harness.status_line().contains("3 words, 14 chars"));
+        harness.run((0..5).map(|_| shift_key(KeyCode::Right)));
+        assert!(harness.status_line().contains("2 words, 5 chars"));
+        harness.press(KeyCode::Right);
+        assert!(harness.status_line().contains("3 words, 14 chars"));
+    }
+}
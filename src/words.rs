@@ -0,0 +1,103 @@
+//! Moving and deleting a word at a time, with Ctrl and the arrow keys,
+//! Backspace or Delete. Words are found the way Unicode says to split
+//! text into them, so `naïve`, `don't` and `3.14` are each one word, and
+//! the spaces and punctuation between words are skipped over along with
+//! the word after them.
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::BufferEditor;
+
+/// Whether a piece of text Unicode splits off as a word is one, rather
+/// than spaces or punctuation.
+fn is_word(segment: &str) -> bool {
+    segment.chars().any(char::is_alphanumeric)
+}
+
+/// Where the word the cursor is in or before ends, going forwards from
+/// `offset` in `text`, or the end of the text if there isn't one.
+pub fn next_word_end(text: &str, offset: usize) -> usize {
+    text[offset..]
+        .split_word_bound_indices()
+        .find(|(_, segment)| is_word(segment))
+        .map_or(text.len(), |(i, segment)| offset + i + segment.len())
+}
+
+/// Where the word the cursor is in or after starts, going back from
+/// `offset` in `text`, or the start of the text if there isn't one.
+pub fn previous_word_start(text: &str, offset: usize) -> usize {
+    text[..offset]
+        .split_word_bound_indices()
+        .rev()
+        .find(|(_, segment)| is_word(segment))
+        .map_or(0, |(i, _)| i)
+}
+
+impl BufferEditor {
+    /// Moves the cursor to the start of the word before it, or the end
+    /// of the word after it.
+    pub fn move_word(&mut self, forwards: bool) {
+        let (text, cursor) = (self.buffer.text(), self.buffer.cursor());
+        let offset = match forwards {
+            true => next_word_end(text, cursor),
+            false => previous_word_start(text, cursor),
+        };
+        self.buffer.set_cursor(offset);
+    }
+
+    /// Deletes back to the start of the word before the cursor, or on to
+    /// the end of the word after it. What's deleted can be pasted back.
+    pub fn delete_word(&mut self, forwards: bool) {
+        let (text, cursor) = (self.buffer.text(), self.buffer.cursor());
+        let range = match forwards {
+            true => cursor..next_word_end(text, cursor),
+            false => previous_word_start(text, cursor)..cursor,
+        };
+        if range.is_empty() {
+            return;
+        }
+        let deleted = String::from(&text[range.clone()]);
+        self.buffer.replace_range(range, "");
+        self.keep(deleted);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use termgame::KeyCode;
+
+    use crate::harness::{ctrl, ctrl_key, Harness};
+
+    #[test]
+    fn words_are_split_the_way_unicode_splits_them() {
+        let text = "let naïve = don't(3.14);";
+        assert_eq!(next_word_end(text, 0), 3);
+        assert_eq!(next_word_end(text, 3), 10);
+        assert_eq!(next_word_end(text, 10), 18);
+        assert_eq!(next_word_end(text, 18), 23);
+        assert_eq!(next_word_end(text, 23), text.len());
+        assert_eq!(previous_word_start(text, text.len()), 19);
+        assert_eq!(previous_word_start(text, 19), 13);
+        assert_eq!(previous_word_start(text, 6), 4);
+        assert_eq!(previous_word_start(text, 3), 0);
+    }
+
+    #[test]
+    fn ctrl_moves_and_deletes_a_word_at_a_time() {
+        let mut harness = Harness::new("one two, three");
+        harness.run([ctrl_key(KeyCode::Right), ctrl_key(KeyCode::Right)]);
+        assert_eq!(harness.editor.buffer.cursor(), 7);
+        harness.run([ctrl_key(KeyCode::Left)]);
+        assert_eq!(harness.editor.buffer.cursor(), 4);
+
+        harness.run([ctrl_key(KeyCode::Delete)]);
+        assert_eq!(harness.text(), "one , three");
+        harness.run([ctrl_key(KeyCode::Backspace)]);
+        assert_eq!(harness.text(), ", three");
+        // What's deleted can be pasted back.
+        harness.run([ctrl('v')]);
+        assert_eq!(harness.text(), "one , three");
+    }
+}
@@ -0,0 +1,314 @@
+//! Soft wrapping, where a line too long for the screen carries on along
+//! the rows under it instead of off the side. It's only how lines are
+//! drawn: they're still numbered, found and jumped to as whole lines.
+//!
+//! `wrap <buffer> on|off` or Ctrl+W turns it on and off for a buffer.
+//!
+//! Which row each line starts on is kept, as far down as has been drawn
+//! or scrolled to, and worked out again only from a line that's changed,
+//! and only from there if that moved the rows after it. So just the rows
+//! in view are wrapped each time it's drawn.
+
+use std::ops::Range;
+
+use crate::buffer::cell_width;
+use crate::BufferEditor;
+
+/// Splits `line` into the byte ranges drawn on each row, none more than
+/// `width` cells across, with tab stops every `tab_width` from the start
+/// of each row. Rows break after the last space that fits, or anywhere
+/// if a word is too long for a row on its own.
+pub fn wrap_line(line: &str, width: usize, tab_width: usize) -> Vec<Range<usize>> {
+    let width = width.max(1);
+    let mut rows = Vec::new();
+    let mut start = 0;
+    loop {
+        let rest = &line[start..];
+        let mut cells = 0;
+        // Where the first char that doesn't fit starts, though a row
+        // always has at least one.
+        let overflow = rest.char_indices().find_map(|(i, c)| {
+            cells += cell_width(c, cells, tab_width);
+            (cells > width).then(|| i.max(c.len_utf8()))
+        });
+        let Some(end) = overflow.filter(|&end| end < rest.len()) else {
+            rows.push(start..line.len());
+            return rows;
+        };
+        let end = match rest[..end].rfind(' ') {
+            Some(space) if space > 0 => space + 1,
+            _ => end,
+        };
+        rows.push(start..start + end);
+        start += end;
+    }
+}
+
+/// The row each line starts on, for the lines from the top which have
+/// been wrapped so far.
+#[derive(Debug, Clone, Default)]
+pub struct Rows {
+    /// How many cells across the lines were wrapped to.
+    width: usize,
+    /// The line of the file the buffer started at, for a huge one.
+    first_line: usize,
+    /// The buffer's revision when the rows were last brought up to date.
+    revision: usize,
+    /// The row each line wrapped so far starts on, then the row after
+    /// the last of them. Empty when nothing has been.
+    starts: Vec<usize>,
+    /// The rows drawn differently since [`Rows::take_changed`] last said,
+    /// running to `usize::MAX` when every row after the first moved.
+    changed: Option<Range<usize>>,
+}
+
+impl Rows {
+    /// How many cells across the lines were wrapped to.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// The rows which have changed since this was last called.
+    pub fn take_changed(&mut self) -> Option<Range<usize>> {
+        self.changed.take()
+    }
+
+    fn change(&mut self, rows: Range<usize>) {
+        self.changed = Some(match self.changed.take() {
+            Some(before) => before.start.min(rows.start)..before.end.max(rows.end),
+            None => rows,
+        });
+    }
+}
+
+/// The rows around the ones in view, when lines wrap.
+pub struct Window {
+    /// The row the first of `rows` is.
+    first: usize,
+    /// Each row, as the line it's part of and which bytes of that line
+    /// go on it.
+    rows: Vec<(usize, Range<usize>)>,
+    /// How many rows there are, or at least, when the lines past the
+    /// window haven't been wrapped yet.
+    pub total: usize,
+}
+
+impl Window {
+    /// The line row `row` is part of, and which bytes of it go on it.
+    pub fn get(&self, row: usize) -> Option<&(usize, Range<usize>)> {
+        self.rows.get(row.checked_sub(self.first)?)
+    }
+
+    /// The rows `lines` are drawn across, as far as they're in the
+    /// window.
+    pub fn rows_of(&self, lines: Range<usize>) -> Range<usize> {
+        let row = |line| self.first + self.rows.partition_point(|&(index, _)| index < line);
+        row(lines.start)..row(lines.end)
+    }
+}
+
+impl BufferEditor {
+    /// How many cells across lines wrap to, when the screen is `columns`
+    /// across. The last column is left for the cursor.
+    fn wrap_width(&self, columns: usize) -> usize {
+        columns.saturating_sub(self.text_left() + 1)
+    }
+
+    /// Brings the rows up to date with the buffer's text, when lines wrap
+    /// `columns` cells across the screen.
+    pub fn update_rows(&mut self, columns: usize) {
+        let width = self.wrap_width(columns);
+        let first_line = self.first_line();
+        let revision = self.buffer.revision();
+        let rows = &mut self.rows;
+        if rows.starts.is_empty() || rows.width != width || rows.first_line != first_line {
+            *rows = Rows {
+                width,
+                first_line,
+                revision,
+                starts: vec![0],
+                changed: Some(0..usize::MAX),
+            };
+            return;
+        }
+        if rows.revision == revision {
+            return;
+        }
+        rows.revision = revision;
+        let Some(lines) = self.buffer.changed_lines() else {
+            return;
+        };
+        let wrapped = rows.starts.len() - 1;
+        if lines.start >= wrapped {
+            return;
+        }
+        let from = rows.starts[lines.start];
+        // Lines edited without any coming or going only move the rows
+        // after them if they now wrap onto more or fewer.
+        if lines.end <= wrapped {
+            let mut row = from;
+            let starts: Vec<usize> = lines
+                .clone()
+                .map(|line| {
+                    let start = row;
+                    let text = self.buffer.line(line).unwrap_or("");
+                    row += wrap_line(text, width, self.config.tab_width).len();
+                    start
+                })
+                .collect();
+            if row == rows.starts[lines.end] {
+                rows.starts[lines].copy_from_slice(&starts);
+                rows.change(from..row);
+                return;
+            }
+        }
+        rows.starts.truncate(lines.start + 1);
+        rows.change(from..usize::MAX);
+    }
+
+    /// Wraps lines until line `line` and row `row` both have been, or
+    /// there are no more.
+    fn wrap_until(&mut self, line: usize, row: usize) {
+        let lines = self.buffer.drawn_lines();
+        let rows = &mut self.rows;
+        while rows.starts.len() <= lines {
+            let wrapped = rows.starts.len() - 1;
+            let end = rows.starts[wrapped];
+            if wrapped >= line && end > row {
+                break;
+            }
+            let text = self.buffer.line(wrapped).unwrap_or("");
+            let count = wrap_line(text, rows.width, self.config.tab_width).len();
+            rows.starts.push(end + count);
+        }
+    }
+
+    /// The rows in `window`, when lines wrap `columns` cells across the
+    /// screen. Only the lines above and in it are wrapped.
+    pub fn wrapped_rows(&mut self, columns: usize, window: Range<usize>) -> Window {
+        self.update_rows(columns);
+        self.wrap_until(0, window.end);
+        let starts = &self.rows.starts;
+        let total = *starts.last().unwrap_or(&0);
+        let mut line = starts.partition_point(|&start| start <= window.start) - 1;
+        let first = window.start.min(total);
+        let mut rows = Vec::new();
+        while line + 1 < starts.len() && starts[line] < window.end {
+            let text = self.buffer.line(line).unwrap_or("");
+            let parts = wrap_line(text, self.rows.width, self.config.tab_width);
+            let skip = first.saturating_sub(starts[line]);
+            let take = window.end - starts[line];
+            rows.extend(
+                parts
+                    .into_iter()
+                    .enumerate()
+                    .filter_map(|(i, part)| (skip..take).contains(&i).then_some((line, part))),
+            );
+            line += 1;
+        }
+        Window { first, rows, total }
+    }
+
+    /// The row the cursor is on, when lines wrap `columns` cells across
+    /// the screen.
+    pub fn cursor_row(&mut self, columns: usize) -> usize {
+        self.update_rows(columns);
+        let line = self.buffer.cursor_line();
+        self.wrap_until(line, 0);
+        let offset = self.buffer.cursor() - self.buffer.line_span(line..line).start;
+        let text = self.buffer.line(line).unwrap_or("");
+        let parts = wrap_line(text, self.rows.width, self.config.tab_width);
+        let part = parts.iter().rposition(|part| part.start <= offset);
+        self.rows.starts[line] + part.unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use termgame::KeyCode;
+
+    use crate::harness::{ctrl, Harness};
+
+    #[test]
+    fn lines_break_after_the_last_space_that_fits() {
+        let line = "the cat sat on the mat";
+        let rows: Vec<&str> = wrap_line(line, 10, 4)
+            .into_iter()
+            .map(|row| &line[row])
+            .collect();
+        assert_eq!(rows, ["the cat ", "sat on ", "the mat"]);
+
+        // A word longer than a row is broken wherever it has to be.
+        let rows: Vec<&str> = wrap_line("abcdefg hi", 3, 4)
+            .into_iter()
+            .map(|row| &"abcdefg hi"[row])
+            .collect();
+        assert_eq!(rows, ["abc", "def", "g ", "hi"]);
+        assert_eq!(wrap_line("", 10, 4).len(), 1);
+        // Tabs take up the cells they're drawn across.
+        assert_eq!(wrap_line("a\tb\tc", 6, 4), [0..3, 3..5]);
+    }
+
+    #[test]
+    fn ctrl_w_wraps_long_lines_onto_the_rows_below() {
+        let long = "word ".repeat(20);
+        let mut harness = Harness::new(&format!("{}\nnext", long));
+        harness.run([ctrl('w')]);
+        // 77 columns are left for text, which fits 15 words.
+        assert_eq!(harness.screen_line(0), "word ".repeat(15).trim_end());
+        assert_eq!(harness.screen_line(1), "word ".repeat(5).trim_end());
+        assert_eq!(harness.screen_line(2), "next");
+
+        // The line is still one line, as far as anything else goes.
+        harness.press(KeyCode::Down);
+        assert!(harness.status_line().contains("Ln 2, Col 1"));
+        harness.run([ctrl('w')]);
+        assert_eq!(harness.screen_line(1), "next");
+    }
+
+    #[test]
+    fn only_the_lines_down_to_the_screen_are_wrapped_and_edits_redraw_their_rows() {
+        let long = "word ".repeat(20);
+        let text: String = (0..1000).map(|i| format!("{}{}\n", i, long)).collect();
+        let mut harness = Harness::new(&text);
+        harness.run([ctrl('w')]);
+        assert!(harness.editor.rows.starts.len() < 100);
+        assert_eq!(
+            harness.screen_line(2),
+            format!("1{}", "word ".repeat(15)).trim_end()
+        );
+
+        // Typing which doesn't make a line wrap onto more rows redraws
+        // just those rows, and typing which does moves the ones under it.
+        harness.type_text("x");
+        assert_eq!(
+            harness.screen_line(0),
+            format!("x0{}", "word ".repeat(15)).trim_end()
+        );
+        assert_eq!(
+            harness.screen_line(2),
+            format!("1{}", "word ".repeat(15)).trim_end()
+        );
+        harness.type_text(&"y".repeat(80));
+        assert_eq!(
+            harness.screen_line(3),
+            format!("1{}", "word ".repeat(15)).trim_end()
+        );
+        assert_eq!(harness.screen_line(4), "word ".repeat(5).trim_end());
+
+        // Scrolling down wraps the lines it gets to.
+        for _ in 0..20 {
+            harness.press(KeyCode::PageDown);
+        }
+        let line = harness.editor.buffer.cursor_line();
+        let columns = harness.editor.rows.width() + 1;
+        let row = harness.editor.cursor_row(columns);
+        let start = harness.editor.rows.starts[line];
+        assert!((start..start + 2).contains(&row));
+        assert!(harness
+            .screen_line(start as i32)
+            .starts_with(&line.to_string()));
+    }
+}